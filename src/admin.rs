@@ -0,0 +1,573 @@
+//! Server-level diagnostics: running threads, status counters, variables,
+//! and replication state.
+
+use serde_json::{json, Value};
+use sqlx::{MySql, Pool, Row};
+
+use crate::db::row_to_json;
+use crate::ddl::quote_literal;
+use crate::error::DbError;
+
+/// Status counters reported when no pattern is given.
+const DEFAULT_STATUS: &[&str] = &[
+    "Uptime",
+    "Threads_connected",
+    "Threads_running",
+    "Max_used_connections",
+    "Connections",
+    "Aborted_connects",
+    "Questions",
+    "Slow_queries",
+    "Innodb_buffer_pool_pages_total",
+    "Innodb_buffer_pool_pages_free",
+    "Innodb_buffer_pool_read_requests",
+    "Innodb_buffer_pool_reads",
+    "Innodb_row_lock_waits",
+];
+
+/// Server variables reported when no pattern is given.
+const DEFAULT_VARIABLES: &[&str] = &[
+    "version",
+    "version_comment",
+    "max_connections",
+    "sql_mode",
+    "innodb_buffer_pool_size",
+    "character_set_server",
+    "collation_server",
+    "time_zone",
+    "long_query_time",
+    "read_only",
+];
+
+/// Threads currently connected to the server, longest-running first. Idle
+/// (`Sleep`) connections are skipped unless `include_idle` is set.
+pub async fn show_processlist(include_idle: bool, pool: &Pool<MySql>) -> Result<Vec<Value>, DbError> {
+    let rows = sqlx::query(
+        "SELECT CAST(id AS UNSIGNED) AS id, user AS user, host AS host, db AS db,
+                command AS command, CAST(time AS SIGNED) AS time, state AS state, info AS info
+         FROM information_schema.processlist
+         WHERE (? OR command <> 'Sleep') AND id <> CONNECTION_ID()
+         ORDER BY time DESC, id",
+    )
+    .bind(include_idle)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(json!({
+                "id": row.try_get::<u64, _>("id")?,
+                "user": row.try_get::<String, _>("user")?,
+                "host": row.try_get::<String, _>("host")?,
+                "database": row.try_get::<Option<String>, _>("db")?,
+                "command": row.try_get::<String, _>("command")?,
+                "timeSecs": row.try_get::<i64, _>("time")?,
+                "state": row.try_get::<Option<String>, _>("state")?,
+                "query": row.try_get::<Option<String>, _>("info")?,
+            }))
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(DbError::from)
+}
+
+/// `KILL QUERY` (or `KILL CONNECTION`) a thread. Returns whether the thread existed.
+pub async fn kill(thread_id: u64, connection: bool, pool: &Pool<MySql>) -> Result<bool, DbError> {
+    let statement = if connection { "KILL CONNECTION" } else { "KILL QUERY" };
+    match sqlx::query(&format!("{statement} {thread_id}")).execute(pool).await {
+        Ok(_) => Ok(true),
+        // ER_NO_SUCH_THREAD
+        Err(sqlx::Error::Database(e))
+            if e.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>().map(|e| e.number()) == Some(1094) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Global status counters and server variables, either a default selection
+/// or those whose names match a SQL `LIKE` pattern.
+pub async fn server_status(
+    pattern: Option<String>,
+    pool: &Pool<MySql>,
+) -> Result<(Value, Value), DbError> {
+    let status = fetch_name_values("SHOW GLOBAL STATUS", DEFAULT_STATUS, pattern.as_deref(), pool).await?;
+    let variables = fetch_name_values("SHOW GLOBAL VARIABLES", DEFAULT_VARIABLES, pattern.as_deref(), pool).await?;
+    Ok((status, variables))
+}
+
+/// Server variables describing replication and GTID setup.
+const REPLICATION_VARIABLES: &[&str] = &[
+    "server_id",
+    "server_uuid",
+    "read_only",
+    "super_read_only",
+    "log_bin",
+    "binlog_format",
+    "gtid_mode",
+    "enforce_gtid_consistency",
+    "gtid_executed",
+    "gtid_purged",
+];
+
+/// Replication state of the server as a replica (one entry per channel) and
+/// as a source (current binary log position), plus GTID variables. Uses the
+/// 8.0.22+ statements and falls back to their older names.
+pub async fn replication_status(pool: &Pool<MySql>) -> Result<Value, DbError> {
+    let replica_rows = show_with_fallback("SHOW REPLICA STATUS", "SHOW SLAVE STATUS", pool).await?;
+    let channels: Vec<Value> = replica_rows.iter().map(replica_channel).collect();
+    let source = show_with_fallback("SHOW BINARY LOG STATUS", "SHOW MASTER STATUS", pool)
+        .await?
+        .first()
+        .map(|row| {
+            json!({
+                "file": field(row, &["File"]),
+                "position": field(row, &["Position"]),
+                "binlogDoDb": field(row, &["Binlog_Do_DB"]),
+                "binlogIgnoreDb": field(row, &["Binlog_Ignore_DB"]),
+                "executedGtidSet": field(row, &["Executed_Gtid_Set"]),
+            })
+        });
+    let variables = fetch_name_values("SHOW GLOBAL VARIABLES", REPLICATION_VARIABLES, None, pool).await?;
+    Ok(json!({
+        "isReplica": !channels.is_empty(),
+        "channels": channels,
+        "source": source,
+        "variables": variables,
+    }))
+}
+
+/// Rows of `statement`, or of `fallback` on servers that do not know it yet.
+async fn show_with_fallback(statement: &str, fallback: &str, pool: &Pool<MySql>) -> Result<Vec<Value>, DbError> {
+    let rows = match sqlx::query(statement).fetch_all(pool).await {
+        Ok(rows) => rows,
+        // ER_PARSE_ERROR
+        Err(sqlx::Error::Database(e))
+            if e.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>().map(|e| e.number()) == Some(1064) =>
+        {
+            sqlx::query(fallback).fetch_all(pool).await?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(rows.iter().map(row_to_json).collect())
+}
+
+/// One `SHOW REPLICA STATUS` row, under the same names whichever statement
+/// produced it.
+fn replica_channel(row: &Value) -> Value {
+    let running = |names: &[&str]| field(row, names).as_str().map(|s| s.eq_ignore_ascii_case("yes"));
+    let io_running = running(&["Replica_IO_Running", "Slave_IO_Running"]);
+    let sql_running = running(&["Replica_SQL_Running", "Slave_SQL_Running"]);
+    let lag = field(row, &["Seconds_Behind_Source", "Seconds_Behind_Master"]);
+    let lag = lag.as_i64().or_else(|| lag.as_str().and_then(|s| s.parse().ok()));
+    json!({
+        "channel": field(row, &["Channel_Name", "Connection_name"]),
+        "sourceHost": field(row, &["Source_Host", "Master_Host"]),
+        "sourcePort": field(row, &["Source_Port", "Master_Port"]),
+        "ioThreadRunning": io_running,
+        "ioThreadState": field(row, &["Replica_IO_State", "Slave_IO_State"]),
+        "sqlThreadRunning": sql_running,
+        "sqlThreadState": field(row, &["Replica_SQL_Running_State", "Slave_SQL_Running_State"]),
+        "secondsBehindSource": lag,
+        "caughtUp": io_running == Some(true) && sql_running == Some(true) && lag == Some(0),
+        "sourceLogFile": field(row, &["Source_Log_File", "Master_Log_File"]),
+        "readSourceLogPos": field(row, &["Read_Source_Log_Pos", "Read_Master_Log_Pos"]),
+        "execSourceLogPos": field(row, &["Exec_Source_Log_Pos", "Exec_Master_Log_Pos"]),
+        "lastIoError": field(row, &["Last_IO_Error"]),
+        "lastSqlError": field(row, &["Last_SQL_Error"]),
+        "retrievedGtidSet": field(row, &["Retrieved_Gtid_Set"]),
+        "executedGtidSet": field(row, &["Executed_Gtid_Set"]),
+        "autoPosition": field(row, &["Auto_Position"]),
+    })
+}
+
+/// The first of `names` present in `row`, or null.
+fn field(row: &Value, names: &[&str]) -> Value {
+    names.iter().find_map(|name| row.get(*name)).cloned().unwrap_or(Value::Null)
+}
+
+/// Grant statements of the connected user, or of `user` (`name` or
+/// `name@host`) when given, which needs SELECT on the `mysql` schema.
+pub async fn show_grants(user: Option<String>, pool: &Pool<MySql>) -> Result<(String, Vec<String>), DbError> {
+    let (account, statement) = match user {
+        Some(user) => {
+            let (name, host) = user.rsplit_once('@').unwrap_or((&user, "%"));
+            (format!("{name}@{host}"), format!("SHOW GRANTS FOR {}@{}", quote_literal(name), quote_literal(host)))
+        }
+        None => (
+            sqlx::query_scalar::<_, String>("SELECT CURRENT_USER()").fetch_one(pool).await?,
+            "SHOW GRANTS".to_string(),
+        ),
+    };
+    let grants = sqlx::query(&statement)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| row.try_get::<String, _>(0))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((account, grants))
+}
+
+/// A `GRANT ... ON ... TO ...` statement as its privileges, the object they
+/// apply to, and whether they can be passed on. Role grants
+/// (`GRANT role TO user`) have no object and list the roles instead.
+pub fn parse_grant(grant: &str) -> Value {
+    let body = grant.strip_prefix("GRANT ").unwrap_or(grant);
+    let grant_option = body.ends_with("WITH GRANT OPTION") || body.ends_with("WITH ADMIN OPTION");
+    let Some((privileges, rest)) = split_keyword(body, " ON ") else {
+        let roles = split_keyword(body, " TO ").map_or(body, |(roles, _)| roles);
+        return json!({
+            "roles": split_list(roles),
+            "grantOption": grant_option,
+            "statement": grant,
+        });
+    };
+    let on = split_keyword(rest, " TO ").map_or(rest, |(on, _)| on);
+    json!({
+        "privileges": split_list(privileges),
+        "on": on,
+        "grantOption": grant_option,
+        "statement": grant,
+    })
+}
+
+/// Split a comma-separated list, keeping commas inside quotes and
+/// parentheses, as in `SELECT (`a`, `b`)`.
+fn split_list(text: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let (mut quote, mut depth) = (None, 0);
+    for c in text.chars() {
+        match (quote, c) {
+            (None, '`' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                items.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    items.push(current.trim().to_string());
+    items
+}
+
+/// Split at the first `keyword` outside backticks and quotes.
+fn split_keyword<'a>(text: &'a str, keyword: &str) -> Option<(&'a str, &'a str)> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '`' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, _) if text[i..].starts_with(keyword) => return Some((&text[..i], &text[i + keyword.len()..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Current lock waits and the latest deadlock InnoDB detected.
+pub async fn innodb_status(pool: &Pool<MySql>) -> Result<Value, DbError> {
+    let lock_waits = lock_waits(pool).await?;
+    let row = sqlx::query("SHOW ENGINE INNODB STATUS").fetch_one(pool).await?;
+    let status: String = row.try_get("Status")?;
+    Ok(json!({ "lockWaits": lock_waits, "latestDeadlock": parse_latest_deadlock(&status) }))
+}
+
+/// Transactions waiting for a row lock and who blocks them, from
+/// `sys.innodb_lock_waits`, or straight from `performance_schema` when the
+/// `sys` schema is missing.
+async fn lock_waits(pool: &Pool<MySql>) -> Result<Vec<Value>, DbError> {
+    const SYS: &str = "SELECT CAST(wait_age_secs AS SIGNED) AS wait_secs, locked_table AS locked_table,
+                locked_index AS locked_index, waiting_lock_mode AS waiting_lock_mode,
+                waiting_trx_id AS waiting_trx_id, CAST(waiting_pid AS UNSIGNED) AS waiting_pid,
+                waiting_query AS waiting_query, blocking_trx_id AS blocking_trx_id,
+                CAST(blocking_pid AS UNSIGNED) AS blocking_pid, blocking_query AS blocking_query,
+                blocking_lock_mode AS blocking_lock_mode
+         FROM sys.innodb_lock_waits
+         ORDER BY wait_age_secs DESC";
+    const PERFORMANCE_SCHEMA: &str = "SELECT CAST(TIMESTAMPDIFF(SECOND, r.trx_wait_started, NOW()) AS SIGNED) AS wait_secs,
+                CONCAT('`', rl.object_schema, '`.`', rl.object_name, '`') AS locked_table,
+                rl.index_name AS locked_index, rl.lock_mode AS waiting_lock_mode,
+                CAST(r.trx_id AS CHAR) AS waiting_trx_id, CAST(r.trx_mysql_thread_id AS UNSIGNED) AS waiting_pid,
+                r.trx_query AS waiting_query, CAST(b.trx_id AS CHAR) AS blocking_trx_id,
+                CAST(b.trx_mysql_thread_id AS UNSIGNED) AS blocking_pid, b.trx_query AS blocking_query,
+                bl.lock_mode AS blocking_lock_mode
+         FROM performance_schema.data_lock_waits w
+         JOIN information_schema.innodb_trx r ON r.trx_id = w.requesting_engine_transaction_id
+         JOIN information_schema.innodb_trx b ON b.trx_id = w.blocking_engine_transaction_id
+         JOIN performance_schema.data_locks rl ON rl.engine_lock_id = w.requesting_engine_lock_id
+         JOIN performance_schema.data_locks bl ON bl.engine_lock_id = w.blocking_engine_lock_id
+         ORDER BY wait_secs DESC";
+    let rows = match sqlx::query(SYS).fetch_all(pool).await {
+        Ok(rows) => rows,
+        // ER_BAD_DB_ERROR, ER_NO_SUCH_TABLE
+        Err(sqlx::Error::Database(e))
+            if matches!(e.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>().map(|e| e.number()), Some(1049 | 1146)) =>
+        {
+            sqlx::query(PERFORMANCE_SCHEMA).fetch_all(pool).await?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let row = row_to_json(row);
+            json!({
+                "waitSecs": row["wait_secs"],
+                "lockedTable": row["locked_table"],
+                "lockedIndex": row["locked_index"],
+                "waiting": {
+                    "trxId": row["waiting_trx_id"],
+                    "threadId": row["waiting_pid"],
+                    "query": row["waiting_query"],
+                    "lockMode": row["waiting_lock_mode"],
+                },
+                "blocking": {
+                    "trxId": row["blocking_trx_id"],
+                    "threadId": row["blocking_pid"],
+                    "query": row["blocking_query"],
+                    "lockMode": row["blocking_lock_mode"],
+                },
+            })
+        })
+        .collect())
+}
+
+/// The LATEST DETECTED DEADLOCK section of the InnoDB monitor output as the
+/// transactions involved, the locks each held and waited for, and which one
+/// was rolled back. Null when no deadlock has happened since startup.
+pub fn parse_latest_deadlock(status: &str) -> Value {
+    let Some(start) = status.find("LATEST DETECTED DEADLOCK") else {
+        return Value::Null;
+    };
+    let section = &status[start..];
+    // The section ends where the TRANSACTIONS section's dashed header starts
+    let end = section.find("\nTRANSACTIONS\n").map_or(section.len(), |i| section[..i].rfind("\n-").unwrap_or(i));
+    let lines: Vec<&str> = section[..end].lines().skip(1).filter(|l| !l.chars().all(|c| c == '-')).collect();
+
+    let mut transactions: Vec<Value> = Vec::new();
+    let mut rolled_back = Value::Null;
+    // Which list of the current transaction lock lines go to
+    let mut lock_list = "";
+    let mut in_query = false;
+    for line in &lines[1.min(lines.len())..] {
+        let line = line.trim_end();
+        if let Some(rest) = line.strip_prefix("*** WE ROLL BACK TRANSACTION (") {
+            rolled_back = json!(rest.trim_end_matches(')').parse::<u64>().ok());
+            continue;
+        }
+        if line.starts_with("*** (") {
+            in_query = false;
+            lock_list = if line.ends_with("TRANSACTION:") {
+                transactions.push(json!({ "holds": [], "waitingFor": [] }));
+                ""
+            } else if line.contains("HOLDS THE LOCK") {
+                "holds"
+            } else if line.contains("WAITING FOR THIS LOCK") {
+                "waitingFor"
+            } else {
+                ""
+            };
+            continue;
+        }
+        let Some(transaction) = transactions.last_mut() else {
+            continue;
+        };
+        if let Some(rest) = line.strip_prefix("TRANSACTION ") {
+            let (id, rest) = rest.split_once(',').unwrap_or((rest, ""));
+            transaction["trxId"] = json!(id);
+            transaction["state"] = json!(rest.trim());
+        } else if let Some(rest) = line.strip_prefix("MySQL thread id ") {
+            transaction["threadId"] = json!(rest.split(',').next().and_then(|id| id.trim().parse::<u64>().ok()));
+            in_query = true;
+        } else if line.starts_with("RECORD LOCKS ") || line.starts_with("TABLE LOCK ") {
+            if !lock_list.is_empty() {
+                if let Some(locks) = transaction[lock_list].as_array_mut() {
+                    locks.push(parse_lock_line(line));
+                }
+            }
+        } else if in_query && !line.is_empty() {
+            let query = transaction["query"].as_str().map_or(line.to_string(), |q| format!("{q}\n{line}"));
+            transaction["query"] = json!(query);
+        }
+    }
+    json!({
+        "time": lines.first().map(|l| l.trim()),
+        "transactions": transactions,
+        "rolledBack": rolled_back,
+    })
+}
+
+/// `RECORD LOCKS space id 2 page no 4 n bits 72 index PRIMARY of table `test`.`t` trx id 1 lock_mode X locks rec but not gap waiting`.
+fn parse_lock_line(line: &str) -> Value {
+    let table = line.split_once(" table ").map(|(_, rest)| rest.split(' ').next().unwrap_or_default());
+    let index = line.split_once(" index ").map(|(_, rest)| rest.split(' ').next().unwrap_or_default());
+    let mode = line
+        .split_once("lock_mode ")
+        .or_else(|| line.split_once("lock mode "))
+        .map(|(_, mode)| mode.trim_end_matches(" waiting").trim());
+    json!({
+        "type": if line.starts_with("RECORD") { "record" } else { "table" },
+        "table": table,
+        "index": index,
+        "mode": mode,
+    })
+}
+
+/// Run a `SHOW ...` statement returning `Variable_name` / `Value` pairs and
+/// keep the wanted names. Filtering happens here rather than with
+/// `SHOW ... LIKE ?`, which cannot take a bound parameter.
+async fn fetch_name_values(
+    statement: &str,
+    defaults: &[&str],
+    pattern: Option<&str>,
+    pool: &Pool<MySql>,
+) -> Result<Value, DbError> {
+    let rows = sqlx::query(statement).fetch_all(pool).await?;
+    let mut values = serde_json::Map::new();
+    for row in rows {
+        let name: String = row.try_get(0)?;
+        let wanted = match pattern {
+            Some(pattern) => like_matches(pattern, &name),
+            None => defaults.iter().any(|d| d.eq_ignore_ascii_case(&name)),
+        };
+        if wanted {
+            values.insert(name, json!(row.try_get::<Option<String>, _>(1)?));
+        }
+    }
+    Ok(Value::Object(values))
+}
+
+/// Case-insensitive SQL `LIKE`: `%` matches any run of characters and `_`
+/// exactly one; `\` escapes the next character.
+pub fn like_matches(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some(('%', rest)) => (0..=text.len()).any(|skip| matches(rest, &text[skip..])),
+            Some(('_', rest)) => !text.is_empty() && matches(rest, &text[1..]),
+            Some(('\\', rest)) if !rest.is_empty() => {
+                text.first() == Some(&rest[0]) && matches(&rest[1..], &text[1..])
+            }
+            Some((c, rest)) => text.first() == Some(c) && matches(rest, &text[1..]),
+        }
+    }
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    matches(&pattern, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replica_channel() {
+        let legacy = json!({
+            "Slave_IO_Running": "Yes",
+            "Slave_SQL_Running": "Yes",
+            "Seconds_Behind_Master": 0,
+            "Master_Host": "db-primary",
+            "Channel_Name": ""
+        });
+        let channel = replica_channel(&legacy);
+        assert_eq!(channel["sourceHost"], "db-primary");
+        assert_eq!(channel["caughtUp"], true);
+
+        let lagging = json!({
+            "Replica_IO_Running": "Yes",
+            "Replica_SQL_Running": "No",
+            "Seconds_Behind_Source": null,
+            "Last_SQL_Error": "Duplicate entry"
+        });
+        let channel = replica_channel(&lagging);
+        assert_eq!(channel["sqlThreadRunning"], false);
+        assert_eq!(channel["secondsBehindSource"], Value::Null);
+        assert_eq!(channel["caughtUp"], false);
+        assert_eq!(channel["lastSqlError"], "Duplicate entry");
+    }
+
+    #[test]
+    fn test_parse_grant() {
+        let grant = parse_grant("GRANT SELECT, INSERT, UPDATE (`email`, `name`) ON `shop`.* TO `app`@`%` WITH GRANT OPTION");
+        assert_eq!(grant["privileges"], json!(["SELECT", "INSERT", "UPDATE (`email`, `name`)"]));
+        assert_eq!(grant["on"], "`shop`.*");
+        assert_eq!(grant["grantOption"], true);
+
+        let grant = parse_grant("GRANT USAGE ON *.* TO `app`@`%`");
+        assert_eq!(grant["privileges"], json!(["USAGE"]));
+        assert_eq!(grant["on"], "*.*");
+        assert_eq!(grant["grantOption"], false);
+
+        let grant = parse_grant("GRANT `reader`@`%`,`writer`@`%` TO `app`@`%`");
+        assert_eq!(grant["roles"], json!(["`reader`@`%`", "`writer`@`%`"]));
+        let grant = parse_grant("GRANT SELECT ON `odd db`.`t ON x` TO `app`@`%`");
+        assert_eq!(grant["on"], "`odd db`.`t ON x`");
+    }
+
+    #[test]
+    fn test_parse_latest_deadlock() {
+        let status = "\
+=====================================
+2024-05-01 10:22:40 INNODB MONITOR OUTPUT
+=====================================
+------------------------
+LATEST DETECTED DEADLOCK
+------------------------
+2024-05-01 10:22:33 140234567
+*** (1) TRANSACTION:
+TRANSACTION 12345, ACTIVE 5 sec starting index read
+mysql tables in use 1, locked 1
+LOCK WAIT 3 lock struct(s), heap size 1136, 2 row lock(s)
+MySQL thread id 10, OS thread handle 1402, query id 55 localhost root updating
+UPDATE accounts SET balance = balance - 10 WHERE id = 2
+*** (1) HOLDS THE LOCK(S):
+RECORD LOCKS space id 2 page no 4 n bits 72 index PRIMARY of table `bank`.`accounts` trx id 12345 lock_mode X locks rec but not gap
+*** (1) WAITING FOR THIS LOCK TO BE GRANTED:
+RECORD LOCKS space id 2 page no 4 n bits 72 index PRIMARY of table `bank`.`accounts` trx id 12345 lock_mode X locks rec but not gap waiting
+*** (2) TRANSACTION:
+TRANSACTION 12346, ACTIVE 4 sec starting index read
+MySQL thread id 11, OS thread handle 1403, query id 56 localhost root updating
+UPDATE accounts SET balance = balance + 10 WHERE id = 1
+*** (2) HOLDS THE LOCK(S):
+RECORD LOCKS space id 2 page no 4 n bits 72 index PRIMARY of table `bank`.`accounts` trx id 12346 lock_mode X locks rec but not gap
+*** (2) WAITING FOR THIS LOCK TO BE GRANTED:
+RECORD LOCKS space id 2 page no 4 n bits 72 index PRIMARY of table `bank`.`accounts` trx id 12346 lock_mode X locks rec but not gap waiting
+*** WE ROLL BACK TRANSACTION (2)
+------------
+TRANSACTIONS
+------------
+Trx id counter 12350
+";
+        let deadlock = parse_latest_deadlock(status);
+        assert_eq!(deadlock["time"], "2024-05-01 10:22:33 140234567");
+        assert_eq!(deadlock["rolledBack"], 2);
+        let transactions = deadlock["transactions"].as_array().unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0]["trxId"], "12345");
+        assert_eq!(transactions[0]["threadId"], 10);
+        assert_eq!(transactions[0]["query"], "UPDATE accounts SET balance = balance - 10 WHERE id = 2");
+        assert_eq!(
+            transactions[1]["waitingFor"][0],
+            json!({ "type": "record", "table": "`bank`.`accounts`", "index": "PRIMARY", "mode": "X locks rec but not gap" })
+        );
+        assert_eq!(parse_latest_deadlock("------------\nTRANSACTIONS\n"), Value::Null);
+    }
+
+    #[test]
+    fn test_like_matches() {
+        assert!(like_matches("innodb_buffer%", "Innodb_buffer_pool_reads"));
+        assert!(like_matches("%connect%", "Threads_connected"));
+        assert!(like_matches("max_connections", "MAX_CONNECTIONS"));
+        assert!(like_matches("versio_", "version"));
+        assert!(!like_matches("versio_", "version_comment"));
+        assert!(like_matches("have\\_ssl", "have_ssl"));
+        assert!(!like_matches("have\\_ssl", "haveXssl"));
+        assert!(!like_matches("uptime", "Uptime_since_flush_status"));
+    }
+}
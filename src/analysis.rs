@@ -0,0 +1,688 @@
+//! Query analysis for the `advise_indexes` tool: which columns a statement
+//! filters, joins, and sorts on, checked against the optimizer's plan and the
+//! existing indexes to suggest candidate indexes.
+//!
+//! The statement is read with a small tokenizer rather than a full parser, so
+//! the analysis is a heuristic: equality columns first, then join columns,
+//! then one range column or the ORDER BY columns, as a B-tree index can use
+//! them.
+
+use serde_json::{json, Value};
+use sqlx::{MySql, Pool, Row};
+
+use crate::db::{explain_query, is_valid_identifier};
+use crate::ddl::qualified_name;
+use crate::error::DbError;
+use crate::introspection::resolve_database;
+use crate::policy::Permissions;
+use crate::rpc::AdviseIndexesArguments;
+
+/// Prefix length for TEXT and BLOB columns, which cannot be indexed whole.
+const PREFIX_LENGTH: u32 = 255;
+
+/// Longest index name MySQL accepts.
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    /// A backtick-quoted identifier.
+    Quoted(String),
+    /// A string, number, variable, or `?` placeholder.
+    Literal(String),
+    Symbol(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Usage {
+    Equality,
+    Join,
+    Range,
+    Order,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRef {
+    pub database: Option<String>,
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnRef {
+    /// Table name or alias the column was qualified with.
+    pub qualifier: Option<String>,
+    pub column: String,
+    pub usage: Usage,
+}
+
+/// The tables a statement reads and how it uses their columns.
+#[derive(Debug, Default, PartialEq)]
+pub struct QueryShape {
+    pub tables: Vec<TableRef>,
+    pub columns: Vec<ColumnRef>,
+}
+
+pub struct IndexInfo {
+    pub name: String,
+    pub unique: bool,
+    pub columns: Vec<String>,
+}
+
+pub struct TableInfo {
+    pub table: TableRef,
+    /// Columns with their data types, in table order.
+    pub columns: Vec<(String, String)>,
+    pub indexes: Vec<IndexInfo>,
+}
+
+/// How the plan reads one table.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PlanTable {
+    pub access_type: Option<String>,
+    pub key: Option<String>,
+    pub rows_examined: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub reason: String,
+    pub statement: String,
+}
+
+pub struct IndexAdvice {
+    pub database: String,
+    pub tables: Vec<(TableInfo, PlanTable)>,
+    pub suggestions: Vec<Suggestion>,
+    pub plan: Value,
+}
+
+/// Explain `query` and suggest indexes for the tables it reads.
+pub async fn advise_indexes(args: AdviseIndexesArguments, pool: &Pool<MySql>) -> Result<IndexAdvice, DbError> {
+    let database = resolve_database(args.database, pool).await?;
+    // EXPLAIN checks the statement is explainable and that its tables exist
+    let plan = explain_query(args.query.clone(), Some(database.clone()), false, pool, Permissions::default()).await?.plan;
+    let shape = query_shape(&args.query);
+
+    let mut tables: Vec<TableInfo> = Vec::new();
+    for table in shape.tables.iter().cloned() {
+        let table_database = table.database.clone().unwrap_or_else(|| database.clone());
+        // Derived tables and CTEs are not in information_schema
+        if !is_valid_identifier(&table_database) || !is_valid_identifier(&table.name) {
+            continue;
+        }
+        let columns = sqlx::query(
+            "SELECT column_name AS column_name, data_type AS data_type
+             FROM information_schema.columns
+             WHERE table_schema = ? AND table_name = ?
+             ORDER BY ordinal_position",
+        )
+        .bind(&table_database)
+        .bind(&table.name)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| Ok((row.try_get("column_name")?, row.try_get("data_type")?)))
+        .collect::<Result<Vec<(String, String)>, sqlx::Error>>()?;
+        if columns.is_empty() {
+            continue;
+        }
+        let mut indexes: Vec<IndexInfo> = Vec::new();
+        let rows = sqlx::query(
+            "SELECT index_name AS index_name, CAST(non_unique AS SIGNED) AS non_unique, column_name AS column_name
+             FROM information_schema.statistics
+             WHERE table_schema = ? AND table_name = ?
+             ORDER BY index_name = 'PRIMARY' DESC, index_name, seq_in_index",
+        )
+        .bind(&table_database)
+        .bind(&table.name)
+        .fetch_all(pool)
+        .await?;
+        for row in rows {
+            let name: String = row.try_get("index_name")?;
+            // Functional key parts have no column
+            let column: Option<String> = row.try_get("column_name")?;
+            let column = column.unwrap_or_default();
+            match indexes.last_mut() {
+                Some(index) if index.name == name => index.columns.push(column),
+                _ => indexes.push(IndexInfo { name, unique: row.try_get::<i64, _>("non_unique")? == 0, columns: vec![column] }),
+            }
+        }
+        tables.push(TableInfo {
+            table: TableRef { database: Some(table_database), ..table },
+            columns,
+            indexes,
+        });
+    }
+
+    let plan_tables = plan_tables(&plan);
+    let suggestions = suggest(&shape, &tables, &plan_tables)?;
+    let tables = tables
+        .into_iter()
+        .map(|info| {
+            let reference = info.table.alias.as_deref().unwrap_or(&info.table.name);
+            let plan = plan_tables.iter().find(|(name, _)| name == reference).map(|(_, plan)| plan.clone());
+            (info, plan.unwrap_or_default())
+        })
+        .collect();
+    Ok(IndexAdvice { database, tables, suggestions, plan })
+}
+
+/// One suggestion per table whose filter, join, and sort columns no existing
+/// index covers as a leftmost prefix.
+pub fn suggest(
+    shape: &QueryShape,
+    tables: &[TableInfo],
+    plan: &[(String, PlanTable)],
+) -> Result<Vec<Suggestion>, DbError> {
+    let mut suggestions = Vec::new();
+    for (index, info) in tables.iter().enumerate() {
+        let mut equality: Vec<String> = Vec::new();
+        let mut join: Vec<String> = Vec::new();
+        let mut range: Vec<String> = Vec::new();
+        let mut order: Vec<String> = Vec::new();
+        // ORDER BY helps only when every sort column is on this table
+        let mut order_elsewhere = false;
+        for column in &shape.columns {
+            let owner = resolve_column(column, tables);
+            if column.usage == Usage::Order && owner.as_ref().is_none_or(|(owner, _)| *owner != index) {
+                order_elsewhere = true;
+            }
+            let Some((_, name)) = owner.filter(|(owner, _)| *owner == index) else {
+                continue;
+            };
+            let list = match column.usage {
+                Usage::Equality => &mut equality,
+                Usage::Join => &mut join,
+                Usage::Range => &mut range,
+                Usage::Order => &mut order,
+            };
+            if !list.contains(&name) {
+                list.push(name);
+            }
+        }
+        join.retain(|c| !equality.contains(c));
+        let leading: Vec<String> = equality.iter().chain(&join).cloned().collect();
+        let sorted = !order_elsewhere && !order.is_empty() && !range.iter().any(|c| !leading.contains(c));
+        let tail: Vec<String> = match range.iter().find(|c| !leading.contains(c)) {
+            Some(range) => vec![range.clone()],
+            None if sorted => order.iter().filter(|c| !leading.contains(c)).cloned().collect(),
+            None => Vec::new(),
+        };
+        if leading.is_empty() && tail.is_empty() {
+            continue;
+        }
+        if info.indexes.iter().any(|index| covers(&index.columns, &leading, &tail)) {
+            continue;
+        }
+
+        let reference = info.table.alias.as_deref().unwrap_or(&info.table.name);
+        let mut reasons = Vec::new();
+        if let Some((_, plan)) = plan.iter().find(|(name, _)| name == reference) {
+            let rows = plan.rows_examined.map_or(String::new(), |rows| format!(" (~{rows} rows examined)"));
+            match plan.access_type.as_deref() {
+                Some("ALL") => reasons.push(format!("full table scan{rows}")),
+                Some("index") => reasons.push(format!("full index scan{rows}")),
+                _ => {}
+            }
+        }
+        for (what, columns) in [("equality on", &equality), ("join on", &join), ("range on", &range), ("sort by", &order)] {
+            if !columns.is_empty() && (what != "sort by" || sorted) {
+                reasons.push(format!("{what} {}", columns.join(", ")));
+            }
+        }
+        let columns: Vec<String> = leading.into_iter().chain(tail).collect();
+        suggestions.push(Suggestion {
+            table: info.table.name.clone(),
+            reason: reasons.join("; "),
+            statement: create_index_sql(info, &columns)?,
+            columns,
+        });
+    }
+    Ok(suggestions)
+}
+
+/// The table (by position in `tables`) and real name of a referenced column.
+fn resolve_column(column: &ColumnRef, tables: &[TableInfo]) -> Option<(usize, String)> {
+    let find = |info: &TableInfo| {
+        info.columns.iter().find(|(name, _)| name.eq_ignore_ascii_case(&column.column)).map(|(name, _)| name.clone())
+    };
+    match &column.qualifier {
+        Some(qualifier) => tables
+            .iter()
+            .position(|info| match &info.table.alias {
+                Some(alias) => alias.eq_ignore_ascii_case(qualifier),
+                None => info.table.name.eq_ignore_ascii_case(qualifier),
+            })
+            .and_then(|i| Some((i, find(&tables[i])?))),
+        None => {
+            let mut owners = tables.iter().enumerate().filter_map(|(i, info)| Some((i, find(info)?)));
+            // An unqualified name in several tables is ambiguous
+            match (owners.next(), owners.next()) {
+                (Some(owner), None) => Some(owner),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Whether `index` starts with the `leading` columns in any order, followed
+/// by the `tail` columns in order.
+pub fn covers(index: &[String], leading: &[String], tail: &[String]) -> bool {
+    index.len() >= leading.len() + tail.len()
+        && leading.iter().all(|c| index[..leading.len()].iter().any(|i| i.eq_ignore_ascii_case(c)))
+        && tail.iter().zip(&index[leading.len()..]).all(|(c, i)| i.eq_ignore_ascii_case(c))
+}
+
+fn create_index_sql(info: &TableInfo, columns: &[String]) -> Result<String, DbError> {
+    let mut name = format!("idx_{}_{}", info.table.name, columns.join("_"));
+    if name.len() > MAX_NAME_LENGTH {
+        let mut end = MAX_NAME_LENGTH;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+    }
+    let parts = columns
+        .iter()
+        .map(|column| {
+            let data_type = info.columns.iter().find(|(name, _)| name == column).map_or("", |(_, t)| t.as_str());
+            let quoted = qualified_name(None, column)?;
+            Ok(if data_type.ends_with("text") || data_type.ends_with("blob") {
+                format!("{quoted}({PREFIX_LENGTH})")
+            } else {
+                quoted
+            })
+        })
+        .collect::<Result<Vec<_>, DbError>>()?;
+    Ok(format!(
+        "CREATE INDEX {} ON {} ({})",
+        qualified_name(None, &name)?,
+        qualified_name(info.table.database.as_deref(), &info.table.name)?,
+        parts.join(", ")
+    ))
+}
+
+/// Every `table` node of an `EXPLAIN FORMAT=JSON` plan, by the name the
+/// query refers to it with (its alias when it has one).
+pub fn plan_tables(plan: &Value) -> Vec<(String, PlanTable)> {
+    let mut tables = Vec::new();
+    let mut stack = vec![plan];
+    while let Some(node) = stack.pop() {
+        match node {
+            Value::Object(map) => {
+                if let Some(table) = map.get("table").filter(|t| t.is_object()) {
+                    if let Some(name) = table["table_name"].as_str() {
+                        tables.push((
+                            name.to_string(),
+                            PlanTable {
+                                access_type: table["access_type"].as_str().map(str::to_string),
+                                key: table["key"].as_str().map(str::to_string),
+                                rows_examined: table["rows_examined_per_scan"].as_u64(),
+                            },
+                        ));
+                    }
+                }
+                stack.extend(map.values());
+            }
+            Value::Array(items) => stack.extend(items),
+            _ => {}
+        }
+    }
+    tables.reverse();
+    tables
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Clause {
+    Tables,
+    Filter,
+    JoinCondition,
+    Order,
+    Other,
+}
+
+/// Words that end a table reference or cannot be a column or alias.
+const KEYWORDS: &[&str] = &[
+    "ALL", "AND", "AS", "ASC", "BETWEEN", "BY", "CASE", "CROSS", "DELETE", "DESC", "DISTINCT", "DIV", "ELSE", "END",
+    "ESCAPE", "EXCEPT", "EXISTS", "FALSE", "FOR", "FORCE", "FROM", "FULL", "GROUP", "HAVING", "IGNORE", "IN", "INDEX",
+    "INNER", "INTERSECT", "INTERVAL", "INTO", "IS", "JOIN", "KEY", "LATERAL", "LEFT", "LIKE", "LIMIT", "LOCK", "MOD",
+    "NATURAL", "NOT", "NULL", "OFFSET", "ON", "OR", "ORDER", "OUTER", "PARTITION", "REGEXP", "RIGHT", "RLIKE", "ROLLUP",
+    "SELECT", "SET", "STRAIGHT_JOIN", "THEN", "TRUE", "UNION", "UPDATE", "USE", "USING", "VALUES", "WHEN", "WHERE",
+    "WINDOW", "WITH", "XOR",
+];
+
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+/// The tables a statement reads and the columns it compares, joins on, and
+/// sorts by.
+pub fn query_shape(query: &str) -> QueryShape {
+    let tokens = tokenize(query);
+    let mut shape = QueryShape::default();
+    let mut clause = Clause::Other;
+    // Clause to return to when each open parenthesis closes
+    let mut outer: Vec<Clause> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let word = match &tokens[i] {
+            Token::Word(word) => Some(word.to_uppercase()),
+            _ => None,
+        };
+        let next_is = |keyword: &str| matches!(tokens.get(i + 1), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+        match (word.as_deref(), &tokens[i]) {
+            (Some("FROM" | "JOIN" | "STRAIGHT_JOIN" | "UPDATE"), _) => {
+                clause = Clause::Tables;
+                i = table_ref(&tokens, i + 1, &mut shape);
+                continue;
+            }
+            (_, Token::Symbol(s)) if s == "," && clause == Clause::Tables => {
+                i = table_ref(&tokens, i + 1, &mut shape);
+                continue;
+            }
+            (Some("WHERE"), _) => clause = Clause::Filter,
+            (Some("ON"), _) => clause = Clause::JoinCondition,
+            (Some("ORDER" | "GROUP"), _) if next_is("BY") => {
+                clause = Clause::Order;
+                i += 2;
+                continue;
+            }
+            (Some("SELECT" | "SET" | "HAVING" | "LIMIT" | "UNION" | "VALUES" | "USING" | "INTO" | "WINDOW"), _) => {
+                clause = Clause::Other
+            }
+            (_, Token::Symbol(s)) if s == "(" => outer.push(clause),
+            (_, Token::Symbol(s)) if s == ")" => clause = outer.pop().unwrap_or(Clause::Other),
+            (Some(w), _) if is_keyword(w) => {}
+            (_, Token::Word(_) | Token::Quoted(_)) if matches!(clause, Clause::Filter | Clause::JoinCondition | Clause::Order) => {
+                let (qualifier, column, end) = identifier_chain(&tokens, i);
+                // Function calls are not columns
+                if !matches!(tokens.get(end), Some(Token::Symbol(s)) if s == "(") {
+                    let usage = if clause == Clause::Order {
+                        Some(Usage::Order)
+                    } else {
+                        predicate_usage(i.checked_sub(1).and_then(|p| tokens.get(p)), &tokens[end.min(tokens.len())..])
+                            .map(|usage| if usage == Usage::Equality && clause == Clause::JoinCondition { Usage::Join } else { usage })
+                    };
+                    if let Some(usage) = usage {
+                        shape.columns.push(ColumnRef { qualifier, column, usage });
+                    }
+                }
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    shape
+}
+
+/// `a`, `t.a`, or `db.t.a` starting at `start`: the qualifier, the column,
+/// and the index after the chain.
+fn identifier_chain(tokens: &[Token], start: usize) -> (Option<String>, String, usize) {
+    let mut parts = Vec::new();
+    let mut i = start;
+    while let Some(Token::Word(part) | Token::Quoted(part)) = tokens.get(i) {
+        parts.push(part.clone());
+        i += 1;
+        if !matches!(tokens.get(i), Some(Token::Symbol(s)) if s == ".") {
+            break;
+        }
+        i += 1;
+    }
+    let column = parts.pop().unwrap_or_default();
+    (parts.pop(), column, i)
+}
+
+/// How a column in a WHERE or ON condition can use an index, from the
+/// operator beside it.
+fn predicate_usage(previous: Option<&Token>, rest: &[Token]) -> Option<Usage> {
+    let upper = |token: Option<&Token>| match token {
+        Some(Token::Word(w)) => w.to_uppercase(),
+        Some(Token::Symbol(s)) => s.clone(),
+        _ => String::new(),
+    };
+    let next = upper(rest.first());
+    match next.as_str() {
+        "=" | "<=>" | "IN" | "IS" => return Some(Usage::Equality),
+        "<" | ">" | "<=" | ">=" | "BETWEEN" => return Some(Usage::Range),
+        // A leading wildcard cannot use an index
+        "LIKE" => {
+            return match rest.get(1) {
+                Some(Token::Literal(pattern)) if pattern.starts_with(['%', '_']) => None,
+                _ => Some(Usage::Range),
+            }
+        }
+        _ => {}
+    }
+    match upper(previous).as_str() {
+        "=" | "<=>" => Some(Usage::Equality),
+        "<" | ">" | "<=" | ">=" => Some(Usage::Range),
+        _ => None,
+    }
+}
+
+/// Read a table name with an optional alias at `start`, returning the index
+/// after it. Derived tables are skipped; their subquery is scanned as usual.
+fn table_ref(tokens: &[Token], start: usize, shape: &mut QueryShape) -> usize {
+    let is_name = |token: Option<&Token>| match token {
+        Some(Token::Word(w)) => !is_keyword(w),
+        Some(Token::Quoted(_)) => true,
+        _ => false,
+    };
+    if !is_name(tokens.get(start)) {
+        return start;
+    }
+    let (database, name, mut i) = identifier_chain(tokens, start);
+    if matches!(tokens.get(i), Some(Token::Symbol(s)) if s == "(") {
+        return start;
+    }
+    if matches!(tokens.get(i), Some(Token::Word(w)) if w.eq_ignore_ascii_case("AS")) {
+        i += 1;
+    }
+    let alias = match tokens.get(i) {
+        Some(Token::Word(alias) | Token::Quoted(alias)) if is_name(tokens.get(i)) => {
+            i += 1;
+            Some(alias.clone())
+        }
+        _ => None,
+    };
+    shape.tables.push(TableRef { database, name, alias });
+    i
+}
+
+fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' || (c == '-' && chars.get(i + 1) == Some(&'-')) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if matches!(c, '\'' | '"' | '`') {
+            let mut value = String::new();
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '\\' && c != '`' {
+                    value.extend(chars.get(i + 1));
+                    i += 2;
+                    continue;
+                }
+                if chars[i] == c {
+                    // A doubled quote is a literal quote
+                    if chars.get(i + 1) == Some(&c) {
+                        value.push(c);
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                value.push(chars[i]);
+                i += 1;
+            }
+            i += 1;
+            tokens.push(if c == '`' { Token::Quoted(value) } else { Token::Literal(value) });
+        } else if c.is_alphanumeric() || c == '_' || c == '$' || c == '@' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$' | '@')) {
+                i += 1;
+            }
+            // Decimals such as 1.5
+            if c.is_ascii_digit() && chars.get(i) == Some(&'.') && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                    i += 1;
+                }
+            }
+            let word: String = chars[start..i].iter().collect();
+            tokens.push(if c.is_ascii_digit() || c == '@' { Token::Literal(word) } else { Token::Word(word) });
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 3)].iter().collect();
+            let symbol = ["<=>", "<=", ">=", "<>", "!=", ":=", "||", "&&"]
+                .into_iter()
+                .find(|s| rest.starts_with(s))
+                .map_or(c.to_string(), str::to_string);
+            i += symbol.chars().count();
+            tokens.push(if symbol == "?" { Token::Literal(symbol) } else { Token::Symbol(symbol) });
+        }
+    }
+    tokens
+}
+
+/// The advice as structured content.
+pub fn advice_json(advice: &IndexAdvice) -> Value {
+    let tables: Vec<Value> = advice
+        .tables
+        .iter()
+        .map(|(info, plan)| {
+            let indexes: Vec<Value> = info
+                .indexes
+                .iter()
+                .map(|index| json!({ "name": index.name, "unique": index.unique, "columns": index.columns }))
+                .collect();
+            json!({
+                "table": info.table.name,
+                "alias": info.table.alias,
+                "accessType": plan.access_type,
+                "key": plan.key,
+                "rowsExamined": plan.rows_examined,
+                "indexes": indexes,
+            })
+        })
+        .collect();
+    let suggestions: Vec<Value> = advice
+        .suggestions
+        .iter()
+        .map(|s| json!({ "table": s.table, "columns": s.columns, "reason": s.reason, "statement": s.statement }))
+        .collect();
+    json!({
+        "database": advice.database,
+        "tables": tables,
+        "suggestions": suggestions,
+        "plan": advice.plan,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str, alias: Option<&str>, columns: &[&str], indexes: &[(&str, &[&str])]) -> TableInfo {
+        TableInfo {
+            table: TableRef { database: Some("shop".into()), name: name.into(), alias: alias.map(str::to_string) },
+            columns: columns.iter().map(|c| (c.to_string(), if *c == "note" { "text" } else { "int" }.to_string())).collect(),
+            indexes: indexes
+                .iter()
+                .map(|(name, columns)| IndexInfo {
+                    name: name.to_string(),
+                    unique: *name == "PRIMARY",
+                    columns: columns.iter().map(|c| c.to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_query_shape() {
+        let shape = query_shape(
+            "SELECT o.id, COUNT(*) FROM shop.orders AS o
+             JOIN customers c ON c.id = o.customer_id
+             WHERE o.status = 'paid' AND o.created_at >= ? AND c.name LIKE '%smith'
+               AND o.id IN (SELECT order_id FROM refunds r WHERE r.amount > 10)
+             ORDER BY o.created_at DESC LIMIT 10",
+        );
+        assert_eq!(
+            shape.tables,
+            vec![
+                TableRef { database: Some("shop".into()), name: "orders".into(), alias: Some("o".into()) },
+                TableRef { database: None, name: "customers".into(), alias: Some("c".into()) },
+                TableRef { database: None, name: "refunds".into(), alias: Some("r".into()) },
+            ]
+        );
+        let columns: Vec<(Option<&str>, &str, Usage)> =
+            shape.columns.iter().map(|c| (c.qualifier.as_deref(), c.column.as_str(), c.usage)).collect();
+        assert_eq!(
+            columns,
+            vec![
+                (Some("c"), "id", Usage::Join),
+                (Some("o"), "customer_id", Usage::Join),
+                (Some("o"), "status", Usage::Equality),
+                (Some("o"), "created_at", Usage::Range),
+                (Some("o"), "id", Usage::Equality),
+                (Some("r"), "amount", Usage::Range),
+                (Some("o"), "created_at", Usage::Order),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        let shape = query_shape(
+            "SELECT * FROM orders o JOIN customers c ON c.id = o.customer_id
+             WHERE o.status = ? AND note LIKE 'x%' ORDER BY o.created_at",
+        );
+        let tables = vec![
+            table("orders", Some("o"), &["id", "customer_id", "status", "created_at", "note"], &[("PRIMARY", &["id"])]),
+            table("customers", Some("c"), &["id", "name"], &[("PRIMARY", &["id"])]),
+        ];
+        let plan = plan_tables(&json!({
+            "query_block": {
+                "nested_loop": [
+                    { "table": { "table_name": "o", "access_type": "ALL", "rows_examined_per_scan": 5000 } },
+                    { "table": { "table_name": "c", "access_type": "eq_ref", "key": "PRIMARY" } }
+                ]
+            }
+        }));
+        assert_eq!(plan[0].0, "o");
+        let suggestions = suggest(&shape, &tables, &plan).unwrap();
+        assert_eq!(
+            suggestions,
+            vec![Suggestion {
+                table: "orders".into(),
+                columns: vec!["status".into(), "customer_id".into(), "note".into()],
+                reason: "full table scan (~5000 rows examined); equality on status; join on customer_id; range on note".into(),
+                statement: "CREATE INDEX `idx_orders_status_customer_id_note` ON `shop`.`orders` (`status`, `customer_id`, `note`(255))".into(),
+            }]
+        );
+
+        // Already covered by an index with the same leading columns
+        let tables = vec![table("orders", None, &["id", "status", "created_at"], &[("by_status", &["status", "created_at", "id"])])];
+        let shape = query_shape("SELECT * FROM orders WHERE status = 'new' ORDER BY created_at");
+        assert!(suggest(&shape, &tables, &[]).unwrap().is_empty());
+        assert!(covers(&["b".into(), "a".into(), "c".into()], &["a".into(), "b".into()], &["c".into()]));
+        assert!(!covers(&["a".into(), "c".into()], &["a".into(), "b".into()], &[]));
+    }
+}
@@ -0,0 +1,160 @@
+//! Append-only audit log of tool calls, one JSON object per line, written to
+//! the file `audit_log` points to. Lines are hash-chained: each carries the
+//! SHA-256 of the previous line as `prevHash` and its own as `hash`, computed
+//! over the line up to (not including) the trailing `,"hash":"..."` member,
+//! so a removed or edited line breaks the chain from there on.
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+
+/// `prevHash` of the very first line.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Strings in logged arguments are cut to this many characters.
+const MAX_STRING_CHARS: usize = 256;
+
+/// Arrays in logged arguments (such as `insert` rows) keep this many items.
+const MAX_ARRAY_ITEMS: usize = 20;
+
+/// The log path and hash of its last line, loaded from the file on first use.
+/// Also serializes appends across sessions so the chain stays linear.
+static CHAIN: Mutex<Option<(PathBuf, String)>> = Mutex::const_new(None);
+
+/// Append `entry` with a timestamp and its chain hashes, rotating the file
+/// first when the line would take it past `audit_log_max_bytes`.
+pub async fn append(config: &Config, mut entry: Value) -> std::io::Result<()> {
+    let Some(path) = config.audit_log.as_deref().map(PathBuf::from) else {
+        return Ok(());
+    };
+    let mut chain = CHAIN.lock().await;
+    let prev_hash = match chain.as_ref() {
+        Some((chain_path, hash)) if *chain_path == path => hash.clone(),
+        _ => last_hash(&path).await?,
+    };
+
+    entry["timestamp"] = json!(humantime::format_rfc3339_millis(SystemTime::now()).to_string());
+    entry["prevHash"] = json!(prev_hash);
+    let body = entry.to_string();
+    let hash = format!("{:x}", Sha256::digest(body.as_bytes()));
+    let line = format!("{},\"hash\":\"{hash}\"}}\n", &body[..body.len() - 1]);
+
+    let size = match tokio::fs::metadata(&path).await {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e),
+    };
+    if size > 0 && size + line.len() as u64 > config.audit_log_max_bytes {
+        rotate(&path, config.audit_log_max_files).await?;
+    }
+    let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await?;
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await?;
+
+    *chain = Some((path, hash));
+    Ok(())
+}
+
+/// Tool arguments with long strings and arrays shortened, so a bulk insert
+/// does not write megabytes per line.
+pub fn summarize_arguments(value: &Value) -> Value {
+    match value {
+        Value::String(s) if s.chars().count() > MAX_STRING_CHARS => {
+            let head: String = s.chars().take(MAX_STRING_CHARS).collect();
+            json!(format!("{head}... ({} chars)", s.chars().count()))
+        }
+        Value::Array(items) => {
+            let mut summary: Vec<Value> = items.iter().take(MAX_ARRAY_ITEMS).map(summarize_arguments).collect();
+            if items.len() > MAX_ARRAY_ITEMS {
+                summary.push(json!(format!("... {} more", items.len() - MAX_ARRAY_ITEMS)));
+            }
+            Value::Array(summary)
+        }
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), summarize_arguments(v))).collect()),
+        other => other.clone(),
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+/// Shift `<path>.N` to `<path>.N+1` (dropping the oldest) and move the
+/// current file to `<path>.1`.
+async fn rotate(path: &Path, max_files: usize) -> std::io::Result<()> {
+    if max_files == 0 {
+        return tokio::fs::remove_file(path).await;
+    }
+    for index in (1..max_files).rev() {
+        match tokio::fs::rename(rotated_path(path, index), rotated_path(path, index + 1)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    tokio::fs::rename(path, rotated_path(path, 1)).await
+}
+
+/// Hash of the last line written, so the chain continues across restarts
+/// and rotations.
+async fn last_hash(path: &Path) -> std::io::Result<String> {
+    for candidate in [path.to_path_buf(), rotated_path(path, 1)] {
+        let text = match tokio::fs::read_to_string(&candidate).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let last = text.lines().rev().find(|line| !line.trim().is_empty());
+        if let Some(hash) = last.and_then(|line| serde_json::from_str::<Value>(line).ok()).and_then(|v| v["hash"].as_str().map(String::from)) {
+            return Ok(hash);
+        }
+    }
+    Ok(GENESIS_HASH.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_audit_chain_and_rotation() {
+        let dir = std::env::temp_dir().join(format!("mcp-audit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log");
+        let mut config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"").unwrap();
+        config.audit_log = Some(path.to_string_lossy().into_owned());
+        config.audit_log_max_bytes = 400;
+        config.audit_log_max_files = 2;
+
+        for i in 0..6 {
+            append(&config, json!({ "tool": "query", "statements": [format!("SELECT {i}")] })).await.unwrap();
+        }
+        let mut lines = Vec::new();
+        for file in [rotated_path(&path, 2), rotated_path(&path, 1), path.clone()] {
+            lines.extend(std::fs::read_to_string(&file).unwrap().lines().map(String::from));
+        }
+        assert!(lines.len() < 6, "the oldest rotated file is dropped");
+        for pair in lines.windows(2) {
+            let (prev, next): (Value, Value) = (serde_json::from_str(&pair[0]).unwrap(), serde_json::from_str(&pair[1]).unwrap());
+            assert_eq!(next["prevHash"], prev["hash"]);
+        }
+        for line in &lines {
+            let value: Value = serde_json::from_str(line).unwrap();
+            let body = format!("{}}}", &line[..line.rfind(",\"hash\"").unwrap()]);
+            assert_eq!(value["hash"], format!("{:x}", Sha256::digest(body.as_bytes())));
+        }
+        let last: Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+        assert_eq!(last["statements"][0], "SELECT 5");
+
+        let summary = summarize_arguments(&json!({ "rows": vec![json!({ "a": 1 }); 25], "query": "x".repeat(300) }));
+        assert_eq!(summary["rows"].as_array().unwrap().len(), MAX_ARRAY_ITEMS + 1);
+        assert!(summary["query"].as_str().unwrap().ends_with("(300 chars)"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+//! Change data capture for the `poll_changes` tool: reads the binary log with
+//! `SHOW BINLOG EVENTS` from a cursor and reports which of the `cdc_tables`
+//! committed transactions inserted, updated, or deleted rows in.
+//!
+//! With `binlog_format = ROW` every data change is a row event naming its
+//! table through the preceding `Table_map` event. `SHOW BINLOG EVENTS` does not
+//! decode row images, so changes say which table changed and how, and the
+//! caller reads the current rows with `select`.
+
+use serde_json::{json, Value};
+use sqlx::{Executor, MySql, Pool, Row};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::record_statement;
+use crate::ddl::quote_literal;
+use crate::error::DbError;
+use crate::rpc::PollChangesArguments;
+
+/// Events fetched per `SHOW BINLOG EVENTS` statement.
+const EVENT_BATCH: usize = 1000;
+
+/// Events scanned per poll before stopping at the next transaction boundary.
+const MAX_EVENTS_PER_POLL: usize = 20_000;
+
+/// Most changes one poll may return.
+pub const MAX_CHANGES: usize = 5000;
+
+/// First event position in every binary log file, after the magic header.
+const FIRST_EVENT_POS: u64 = 4;
+
+/// A row event on a watched table. One event can carry several rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub table: String,
+    pub operation: &'static str,
+    /// `file:pos` of the event.
+    pub position: String,
+}
+
+pub struct ChangesResult {
+    pub changes: Vec<Change>,
+    /// Resume from here on the next poll.
+    pub cursor: String,
+    /// More events were waiting when the poll stopped.
+    pub has_more: bool,
+    pub binlog_format: Option<String>,
+}
+
+/// Read committed changes after `cursor`, or from the current end of the
+/// binary log when no cursor is given. The returned cursor always sits on a
+/// transaction boundary, so every poll sees whole transactions.
+pub async fn poll_changes(
+    args: PollChangesArguments,
+    watched: &[String],
+    pool: &Pool<MySql>,
+) -> Result<ChangesResult, DbError> {
+    let limit = args.limit.clamp(1, MAX_CHANGES);
+    let logs = binary_logs(pool).await?;
+    let binlog_format: Option<String> = sqlx::query_scalar("SELECT @@GLOBAL.binlog_format").fetch_one(pool).await?;
+
+    let (mut file, mut pos) = match &args.cursor {
+        Some(cursor) => parse_cursor(cursor)?,
+        None => {
+            let (file, size) = logs.last().cloned().ok_or_else(|| DbError::NotFound("No binary logs".to_string()))?;
+            return Ok(ChangesResult { changes: Vec::new(), cursor: format_cursor(&file, size), has_more: false, binlog_format });
+        }
+    };
+    let mut file_index = logs.iter().position(|(name, _)| *name == file).ok_or_else(|| {
+        DbError::InvalidInput(format!(
+            "Binary log {file} no longer exists (purged or rotated away); poll without a cursor to start again"
+        ))
+    })?;
+
+    let mut scanner = Scanner::new(watched);
+    let mut committed = format_cursor(&file, pos);
+    let mut scanned = 0;
+    let has_more = loop {
+        let statement = format!(
+            "SHOW BINLOG EVENTS IN {} FROM {pos} LIMIT {EVENT_BATCH}",
+            quote_literal(&file)
+        );
+        record_statement(&statement);
+        let rows = pool.fetch_all(statement.as_str()).await?;
+        for row in &rows {
+            let event_type: String = row.try_get("Event_type")?;
+            let info: String = row.try_get::<Option<String>, _>("Info")?.unwrap_or_default();
+            let start: u64 = row.try_get("Pos")?;
+            let end: u64 = row.try_get("End_log_pos")?;
+            pos = end;
+            if scanner.event(&event_type, &info, &format_cursor(&file, start)) {
+                committed = format_cursor(&file, end);
+            }
+        }
+        scanned += rows.len();
+        let at_boundary = scanner.pending.is_empty() && !scanner.in_transaction;
+        if at_boundary && (scanner.changes.len() >= limit || scanned >= MAX_EVENTS_PER_POLL) {
+            break !rows.is_empty();
+        }
+        if rows.len() < EVENT_BATCH {
+            if file_index + 1 == logs.len() {
+                break false;
+            }
+            // The rest of the log continues in the next file
+            file_index += 1;
+            file = logs[file_index].0.clone();
+            pos = FIRST_EVENT_POS;
+            if at_boundary {
+                committed = format_cursor(&file, pos);
+            }
+        }
+    };
+
+    // Whole transactions are kept, so a poll can go a little past `limit`
+    Ok(ChangesResult { changes: scanner.changes, cursor: committed, has_more, binlog_format })
+}
+
+/// `(file, size)` of each binary log, oldest first.
+async fn binary_logs(pool: &Pool<MySql>) -> Result<Vec<(String, u64)>, DbError> {
+    match pool.fetch_all("SHOW BINARY LOGS").await {
+        Ok(rows) => rows
+            .iter()
+            .map(|row| Ok((row.try_get::<String, _>(0)?, row.try_get::<u64, _>(1)?)))
+            .collect::<Result<Vec<_>, sqlx::Error>>()
+            .map_err(DbError::from),
+        // ER_NO_BINARY_LOGGING
+        Err(sqlx::Error::Database(e))
+            if e.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>().map(|e| e.number()) == Some(1381) =>
+        {
+            Err(DbError::InvalidInput("Binary logging is disabled on this server (log_bin = OFF)".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Turns a stream of binlog events into committed changes on watched tables.
+pub struct Scanner<'a> {
+    watched: &'a [String],
+    /// `table_id` to `db.table`, from `Table_map` events.
+    tables: HashMap<u64, String>,
+    /// Changes of the open transaction, published when it commits.
+    pending: Vec<Change>,
+    in_transaction: bool,
+    pub changes: Vec<Change>,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(watched: &'a [String]) -> Self {
+        Scanner { watched, tables: HashMap::new(), pending: Vec::new(), in_transaction: false, changes: Vec::new() }
+    }
+
+    /// Feed one event. Returns true when it ended a transaction, so the
+    /// position after it is a safe cursor.
+    pub fn event(&mut self, event_type: &str, info: &str, position: &str) -> bool {
+        match event_type {
+            "Table_map" => {
+                if let Some((id, table)) = parse_table_map(info) {
+                    self.tables.insert(id, table);
+                }
+                false
+            }
+            "Query" => {
+                let statement = info.trim().to_uppercase();
+                if statement == "BEGIN" || statement.starts_with("XA START") {
+                    self.in_transaction = true;
+                    false
+                } else if statement == "COMMIT" || statement == "ROLLBACK" {
+                    self.finish(statement == "ROLLBACK")
+                } else if statement.starts_with("XA END") {
+                    false
+                } else {
+                    // DDL and other statements outside a transaction commit on their own
+                    !self.in_transaction && self.pending.is_empty()
+                }
+            }
+            "Xid" | "XA_prepare" => self.finish(false),
+            other => {
+                let operation = if other.starts_with("Write_rows") {
+                    "insert"
+                } else if other.starts_with("Update_rows") || other.starts_with("Partial_update_rows") {
+                    "update"
+                } else if other.starts_with("Delete_rows") {
+                    "delete"
+                } else {
+                    return false;
+                };
+                let table = info
+                    .split_whitespace()
+                    .skip_while(|word| *word != "table_id:")
+                    .nth(1)
+                    .and_then(|id| id.parse().ok())
+                    .and_then(|id: u64| self.tables.get(&id));
+                if let Some(table) = table.filter(|table| is_watched(self.watched, table)) {
+                    self.pending.push(Change { table: table.clone(), operation, position: position.to_string() });
+                }
+                false
+            }
+        }
+    }
+
+    fn finish(&mut self, rolled_back: bool) -> bool {
+        if !rolled_back {
+            self.changes.append(&mut self.pending);
+        }
+        self.pending.clear();
+        self.tables.clear();
+        self.in_transaction = false;
+        true
+    }
+}
+
+/// `table_id: 108 (shop.orders)` to `(108, "shop.orders")`.
+pub fn parse_table_map(info: &str) -> Option<(u64, String)> {
+    let rest = info.strip_prefix("table_id:")?.trim_start();
+    let (id, rest) = rest.split_once(' ')?;
+    let table = rest.trim().strip_prefix('(')?.strip_suffix(')')?;
+    Some((id.parse().ok()?, table.to_string()))
+}
+
+/// Whether `db.table` matches a `db.table` or `db.*` entry.
+pub fn is_watched(watched: &[String], table: &str) -> bool {
+    watched.iter().any(|pattern| match pattern.strip_suffix(".*") {
+        Some(database) => table.split_once('.').is_some_and(|(db, _)| db == database),
+        None => pattern == table,
+    })
+}
+
+fn format_cursor(file: &str, pos: u64) -> String {
+    format!("{file}:{pos}")
+}
+
+fn parse_cursor(cursor: &str) -> Result<(String, u64), DbError> {
+    let invalid = || DbError::InvalidInput(format!("Invalid cursor: {cursor}"));
+    let (file, pos) = cursor.rsplit_once(':').ok_or_else(invalid)?;
+    let valid_file = !file.is_empty() && file.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if !valid_file {
+        return Err(invalid());
+    }
+    Ok((file.to_string(), pos.parse().map_err(|_| invalid())?))
+}
+
+/// Change counts per table and operation, for the summary.
+pub fn tally(changes: &[Change]) -> Value {
+    let mut tables: BTreeMap<&str, BTreeMap<&str, u64>> = BTreeMap::new();
+    for change in changes {
+        *tables.entry(&change.table).or_default().entry(change.operation).or_default() += 1;
+    }
+    json!(tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanner() {
+        let watched = vec!["shop.orders".to_string(), "crm.*".to_string()];
+        let mut scanner = Scanner::new(&watched);
+        let events = [
+            ("Query", "BEGIN"),
+            ("Table_map", "table_id: 90 (shop.orders)"),
+            ("Write_rows", "table_id: 90 flags: STMT_END_F"),
+            ("Table_map", "table_id: 91 (shop.audit)"),
+            ("Write_rows", "table_id: 91 flags: STMT_END_F"),
+            ("Xid", "COMMIT /* xid=12 */"),
+            ("Query", "BEGIN"),
+            ("Table_map", "table_id: 92 (crm.contacts)"),
+            ("Delete_rows_v1", "table_id: 92 flags: STMT_END_F"),
+            ("Query", "ROLLBACK"),
+            ("Query", "BEGIN"),
+            ("Table_map", "table_id: 92 (crm.contacts)"),
+            ("Update_rows", "table_id: 92 flags: STMT_END_F"),
+        ];
+        let boundaries: Vec<bool> = events
+            .iter()
+            .enumerate()
+            .map(|(i, (event_type, info))| scanner.event(event_type, info, &format!("binlog.000001:{}", 100 + i)))
+            .collect();
+        assert_eq!(boundaries.iter().filter(|b| **b).count(), 2);
+        // The last transaction has not committed yet
+        assert_eq!(
+            scanner.changes,
+            vec![Change { table: "shop.orders".into(), operation: "insert", position: "binlog.000001:102".into() }]
+        );
+        assert!(scanner.in_transaction);
+        assert_eq!(scanner.pending.len(), 1);
+        assert_eq!(tally(&scanner.changes), json!({ "shop.orders": { "insert": 1 } }));
+    }
+
+    #[test]
+    fn test_parse_helpers() {
+        assert_eq!(parse_table_map("table_id: 108 (shop.orders)"), Some((108, "shop.orders".into())));
+        assert_eq!(parse_table_map("something else"), None);
+        assert_eq!(parse_cursor("binlog.000042:1337").unwrap(), ("binlog.000042".into(), 1337));
+        assert!(parse_cursor("binlog.000042").is_err());
+        assert!(parse_cursor("x' OR 1:4").is_err());
+        assert!(is_watched(&["crm.*".into()], "crm.contacts"));
+        assert!(!is_watched(&["crm.*".into()], "crmx.contacts"));
+        assert!(!is_watched(&["shop.orders".into()], "shop.order_items"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Two-step confirmation of high-impact tool calls, enabled with
+//! `require_confirmation`. A call that drops or truncates, deletes without
+//! pinning a unique key, or would change more than `confirmation_threshold`
+//! rows is refused with a one-time token; repeating the same call with
+//! `confirmation_token` set to it runs it.
+
+use serde_json::{Map, Value};
+use sqlx::{MySql, Pool};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::db::{count_matching, unique_keys};
+use crate::error::DbError;
+use crate::policy;
+use crate::transaction::{Connection, TransactionManager};
+
+/// How long an issued token can be redeemed.
+pub const TOKEN_TTL: Duration = Duration::from_secs(300);
+
+/// Tools whose calls may need confirmation, and so accept `confirmation_token`.
+pub const TOOLS: &[&str] = &["query", "run_script", "update", "delete", "drop_table"];
+
+#[derive(Default)]
+pub struct Confirmations {
+    /// Outstanding tokens with the fingerprint of the call they confirm and
+    /// when they were issued.
+    pending: std::sync::Mutex<HashMap<String, (u64, Instant)>>,
+}
+
+impl Confirmations {
+    /// Let the call through when it is not high-impact or carries a token
+    /// issued for exactly these arguments; otherwise refuse it with a new token.
+    pub async fn check(
+        &self,
+        config: &Config,
+        tool: &str,
+        arguments: &Value,
+        pool: &Pool<MySql>,
+        transactions: &TransactionManager,
+    ) -> Result<(), DbError> {
+        if !config.require_confirmation || !TOOLS.contains(&tool) {
+            return Ok(());
+        }
+        let Some(mut reason) = high_impact_reason(config, tool, arguments, pool, transactions).await? else {
+            return Ok(());
+        };
+        let fingerprint = fingerprint(tool, arguments);
+        let now = Instant::now();
+        if let Some(token) = arguments["confirmation_token"].as_str() {
+            if self.redeem(token, fingerprint, now) {
+                return Ok(());
+            }
+            reason.push_str(" (the confirmation_token given has expired or was issued for different arguments)");
+        }
+        Err(DbError::ConfirmationRequired { token: self.issue(fingerprint, now), reason })
+    }
+
+    fn issue(&self, fingerprint: u64, now: Instant) -> String {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, (_, issued)| now.duration_since(*issued) < TOKEN_TTL);
+        let token = uuid::Uuid::new_v4().to_string();
+        pending.insert(token.clone(), (fingerprint, now));
+        token
+    }
+
+    /// Consume `token` if it confirms the call with `fingerprint` and has not
+    /// expired. A token is spent even when it does not match.
+    fn redeem(&self, token: &str, fingerprint: u64, now: Instant) -> bool {
+        match self.pending.lock().unwrap().remove(token) {
+            Some((issued_for, issued)) => issued_for == fingerprint && now.duration_since(issued) < TOKEN_TTL,
+            None => false,
+        }
+    }
+}
+
+/// Why the call needs confirmation, or `None` when it does not. Dry runs change
+/// nothing and never need it.
+async fn high_impact_reason(
+    config: &Config,
+    tool: &str,
+    arguments: &Value,
+    pool: &Pool<MySql>,
+    transactions: &TransactionManager,
+) -> Result<Option<String>, DbError> {
+    if arguments["dry_run"].as_bool() == Some(true) {
+        return Ok(None);
+    }
+    let table_name = arguments["table_name"].as_str().unwrap_or_default();
+    match tool {
+        "drop_table" => Ok(Some(format!("dropping '{table_name}' deletes the table and all its data"))),
+        "query" | "run_script" => {
+            let sql = arguments[if tool == "query" { "query" } else { "script" }].as_str().unwrap_or_default();
+            Ok(policy::destructive_keyword(sql).map(|keyword| format!("the SQL contains a {keyword} statement")))
+        }
+        "update" | "delete" => {
+            let Some(conditions) = arguments["conditions"].as_object() else {
+                return Ok(None);
+            };
+            let mut conn = Connection::acquire(pool, transactions, arguments["transaction_id"].as_str()).await?;
+            if tool == "delete" {
+                let database: Option<String> = sqlx::query_scalar("SELECT DATABASE()").fetch_one(&mut *conn).await?;
+                let keys = unique_keys(&database.unwrap_or_default(), table_name, &mut conn).await?;
+                if !pins_unique_key(conditions, &keys) {
+                    return Ok(Some(format!(
+                        "the conditions do not fix every column of a unique key of '{table_name}', so they may match many rows"
+                    )));
+                }
+            }
+            let limit = arguments["limit"].as_u64();
+            let matching = count_matching(table_name, &arguments["conditions"], limit, &mut conn).await?;
+            Ok((matching > config.confirmation_threshold)
+                .then(|| format!("this {tool} matches {matching} rows, more than confirmation_threshold ({})", config.confirmation_threshold)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Whether `conditions` compare every column of one of `keys` for equality
+/// with a non-null value, so at most one row matches.
+fn pins_unique_key(conditions: &Map<String, Value>, keys: &[Vec<String>]) -> bool {
+    let is_equality = |condition: &Value| match condition {
+        Value::Null | Value::Array(_) => false,
+        Value::Object(operator) => operator["op"].as_str() == Some("=") && !operator["value"].is_null(),
+        _ => true,
+    };
+    keys.iter().any(|key| {
+        key.iter().all(|column| {
+            conditions.iter().any(|(name, condition)| name.eq_ignore_ascii_case(column) && is_equality(condition))
+        })
+    })
+}
+
+/// Identifies a call by tool and arguments, leaving out the token itself.
+fn fingerprint(tool: &str, arguments: &Value) -> u64 {
+    let mut arguments = arguments.clone();
+    if let Some(object) = arguments.as_object_mut() {
+        object.remove("confirmation_token");
+    }
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    tool.hash(&mut hasher);
+    arguments.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_confirmations() {
+        let confirmations = Confirmations::default();
+        let call = json!({ "table_name": "orders", "conditions": { "status": "old" } });
+        let start = Instant::now();
+        let token = confirmations.issue(fingerprint("delete", &call), start);
+        let mut confirmed = call.clone();
+        confirmed["confirmation_token"] = json!(token);
+        assert_eq!(fingerprint("delete", &confirmed), fingerprint("delete", &call));
+        assert_ne!(fingerprint("update", &call), fingerprint("delete", &call));
+        assert!(confirmations.redeem(&token, fingerprint("delete", &call), start + Duration::from_secs(10)));
+        assert!(!confirmations.redeem(&token, fingerprint("delete", &call), start + Duration::from_secs(10)), "tokens are single-use");
+
+        let token = confirmations.issue(fingerprint("delete", &call), start);
+        assert!(!confirmations.redeem(&token, fingerprint("delete", &json!({ "table_name": "users" })), start));
+        let token = confirmations.issue(fingerprint("delete", &call), start);
+        assert!(!confirmations.redeem(&token, fingerprint("delete", &call), start + TOKEN_TTL));
+
+        let keys = vec![vec!["id".to_string()], vec!["tenant_id".to_string(), "email".to_string()]];
+        let pins = |conditions: Value| pins_unique_key(conditions.as_object().unwrap(), &keys);
+        assert!(pins(json!({ "ID": 7 })));
+        assert!(pins(json!({ "tenant_id": 1, "email": { "op": "=", "value": "a@b.c" } })));
+        assert!(!pins(json!({ "email": "a@b.c" })));
+        assert!(!pins(json!({ "id": null })));
+        assert!(!pins(json!({ "id": { "op": ">", "value": 7 } })));
+
+        assert_eq!(policy::destructive_keyword("SELECT 1; truncate table logs"), Some("TRUNCATE"));
+        assert_eq!(policy::destructive_keyword("DROP TABLE IF EXISTS t"), Some("DROP"));
+        assert_eq!(policy::destructive_keyword("SELECT dropped_at FROM t"), None);
+        assert_eq!(policy::destructive_keyword("DROP EVENT nightly"), Some("DROP"));
+    }
+}
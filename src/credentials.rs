@@ -0,0 +1,224 @@
+//! Passwords read when connections open instead of at startup, from
+//! `password_file` or the OS keyring (`password_keyring`), so the secret
+//! stays out of MCP client configs and `ps` output. Each pool rereads its
+//! source periodically and opens later connections with the current value,
+//! so a rotated password takes effect without a restart. RDS IAM tokens
+//! (`auth = "aws-iam"`) are handed to pools the same way.
+
+use log::{info, warn};
+use sqlx::mysql::{MySqlConnectOptions, MySqlDatabaseError, MySqlSslMode};
+use sqlx::{MySql, Pool};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use crate::config::{AuthMode, Config};
+use crate::error::DbError;
+use crate::{rds_iam, redact, tunnel};
+
+/// A pool whose credentials come from a password source or RDS IAM.
+struct Watched {
+    pool: Pool<MySql>,
+    /// The options the pool was opened from, before [`apply`] and [`tunnel::route`].
+    options: MySqlConnectOptions,
+    config: Config,
+    /// The password the pool connects with; unused for RDS IAM.
+    current: Option<String>,
+}
+
+static WATCHED: Mutex<Vec<Watched>> = Mutex::const_new(Vec::new());
+
+/// The password from `password_file` or `password_keyring`, or `None` when
+/// neither is set and the configured `password` applies. `account` is the
+/// user the keyring entry is stored under.
+async fn read_password(config: &Config, account: &str) -> Result<Option<String>, sqlx::Error> {
+    let password = match (&config.password_file, &config.password_keyring) {
+        (None, None) => return Ok(None),
+        (Some(_), Some(_)) => {
+            return Err(sqlx::Error::Configuration("set password_file or password_keyring, not both".into()));
+        }
+        (Some(path), None) => {
+            let content = tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| sqlx::Error::Configuration(format!("Failed to read password_file {path}: {e}").into()))?;
+            // Editors and `echo` leave a trailing newline that is not part of the secret
+            content.trim_end_matches(['\r', '\n']).to_string()
+        }
+        (None, Some(service)) => keyring_lookup(service, account).await?,
+    };
+    redact::register_secret(&password);
+    Ok(Some(password))
+}
+
+/// `options` with the password read from the configured source, if any, or
+/// a new RDS IAM token. Tokens are sent in cleartext as MySQL requires, so
+/// TLS is required for them.
+pub async fn apply(options: MySqlConnectOptions, config: &Config) -> Result<MySqlConnectOptions, sqlx::Error> {
+    if config.auth == AuthMode::AwsIam {
+        let ssl_mode = match options.get_ssl_mode() {
+            MySqlSslMode::Disabled => {
+                return Err(sqlx::Error::Configuration("auth = \"aws-iam\" needs TLS; ssl_mode cannot be disabled".into()));
+            }
+            MySqlSslMode::Preferred => MySqlSslMode::Required,
+            mode => mode,
+        };
+        let token = rds_iam::auth_token(config, options.get_host(), options.get_port(), options.get_username())
+            .await
+            .map_err(|e| sqlx::Error::Configuration(format!("Cannot create an RDS IAM auth token: {e}").into()))?;
+        return Ok(options.ssl_mode(ssl_mode).enable_cleartext_plugin(true).password(&token));
+    }
+    let account = options.get_username().to_string();
+    Ok(match read_password(config, &account).await? {
+        Some(password) => options.password(&password),
+        None => options,
+    })
+}
+
+/// Keep track of `pool` for [`reload`] when its password comes from a file or
+/// the keyring, and with RDS IAM also hand it a freshly signed token every
+/// [`rds_iam::REFRESH_INTERVAL`] until it is closed. `options` are the ones
+/// the pool was opened from, before [`apply`] and [`tunnel::route`].
+pub async fn watch(pool: &Pool<MySql>, options: MySqlConnectOptions, config: &Config) {
+    let iam = config.auth == AuthMode::AwsIam;
+    if !iam && config.password_file.is_none() && config.password_keyring.is_none() {
+        return;
+    }
+    let current = match iam {
+        true => None,
+        false => read_password(config, options.get_username()).await.ok().flatten(),
+    };
+    let mut watched = WATCHED.lock().await;
+    watched.retain(|watched| !watched.pool.is_closed());
+    watched.push(Watched { pool: pool.clone(), options: options.clone(), config: config.clone(), current });
+    drop(watched);
+    if iam {
+        let pool = pool.clone();
+        let config = config.clone();
+        tokio::spawn(async move {
+            while !pool.is_closed() {
+                tokio::time::sleep(rds_iam::REFRESH_INTERVAL).await;
+                refresh(&pool, &options, &config).await;
+            }
+        });
+    }
+}
+
+/// Reread every watched pool's password source, or sign it a new RDS IAM
+/// token, after a connection was refused with [`is_access_denied`]. Whether
+/// any pool now connects differently, so the refused call is worth retrying.
+pub async fn reload() -> bool {
+    let mut watched = WATCHED.lock().await;
+    watched.retain(|watched| !watched.pool.is_closed());
+    let mut changed = false;
+    for watched in watched.iter_mut() {
+        if watched.config.auth != AuthMode::AwsIam {
+            match read_password(&watched.config, watched.options.get_username()).await {
+                Ok(password) if password != watched.current => {
+                    info!("Database password changed; new connections will use it");
+                    watched.current = password;
+                }
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Could not reread the database password, keeping the previous one: {e}");
+                    continue;
+                }
+            }
+        }
+        changed |= refresh(&watched.pool, &watched.options, &watched.config).await;
+    }
+    changed
+}
+
+/// Point `pool`'s future connections at the current credentials.
+async fn refresh(pool: &Pool<MySql>, options: &MySqlConnectOptions, config: &Config) -> bool {
+    let refreshed = async { tunnel::route(apply(options.clone(), config).await?, config).await };
+    match refreshed.await {
+        Ok(options) => {
+            pool.set_connect_options(options);
+            true
+        }
+        Err(e) => {
+            warn!("Could not refresh the database credentials, keeping the previous ones: {e}");
+            false
+        }
+    }
+}
+
+/// Whether MySQL refused a new connection's user or password
+/// (`ER_ACCESS_DENIED_ERROR`), as it does once a rotated password is stale.
+pub fn is_access_denied(error: &DbError) -> bool {
+    match error {
+        DbError::ConnectionError(sqlx::Error::Database(e)) | DbError::SqlError(sqlx::Error::Database(e)) => {
+            e.try_downcast_ref::<MySqlDatabaseError>().map(|e| e.number()) == Some(1045)
+        }
+        _ => false,
+    }
+}
+
+/// The secret stored for `account` under `service` in the macOS Keychain,
+/// or the Secret Service (GNOME Keyring, KWallet) elsewhere.
+async fn keyring_lookup(service: &str, account: &str) -> Result<String, sqlx::Error> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args(["find-generic-password", "-s", service, "-a", account, "-w"]);
+        command
+    } else if cfg!(windows) {
+        return Err(sqlx::Error::Configuration("password_keyring is not supported on Windows; use password_file".into()));
+    } else {
+        let mut command = Command::new("secret-tool");
+        command.args(["lookup", "service", service, "account", account]);
+        command
+    };
+    let output = command
+        .output()
+        .await
+        .map_err(|e| sqlx::Error::Configuration(format!("Failed to run the keyring client: {e}").into()))?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(sqlx::Error::Configuration(
+            format!("No keyring entry for service '{service}' and account '{account}'").into(),
+        ));
+    }
+    let secret = String::from_utf8_lossy(&output.stdout);
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_password() {
+        let mut config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"\npassword = \"static\"").unwrap();
+        assert_eq!(read_password(&config, "u").await.unwrap(), None);
+
+        let path = std::env::temp_dir().join(format!("mcp-password-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "rotated secret\n").unwrap();
+        config.password_file = Some(path.display().to_string());
+        assert_eq!(read_password(&config, "u").await.unwrap().as_deref(), Some("rotated secret"));
+
+        config.password_keyring = Some("mcp-server-mysql".to_string());
+        assert!(read_password(&config, "u").await.is_err(), "only one source may be set");
+        config.password_keyring = None;
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_password(&config, "u").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        let path = std::env::temp_dir().join(format!("mcp-password-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "first").unwrap();
+        let mut config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"").unwrap();
+        config.password_file = Some(path.display().to_string());
+        let options = MySqlConnectOptions::new().host("127.0.0.1").username("u");
+        let pool = sqlx::mysql::MySqlPoolOptions::new().connect_lazy_with(options.clone());
+        watch(&pool, options, &config).await;
+        assert!(!reload().await, "nothing to retry while the password is unchanged");
+
+        std::fs::write(&path, "second\n").unwrap();
+        assert!(reload().await);
+        assert!(!reload().await);
+        pool.close().await;
+        std::fs::write(&path, "third").unwrap();
+        assert!(!reload().await, "closed pools are forgotten");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,38 +1,242 @@
 use log::{debug, error, info, warn};
 use serde_json::{json, Value};
-use sqlx::{Column, MySql, Pool, Row, TypeInfo};
+use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions, MySqlSslMode};
+use sqlx::{Column, Executor, MySql, Pool, Row, TypeInfo};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio_stream::StreamExt;
+use crate::config::{Config, SslMode};
+use crate::credentials;
 use crate::error::DbError;
+use crate::history::MAX_STATEMENTS_PER_CALL;
+use crate::import::{insert_statement, MAX_PLACEHOLDERS};
+use crate::ddl::{qualified_name, quote_identifier, quote_literal, quote_name};
+use crate::masking::Masks;
+use crate::policy::{self, Guardrails, Permissions, StatementKind, TableAccess};
+use crate::rpc::{AggregateArguments, AggregateFunction, DeleteArguments, DuplicatesArguments, FulltextMode, FulltextSearchArguments, InsertArguments, OnDuplicate, OrderBy, QueryArguments, SelectArguments, SortDirection, UpdateArguments, UpsertArguments};
+use crate::tunnel;
+
+/// Callback receiving `(progress, total, message)` updates for a request.
+pub type ProgressSink = Box<dyn Fn(u64, Option<u64>, &str) + Send + Sync>;
+
+/// Per-request hooks the server installs around each call via [`REQUEST_CONTEXT`].
+#[derive(Default)]
+pub struct RequestContext {
+    /// MySQL connection running the request's statement (0 if none yet), so a
+    /// cancellation can `KILL QUERY` it.
+    pub connection_id: AtomicU64,
+    /// Pool of the server that connection is on, when not the session's default.
+    pub pool: std::sync::Mutex<Option<Pool<MySql>>>,
+    /// Set when the client asked for progress notifications.
+    pub progress: Option<ProgressSink>,
+    /// SQL the request ran, for the session's query history.
+    pub statements: std::sync::Mutex<Vec<String>>,
+}
+
+tokio::task_local! {
+    pub static REQUEST_CONTEXT: Arc<RequestContext>;
+}
+
+/// Publish the connection id of `conn` to the current request's context, if any.
+pub async fn track_connection(conn: &mut sqlx::MySqlConnection) -> Result<(), DbError> {
+    if let Ok(ctx) = REQUEST_CONTEXT.try_with(Arc::clone) {
+        let connection_id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()")
+            .fetch_one(&mut *conn)
+            .await?;
+        ctx.connection_id.store(connection_id, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+/// Note a statement the current request ran in its query history entry.
+pub fn record_statement(sql: &str) {
+    let _ = REQUEST_CONTEXT.try_with(|ctx| {
+        let mut statements = ctx.statements.lock().unwrap();
+        if statements.len() < MAX_STATEMENTS_PER_CALL {
+            statements.push(sql.to_string());
+        }
+    });
+}
+
+/// Report progress for the current request if the client asked for it.
+pub fn report_progress(progress: u64, total: Option<u64>, message: &str) {
+    let _ = REQUEST_CONTEXT.try_with(|ctx| {
+        if let Some(sink) = &ctx.progress {
+            sink(progress, total, message);
+        }
+    });
+}
+
+/// Interrupt the statement currently running on `connection_id`, leaving the connection open.
+pub async fn kill_query(pool: &Pool<MySql>, connection_id: u64) -> Result<(), DbError> {
+    debug!("Killing query on connection {connection_id}");
+    sqlx::query(&format!("KILL QUERY {connection_id}"))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
 
-/// Validates that an identifier (table name, database name) is safe for use in backtick-quoted SQL.
-/// Rejects empty strings, strings longer than 64 chars, and strings containing backticks or null bytes.
+/// Round-trip a trivial statement to check that the pool can still reach the server.
+pub async fn ping(pool: &Pool<MySql>) -> Result<(), DbError> {
+    sqlx::query("SELECT 1")
+        .execute(pool)
+        .await
+        .map_err(DbError::ConnectionError)?;
+    Ok(())
+}
+
+/// Validates that an identifier (table name, database name) is one MySQL can
+/// name: 1 to 64 characters, without null bytes or a trailing space. Any
+/// other character, backticks included, is fine once quoted with
+/// [`quote_name`].
 pub fn is_valid_identifier(name: &str) -> bool {
     !name.is_empty()
-        && name.len() <= 64
-        && !name.contains('`')
+        && name.chars().count() <= 64
         && !name.contains('\0')
+        && !name.ends_with(' ')
+}
+
+/// Connection options for `database_url` with the configured socket and TLS
+/// settings applied on top of any given in the URL.
+pub fn connect_options(database_url: &str, config: &Config) -> Result<MySqlConnectOptions, sqlx::Error> {
+    let mut options: MySqlConnectOptions = database_url.parse()?;
+    if let Some(socket) = &config.socket {
+        options = options.socket(socket);
+    }
+    if let Some(mode) = config.ssl_mode {
+        options = options.ssl_mode(match mode {
+            SslMode::Disabled => MySqlSslMode::Disabled,
+            SslMode::Preferred => MySqlSslMode::Preferred,
+            SslMode::Required => MySqlSslMode::Required,
+            SslMode::VerifyCa => MySqlSslMode::VerifyCa,
+            SslMode::VerifyIdentity => MySqlSslMode::VerifyIdentity,
+        });
+    }
+    if let Some(ca) = &config.ssl_ca {
+        options = options.ssl_ca(ca);
+    }
+    match (&config.ssl_cert, &config.ssl_key) {
+        (Some(cert), Some(key)) => options = options.ssl_client_cert(cert).ssl_client_key(key),
+        (None, None) => {}
+        _ => return Err(sqlx::Error::Configuration("ssl_cert and ssl_key must be set together".into())),
+    }
+    Ok(options)
+}
+
+/// Pool sizing and connection recycling from the `pool_*` settings.
+fn pool_options(config: &Config) -> MySqlPoolOptions {
+    let seconds = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+    MySqlPoolOptions::new()
+        .max_connections(config.pool_max_connections.max(1))
+        .min_connections(config.pool_min_connections)
+        .acquire_timeout(Duration::from_secs(config.pool_acquire_timeout_secs))
+        .idle_timeout(seconds(config.pool_idle_timeout_secs))
+        .max_lifetime(seconds(config.pool_max_lifetime_secs))
+        .test_before_acquire(config.pool_test_before_acquire)
+}
+
+/// Statements that set up each new connection: `READ ONLY` transactions in
+/// read-only mode, first so the rest cannot write, then `charset`,
+/// `collation`, `time_zone`, `sql_mode`, and `init_statements`, in that order.
+fn session_init_statements(config: &Config) -> Vec<String> {
+    let mut statements = Vec::new();
+    if Permissions::new(config).is_read_only() {
+        statements.push("SET SESSION TRANSACTION READ ONLY".to_string());
+    }
+    match (&config.charset, &config.collation) {
+        (Some(charset), Some(collation)) => {
+            statements.push(format!("SET NAMES {} COLLATE {}", quote_literal(charset), quote_literal(collation)))
+        }
+        (Some(charset), None) => statements.push(format!("SET NAMES {}", quote_literal(charset))),
+        (None, Some(collation)) => statements.push(format!("SET SESSION collation_connection = {}", quote_literal(collation))),
+        (None, None) => {}
+    }
+    if let Some(time_zone) = &config.time_zone {
+        statements.push(format!("SET SESSION time_zone = {}", quote_literal(time_zone)));
+    }
+    if let Some(sql_mode) = &config.sql_mode {
+        statements.push(format!("SET SESSION sql_mode = {}", quote_literal(sql_mode)));
+    }
+    statements.extend(config.init_statements.iter().cloned());
+    statements
+}
+
+/// The statement bounding each SELECT on a connection to `query_timeout`.
+fn max_execution_time_statement(config: &Config) -> Option<String> {
+    let secs = config.query_timeout.filter(|secs| *secs > 0)?;
+    Some(format!("SET SESSION max_execution_time = {}", secs * 1000))
 }
 
-pub async fn connect_with_retry(database_url: &str) -> Result<Pool<MySql>, Box<dyn std::error::Error>> {
+/// Open the connection pool, retrying while the server is unreachable. Each
+/// connection runs [`session_init_statements`] first, so in read-only mode
+/// MySQL itself rejects writes that get past statement classification; a
+/// failing one fails the connection. With `query_timeout`,
+/// `max_execution_time` then bounds SELECTs. With `ssh_host`, connections go
+/// through an SSH tunnel opened on demand. Connections that come back with
+/// another database selected are switched back, or closed.
+pub async fn connect_with_retry(database_url: &str, config: &Config) -> Result<Pool<MySql>, sqlx::Error> {
     let mut retry_count = 0;
     const MAX_RETRIES: u32 = 5;
     const RETRY_DELAY_MS: u64 = 1000;
+    let max_execution_time: Option<Arc<str>> = max_execution_time_statement(config).map(Into::into);
+    let connect_options = connect_options(database_url, config)?;
+    let init_statements: Arc<[String]> = session_init_statements(config).into();
+    let default_database: Option<Arc<str>> = connect_options.get_database().map(Into::into);
 
     loop {
-        match sqlx::mysql::MySqlPoolOptions::new()
-            .max_connections(5)
-            .connect(database_url)
-            .await
-        {
+        let init_statements = init_statements.clone();
+        let max_execution_time = max_execution_time.clone();
+        let default_database = default_database.clone();
+        let pool_options = pool_options(config)
+            .after_connect(move |conn, _meta| {
+                let init_statements = init_statements.clone();
+                let max_execution_time = max_execution_time.clone();
+                Box::pin(async move {
+                    for statement in init_statements.iter() {
+                        conn.execute(statement.as_str()).await?;
+                    }
+                    if let Some(statement) = max_execution_time.as_deref() {
+                        // MariaDB has max_statement_time instead; the tool call timeout still applies there
+                        if let Err(e) = conn.execute(statement).await {
+                            warn!("Could not set max_execution_time: {e}");
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            // A `USE` in a query must not change what unqualified names mean
+            // for the next caller, or for the table access checks made on it
+            .after_release(move |conn, _meta| {
+                let default_database = default_database.clone();
+                Box::pin(async move {
+                    let Ok(current) = sqlx::query_scalar::<_, Option<String>>("SELECT DATABASE()").fetch_one(&mut *conn).await else {
+                        return Ok(false);
+                    };
+                    match (current.as_deref(), default_database.as_deref()) {
+                        (current, default) if current == default => Ok(true),
+                        (_, Some(default)) => Ok(conn.execute(format!("USE {}", quote_name(default)).as_str()).await.is_ok()),
+                        // No way back to having no database selected
+                        (_, None) => Ok(false),
+                    }
+                })
+            });
+        let connected = async {
+            let options = credentials::apply(connect_options.clone(), config).await?;
+            let options = tunnel::route(options, config).await?;
+            pool_options.connect_with(options).await
+        };
+        match connected.await {
             Ok(pool) => {
                 info!("Successfully connected to MySQL database");
+                credentials::watch(&pool, connect_options.clone(), config).await;
                 return Ok(pool);
             }
             Err(e) => {
                 retry_count += 1;
                 if retry_count >= MAX_RETRIES {
                     error!("Failed to connect to database after {MAX_RETRIES} retries: {e}");
-                    return Err(e.into());
+                    return Err(e);
                 }
                 warn!("Database connection failed (attempt {retry_count}/{MAX_RETRIES}): {e}");
                 info!("Retrying in {RETRY_DELAY_MS}ms...");
@@ -50,28 +254,81 @@ pub struct SchemaResult {
 }
 
 pub struct QueryResult {
+    pub columns: Vec<String>,
     pub rows: Vec<Value>,
     pub row_count: usize,
     pub truncated: bool,
+    /// Cursor for the rows after this page, when the query tool truncated.
+    pub next_cursor: Option<String>,
+}
+
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Value>,
+    pub truncated: bool,
+}
+
+pub struct ProcedureResult {
+    pub result_sets: Vec<ResultSet>,
+    pub affected_rows: u64,
+}
+
+pub struct ExplainResult {
+    pub plan: Value,
+    /// `EXPLAIN ANALYZE` output (an indented tree of timed iterators), if requested.
+    pub analyze: Option<String>,
 }
 
 pub struct InsertResult {
+    pub rows: u64,
+    pub batches: u64,
+    /// Auto-increment ids of the first and last inserted rows (0 when the
+    /// table has none). The last id assumes each batch got consecutive ids,
+    /// as InnoDB assigns them for multi-row INSERTs.
+    pub first_insert_id: u64,
     pub last_insert_id: u64,
+    /// The rows as stored, with defaults and trigger changes, when
+    /// `return_rows` was asked for.
+    pub inserted: Option<Vec<Value>>,
+    /// With `dry_run`, the first batch's statement; `rows` is then the number
+    /// of rows that would be inserted.
+    pub dry_run: Option<DryRun>,
 }
 
 pub struct MutationResult {
+    /// Rows changed, or with `dry_run` the rows that would be.
+    pub affected_rows: u64,
+    pub dry_run: Option<DryRun>,
+}
+
+/// The statement a mutation would have run, returned instead of running it.
+pub struct DryRun {
+    pub sql: String,
+    pub params: Vec<Value>,
+}
+
+pub struct UpsertResult {
+    /// 1 when a row was inserted (or an existing row already held these
+    /// values), 2 when an existing row was updated.
     pub affected_rows: u64,
+    pub last_insert_id: u64,
 }
 
 pub async fn get_schema(
     table_name: String,
+    access: &TableAccess,
     pool: &Pool<MySql>,
 ) -> Result<SchemaResult, DbError> {
     debug!("Getting schema for: {table_name}");
 
     if table_name == "all-tables" {
-        let schemas = get_all_table_schemas(pool).await?;
-        let description = format!("Retrieved schemas for {} tables.", schemas.len());
+        let mut schemas = get_all_table_schemas(pool).await?;
+        if access.is_restricted() {
+            let current_db = current_database(pool).await?;
+            schemas.retain(|s| access.permits(&current_db, s["table_name"].as_str().unwrap_or_default()));
+        }
+        let views = schemas.iter().filter(|s| s["type"] == "view").count();
+        let description = format!("Retrieved schemas for {} tables and {views} views.", schemas.len() - views);
         info!("Successfully retrieved schemas for {} tables", schemas.len());
         Ok(SchemaResult { schemas, description })
     } else {
@@ -79,44 +336,166 @@ pub async fn get_schema(
             return Err(DbError::InvalidIdentifier(table_name));
         }
 
-        let schema = get_table_schema(pool, &table_name).await?;
+        let current_db = current_database(pool).await?;
+        access.check(&current_db, &table_name)?;
+        let schema = get_table_schema(pool, &current_db, &table_name).await?;
         let description = format!("Retrieved schema for table '{}'.", table_name);
         info!("Successfully retrieved schema for table '{table_name}'");
         Ok(SchemaResult { schemas: vec![schema], description })
     }
 }
 
-async fn get_table_schema(pool: &Pool<MySql>, table_name: &str) -> Result<Value, DbError> {
+/// The default database of a pool, or of one connection, which keeps what
+/// its last `USE` selected.
+pub async fn current_database<'c>(executor: impl sqlx::Executor<'c, Database = MySql>) -> Result<String, DbError> {
     let current_db: Option<String> = sqlx::query_scalar("SELECT DATABASE()")
-        .fetch_optional(pool)
+        .fetch_optional(executor)
         .await?;
-    let current_db = current_db.ok_or(DbError::NoDatabaseSelected)?;
+    current_db.ok_or(DbError::NoDatabaseSelected)
+}
+
+/// Columns of each unique key (including the primary key), in key order.
+pub async fn unique_keys(database: &str, table: &str, conn: &mut sqlx::MySqlConnection) -> Result<Vec<Vec<String>>, DbError> {
+    let rows = sqlx::query(
+        "SELECT index_name AS index_name, column_name AS column_name
+         FROM information_schema.statistics
+         WHERE table_schema = ? AND table_name = ? AND non_unique = 0 AND column_name IS NOT NULL
+         ORDER BY index_name, seq_in_index",
+    )
+    .bind(database)
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut keys: Vec<(String, Vec<String>)> = Vec::new();
+    for row in rows {
+        let index: String = row.try_get("index_name")?;
+        let column: String = row.try_get("column_name")?;
+        match keys.last_mut() {
+            Some((name, columns)) if *name == index => columns.push(column),
+            _ => keys.push((index, vec![column])),
+        }
+    }
+    Ok(keys.into_iter().map(|(_, columns)| columns).collect())
+}
+
+pub struct ForeignKey {
+    pub name: String,
+    pub table_name: String,
+    pub columns: Vec<String>,
+    pub referenced_database: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    pub on_update: String,
+    pub on_delete: String,
+}
+
+impl ForeignKey {
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "columns": self.columns,
+            "referenced_database": self.referenced_database,
+            "referenced_table": self.referenced_table,
+            "referenced_columns": self.referenced_columns,
+            "on_update": self.on_update,
+            "on_delete": self.on_delete,
+        })
+    }
+}
+
+/// Foreign keys declared in `database`, optionally only those of `table_name`,
+/// with multi-column keys grouped into one entry.
+pub async fn foreign_keys(
+    pool: &Pool<MySql>,
+    database: &str,
+    table_name: Option<&str>,
+) -> Result<Vec<ForeignKey>, DbError> {
+    let rows = sqlx::query(
+        "SELECT k.constraint_name AS constraint_name, k.table_name AS table_name,
+                k.column_name AS column_name, k.referenced_table_schema AS referenced_table_schema,
+                k.referenced_table_name AS referenced_table_name,
+                k.referenced_column_name AS referenced_column_name,
+                r.update_rule AS update_rule, r.delete_rule AS delete_rule
+         FROM information_schema.key_column_usage k
+         JOIN information_schema.referential_constraints r
+           ON r.constraint_schema = k.constraint_schema
+          AND r.constraint_name = k.constraint_name
+          AND r.table_name = k.table_name
+         WHERE k.table_schema = ? AND (? IS NULL OR k.table_name = ?)
+           AND k.referenced_table_name IS NOT NULL
+         ORDER BY k.table_name, k.constraint_name, k.ordinal_position",
+    )
+    .bind(database)
+    .bind(table_name)
+    .bind(table_name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut keys: Vec<ForeignKey> = Vec::new();
+    for row in rows {
+        let name: String = row.try_get("constraint_name")?;
+        let table: String = row.try_get("table_name")?;
+        let column: String = row.try_get("column_name")?;
+        let referenced_column: String = row.try_get("referenced_column_name")?;
+        match keys.last_mut() {
+            Some(key) if key.name == name && key.table_name == table => {
+                key.columns.push(column);
+                key.referenced_columns.push(referenced_column);
+            }
+            _ => keys.push(ForeignKey {
+                name,
+                table_name: table,
+                columns: vec![column],
+                referenced_database: row.try_get("referenced_table_schema")?,
+                referenced_table: row.try_get("referenced_table_name")?,
+                referenced_columns: vec![referenced_column],
+                on_update: row.try_get("update_rule")?,
+                on_delete: row.try_get("delete_rule")?,
+            }),
+        }
+    }
+    Ok(keys)
+}
 
-    let table_info_query = "SELECT * FROM information_schema.tables WHERE table_name = ? AND table_schema = ?";
+async fn get_table_schema(pool: &Pool<MySql>, current_db: &str, table_name: &str) -> Result<Value, DbError> {
+    let table_info_query =
+        "SELECT t.table_type AS table_type, t.engine AS engine, t.row_format AS row_format,
+                t.table_collation AS table_collation, c.character_set_name AS table_charset
+         FROM information_schema.tables t
+         LEFT JOIN information_schema.collation_character_set_applicability c
+           ON c.collation_name = t.table_collation
+         WHERE t.table_name = ? AND t.table_schema = ?";
     let table_info = sqlx::query(table_info_query)
         .bind(table_name)
-        .bind(&current_db)
+        .bind(current_db)
         .fetch_optional(pool)
         .await?;
 
-    if table_info.is_none() {
+    let Some(table_info) = table_info else {
         return Err(DbError::NotFound(format!("Table '{}' not found", table_name)));
-    }
+    };
+    let is_view = table_info.try_get::<String, _>("table_type")? == "VIEW";
 
     let columns_query =
-        "SELECT column_name, data_type, is_nullable, column_default, column_key, extra, column_comment
+        "SELECT column_name, data_type, is_nullable, column_default, column_key, extra, column_comment,
+                character_set_name AS character_set_name, collation_name AS collation_name
          FROM information_schema.columns
          WHERE table_name = ? AND table_schema = ?
          ORDER BY ordinal_position";
 
     let columns = sqlx::query(columns_query)
         .bind(table_name)
-        .bind(&current_db)
+        .bind(current_db)
         .fetch_all(pool)
         .await?;
 
-    let indexes_query = format!("SHOW INDEX FROM `{}`.`{}`", current_db, table_name);
-    let indexes = sqlx::query(&indexes_query).fetch_all(pool).await?;
+    // Views have no indexes of their own
+    let indexes = if is_view {
+        Vec::new()
+    } else {
+        let indexes_query = format!("SHOW INDEX FROM {}.{}", quote_name(current_db), quote_name(table_name));
+        sqlx::query(&indexes_query).fetch_all(pool).await?
+    };
 
     let column_info: Vec<Value> = columns
         .into_iter()
@@ -129,6 +508,8 @@ async fn get_table_schema(pool: &Pool<MySql>, table_name: &str) -> Result<Value,
                 "key": row.try_get::<String, _>("column_key").unwrap_or_default(),
                 "extra": row.try_get::<String, _>("extra").unwrap_or_default(),
                 "comment": row.try_get::<String, _>("column_comment").unwrap_or_default(),
+                "charset": row.try_get::<Option<String>, _>("character_set_name").unwrap_or_default(),
+                "collation": row.try_get::<Option<String>, _>("collation_name").unwrap_or_default(),
             })
         })
         .collect();
@@ -145,305 +526,2086 @@ async fn get_table_schema(pool: &Pool<MySql>, table_name: &str) -> Result<Value,
         })
         .collect();
 
-    Ok(json!({
+    let triggers = sqlx::query(
+        "SELECT trigger_name AS trigger_name, event_manipulation AS event,
+                action_timing AS timing, action_statement AS statement
+         FROM information_schema.triggers
+         WHERE event_object_table = ? AND event_object_schema = ?
+         ORDER BY action_timing, event_manipulation, action_order",
+    )
+    .bind(table_name)
+    .bind(current_db)
+    .fetch_all(pool)
+    .await?;
+
+    let trigger_info: Vec<Value> = triggers
+        .into_iter()
+        .map(|row| {
+            json!({
+                "name": row.try_get::<String, _>("trigger_name").unwrap_or_default(),
+                "event": row.try_get::<String, _>("event").unwrap_or_default(),
+                "timing": row.try_get::<String, _>("timing").unwrap_or_default(),
+                "statement": row.try_get::<String, _>("statement").unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    let foreign_key_info: Vec<Value> = foreign_keys(pool, current_db, Some(table_name))
+        .await?
+        .iter()
+        .map(ForeignKey::to_json)
+        .collect();
+
+    let mut schema = json!({
         "table_name": table_name,
+        "type": if is_view { "view" } else { "table" },
         "columns": column_info,
-        "indexes": index_info
-    }))
-}
+        "indexes": index_info,
+        "foreign_keys": foreign_key_info,
+        "triggers": trigger_info
+    });
+
+    if !is_view {
+        schema["engine"] = json!(table_info.try_get::<Option<String>, _>("engine").unwrap_or_default());
+        schema["row_format"] = json!(table_info.try_get::<Option<String>, _>("row_format").unwrap_or_default());
+        schema["charset"] = json!(table_info.try_get::<Option<String>, _>("table_charset").unwrap_or_default());
+        schema["collation"] = json!(table_info.try_get::<Option<String>, _>("table_collation").unwrap_or_default());
+    }
 
-async fn get_all_table_schemas(pool: &Pool<MySql>) -> Result<Vec<Value>, DbError> {
-    let current_db: Option<String> = sqlx::query_scalar("SELECT DATABASE()")
+    if !is_view {
+        let partitions = sqlx::query(
+            "SELECT partition_name AS partition_name, subpartition_name AS subpartition_name,
+                    partition_method AS partition_method, partition_expression AS partition_expression,
+                    subpartition_method AS subpartition_method,
+                    subpartition_expression AS subpartition_expression,
+                    partition_description AS partition_description,
+                    CAST(table_rows AS UNSIGNED) AS table_rows
+             FROM information_schema.partitions
+             WHERE table_name = ? AND table_schema = ? AND partition_name IS NOT NULL
+             ORDER BY partition_ordinal_position, subpartition_ordinal_position",
+        )
+        .bind(table_name)
+        .bind(current_db)
+        .fetch_all(pool)
+        .await?;
+        schema["partitioning"] = partitioning_info(&partitions.iter().map(row_to_json).collect::<Vec<_>>());
+    }
+
+    if is_view {
+        let view = sqlx::query(
+            "SELECT view_definition AS view_definition, is_updatable AS is_updatable,
+                    check_option AS check_option
+             FROM information_schema.views
+             WHERE table_name = ? AND table_schema = ?",
+        )
+        .bind(table_name)
+        .bind(current_db)
         .fetch_optional(pool)
         .await?;
-    let current_db = current_db.ok_or(DbError::NoDatabaseSelected)?;
+        if let Some(view) = view {
+            // view_definition is empty unless the user has SHOW VIEW or is the definer
+            schema["definition"] = json!(view.try_get::<Option<String>, _>("view_definition").unwrap_or_default());
+            schema["updatable"] = json!(view.try_get::<String, _>("is_updatable").unwrap_or_default() == "YES");
+            schema["check_option"] = json!(view.try_get::<String, _>("check_option").unwrap_or_default());
+        }
+    }
+
+    Ok(schema)
+}
+
+/// Partitioning scheme and partitions (with their subpartitions) from
+/// `information_schema.partitions` rows, or null for an unpartitioned table.
+fn partitioning_info(rows: &[Value]) -> Value {
+    let Some(first) = rows.first() else {
+        return Value::Null;
+    };
+    // (name, description, rows, subpartitions)
+    let mut partitions: Vec<(&Value, &Value, u64, Vec<&Value>)> = Vec::new();
+    for row in rows {
+        match partitions.last_mut() {
+            Some(partition) if *partition.0 == row["partition_name"] => {}
+            _ => partitions.push((&row["partition_name"], &row["partition_description"], 0, Vec::new())),
+        }
+        if let Some((_, _, estimate, subpartitions)) = partitions.last_mut() {
+            *estimate += row["table_rows"].as_u64().unwrap_or(0);
+            if !row["subpartition_name"].is_null() {
+                subpartitions.push(&row["subpartition_name"]);
+            }
+        }
+    }
+    let partitions: Vec<Value> = partitions
+        .into_iter()
+        .map(|(name, description, rows, subpartitions)| {
+            json!({ "name": name, "description": description, "rows": rows, "subpartitions": subpartitions })
+        })
+        .collect();
+    json!({
+        "method": first["partition_method"],
+        "expression": first["partition_expression"],
+        "subpartition_method": first["subpartition_method"],
+        "subpartition_expression": first["subpartition_expression"],
+        "partitions": partitions,
+    })
+}
 
-    let tables_query = "SELECT table_name FROM information_schema.tables WHERE table_schema = ? AND table_type = 'BASE TABLE'";
+async fn get_all_table_schemas(pool: &Pool<MySql>) -> Result<Vec<Value>, DbError> {
+    let current_db = current_database(pool).await?;
+
+    let tables_query = "SELECT table_name AS table_name FROM information_schema.tables WHERE table_schema = ? AND table_type IN ('BASE TABLE', 'VIEW')";
     let tables = sqlx::query(tables_query)
-        .bind(current_db)
+        .bind(&current_db)
         .fetch_all(pool)
         .await?;
 
+    let total = tables.len() as u64;
     let mut schemas = Vec::new();
-    for table_row in tables {
+    for (i, table_row) in tables.into_iter().enumerate() {
         let table_name: String = table_row.try_get("table_name")?;
-        match get_table_schema(pool, &table_name).await {
+        match get_table_schema(pool, &current_db, &table_name).await {
             Ok(schema) => schemas.push(schema),
             Err(e) => {
                 warn!("Failed to get schema for table {table_name}: {e}");
             }
         }
+        report_progress(i as u64 + 1, Some(total), &format!("Processed table '{table_name}'"));
     }
 
     Ok(schemas)
 }
 
-pub async fn execute_query(
-    query: String,
+pub struct DatabaseEntry {
+    pub name: String,
+    pub charset: String,
+    pub collation: String,
+}
+
+/// List schema names visible to the connected user with their defaults.
+pub async fn list_databases(pool: &Pool<MySql>) -> Result<Vec<DatabaseEntry>, DbError> {
+    let rows = sqlx::query(
+        "SELECT schema_name AS name,
+                default_character_set_name AS charset,
+                default_collation_name AS collation
+         FROM information_schema.schemata
+         ORDER BY schema_name",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(DatabaseEntry {
+                name: row.try_get("name")?,
+                charset: row.try_get("charset")?,
+                collation: row.try_get("collation")?,
+            })
+        })
+        .collect()
+}
+
+pub struct TableEntry {
+    pub database: String,
+    pub table_name: String,
+    pub comment: String,
+}
+
+/// List the base tables of the current database.
+pub async fn list_tables(pool: &Pool<MySql>) -> Result<Vec<TableEntry>, DbError> {
+    let current_db = current_database(pool).await?;
+    let rows = sqlx::query(
+        "SELECT table_name, table_comment FROM information_schema.tables
+         WHERE table_schema = ? AND table_type = 'BASE TABLE'
+         ORDER BY table_name",
+    )
+    .bind(&current_db)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(TableEntry {
+                database: current_db.clone(),
+                table_name: row.try_get("table_name")?,
+                comment: row.try_get::<Option<String>, _>("table_comment")?.unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+pub struct TableDetails {
+    pub table_name: String,
+    pub table_type: String,
+    pub engine: Option<String>,
+    /// InnoDB's estimate, which can be off by 40-50%.
+    pub estimated_rows: Option<u64>,
+    pub data_bytes: Option<u64>,
+    pub index_bytes: Option<u64>,
+    pub comment: String,
+}
+
+/// Tables of `database` (or the current database) with storage metadata from
+/// information_schema, without fetching any column definitions.
+pub async fn list_table_details(
     database: Option<String>,
     pool: &Pool<MySql>,
-    allow_dangerous_queries: bool,
-    max_rows: usize,
-) -> Result<QueryResult, DbError> {
-    // Lightweight client-side pre-check (fast-fail, NOT a security boundary)
-    if !allow_dangerous_queries {
-        let trimmed_upper = query.trim().to_uppercase();
-        if !trimmed_upper.starts_with("SELECT")
-            && !trimmed_upper.starts_with("SHOW")
-            && !trimmed_upper.starts_with("DESCRIBE")
-            && !trimmed_upper.starts_with("EXPLAIN")
+) -> Result<(String, Vec<TableDetails>), DbError> {
+    let database = match database {
+        Some(db) if !is_valid_identifier(&db) => return Err(DbError::InvalidIdentifier(db)),
+        Some(db) => db,
+        None => current_database(pool).await?,
+    };
+    let rows = sqlx::query(
+        "SELECT table_name AS table_name, table_type AS table_type, engine AS engine,
+                table_rows AS table_rows, data_length AS data_length,
+                index_length AS index_length, table_comment AS table_comment
+         FROM information_schema.tables
+         WHERE table_schema = ?
+         ORDER BY table_name",
+    )
+    .bind(&database)
+    .fetch_all(pool)
+    .await?;
+
+    let tables = rows
+        .into_iter()
+        .map(|row| {
+            Ok(TableDetails {
+                table_name: row.try_get("table_name")?,
+                table_type: row.try_get("table_type")?,
+                engine: row.try_get("engine")?,
+                estimated_rows: row.try_get("table_rows")?,
+                data_bytes: row.try_get("data_length")?,
+                index_bytes: row.try_get("index_length")?,
+                comment: row.try_get::<Option<String>, _>("table_comment")?.unwrap_or_default(),
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+    Ok((database, tables))
+}
+
+/// Object types `SHOW CREATE` supports, with the result column holding the DDL.
+pub const SHOW_CREATE_TYPES: &[(&str, &str)] = &[
+    ("table", "Create Table"),
+    ("view", "Create View"),
+    ("procedure", "Create Procedure"),
+    ("function", "Create Function"),
+    ("trigger", "SQL Original Statement"),
+    ("event", "Create Event"),
+];
+
+/// Exact DDL for an object via `SHOW CREATE <type>`, including constraints,
+/// charset, and partitioning that the column-level schema leaves out.
+pub async fn show_create(
+    object_type: &str,
+    name: String,
+    database: Option<String>,
+    pool: &Pool<MySql>,
+) -> Result<String, DbError> {
+    let object_type = object_type.to_lowercase();
+    let Some(&(_, column)) = SHOW_CREATE_TYPES.iter().find(|(t, _)| *t == object_type) else {
+        let types: Vec<&str> = SHOW_CREATE_TYPES.iter().map(|(t, _)| *t).collect();
+        return Err(DbError::InvalidInput(format!(
+            "Unknown object type '{object_type}', expected one of: {}",
+            types.join(", ")
+        )));
+    };
+    if !is_valid_identifier(&name) {
+        return Err(DbError::InvalidIdentifier(name));
+    }
+    let qualified = match database {
+        Some(db) if !is_valid_identifier(&db) => return Err(DbError::InvalidIdentifier(db)),
+        Some(db) => format!("{}.{}", quote_name(&db), quote_name(&name)),
+        None => quote_name(&name),
+    };
+
+    let query = format!("SHOW CREATE {} {qualified}", object_type.to_uppercase());
+    debug!("Executing: {query}");
+    record_statement(&query);
+    let row = sqlx::query(&query).fetch_one(pool).await.map_err(|e| match e {
+        // ER_NO_SUCH_TABLE, ER_SP_DOES_NOT_EXIST, ER_TRG_DOES_NOT_EXIST, ER_EVENT_DOES_NOT_EXIST
+        sqlx::Error::Database(ref db_err)
+            if matches!(
+                db_err.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>().map(|e| e.number()),
+                Some(1146 | 1305 | 1360 | 1539)
+            ) =>
         {
-            return Err(DbError::ReadOnlyViolation(
-                "Only SELECT, SHOW, DESCRIBE, and EXPLAIN queries are allowed. Use --allow-dangerous-queries flag to execute other query types.".to_string(),
-            ));
+            DbError::NotFound(format!("{object_type} '{name}' not found: {db_err}"))
         }
-    }
+        e => DbError::SqlError(e),
+    })?;
+
+    // Some servers return these columns as binary strings
+    let ddl = match row.try_get::<Option<String>, _>(column) {
+        Ok(ddl) => ddl,
+        Err(_) => row
+            .try_get::<Option<Vec<u8>>, _>(column)?
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned()),
+    };
+    // NULL when the user lacks privileges on the routine body
+    ddl.ok_or_else(|| DbError::NotFound(format!("No definition visible for {object_type} '{name}' (insufficient privileges?)")))
+}
 
-    debug!("Executing query: {}", query);
+/// Schema of `database`.`table_name` plus its first `sample_rows` rows.
+pub async fn read_table(
+    database: &str,
+    table_name: &str,
+    sample_rows: usize,
+    masks: &Masks,
+    pool: &Pool<MySql>,
+) -> Result<Value, DbError> {
+    for name in [database, table_name] {
+        if !is_valid_identifier(name) {
+            return Err(DbError::InvalidIdentifier(name.to_string()));
+        }
+    }
 
-    let mut conn = pool.acquire().await.map_err(DbError::ConnectionError)?;
+    let mut schema = get_table_schema(pool, database, table_name).await?;
+    schema["database"] = json!(database);
+    schema["sample_rows"] = json!(read_table_rows(database, table_name, sample_rows, masks, pool).await?);
+    Ok(schema)
+}
 
-    // Set database context if specified
-    if let Some(db) = database {
-        debug!("Setting database context to: {}", db);
-        if !is_valid_identifier(&db) {
-            return Err(DbError::InvalidIdentifier(db));
+/// The first `limit` rows of `database`.`table_name`, masked.
+pub async fn read_table_rows(
+    database: &str,
+    table_name: &str,
+    limit: usize,
+    masks: &Masks,
+    pool: &Pool<MySql>,
+) -> Result<Vec<Value>, DbError> {
+    for name in [database, table_name] {
+        if !is_valid_identifier(name) {
+            return Err(DbError::InvalidIdentifier(name.to_string()));
         }
-        let use_query = format!("USE `{}`", db);
-        sqlx::query(&use_query).execute(&mut *conn).await?;
     }
 
-    // Read-only enforcement via MySQL transaction
-    if !allow_dangerous_queries {
-        sqlx::query("START TRANSACTION READ ONLY")
-            .execute(&mut *conn)
-            .await?;
-    }
+    let rows_query = format!("SELECT * FROM {}.{} LIMIT {}", quote_name(database), quote_name(table_name), limit);
+    let rows = sqlx::query(&rows_query).fetch_all(pool).await?;
+    let mut rows: Vec<Value> = rows.iter().map(row_to_json).collect();
+    masks.apply_to_rows(&mut rows, Some(database), table_name);
+    Ok(rows)
+}
 
-    let result = sqlx::query(&query).fetch_all(&mut *conn).await;
+/// Rows `preview` returns at most, whatever the caller asks for.
+pub const PREVIEW_MAX_ROWS: usize = 100;
 
-    // Always rollback the read-only transaction (whether query succeeded or failed)
-    if !allow_dangerous_queries {
-        let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+/// Tables with fewer estimated rows are sampled with a plain `ORDER BY RAND()`.
+const SAMPLE_SORT_THRESHOLD: u64 = 10_000;
+
+/// The first `limit` rows of a table, or with `sample` a random selection.
+pub async fn preview_table(
+    table_name: String,
+    database: Option<String>,
+    limit: usize,
+    sample: bool,
+    masks: &Masks,
+    pool: &Pool<MySql>,
+) -> Result<QueryResult, DbError> {
+    let database = match database {
+        Some(db) => db,
+        None => current_database(pool).await?,
+    };
+    for name in [&database, &table_name] {
+        if !is_valid_identifier(name) {
+            return Err(DbError::InvalidIdentifier(name.clone()));
+        }
     }
+    let limit = limit.clamp(1, PREVIEW_MAX_ROWS);
+    let source = format!("{}.{}", quote_name(&database), quote_name(&table_name));
+
+    let query = if sample {
+        let estimated_rows: Option<u64> = sqlx::query_scalar(
+            "SELECT CAST(table_rows AS UNSIGNED) AS table_rows FROM information_schema.tables
+             WHERE table_schema = ? AND table_name = ?",
+        )
+        .bind(&database)
+        .bind(&table_name)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+        // Emulate TABLESAMPLE on large tables: keep a random fraction of rows
+        // first so only that fraction needs sorting
+        match sample_fraction(limit, estimated_rows.unwrap_or(0)) {
+            Some(fraction) => format!(
+                "SELECT * FROM {source} WHERE RAND() < {fraction} ORDER BY RAND() LIMIT {limit}"
+            ),
+            None => format!("SELECT * FROM {source} ORDER BY RAND() LIMIT {limit}"),
+        }
+    } else {
+        format!("SELECT * FROM {source} LIMIT {}", limit + 1)
+    };
+    debug!("Previewing: {query}");
+    let rows = sqlx::query(&query).fetch_all(pool).await?;
+
+    let truncated = rows.len() > limit;
+    let columns = rows
+        .first()
+        .map(|row| row.columns().iter().map(|c| c.name().to_string()).collect())
+        .unwrap_or_default();
+    let rows: Vec<Value> = rows.iter().take(limit).map(row_to_json).collect();
+    let mut result = QueryResult { columns, row_count: rows.len(), rows, truncated, next_cursor: None };
+    masks.apply_to_table(&mut result, Some(&database), &table_name);
+    Ok(result)
+}
 
-    let rows = result?;
+/// Fraction of rows to pre-select when sampling `limit` rows from a table of
+/// `estimated_rows`, or `None` when the table is small enough to sort whole.
+/// The margin covers the estimate being off and RAND() falling short.
+fn sample_fraction(limit: usize, estimated_rows: u64) -> Option<f64> {
+    if estimated_rows < SAMPLE_SORT_THRESHOLD {
+        return None;
+    }
+    let fraction = (limit as f64 * 10.0) / estimated_rows as f64;
+    (fraction < 1.0).then_some(fraction)
+}
 
-    let total_count = rows.len();
-    let truncated = total_count > max_rows;
-    let rows_to_process = if truncated { &rows[..max_rows] } else { &rows[..] };
+/// Tables `search` looks at before giving up and reporting `truncated`.
+const SEARCH_MAX_TABLES: usize = 200;
 
-    let mut results = Vec::with_capacity(rows_to_process.len());
+/// Matching rows `search` returns per table at most.
+pub const SEARCH_MAX_ROWS_PER_TABLE: usize = 20;
 
-    for row in rows_to_process {
-        let mut row_data = serde_json::Map::new();
+/// Time one table's search query may take (MySQL's `MAX_EXECUTION_TIME` hint).
+const SEARCH_TABLE_TIMEOUT_MS: u64 = 5_000;
 
-        for (i, column) in row.columns().iter().enumerate() {
-            let column_name = column.name();
-            let type_name = column.type_info().name();
+pub struct SearchMatch {
+    pub table_name: String,
+    /// Columns whose value matched in at least one returned row.
+    pub columns: Vec<String>,
+    pub rows: Vec<Value>,
+}
 
-            let value_json = match type_name {
-                "BOOLEAN" | "TINYINT" => {
-                    if let Ok(v) = row.try_get::<Option<bool>, _>(i) {
-                        json!(v)
-                    } else {
-                        json!(row.try_get::<Option<i64>, _>(i).unwrap_or(None))
-                    }
-                }
-                "SMALLINT" | "INT" | "INTEGER" | "BIGINT" => {
-                    json!(row.try_get::<Option<i64>, _>(i).unwrap_or(None))
-                }
-                "FLOAT" | "DOUBLE" | "REAL" => {
-                    json!(row.try_get::<Option<f64>, _>(i).unwrap_or(None))
-                }
-                "DECIMAL" | "NUMERIC" => {
-                    if let Ok(v) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(i) {
-                        json!(v.map(|d| d.to_string()))
-                    } else {
-                        json!(null)
-                    }
-                }
-                "DATE" | "TIME" | "DATETIME" | "TIMESTAMP" => {
-                    json!(row.try_get::<Option<String>, _>(i).unwrap_or(None))
-                }
-                _ => {
-                    json!(row.try_get::<Option<String>, _>(i).unwrap_or(None))
-                }
-            };
+pub struct SearchResult {
+    pub database: String,
+    pub matches: Vec<SearchMatch>,
+    pub tables_searched: usize,
+    /// Tables whose search hit the time limit or failed.
+    pub skipped: Vec<String>,
+    /// True when there were more candidate tables than the search covers.
+    pub truncated: bool,
+}
 
-            row_data.insert(column_name.to_string(), value_json);
+/// Search the text columns of every table (or just `tables`) for `value`,
+/// one table at a time. Columns covered by a FULLTEXT index are searched with
+/// `MATCH ... AGAINST` as a phrase, the rest with `LIKE '%value%'` (or `=` when
+/// `exact`). Each table's query is time-limited and a slow table is skipped
+/// rather than failing the search. Masked columns are not searched, so a
+/// match cannot reveal what they hold, and are masked in the rows returned.
+pub async fn search_values(
+    value: String,
+    database: Option<String>,
+    tables: Option<Vec<String>>,
+    exact: bool,
+    rows_per_table: usize,
+    masks: &Masks,
+    pool: &Pool<MySql>,
+) -> Result<SearchResult, DbError> {
+    if value.is_empty() {
+        return Err(DbError::InvalidInput("Search value is empty".to_string()));
+    }
+    let database = match database {
+        Some(db) if !is_valid_identifier(&db) => return Err(DbError::InvalidIdentifier(db)),
+        Some(db) => db,
+        None => current_database(pool).await?,
+    };
+    let rows_per_table = rows_per_table.clamp(1, SEARCH_MAX_ROWS_PER_TABLE);
+
+    // Text columns long enough to hold the value, grouped by table
+    let column_rows = sqlx::query(
+        "SELECT c.table_name AS table_name, c.column_name AS column_name
+         FROM information_schema.columns c
+         JOIN information_schema.tables t
+           ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+         WHERE c.table_schema = ? AND t.table_type = 'BASE TABLE'
+           AND c.data_type IN ('char', 'varchar', 'tinytext', 'text', 'mediumtext', 'longtext', 'enum', 'set')
+           AND (c.character_maximum_length IS NULL OR c.character_maximum_length >= ?)
+         ORDER BY c.table_name, c.ordinal_position",
+    )
+    .bind(&database)
+    .bind(value.chars().count() as u64)
+    .fetch_all(pool)
+    .await?;
+    let mut candidates: Vec<(String, Vec<String>)> = Vec::new();
+    for row in column_rows {
+        let table: String = row.try_get("table_name")?;
+        let column: String = row.try_get("column_name")?;
+        if tables.as_ref().is_some_and(|only| !only.contains(&table)) || masks.is_masked(Some(&database), &table, &column) {
+            continue;
+        }
+        match candidates.last_mut() {
+            Some((last, columns)) if *last == table => columns.push(column),
+            _ => candidates.push((table, vec![column])),
+        }
+    }
+    let truncated = candidates.len() > SEARCH_MAX_TABLES;
+    candidates.truncate(SEARCH_MAX_TABLES);
+
+    let fulltext = if exact { Vec::new() } else { fulltext_indexes(&database, pool).await? };
+    let needle = value.to_lowercase();
+    let total = candidates.len() as u64;
+    let mut matches = Vec::new();
+    let mut skipped = Vec::new();
+    for (i, (table, columns)) in candidates.iter().enumerate() {
+        if !columns.iter().all(|c| is_valid_identifier(c)) || !is_valid_identifier(table) {
+            skipped.push(table.clone());
+            continue;
+        }
+        let (conditions, params) = search_conditions(table, columns, &fulltext, &value, exact);
+        let query = format!(
+            "SELECT /*+ MAX_EXECUTION_TIME({SEARCH_TABLE_TIMEOUT_MS}) */ * FROM {}.{} WHERE {} LIMIT {rows_per_table}",
+            quote_name(&database),
+            quote_name(table),
+            conditions.join(" OR ")
+        );
+        debug!("Searching: {query}");
+        let mut q = sqlx::query(&query);
+        for param in params {
+            q = q.bind(param);
+        }
+        report_progress(i as u64 + 1, Some(total), &format!("Searching {table}"));
+        let rows = match q.fetch_all(pool).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                debug!("Search of {table} failed: {e}");
+                skipped.push(table.clone());
+                continue;
+            }
+        };
+        if rows.is_empty() {
+            continue;
         }
 
-        results.push(json!(row_data));
+        let rows = rows.iter().map(row_to_json).collect();
+        matches.push(search_match(table, columns, rows, &needle, exact, &database, masks));
     }
 
-    Ok(QueryResult {
-        row_count: results.len(),
-        rows: results,
-        truncated,
-    })
+    Ok(SearchResult { database, matches, tables_searched: candidates.len() - skipped.len(), skipped, truncated })
 }
 
-pub async fn insert_data(
-    table_name: String,
-    data: Value,
-    pool: &Pool<MySql>,
-) -> Result<InsertResult, DbError> {
-    let mut conn = pool.acquire().await.map_err(DbError::ConnectionError)?;
-
-    if !is_valid_identifier(&table_name) {
-        return Err(DbError::InvalidIdentifier(table_name));
-    }
-
-    let data_map = data
-        .as_object()
-        .ok_or_else(|| DbError::InvalidInput("Data must be an object".to_string()))?;
+/// The rows found in `table` with the searched `columns` they matched
+/// `needle` (lowercased) in, then masked.
+fn search_match(table: &str, columns: &[String], mut rows: Vec<Value>, needle: &str, exact: bool, database: &str, masks: &Masks) -> SearchMatch {
+    let matched_columns = columns
+        .iter()
+        .filter(|column| {
+            rows.iter().any(|row| {
+                row[column.as_str()].as_str().is_some_and(|text| {
+                    let text = text.to_lowercase();
+                    if exact { text == needle } else { text.contains(needle) }
+                })
+            })
+        })
+        .cloned()
+        .collect();
+    masks.apply_to_rows(&mut rows, Some(database), table);
+    SearchMatch { table_name: table.to_string(), columns: matched_columns, rows }
+}
 
-    if data_map.is_empty() {
-        return Err(DbError::InvalidInput("Data object is empty".to_string()));
+/// WHERE conditions (joined with OR) and their bound values for searching
+/// `columns` of `table`. A FULLTEXT index whose columns are all candidates
+/// replaces the LIKE conditions on those columns.
+fn search_conditions(
+    table: &str,
+    columns: &[String],
+    fulltext: &[(String, String, Vec<String>)],
+    value: &str,
+    exact: bool,
+) -> (Vec<String>, Vec<String>) {
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    let mut covered: Vec<&String> = Vec::new();
+    if !exact {
+        for (_, _, index_columns) in fulltext
+            .iter()
+            .filter(|(t, _, index_columns)| t == table && index_columns.iter().all(|c| columns.contains(c)))
+        {
+            let list: Vec<String> = index_columns.iter().map(|c| quote_name(c)).collect();
+            conditions.push(format!("MATCH({}) AGAINST (? IN BOOLEAN MODE)", list.join(", ")));
+            // Search for the whole value as a phrase
+            params.push(format!("\"{}\"", value.replace('"', "")));
+            covered.extend(index_columns);
+        }
     }
-
-    let columns: Vec<String> = data_map.keys().cloned().collect();
-    for col in &columns {
-        if !is_valid_identifier(col) {
-            return Err(DbError::InvalidIdentifier(col.clone()));
+    for column in columns.iter().filter(|c| !covered.contains(c)) {
+        if exact {
+            conditions.push(format!("{} = ?", quote_name(column)));
+            params.push(value.to_string());
+        } else {
+            conditions.push(format!("{} LIKE ?", quote_name(column)));
+            params.push(format!("%{}%", escape_like(value)));
         }
     }
+    (conditions, params)
+}
 
-    let placeholders: Vec<String> = (0..columns.len()).map(|_| "?".to_string()).collect();
-    let query = format!(
-        "INSERT INTO `{}` ({}) VALUES ({})",
-        table_name,
-        columns.iter().map(|c| format!("`{}`", c)).collect::<Vec<_>>().join(", "),
-        placeholders.join(", ")
-    );
-
-    let mut query_builder = sqlx::query(&query);
-    for column in &columns {
-        if let Some(value) = data_map.get(column) {
-            query_builder = query_builder.bind(value);
+/// FULLTEXT indexes of `database` as (table, index, columns).
+async fn fulltext_indexes(database: &str, pool: &Pool<MySql>) -> Result<Vec<(String, String, Vec<String>)>, DbError> {
+    let rows = sqlx::query(
+        "SELECT table_name AS table_name, index_name AS index_name, column_name AS column_name
+         FROM information_schema.statistics
+         WHERE table_schema = ? AND index_type = 'FULLTEXT'
+         ORDER BY table_name, index_name, seq_in_index",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    let mut indexes: Vec<(String, String, Vec<String>)> = Vec::new();
+    for row in rows {
+        let table: String = row.try_get("table_name")?;
+        let index: String = row.try_get("index_name")?;
+        let column: String = row.try_get("column_name")?;
+        match indexes.last_mut() {
+            Some((t, i, columns)) if *t == table && *i == index => columns.push(column),
+            _ => indexes.push((table, index, vec![column])),
         }
     }
+    Ok(indexes)
+}
 
-    debug!("Executing insert query: {}", query);
-    query_builder.execute(&mut *conn).await?;
-
-    let last_id: u64 = sqlx::query_scalar("SELECT LAST_INSERT_ID()")
-        .fetch_one(&mut *conn)
-        .await
-        .unwrap_or(0);
-
-    Ok(InsertResult { last_insert_id: last_id })
+pub struct FulltextResult {
+    pub index: String,
+    pub columns: Vec<String>,
+    pub result: QueryResult,
 }
 
-pub async fn update_data(
-    table_name: String,
-    data: Value,
-    conditions: Value,
+/// Rank the rows of a table against `query` with `MATCH ... AGAINST` on one
+/// of its FULLTEXT indexes, best match first, with the relevance as `_score`.
+pub async fn fulltext_search(
+    args: FulltextSearchArguments,
     pool: &Pool<MySql>,
-) -> Result<MutationResult, DbError> {
-    let mut conn = pool.acquire().await.map_err(DbError::ConnectionError)?;
-
-    if !is_valid_identifier(&table_name) {
-        return Err(DbError::InvalidIdentifier(table_name));
+    max_rows: usize,
+    masks: &Masks,
+) -> Result<FulltextResult, DbError> {
+    if args.query.trim().is_empty() {
+        return Err(DbError::InvalidInput("Search query is empty".to_string()));
     }
+    let database = match &args.database {
+        Some(db) if !is_valid_identifier(db) => return Err(DbError::InvalidIdentifier(db.clone())),
+        Some(db) => db.clone(),
+        None => current_database(pool).await?,
+    };
+    if !is_valid_identifier(&args.table_name) {
+        return Err(DbError::InvalidIdentifier(args.table_name));
+    }
+    let indexes: Vec<(String, Vec<String>)> = fulltext_indexes(&database, pool)
+        .await?
+        .into_iter()
+        .filter(|(table, _, _)| *table == args.table_name)
+        .map(|(_, index, columns)| (index, columns))
+        .collect();
+    let (index, columns) = choose_fulltext_index(&args.table_name, indexes, args.columns.as_deref())?;
+
+    let limit = args.limit.unwrap_or(max_rows).min(max_rows);
+    let (query, params) = fulltext_sql(&args, &database, &columns, limit)?;
+    debug!("Executing full-text search: {}", query);
+    record_statement(&query);
+    let mut result = fetch_result(&query, &params, limit, pool).await?;
+    masks.apply_to_table(&mut result, Some(&database), &args.table_name);
+    Ok(FulltextResult { index, columns, result })
+}
 
-    let data_map = data
-        .as_object()
-        .ok_or_else(|| DbError::InvalidInput("Data must be an object".to_string()))?;
-
-    let conditions_map = conditions
+/// The FULLTEXT index whose columns are exactly `columns` (in any order), or
+/// the table's only one when no columns are given.
+fn choose_fulltext_index(
+    table: &str,
+    indexes: Vec<(String, Vec<String>)>,
+    columns: Option<&[String]>,
+) -> Result<(String, Vec<String>), DbError> {
+    let available = || {
+        indexes
+            .iter()
+            .map(|(name, columns)| format!("{name} ({})", columns.join(", ")))
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+    if indexes.is_empty() {
+        return Err(DbError::InvalidInput(format!(
+            "Table '{table}' has no FULLTEXT index; create one with create_index (type fulltext) or use search, which falls back to LIKE"
+        )));
+    }
+    match columns {
+        Some(wanted) => {
+            let same = |index_columns: &[String]| {
+                index_columns.len() == wanted.len()
+                    && wanted.iter().all(|w| index_columns.iter().any(|c| c.eq_ignore_ascii_case(w)))
+            };
+            match indexes.iter().position(|(_, index_columns)| same(index_columns)) {
+                Some(i) => Ok(indexes[i].clone()),
+                None => Err(DbError::InvalidInput(format!(
+                    "No FULLTEXT index on '{table}' has exactly the columns {}; available: {}",
+                    wanted.join(", "),
+                    available()
+                ))),
+            }
+        }
+        None if indexes.len() == 1 => Ok(indexes[0].clone()),
+        None => Err(DbError::InvalidInput(format!(
+            "Table '{table}' has several FULLTEXT indexes; pass the columns of one: {}",
+            available()
+        ))),
+    }
+}
+
+/// The ranked full-text SELECT and its bound values. One row past `limit` is
+/// requested so truncation can be detected.
+fn fulltext_sql(
+    args: &FulltextSearchArguments,
+    database: &str,
+    columns: &[String],
+    limit: usize,
+) -> Result<(String, Vec<Value>), DbError> {
+    let modifier = match args.mode {
+        FulltextMode::Natural => "IN NATURAL LANGUAGE MODE",
+        FulltextMode::Boolean => "IN BOOLEAN MODE",
+        FulltextMode::Expansion => "WITH QUERY EXPANSION",
+    };
+    let matches = format!("MATCH({}) AGAINST (? {modifier})", identifier_list(columns)?);
+    let table = qualified_name(Some(database), &args.table_name)?;
+    // Once for the score and once for the filter
+    let mut params = vec![json!(args.query), json!(args.query)];
+    let mut query = format!("SELECT *, {matches} AS `_score` FROM {table} WHERE {matches}");
+
+    let (clause, condition_params) = where_clause(args.conditions.as_ref())?;
+    if let Some(conditions) = clause.strip_prefix(" WHERE ") {
+        query.push_str(&format!(" AND {conditions}"));
+        params.extend(condition_params);
+    }
+    if let Some(min_score) = args.min_score {
+        query.push_str(" HAVING `_score` >= ?");
+        params.push(json!(min_score));
+    }
+    query.push_str(&format!(" ORDER BY `_score` DESC LIMIT {}", limit + 1));
+    if args.offset > 0 {
+        query.push_str(&format!(" OFFSET {}", args.offset));
+    }
+    Ok((query, params))
+}
+
+/// Escape `%`, `_`, and `\` so `value` matches literally inside a LIKE pattern.
+pub fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// What kind of identifier an argument completion is for.
+pub enum IdentifierKind {
+    Database,
+    /// Tables of the given database, or of the current one if `None`.
+    Table(Option<String>),
+}
+
+/// Identifiers starting with `prefix`, at most `limit` of them, plus whether more exist.
+pub async fn complete_identifiers(
+    kind: IdentifierKind,
+    prefix: &str,
+    limit: usize,
+    pool: &Pool<MySql>,
+) -> Result<(Vec<String>, bool), DbError> {
+    let pattern = format!("{}%", escape_like(prefix));
+    let fetch_limit = limit as i64 + 1;
+    let mut names: Vec<String> = match kind {
+        IdentifierKind::Database => {
+            sqlx::query_scalar(
+                "SELECT schema_name FROM information_schema.schemata
+                 WHERE schema_name LIKE ? ORDER BY schema_name LIMIT ?",
+            )
+            .bind(&pattern)
+            .bind(fetch_limit)
+            .fetch_all(pool)
+            .await?
+        }
+        IdentifierKind::Table(database) => {
+            let database = match database {
+                Some(db) => db,
+                None => current_database(pool).await?,
+            };
+            sqlx::query_scalar(
+                "SELECT table_name FROM information_schema.tables
+                 WHERE table_schema = ? AND table_name LIKE ? ORDER BY table_name LIMIT ?",
+            )
+            .bind(&database)
+            .bind(&pattern)
+            .bind(fetch_limit)
+            .fetch_all(pool)
+            .await?
+        }
+    };
+
+    let has_more = names.len() > limit;
+    names.truncate(limit);
+    Ok((names, has_more))
+}
+
+/// A hash of a table's column definitions and create/update times, used to
+/// detect DDL and data changes. `None` if the table does not exist.
+/// A connection whose information_schema table statistics are read live.
+/// MySQL 8 caches UPDATE_TIME, TABLE_ROWS and sizes for a day by default
+/// (the variable doesn't exist on MariaDB or 5.7, which don't cache).
+pub async fn acquire_with_fresh_stats(
+    pool: &Pool<MySql>,
+) -> Result<sqlx::pool::PoolConnection<MySql>, DbError> {
+    let mut conn = pool.acquire().await.map_err(DbError::ConnectionError)?;
+    let _ = sqlx::query("SET SESSION information_schema_stats_expiry = 0")
+        .execute(&mut *conn)
+        .await;
+    Ok(conn)
+}
+
+pub async fn table_fingerprint(
+    database: &str,
+    table_name: &str,
+    pool: &Pool<MySql>,
+) -> Result<Option<u64>, DbError> {
+    use std::hash::{Hash, Hasher};
+
+    let mut conn = acquire_with_fresh_stats(pool).await?;
+
+    let times = sqlx::query(
+        "SELECT CAST(create_time AS CHAR) AS create_time, CAST(update_time AS CHAR) AS update_time
+         FROM information_schema.tables
+         WHERE table_schema = ? AND table_name = ?",
+    )
+    .bind(database)
+    .bind(table_name)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(times) = times else {
+        return Ok(None);
+    };
+
+    let columns = sqlx::query(
+        "SELECT column_name, column_type, is_nullable, column_default, column_key, extra
+         FROM information_schema.columns
+         WHERE table_schema = ? AND table_name = ?
+         ORDER BY ordinal_position",
+    )
+    .bind(database)
+    .bind(table_name)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    times.try_get::<Option<String>, _>("create_time")?.hash(&mut hasher);
+    times.try_get::<Option<String>, _>("update_time")?.hash(&mut hasher);
+    for column in &columns {
+        for field in ["column_name", "column_type", "is_nullable", "column_default", "column_key", "extra"] {
+            column.try_get::<Option<String>, _>(field)?.hash(&mut hasher);
+        }
+    }
+    Ok(Some(hasher.finish()))
+}
+
+/// Run `query` and return up to `max_rows` rows. A truncated result carries
+/// a cursor; passing it back with the same query skips the rows already
+/// returned.
+pub async fn execute_query(
+    args: QueryArguments,
+    conn: &mut sqlx::MySqlConnection,
+    permissions: Permissions,
+    max_rows: usize,
+    masks: &Masks,
+    guardrails: Guardrails,
+) -> Result<QueryResult, DbError> {
+    let QueryArguments { query, params, database, cursor, .. } = args;
+    let mask_database = database.clone();
+    let offset = match &cursor {
+        Some(cursor) => parse_query_cursor(cursor, &query, &params)?,
+        None => 0,
+    };
+
+    // Classified from the parsed statement; in read-only mode the transaction
+    // below remains the enforcement on the server side
+    if !permissions.allows_all() {
+        policy::ensure_permitted(&query, permissions)?;
+    }
+    if guardrails.is_active() {
+        ensure_within_guardrails(&query, database.as_deref(), guardrails, conn).await?;
+    }
+
+    // Cap the rows MySQL produces, not only those read from the stream: one
+    // more than a page past the cursor, so truncation is still detected
+    let capped = policy::cap_limit(&query, (offset + max_rows + 1) as u64);
+    let sql = capped.as_deref().unwrap_or(&query);
+
+    debug!("Executing query: {}", sql);
+    record_statement(sql);
+
+    track_connection(conn).await?;
+
+    // Set database context if specified
+    if let Some(db) = database {
+        debug!("Setting database context to: {}", db);
+        if !is_valid_identifier(&db) {
+            return Err(DbError::InvalidIdentifier(db));
+        }
+        let use_query = format!("USE {}", quote_name(&db));
+        sqlx::query(&use_query).execute(&mut *conn).await?;
+    }
+
+    // Read-only enforcement via MySQL transaction
+    if permissions.is_read_only() {
+        sqlx::query("START TRANSACTION READ ONLY")
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let result = fetch_rows(conn, sql, &params, offset, max_rows).await;
+
+    // Always rollback the read-only transaction (whether query succeeded or failed)
+    if permissions.is_read_only() {
+        let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+    }
+
+    let rows = result?;
+
+    let truncated = rows.len() > max_rows;
+    let rows_to_process = if truncated { &rows[..max_rows] } else { &rows[..] };
+
+    let results: Vec<Value> = rows_to_process.iter().map(row_to_json).collect();
+    // Row objects are keyed by name, so keep the select-list order separately
+    let columns = rows
+        .first()
+        .map(|row| row.columns().iter().map(|c| c.name().to_string()).collect())
+        .unwrap_or_default();
+
+    let mut result = QueryResult {
+        columns,
+        row_count: results.len(),
+        rows: results,
+        truncated,
+        next_cursor: truncated.then(|| query_cursor(&query, &params, offset + max_rows)),
+    };
+    masks.apply_to_query(&mut result, &query, mask_database.as_deref());
+    Ok(result)
+}
+
+/// `<offset>:<fingerprint>` where the fingerprint ties the cursor to the
+/// query text and params it was issued for.
+fn query_cursor(query: &str, params: &[Value], offset: usize) -> String {
+    format!("{}:{:016x}", offset, query_fingerprint(query, params))
+}
+
+fn parse_query_cursor(cursor: &str, query: &str, params: &[Value]) -> Result<usize, DbError> {
+    let invalid = || DbError::InvalidInput(format!("Invalid cursor: {}", cursor));
+    let (offset, fingerprint) = cursor.split_once(':').ok_or_else(invalid)?;
+    let offset = offset.parse().map_err(|_| invalid())?;
+    if fingerprint != format!("{:016x}", query_fingerprint(query, params)) {
+        return Err(DbError::InvalidInput(
+            "Cursor belongs to a different query; pass the same query and params it was returned for".to_string(),
+        ));
+    }
+    Ok(offset)
+}
+
+fn query_fingerprint(query: &str, params: &[Value]) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    query.trim().hash(&mut hasher);
+    Value::Array(params.to_vec()).to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// `EXPLAIN ANALYZE` appeared in MySQL 8.0.18; MariaDB has a different syntax.
+pub fn supports_explain_analyze(version: &str) -> bool {
+    if version.to_lowercase().contains("mariadb") {
+        return false;
+    }
+    let mut parts = version
+        .split(|c: char| !c.is_ascii_digit())
+        .take(3)
+        .map(|p| p.parse::<u32>().unwrap_or(0));
+    let (major, minor, patch) = (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+    );
+    (major, minor, patch) >= (8, 0, 18)
+}
+
+/// Return the optimizer's JSON plan for `query`, plus measured `EXPLAIN ANALYZE`
+/// output when `analyze` is set. ANALYZE actually runs the statement, so outside
+/// dangerous mode it is limited to SELECTs inside a read-only transaction.
+pub async fn explain_query(
+    query: String,
+    database: Option<String>,
+    analyze: bool,
+    pool: &Pool<MySql>,
+    permissions: Permissions,
+) -> Result<ExplainResult, DbError> {
+    let trimmed_upper = query.trim().to_uppercase();
+    let explainable = ["SELECT", "WITH", "TABLE", "INSERT", "REPLACE", "UPDATE", "DELETE"];
+    if !explainable.iter().any(|kw| trimmed_upper.starts_with(kw)) {
+        return Err(DbError::InvalidInput(
+            "Only SELECT, TABLE, WITH, INSERT, REPLACE, UPDATE, and DELETE statements can be explained".to_string(),
+        ));
+    }
+    let kinds = policy::classify(&query)?;
+    let read_only_statement = kinds.iter().all(|k| *k == StatementKind::Read);
+    if analyze && !kinds.iter().all(|k| permissions.allows(*k)) {
+        return Err(DbError::ReadOnlyViolation(
+            "EXPLAIN ANALYZE executes the statement, so only statements enabled by the allow_* flags can be analyzed".to_string(),
+        ));
+    }
+
+    let mut conn = pool.acquire().await.map_err(DbError::ConnectionError)?;
+    track_connection(&mut conn).await?;
+
+    if let Some(db) = database {
+        if !is_valid_identifier(&db) {
+            return Err(DbError::InvalidIdentifier(db));
+        }
+        sqlx::query(&format!("USE {}", quote_name(&db))).execute(&mut *conn).await?;
+    }
+
+    debug!("Explaining query: {}", query);
+    record_statement(&format!("EXPLAIN FORMAT=JSON {query}"));
+    let plan: String = sqlx::query_scalar(&format!("EXPLAIN FORMAT=JSON {query}"))
+        .fetch_one(&mut *conn)
+        .await?;
+    let plan = serde_json::from_str(&plan).unwrap_or(Value::String(plan));
+
+    let analyze = if analyze {
+        let version: String = sqlx::query_scalar("SELECT VERSION()").fetch_one(&mut *conn).await?;
+        if !supports_explain_analyze(&version) {
+            return Err(DbError::InvalidInput(format!(
+                "EXPLAIN ANALYZE requires MySQL 8.0.18 or later (server is {version})"
+            )));
+        }
+        let read_only = read_only_statement;
+        if read_only {
+            sqlx::query("START TRANSACTION READ ONLY").execute(&mut *conn).await?;
+        }
+        record_statement(&format!("EXPLAIN ANALYZE {query}"));
+        let result = sqlx::query_scalar::<_, String>(&format!("EXPLAIN ANALYZE {query}"))
+            .fetch_one(&mut *conn)
+            .await;
+        if read_only {
+            let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+        }
+        Some(result?)
+    } else {
+        None
+    };
+
+    Ok(ExplainResult { plan, analyze })
+}
+
+/// Convert a result row into a JSON object keyed by column name.
+pub fn row_to_json(row: &sqlx::mysql::MySqlRow) -> Value {
+    let mut row_data = serde_json::Map::new();
+
+    for (i, column) in row.columns().iter().enumerate() {
+        let column_name = column.name();
+        let type_name = column.type_info().name();
+
+        let value_json = match type_name {
+            "BOOLEAN" | "TINYINT" => {
+                if let Ok(v) = row.try_get::<Option<bool>, _>(i) {
+                    json!(v)
+                } else {
+                    json!(row.try_get::<Option<i64>, _>(i).unwrap_or(None))
+                }
+            }
+            "SMALLINT" | "INT" | "INTEGER" | "BIGINT" => {
+                json!(row.try_get::<Option<i64>, _>(i).unwrap_or(None))
+            }
+            "FLOAT" | "DOUBLE" | "REAL" => {
+                json!(row.try_get::<Option<f64>, _>(i).unwrap_or(None))
+            }
+            "DECIMAL" | "NUMERIC" => {
+                if let Ok(v) = row.try_get::<Option<sqlx::types::BigDecimal>, _>(i) {
+                    json!(v.map(|d| d.to_string()))
+                } else {
+                    json!(null)
+                }
+            }
+            "DATE" | "TIME" | "DATETIME" | "TIMESTAMP" => {
+                json!(row.try_get::<Option<String>, _>(i).unwrap_or(None))
+            }
+            _ => {
+                json!(row.try_get::<Option<String>, _>(i).unwrap_or(None))
+            }
+        };
+
+        row_data.insert(column_name.to_string(), value_json);
+    }
+
+    json!(row_data)
+}
+
+/// Rows between progress notifications while streaming a result set.
+pub const PROGRESS_INTERVAL_ROWS: usize = 500;
+
+/// Stream the result of `query`, discarding the first `skip` rows and
+/// stopping after `max_rows + 1` more so a truncated result can be detected
+/// without buffering the whole table.
+async fn fetch_rows(
+    conn: &mut sqlx::MySqlConnection,
+    query: &str,
+    params: &[Value],
+    skip: usize,
+    max_rows: usize,
+) -> Result<Vec<sqlx::mysql::MySqlRow>, sqlx::Error> {
+    let mut query = sqlx::query(query);
+    for param in params {
+        query = bind_param(query, param);
+    }
+    let mut stream = query.fetch(&mut *conn).skip(skip);
+    let mut rows = Vec::new();
+    while let Some(row) = stream.next().await {
+        rows.push(row?);
+        if rows.len() % PROGRESS_INTERVAL_ROWS == 0 {
+            report_progress(rows.len() as u64, None, &format!("Fetched {} rows", rows.len()));
+        }
+        if rows.len() > max_rows {
+            break;
+        }
+    }
+    Ok(rows)
+}
+
+/// `CALL` a stored procedure with bound arguments and collect every result set
+/// it produces, each capped at `max_rows` rows.
+pub async fn call_procedure(
+    name: String,
+    params: Vec<Value>,
+    database: Option<String>,
+    conn: &mut sqlx::MySqlConnection,
+    max_rows: usize,
+    masks: &Masks,
+) -> Result<ProcedureResult, DbError> {
+    if !is_valid_identifier(&name) {
+        return Err(DbError::InvalidIdentifier(name));
+    }
+    track_connection(conn).await?;
+    if let Some(db) = database {
+        if !is_valid_identifier(&db) {
+            return Err(DbError::InvalidIdentifier(db));
+        }
+        sqlx::query(&format!("USE {}", quote_name(&db))).execute(&mut *conn).await?;
+    }
+
+    let placeholders = vec!["?"; params.len()].join(", ");
+    let call = format!("CALL {}({placeholders})", quote_name(&name));
+    debug!("Executing: {call}");
+    record_statement(&call);
+    let mut query = sqlx::query(&call);
+    for param in &params {
+        query = bind_param(query, param);
+    }
+
+    // The server sends every result set followed by an OK packet, then a final
+    // OK for the CALL itself; each OK closes the rows collected so far
+    let mut result_sets = Vec::new();
+    let mut current = ResultSet { columns: Vec::new(), rows: Vec::new(), truncated: false };
+    let mut affected_rows = 0;
+    let mut stream = sqlx::Executor::fetch_many(&mut *conn, query);
+    while let Some(item) = stream.next().await {
+        match item? {
+            sqlx::Either::Left(done) => {
+                affected_rows += done.rows_affected();
+                if !current.rows.is_empty() {
+                    result_sets.push(std::mem::replace(
+                        &mut current,
+                        ResultSet { columns: Vec::new(), rows: Vec::new(), truncated: false },
+                    ));
+                }
+            }
+            sqlx::Either::Right(row) => {
+                if current.rows.is_empty() {
+                    current.columns = row.columns().iter().map(|c| c.name().to_string()).collect();
+                }
+                if current.rows.len() < max_rows {
+                    current.rows.push(row_to_json(&row));
+                } else {
+                    current.truncated = true;
+                }
+            }
+        }
+    }
+    if !current.rows.is_empty() {
+        result_sets.push(current);
+    }
+    // What a procedure selects from is unknown, so its columns are masked by name
+    for set in &mut result_sets {
+        masks.for_query(&set.columns, &call, None).apply(&mut set.rows);
+    }
+
+    Ok(ProcedureResult { result_sets, affected_rows })
+}
+
+/// Bind a JSON value to the next `?` placeholder as the matching SQL type.
+/// Arrays and objects are sent as JSON text.
+pub fn bind_param<'q>(
+    query: sqlx::query::Query<'q, MySql, sqlx::mysql::MySqlArguments>,
+    value: &Value,
+) -> sqlx::query::Query<'q, MySql, sqlx::mysql::MySqlArguments> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(b) => query.bind(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                query.bind(i)
+            } else if let Some(u) = n.as_u64() {
+                query.bind(u)
+            } else {
+                query.bind(n.as_f64())
+            }
+        }
+        Value::String(s) => query.bind(s.clone()),
+        Value::Array(_) | Value::Object(_) => query.bind(value.to_string()),
+    }
+}
+
+/// Insert one row object or an array of them, as multi-row INSERTs of
+/// `batch_size` rows. Outside an explicit transaction several batches run in
+/// one, so a failing batch inserts nothing. With `return_rows` up to
+/// `max_rows` of the inserted rows are read back.
+pub async fn insert_data(
+    args: InsertArguments,
+    conn: &mut sqlx::MySqlConnection,
+    in_transaction: bool,
+    max_rows: usize,
+) -> Result<InsertResult, DbError> {
+    if !is_valid_identifier(&args.table_name) {
+        return Err(DbError::InvalidIdentifier(args.table_name));
+    }
+    let rows = insert_rows(args.data)?;
+
+    // Union of all keys in order of first appearance
+    let mut columns: Vec<String> = Vec::new();
+    for row in &rows {
+        for col in row.keys() {
+            if !columns.contains(col) {
+                if !is_valid_identifier(col) {
+                    return Err(DbError::InvalidIdentifier(col.clone()));
+                }
+                columns.push(col.clone());
+            }
+        }
+    }
+    if columns.is_empty() {
+        return Err(DbError::InvalidInput("Data object is empty".to_string()));
+    }
+    let batch_size = args.batch_size.clamp(1, (MAX_PLACEHOLDERS / columns.len()).max(1));
+
+    let table = quote_name(&args.table_name);
+    // Settle how to find the rows again before writing anything
+    let read_back = match (args.return_rows, args.key_columns) {
+        (false, _) => None,
+        (true, Some(keys)) => {
+            if keys.is_empty() {
+                return Err(DbError::InvalidInput("key_columns must name at least one column".to_string()));
+            }
+            if let Some(key) = keys.iter().find(|k| rows.iter().any(|row| !row.contains_key(*k))) {
+                return Err(DbError::InvalidInput(format!("Key column '{}' is missing from a row", key)));
+            }
+            Some(ReadBack::Keys(keys))
+        }
+        (true, None) => {
+            let column: Option<String> = sqlx::query_scalar(
+                "SELECT column_name FROM information_schema.columns
+                 WHERE table_schema = DATABASE() AND table_name = ? AND extra LIKE '%auto_increment%'",
+            )
+            .bind(&args.table_name)
+            .fetch_optional(&mut *conn)
+            .await?;
+            let column = column.ok_or_else(|| {
+                DbError::InvalidInput(format!(
+                    "Table '{}' has no auto-increment column; pass key_columns to return the inserted rows",
+                    args.table_name
+                ))
+            })?;
+            // Explicit ids leave LAST_INSERT_ID unset, but then they are the key
+            if rows.iter().all(|row| row.contains_key(&column)) {
+                Some(ReadBack::Keys(vec![column]))
+            } else {
+                Some(ReadBack::AutoIncrement(column))
+            }
+        }
+    };
+
+    if args.dry_run {
+        let (sql, params) = insert_statement(&table, &columns, &rows[..batch_size.min(rows.len())], OnDuplicate::Error);
+        return Ok(InsertResult {
+            rows: rows.len() as u64,
+            batches: rows.len().div_ceil(batch_size) as u64,
+            first_insert_id: 0,
+            last_insert_id: 0,
+            inserted: None,
+            dry_run: Some(DryRun { sql, params }),
+        });
+    }
+
+    let batched = rows.len() > batch_size && !in_transaction;
+    if batched {
+        sqlx::query("START TRANSACTION").execute(&mut *conn).await?;
+    }
+    let result = insert_row_batches(&table, &columns, &rows, batch_size, conn).await;
+    if batched {
+        let end = if result.is_ok() { "COMMIT" } else { "ROLLBACK" };
+        let finished = sqlx::query(end).execute(&mut *conn).await;
+        if result.is_ok() {
+            finished?;
+        }
+    }
+    let mut result = result?;
+
+    if let Some(read_back) = read_back {
+        let (query, params) = match read_back {
+            ReadBack::Keys(keys) => inserted_by_key_sql(&table, &keys, &rows, max_rows)?,
+            ReadBack::AutoIncrement(column) => (
+                format!("SELECT * FROM {} WHERE {} BETWEEN ? AND ? ORDER BY {} LIMIT {}",
+                    table, quote_identifier(&column)?, quote_identifier(&column)?, max_rows),
+                vec![json!(result.first_insert_id), json!(result.last_insert_id)],
+            ),
+        };
+        let mut query_builder = sqlx::query(&query);
+        for param in &params {
+            query_builder = bind_param(query_builder, param);
+        }
+        debug!("Reading back inserted rows: {}", query);
+        record_statement(&query);
+        let inserted = query_builder.fetch_all(&mut *conn).await?;
+        result.inserted = Some(inserted.iter().map(row_to_json).collect());
+    }
+    Ok(result)
+}
+
+/// How `insert` finds the rows it wrote.
+enum ReadBack {
+    Keys(Vec<String>),
+    AutoIncrement(String),
+}
+
+/// `SELECT * ... WHERE (k1 = ? AND k2 = ?) OR ...` matching each row's
+/// values for `keys`.
+fn inserted_by_key_sql(
+    table: &str,
+    keys: &[String],
+    rows: &[serde_json::Map<String, Value>],
+    limit: usize,
+) -> Result<(String, Vec<Value>), DbError> {
+    let mut clauses = Vec::new();
+    let mut params = Vec::new();
+    for row in rows.iter().take(limit) {
+        let key_values: serde_json::Map<String, Value> = keys.iter().map(|k| (k.clone(), row[k].clone())).collect();
+        let (clause, row_params) = conditions_clause(&key_values)?;
+        clauses.push(format!("({})", clause));
+        params.extend(row_params);
+    }
+    Ok((format!("SELECT * FROM {} WHERE {} LIMIT {}", table, clauses.join(" OR "), limit), params))
+}
+
+/// The rows in `data`: a single object or an array of objects.
+fn insert_rows(data: Value) -> Result<Vec<serde_json::Map<String, Value>>, DbError> {
+    let rows = match data {
+        Value::Object(row) => vec![row],
+        Value::Array(rows) if !rows.is_empty() => rows
+            .into_iter()
+            .map(|row| match row {
+                Value::Object(row) => Ok(row),
+                _ => Err(DbError::InvalidInput("Every row must be an object".to_string())),
+            })
+            .collect::<Result<_, _>>()?,
+        Value::Array(_) => return Err(DbError::InvalidInput("Data array is empty".to_string())),
+        _ => return Err(DbError::InvalidInput("Data must be an object or an array of objects".to_string())),
+    };
+    Ok(rows)
+}
+
+async fn insert_row_batches(
+    table: &str,
+    columns: &[String],
+    rows: &[serde_json::Map<String, Value>],
+    batch_size: usize,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<InsertResult, DbError> {
+    let mut inserted = InsertResult { rows: 0, batches: 0, first_insert_id: 0, last_insert_id: 0, inserted: None, dry_run: None };
+    for batch in rows.chunks(batch_size) {
+        let (query, params) = insert_statement(table, columns, batch, OnDuplicate::Error);
+        let mut query_builder = sqlx::query(&query);
+        for param in &params {
+            query_builder = bind_param(query_builder, param);
+        }
+        debug!("Executing insert query: {}", query);
+        record_statement(&query);
+        let result = query_builder.execute(&mut *conn).await?;
+
+        // LAST_INSERT_ID is the id of the first row of the statement
+        let id = result.last_insert_id();
+        if id != 0 {
+            if inserted.first_insert_id == 0 {
+                inserted.first_insert_id = id;
+            }
+            inserted.last_insert_id = id + batch.len() as u64 - 1;
+        }
+        inserted.rows += result.rows_affected();
+        inserted.batches += 1;
+    }
+    Ok(inserted)
+}
+
+/// Insert `data`, or when it collides with a primary or unique key, update
+/// `update_columns` of the existing row to the values in `data`.
+pub async fn upsert_data(args: UpsertArguments, conn: &mut sqlx::MySqlConnection) -> Result<UpsertResult, DbError> {
+    let data = args
+        .data
         .as_object()
-        .ok_or_else(|| DbError::InvalidInput("Conditions must be an object".to_string()))?;
+        .ok_or_else(|| DbError::InvalidInput("Data must be an object".to_string()))?;
+    let table = qualified_name(args.database.as_deref(), &args.table_name)?;
+    let query = upsert_sql(&table, data, &args.update_columns)?;
 
-    if data_map.is_empty() {
+    let mut query_builder = sqlx::query(&query);
+    for value in data.values() {
+        query_builder = bind_param(query_builder, value);
+    }
+    debug!("Executing upsert query: {}", query);
+    record_statement(&query);
+    let result = query_builder.execute(&mut *conn).await?;
+
+    Ok(UpsertResult { affected_rows: result.rows_affected(), last_insert_id: result.last_insert_id() })
+}
+
+/// `INSERT ... ON DUPLICATE KEY UPDATE` with one placeholder per entry of
+/// `data`, in map order.
+fn upsert_sql(table: &str, data: &serde_json::Map<String, Value>, update_columns: &[String]) -> Result<String, DbError> {
+    if data.is_empty() {
         return Err(DbError::InvalidInput("Data object is empty".to_string()));
     }
+    if update_columns.is_empty() {
+        return Err(DbError::InvalidInput("update_columns must name at least one column".to_string()));
+    }
+    if let Some(column) = update_columns.iter().find(|c| !data.contains_key(*c)) {
+        return Err(DbError::InvalidInput(format!("Update column '{}' is not in data", column)));
+    }
+    let columns: Vec<String> = data.keys().cloned().collect();
+    let updates = update_columns
+        .iter()
+        .map(|c| quote_identifier(c).map(|c| format!("{c} = VALUES({c})")))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!(
+        "INSERT INTO {} ({}) VALUES ({}) ON DUPLICATE KEY UPDATE {}",
+        table,
+        identifier_list(&columns)?,
+        vec!["?"; columns.len()].join(", "),
+        updates.join(", ")
+    ))
+}
+
+/// The WHERE expression for a conditions object and its bound values in
+/// placeholder order. Entries are ANDed; a plain value compares for
+/// equality (`null` with IS NULL), and an operator object such as
+/// `{"op": ">", "value": 5}` applies that operator.
+pub fn conditions_clause(conditions: &serde_json::Map<String, Value>) -> Result<(String, Vec<Value>), DbError> {
+    let mut params = Vec::new();
+    let clauses = conditions
+        .iter()
+        .map(|(column, condition)| {
+            let column = quote_identifier(column)?;
+            condition_sql(&column, condition, &mut params)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((clauses.join(" AND "), params))
+}
 
+/// ` WHERE ...` and its bound values for optional conditions, or nothing
+/// when there are none.
+pub fn where_clause(conditions: Option<&Value>) -> Result<(String, Vec<Value>), DbError> {
+    let conditions_map = match conditions {
+        None | Some(Value::Null) => return Ok((String::new(), Vec::new())),
+        Some(conditions) => conditions
+            .as_object()
+            .ok_or_else(|| DbError::InvalidInput("Conditions must be an object".to_string()))?,
+    };
     if conditions_map.is_empty() {
-        return Err(DbError::InvalidInput("Conditions object is empty".to_string()));
+        return Ok((String::new(), Vec::new()));
     }
+    let (clause, params) = conditions_clause(conditions_map)?;
+    Ok((format!(" WHERE {}", clause), params))
+}
 
-    for k in data_map.keys().chain(conditions_map.keys()) {
-        if !is_valid_identifier(k) {
-            return Err(DbError::InvalidIdentifier(k.clone()));
+/// One condition on an already quoted column.
+fn condition_sql(column: &str, condition: &Value, params: &mut Vec<Value>) -> Result<String, DbError> {
+    let operator = match condition {
+        Value::Null => return Ok(format!("{} IS NULL", column)),
+        Value::Object(object) if object.contains_key("op") => object,
+        _ => {
+            params.push(condition.clone());
+            return Ok(format!("{} = ?", column));
+        }
+    };
+    let op = operator["op"]
+        .as_str()
+        .ok_or_else(|| DbError::InvalidInput(format!("Operator for {} must be a string", column)))?
+        .trim()
+        .to_uppercase();
+    let value = operator.get("value");
+    let scalar = || match value {
+        Some(Value::Array(_)) | Some(Value::Object(_)) | None => Err(DbError::InvalidInput(format!(
+            "Operator {} on {} needs a single value",
+            op, column
+        ))),
+        Some(value) => Ok(value.clone()),
+    };
+    let list = || match value {
+        Some(Value::Array(values)) if !values.is_empty() => Ok(values.clone()),
+        _ => Err(DbError::InvalidInput(format!("Operator {} on {} needs a non-empty array", op, column))),
+    };
+
+    Ok(match op.as_str() {
+        // `= NULL` is never true; compare the way a plain `null` does
+        "=" if value == Some(&Value::Null) => format!("{} IS NULL", column),
+        "!=" | "<>" if value == Some(&Value::Null) => format!("{} IS NOT NULL", column),
+        "<" | "<=" | ">" | ">=" | "LIKE" | "NOT LIKE" if value == Some(&Value::Null) => {
+            return Err(DbError::InvalidInput(format!("Operator {} on {} needs a non-null value", op, column)));
+        }
+        "=" | "!=" | "<>" | "<" | "<=" | ">" | ">=" | "LIKE" | "NOT LIKE" => {
+            params.push(scalar()?);
+            format!("{} {} ?", column, op)
+        }
+        "IN" | "NOT IN" => {
+            let values = list()?;
+            let placeholders = vec!["?"; values.len()].join(", ");
+            params.extend(values);
+            format!("{} {} ({})", column, op, placeholders)
+        }
+        "BETWEEN" | "NOT BETWEEN" => {
+            let values = list()?;
+            if values.len() != 2 {
+                return Err(DbError::InvalidInput(format!("Operator {} on {} needs [low, high]", op, column)));
+            }
+            params.extend(values);
+            format!("{} {} ? AND ?", column, op)
+        }
+        "IS NULL" | "IS NOT NULL" => format!("{} {}", column, op),
+        _ => {
+            return Err(DbError::InvalidInput(format!(
+                "Unsupported operator '{}'; use =, !=, <, <=, >, >=, like, not like, in, not in, between, not between, is null, or is not null",
+                op
+            )))
         }
+    })
+}
+
+/// Rows of `table_name` matching `conditions` (all rows when there are
+/// none).
+pub async fn count_rows(
+    table_name: String,
+    database: Option<String>,
+    conditions: Option<Value>,
+    pool: &Pool<MySql>,
+) -> Result<i64, DbError> {
+    if !is_valid_identifier(&table_name) {
+        return Err(DbError::InvalidIdentifier(table_name));
     }
+    let mut query = match database {
+        Some(db) if !is_valid_identifier(&db) => return Err(DbError::InvalidIdentifier(db)),
+        Some(db) => format!("SELECT COUNT(*) FROM {}.{}", quote_name(&db), quote_name(&table_name)),
+        None => format!("SELECT COUNT(*) FROM {}", quote_name(&table_name)),
+    };
+    let (clause, params) = where_clause(conditions.as_ref())?;
+    query.push_str(&clause);
 
-    let set_clause: Vec<String> = data_map.keys().map(|k| format!("`{}` = ?", k)).collect();
-    let where_clause: Vec<String> = conditions_map.keys().map(|k| format!("`{}` = ?", k)).collect();
-    let query = format!(
-        "UPDATE `{}` SET {} WHERE {}",
-        table_name,
-        set_clause.join(", "),
-        where_clause.join(" AND ")
+    let mut query_builder = sqlx::query(&query);
+    for value in &params {
+        query_builder = bind_param(query_builder, value);
+    }
+    debug!("Executing count query: {}", query);
+    record_statement(&query);
+    Ok(query_builder.fetch_one(pool).await?.try_get(0)?)
+}
+
+/// SELECT built from structured arguments, with condition values bound.
+/// At most `max_rows` rows are returned, fewer if `limit` asks for less.
+pub async fn select_rows(args: SelectArguments, pool: &Pool<MySql>, max_rows: usize, masks: &Masks) -> Result<QueryResult, DbError> {
+    let limit = args.limit.unwrap_or(max_rows).min(max_rows);
+    let (query, params) = select_sql(&args, limit)?;
+    debug!("Executing select query: {}", query);
+    record_statement(&query);
+    let mut result = fetch_result(&query, &params, limit, pool).await?;
+    masks.apply_to_table(&mut result, args.database.as_deref(), &args.table_name);
+    Ok(result)
+}
+
+/// The SELECT for `args` and its bound values. One row past `limit` is
+/// requested so truncation can be detected.
+fn select_sql(args: &SelectArguments, limit: usize) -> Result<(String, Vec<Value>), DbError> {
+    let select_list = match &args.columns {
+        Some(columns) if !columns.is_empty() => identifier_list(columns)?,
+        _ => "*".to_string(),
+    };
+    let table = qualified_name(args.database.as_deref(), &args.table_name)?;
+    let mut query = format!("SELECT {} FROM {}", select_list, table);
+
+    let (clause, params) = where_clause(args.conditions.as_ref())?;
+    query.push_str(&clause);
+    if !args.order_by.is_empty() {
+        query.push_str(&format!(" ORDER BY {}", order_by_clause(&args.order_by)?));
+    }
+    query.push_str(&format!(" LIMIT {}", limit + 1));
+    if args.offset > 0 {
+        query.push_str(&format!(" OFFSET {}", args.offset));
+    }
+    Ok((query, params))
+}
+
+/// Run a generated SELECT and return up to `limit` rows, flagging truncation.
+async fn fetch_result(query: &str, params: &[Value], limit: usize, pool: &Pool<MySql>) -> Result<QueryResult, DbError> {
+    let mut conn = pool.acquire().await.map_err(DbError::ConnectionError)?;
+    track_connection(&mut conn).await?;
+    let rows = fetch_rows(&mut conn, query, params, 0, limit).await?;
+
+    let truncated = rows.len() > limit;
+    let columns = rows
+        .first()
+        .map(|row| row.columns().iter().map(|c| c.name().to_string()).collect())
+        .unwrap_or_default();
+    let rows: Vec<Value> = rows.iter().take(limit).map(row_to_json).collect();
+    Ok(QueryResult { columns, row_count: rows.len(), rows, truncated, next_cursor: None })
+}
+
+fn order_by_clause(order_by: &[OrderBy]) -> Result<String, DbError> {
+    let order = order_by
+        .iter()
+        .map(|o| {
+            let direction = match o.direction {
+                SortDirection::Asc => "ASC",
+                SortDirection::Desc => "DESC",
+            };
+            Ok(format!("{} {}", quote_identifier(&o.column)?, direction))
+        })
+        .collect::<Result<Vec<_>, DbError>>()?;
+    Ok(order.join(", "))
+}
+
+fn identifier_list(names: &[String]) -> Result<String, DbError> {
+    Ok(names.iter().map(|n| quote_identifier(n)).collect::<Result<Vec<_>, _>>()?.join(", "))
+}
+
+/// GROUP BY query built from structured aggregations, returning at most
+/// `max_rows` groups. Groups and aggregates of masked columns are masked.
+pub async fn aggregate_rows(args: AggregateArguments, pool: &Pool<MySql>, max_rows: usize, masks: &Masks) -> Result<QueryResult, DbError> {
+    let limit = args.limit.unwrap_or(max_rows).min(max_rows);
+    let (query, params) = aggregate_sql(&args, limit)?;
+    debug!("Executing aggregate query: {}", query);
+    record_statement(&query);
+    let mut result = fetch_result(&query, &params, limit, pool).await?;
+    masks.apply_to_query(&mut result, &query, args.database.as_deref());
+    Ok(result)
+}
+
+fn aggregate_sql(args: &AggregateArguments, limit: usize) -> Result<(String, Vec<Value>), DbError> {
+    if args.aggregations.is_empty() {
+        return Err(DbError::InvalidInput("At least one aggregation is required".to_string()));
+    }
+    let mut select = args.group_by.iter().map(|c| quote_identifier(c)).collect::<Result<Vec<_>, _>>()?;
+    let mut aliases = Vec::new();
+    for aggregation in &args.aggregations {
+        let function = match aggregation.function {
+            AggregateFunction::Count => "COUNT",
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+        };
+        let argument = match (&aggregation.column, aggregation.function) {
+            (Some(column), _) => {
+                let column = quote_identifier(column)?;
+                if aggregation.distinct { format!("DISTINCT {}", column) } else { column }
+            }
+            (None, AggregateFunction::Count) if !aggregation.distinct => "*".to_string(),
+            (None, _) => {
+                return Err(DbError::InvalidInput(format!("{} needs a column", function.to_lowercase())))
+            }
+        };
+        // count, sum_total, count_distinct_customer_id
+        let alias = aggregation.alias.clone().unwrap_or_else(|| {
+            let mut alias = function.to_lowercase();
+            if aggregation.distinct {
+                alias.push_str("_distinct");
+            }
+            if let Some(column) = &aggregation.column {
+                alias.push_str(&format!("_{}", column));
+            }
+            alias
+        });
+        select.push(format!("{}({}) AS {}", function, argument, quote_identifier(&alias)?));
+        aliases.push(alias);
+    }
+
+    let mut query = format!(
+        "SELECT {} FROM {}",
+        select.join(", "),
+        qualified_name(args.database.as_deref(), &args.table_name)?
     );
+    let (clause, params) = where_clause(args.conditions.as_ref())?;
+    query.push_str(&clause);
+    if !args.group_by.is_empty() {
+        query.push_str(&format!(" GROUP BY {}", identifier_list(&args.group_by)?));
+    }
+    if let Some(unknown) = args
+        .order_by
+        .iter()
+        .find(|o| !args.group_by.contains(&o.column) && !aliases.contains(&o.column))
+    {
+        return Err(DbError::InvalidInput(format!(
+            "Cannot order by '{}': use a group_by column or an aggregation alias",
+            unknown.column
+        )));
+    }
+    if !args.order_by.is_empty() {
+        query.push_str(&format!(" ORDER BY {}", order_by_clause(&args.order_by)?));
+    }
+    query.push_str(&format!(" LIMIT {}", limit + 1));
+    Ok((query, params))
+}
 
-    let mut query_builder = sqlx::query(&query);
-    for key in data_map.keys() {
-        if let Some(value) = data_map.get(key) {
-            query_builder = query_builder.bind(value);
-        }
+pub struct DuplicateGroup {
+    /// The shared key values.
+    pub key: Value,
+    pub count: i64,
+    /// Up to `rows_per_group` of the rows sharing the key.
+    pub rows: Vec<Value>,
+}
+
+pub struct DuplicatesResult {
+    pub groups: Vec<DuplicateGroup>,
+    /// Groups with more than one row in the whole table.
+    pub total_groups: i64,
+    /// Rows beyond the first of each group, i.e. how many a cleanup would remove.
+    pub excess_rows: i64,
+    /// Offset of the next page, when there are more groups.
+    pub next_offset: Option<usize>,
+}
+
+/// Groups of rows sharing the values of `columns`, largest first, a page at
+/// a time, with masked columns masked in keys and rows.
+pub async fn find_duplicates(args: DuplicatesArguments, pool: &Pool<MySql>, max_rows: usize, masks: &Masks) -> Result<DuplicatesResult, DbError> {
+    let limit = args.limit.clamp(1, max_rows);
+    let (query, params) = duplicates_sql(&args, limit)?;
+    debug!("Executing duplicates query: {}", query);
+    record_statement(&query);
+    let mut conn = pool.acquire().await.map_err(DbError::ConnectionError)?;
+    track_connection(&mut conn).await?;
+    let rows = fetch_rows(&mut conn, &query, &params, 0, limit).await?;
+    let next_offset = (rows.len() > limit).then_some(args.offset + limit);
+
+    let table = qualified_name(args.database.as_deref(), &args.table_name)?;
+    let (clause, where_params) = where_clause(args.conditions.as_ref())?;
+    let totals = format!(
+        "SELECT COUNT(*), CAST(COALESCE(SUM(n) - COUNT(*), 0) AS SIGNED) FROM \
+         (SELECT COUNT(*) AS n FROM {table}{clause} GROUP BY {} HAVING COUNT(*) > 1) AS duplicates",
+        identifier_list(&args.columns)?
+    );
+    record_statement(&totals);
+    let mut totals_query = sqlx::query(&totals);
+    for param in &where_params {
+        totals_query = bind_param(totals_query, param);
     }
-    for key in conditions_map.keys() {
-        if let Some(value) = conditions_map.get(key) {
-            query_builder = query_builder.bind(value);
+    let totals = totals_query.fetch_one(&mut *conn).await?;
+
+    let mut groups = Vec::new();
+    for row in rows.iter().take(limit) {
+        let mut key = row_to_json(row);
+        let count = key
+            .as_object_mut()
+            .and_then(|k| k.remove("duplicate_count"))
+            .and_then(|c| c.as_i64())
+            .unwrap_or(0);
+        let mut rows = Vec::new();
+        if args.rows_per_group > 0 {
+            // <=> so groups keyed on NULL find their rows too
+            let matches = args.columns.iter().map(|c| Ok(format!("{} <=> ?", quote_identifier(c)?))).collect::<Result<Vec<_>, DbError>>()?;
+            let sql = format!(
+                "SELECT * FROM {table}{clause}{}{} LIMIT {}",
+                if clause.is_empty() { " WHERE " } else { " AND " },
+                matches.join(" AND "),
+                args.rows_per_group
+            );
+            record_statement(&sql);
+            let mut params = where_params.clone();
+            params.extend(args.columns.iter().map(|c| key[c].clone()));
+            rows = fetch_rows(&mut conn, &sql, &params, 0, args.rows_per_group).await?.iter().map(row_to_json).collect();
+            masks.apply_to_rows(&mut rows, args.database.as_deref(), &args.table_name);
         }
+        masks.apply_to_rows(std::slice::from_mut(&mut key), args.database.as_deref(), &args.table_name);
+        groups.push(DuplicateGroup { key, count, rows });
     }
 
+    Ok(DuplicatesResult {
+        groups,
+        total_groups: totals.try_get(0)?,
+        excess_rows: totals.try_get(1)?,
+        next_offset,
+    })
+}
+
+fn duplicates_sql(args: &DuplicatesArguments, limit: usize) -> Result<(String, Vec<Value>), DbError> {
+    if args.columns.is_empty() {
+        return Err(DbError::InvalidInput("At least one key column is required".to_string()));
+    }
+    let columns = identifier_list(&args.columns)?;
+    let (clause, params) = where_clause(args.conditions.as_ref())?;
+    let mut query = format!(
+        "SELECT {columns}, COUNT(*) AS `duplicate_count` FROM {}{clause} GROUP BY {columns} \
+         HAVING COUNT(*) > 1 ORDER BY `duplicate_count` DESC, {columns} LIMIT {}",
+        qualified_name(args.database.as_deref(), &args.table_name)?,
+        limit + 1
+    );
+    if args.offset > 0 {
+        query.push_str(&format!(" OFFSET {}", args.offset));
+    }
+    Ok((query, params))
+}
+
+pub async fn update_data(
+    args: UpdateArguments,
+    max_affected_rows: Option<u64>,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<MutationResult, DbError> {
+    let (query, params) = update_sql(&args)?;
+    if args.dry_run {
+        let affected_rows = count_matching(&args.table_name, &args.conditions, args.limit, conn).await?;
+        return Ok(MutationResult { affected_rows, dry_run: Some(DryRun { sql: query, params }) });
+    }
+    if let Some(max) = max_affected_rows.filter(|_| !args.force) {
+        let matching = count_matching(&args.table_name, &args.conditions, args.limit, conn).await?;
+        ensure_within_affected_limit("update", matching, max)?;
+    }
+
+    let mut query_builder = sqlx::query(&query);
+    for param in &params {
+        query_builder = bind_param(query_builder, param);
+    }
     debug!("Executing update query: {}", query);
+    record_statement(&query);
     let result = query_builder.execute(&mut *conn).await?;
 
-    Ok(MutationResult {
-        affected_rows: result.rows_affected(),
-    })
+    Ok(MutationResult { affected_rows: result.rows_affected(), dry_run: None })
 }
 
 pub async fn delete_data(
-    table_name: String,
-    conditions: Value,
-    pool: &Pool<MySql>,
+    args: DeleteArguments,
+    max_affected_rows: Option<u64>,
+    require_keyed_deletes: bool,
+    conn: &mut sqlx::MySqlConnection,
 ) -> Result<MutationResult, DbError> {
-    let mut conn = pool.acquire().await.map_err(DbError::ConnectionError)?;
+    let (query, params) = delete_sql(&args)?;
+    if args.dry_run {
+        let affected_rows = count_matching(&args.table_name, &args.conditions, args.limit, conn).await?;
+        return Ok(MutationResult { affected_rows, dry_run: Some(DryRun { sql: query, params }) });
+    }
+    if require_keyed_deletes && !args.force {
+        let database: Option<String> = sqlx::query_scalar("SELECT DATABASE()").fetch_one(&mut *conn).await?;
+        let keys = unique_keys(&database.unwrap_or_default(), &args.table_name, conn).await?;
+        ensure_keyed_conditions(&args.table_name, mutation_conditions(&args.conditions)?, &keys)?;
+    }
+    if let Some(max) = max_affected_rows.filter(|_| !args.force) {
+        let matching = count_matching(&args.table_name, &args.conditions, args.limit, conn).await?;
+        ensure_within_affected_limit("delete", matching, max)?;
+    }
 
-    if !is_valid_identifier(&table_name) {
-        return Err(DbError::InvalidIdentifier(table_name));
+    let mut query_builder = sqlx::query(&query);
+    for param in &params {
+        query_builder = bind_param(query_builder, param);
     }
+    debug!("Executing delete query: {}", query);
+    record_statement(&query);
+    let result = query_builder.execute(&mut *conn).await?;
 
-    let conditions_map = conditions
+    Ok(MutationResult { affected_rows: result.rows_affected(), dry_run: None })
+}
+
+/// Refuse a mutation matching more than `max_affected_rows` rows.
+fn ensure_within_affected_limit(verb: &str, matching: u64, max: u64) -> Result<(), DbError> {
+    if matching > max {
+        return Err(DbError::InvalidInput(format!(
+            "This {verb} matches {matching} rows, more than max_affected_rows ({max}). Narrow the conditions, \
+             check what would change with dry_run: true, or pass force: true to run it anyway."
+        )));
+    }
+    Ok(())
+}
+
+/// Refuse conditions that pin no column of a primary or unique key, such as
+/// `{"status": "inactive"}` or `{"id": {"op": "!=", "value": 0}}`, which can
+/// match any number of rows. A key column pins rows when compared with `=`
+/// to a non-null value, or with `in`.
+fn ensure_keyed_conditions(
+    table_name: &str,
+    conditions: &serde_json::Map<String, Value>,
+    keys: &[Vec<String>],
+) -> Result<(), DbError> {
+    let key_columns: Vec<&String> = keys.iter().flatten().collect();
+    let pins = |condition: &Value| match condition {
+        Value::Null | Value::Array(_) => false,
+        Value::Object(operator) if operator.contains_key("op") => {
+            let op = operator["op"].as_str().map(|op| op.trim().to_uppercase());
+            match (op.as_deref(), operator.get("value").unwrap_or(&Value::Null)) {
+                (Some("="), Value::Null | Value::Array(_) | Value::Object(_)) => false,
+                (Some("="), _) => true,
+                (Some("IN"), Value::Array(values)) => !values.is_empty(),
+                _ => false,
+            }
+        }
+        _ => true,
+    };
+    if conditions
+        .iter()
+        .any(|(column, condition)| pins(condition) && key_columns.iter().any(|key| key.eq_ignore_ascii_case(column)))
+    {
+        return Ok(());
+    }
+    let hint = if key_columns.is_empty() {
+        format!("'{table_name}' has no primary or unique key")
+    } else {
+        let mut names: Vec<&str> = key_columns.iter().map(|c| c.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        format!("key columns of '{table_name}': {}", names.join(", "))
+    };
+    Err(DbError::InvalidInput(format!(
+        "require_keyed_deletes is on and these conditions use no primary or unique key column ({hint}). \
+         Add a key column to the conditions, or pass force: true to delete anyway."
+    )))
+}
+
+/// The UPDATE for `data` and equality `conditions`, with SET values bound
+/// before condition values.
+fn update_sql(args: &UpdateArguments) -> Result<(String, Vec<Value>), DbError> {
+    if !is_valid_identifier(&args.table_name) {
+        return Err(DbError::InvalidIdentifier(args.table_name.clone()));
+    }
+
+    let data_map = args
+        .data
         .as_object()
-        .ok_or_else(|| DbError::InvalidInput("Conditions must be an object".to_string()))?;
+        .ok_or_else(|| DbError::InvalidInput("Data must be an object".to_string()))?;
+    let conditions_map = mutation_conditions(&args.conditions)?;
 
-    if conditions_map.is_empty() {
-        return Err(DbError::InvalidInput("Conditions object is empty".to_string()));
+    if data_map.is_empty() {
+        return Err(DbError::InvalidInput("Data object is empty".to_string()));
     }
 
-    for k in conditions_map.keys() {
+    for k in data_map.keys() {
         if !is_valid_identifier(k) {
             return Err(DbError::InvalidIdentifier(k.clone()));
         }
     }
 
-    let where_clause: Vec<String> = conditions_map.keys().map(|k| format!("`{}` = ?", k)).collect();
+    let set_clause: Vec<String> = data_map.keys().map(|k| format!("{} = ?", quote_name(k))).collect();
+    let (clause, condition_params) = conditions_clause(conditions_map)?;
     let query = format!(
-        "DELETE FROM `{}` WHERE {}",
-        table_name,
-        where_clause.join(" AND ")
+        "UPDATE {} SET {} WHERE {}{}",
+        quote_name(&args.table_name),
+        set_clause.join(", "),
+        clause,
+        mutation_limit_clause(&args.order_by, args.limit)?
     );
+    let params = data_map.values().cloned().chain(condition_params).collect();
+    Ok((query, params))
+}
 
-    let mut query_builder = sqlx::query(&query);
-    for key in conditions_map.keys() {
-        if let Some(value) = conditions_map.get(key) {
-            query_builder = query_builder.bind(value);
+fn delete_sql(args: &DeleteArguments) -> Result<(String, Vec<Value>), DbError> {
+    if !is_valid_identifier(&args.table_name) {
+        return Err(DbError::InvalidIdentifier(args.table_name.clone()));
+    }
+    let conditions_map = mutation_conditions(&args.conditions)?;
+
+    let (clause, params) = conditions_clause(conditions_map)?;
+    let query = format!(
+        "DELETE FROM {} WHERE {}{}",
+        quote_name(&args.table_name),
+        clause,
+        mutation_limit_clause(&args.order_by, args.limit)?
+    );
+    Ok((query, params))
+}
+
+/// ` ORDER BY ... LIMIT n` for a single-table UPDATE or DELETE, so large
+/// changes can be made in chunks.
+fn mutation_limit_clause(order_by: &[OrderBy], limit: Option<u64>) -> Result<String, DbError> {
+    let mut clause = String::new();
+    if !order_by.is_empty() {
+        if limit.is_none() {
+            return Err(DbError::InvalidInput("order_by requires a limit".to_string()));
         }
+        clause.push_str(&format!(" ORDER BY {}", order_by_clause(order_by)?));
+    }
+    if let Some(limit) = limit {
+        clause.push_str(&format!(" LIMIT {}", limit));
     }
+    Ok(clause)
+}
 
-    debug!("Executing delete query: {}", query);
-    let result = query_builder.execute(&mut *conn).await?;
+/// Conditions of an update or delete, which must not be empty so a whole
+/// table is never changed by accident.
+fn mutation_conditions(conditions: &Value) -> Result<&serde_json::Map<String, Value>, DbError> {
+    let conditions_map = conditions
+        .as_object()
+        .ok_or_else(|| DbError::InvalidInput("Conditions must be an object".to_string()))?;
+    if conditions_map.is_empty() {
+        return Err(DbError::InvalidInput("Conditions object is empty".to_string()));
+    }
+    Ok(conditions_map)
+}
 
-    Ok(MutationResult {
-        affected_rows: result.rows_affected(),
-    })
+/// Apply `guardrails` to `sql`, looking up the estimated size of every table
+/// it reads with an unlimited `SELECT *`.
+pub async fn ensure_within_guardrails(
+    sql: &str,
+    database: Option<&str>,
+    guardrails: Guardrails,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<(), DbError> {
+    let star_tables = guardrails.check_sql(sql)?;
+    let Some(max) = guardrails.select_star_max_rows else {
+        return Ok(());
+    };
+    for (table_database, table) in star_tables {
+        // Views have no row estimate and are let through
+        let estimate: Option<Option<u64>> = sqlx::query_scalar(
+            "SELECT CAST(table_rows AS UNSIGNED) FROM information_schema.tables
+             WHERE table_schema = COALESCE(?, DATABASE()) AND table_name = ?",
+        )
+        .bind(table_database.as_deref().or(database))
+        .bind(&table)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if let Some(rows) = estimate.flatten().filter(|rows| *rows > max) {
+            return Err(DbError::InvalidInput(format!(
+                "SELECT * on '{table}' (about {rows} rows, more than select_star_max_rows = {max}) needs a LIMIT. \
+                 Add LIMIT n, or name the columns you need."
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Rows an update or delete with these conditions (and `limit`) would touch.
+pub async fn count_matching(
+    table_name: &str,
+    conditions: &Value,
+    limit: Option<u64>,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<u64, DbError> {
+    let conditions_map = mutation_conditions(conditions)?;
+    let (clause, params) = conditions_clause(conditions_map)?;
+    let query = format!("SELECT COUNT(*) FROM {} WHERE {}", quote_name(table_name), clause);
+    let mut query_builder = sqlx::query(&query);
+    for value in &params {
+        query_builder = bind_param(query_builder, value);
+    }
+    debug!("Counting rows for dry run: {}", query);
+    record_statement(&query);
+    let count: i64 = query_builder.fetch_one(&mut *conn).await?.try_get(0)?;
+    Ok(limit.map_or(count as u64, |limit| limit.min(count as u64)))
 }
 
 #[cfg(test)]
@@ -461,14 +2623,510 @@ mod tests {
         assert!(is_valid_identifier("table-with-dashes"));
         assert!(is_valid_identifier("table with spaces"));
         assert!(is_valid_identifier("my.table"));
+        assert!(is_valid_identifier("my-app-prod"));
+        assert!(is_valid_identifier("commandes_été"));
+        assert!(is_valid_identifier("table`; DROP TABLE users"));
 
         // Invalid identifiers
         assert!(!is_valid_identifier(""));
-        assert!(!is_valid_identifier("table`; DROP TABLE users"));
         assert!(!is_valid_identifier("name\0injection"));
+        assert!(!is_valid_identifier("trailing "));
         assert!(!is_valid_identifier(&"a".repeat(65)));
 
-        // 64 chars is OK
+        // 64 chars is OK, counted in characters
         assert!(is_valid_identifier(&"a".repeat(64)));
+        assert!(is_valid_identifier(&"é".repeat(64)));
+
+        // Backticks are doubled inside the quotes
+        assert_eq!(quote_name("order-items"), "`order-items`");
+        assert_eq!(quote_name("table`; DROP TABLE users"), "`table``; DROP TABLE users`");
+    }
+
+    #[test]
+    fn test_supports_explain_analyze() {
+        assert!(supports_explain_analyze("8.0.18"));
+        assert!(supports_explain_analyze("8.4.0-log"));
+        assert!(supports_explain_analyze("9.1.0"));
+        assert!(!supports_explain_analyze("8.0.17"));
+        assert!(!supports_explain_analyze("5.7.44-log"));
+        assert!(!supports_explain_analyze("10.11.6-MariaDB"));
+    }
+
+    #[test]
+    fn test_sample_fraction() {
+        assert_eq!(sample_fraction(10, 500), None);
+        assert_eq!(sample_fraction(10, 1_000_000), Some(0.0001));
+        assert_eq!(sample_fraction(100, 10_000), Some(0.1));
+        // A fraction of 1 or more would keep every row anyway
+        assert_eq!(sample_fraction(2_000, 10_000), None);
+    }
+
+    #[test]
+    fn test_search_conditions() {
+        let columns = vec!["email".to_string(), "title".to_string(), "body".to_string()];
+        let fulltext = vec![
+            ("posts".to_string(), "ft_post".to_string(), vec!["title".to_string(), "body".to_string()]),
+            ("other".to_string(), "ft_email".to_string(), vec!["email".to_string()]),
+        ];
+        let (conditions, params) = search_conditions("posts", &columns, &fulltext, "a_b", false);
+        assert_eq!(conditions, vec!["MATCH(`title`, `body`) AGAINST (? IN BOOLEAN MODE)", "`email` LIKE ?"]);
+        assert_eq!(params, vec!["\"a_b\"", "%a\\_b%"]);
+
+        let (conditions, params) = search_conditions("posts", &columns[..1], &fulltext, "x@y.z", true);
+        assert_eq!(conditions, vec!["`email` = ?"]);
+        assert_eq!(params, vec!["x@y.z"]);
+    }
+
+    #[test]
+    fn test_search_match() {
+        let config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"\ncolumn_masks = { \"users.email\" = \"partial\", \"*.ssn\" = \"null\" }").unwrap();
+        let masks = Masks::new(&config);
+        let rows = vec![json!({ "id": 1, "name": "Jane Smith", "email": "jane@example.com", "ssn": "123-45-6789" })];
+        let columns = vec!["name".to_string()];
+        let found = search_match("users", &columns, rows, "smith", false, "shop", &masks);
+        assert_eq!(found.columns, vec!["name"]);
+        assert_eq!(found.rows[0], json!({ "id": 1, "name": "Jane Smith", "email": "j***@example.com", "ssn": null }));
+    }
+
+    #[test]
+    fn test_fulltext_sql() {
+        let args: FulltextSearchArguments = serde_json::from_value(json!({
+            "table_name": "posts",
+            "query": "+mysql -oracle",
+            "mode": "boolean",
+            "conditions": { "status": "published" },
+            "min_score": 0.5,
+            "offset": 20
+        }))
+        .unwrap();
+        let columns = vec!["title".to_string(), "body".to_string()];
+        let (query, params) = fulltext_sql(&args, "blog", &columns, 10).unwrap();
+        assert_eq!(
+            query,
+            "SELECT *, MATCH(`title`, `body`) AGAINST (? IN BOOLEAN MODE) AS `_score` FROM `blog`.`posts` \
+             WHERE MATCH(`title`, `body`) AGAINST (? IN BOOLEAN MODE) AND `status` = ? \
+             HAVING `_score` >= ? ORDER BY `_score` DESC LIMIT 11 OFFSET 20"
+        );
+        assert_eq!(params, vec![json!("+mysql -oracle"), json!("+mysql -oracle"), json!("published"), json!(0.5)]);
+
+        let indexes = vec![
+            ("ft_title".to_string(), vec!["title".to_string()]),
+            ("ft_post".to_string(), columns.clone()),
+        ];
+        let wanted = vec!["BODY".to_string(), "title".to_string()];
+        assert_eq!(choose_fulltext_index("posts", indexes.clone(), Some(&wanted)).unwrap().0, "ft_post");
+        assert!(choose_fulltext_index("posts", indexes.clone(), None).is_err());
+        assert!(choose_fulltext_index("posts", indexes[..1].to_vec(), Some(&wanted)).is_err());
+        assert!(choose_fulltext_index("posts", Vec::new(), None).is_err());
+    }
+
+    #[test]
+    fn test_query_cursor() {
+        let query = "SELECT * FROM orders ORDER BY id";
+        let params = vec![json!(7)];
+        let cursor = query_cursor(query, &params, 500);
+        assert_eq!(parse_query_cursor(&cursor, query, &params).unwrap(), 500);
+        assert!(parse_query_cursor(&cursor, "SELECT * FROM users ORDER BY id", &params).is_err());
+        assert!(parse_query_cursor(&cursor, query, &[json!(8)]).is_err());
+        assert!(parse_query_cursor("500", query, &params).is_err());
+        assert!(parse_query_cursor("x:0", query, &params).is_err());
+    }
+
+    #[test]
+    fn test_connect_options() {
+        let mut config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"").unwrap();
+        let url = "mysql://app:pw@db.internal:3307/shop?ssl-mode=required";
+        let options = connect_options(url, &config).unwrap();
+        assert!(matches!(options.get_ssl_mode(), MySqlSslMode::Required));
+        assert_eq!((options.get_host(), options.get_port(), options.get_database()), ("db.internal", 3307, Some("shop")));
+
+        assert_eq!(options.get_socket(), None);
+        config.socket = Some("/var/run/mysqld/mysqld.sock".to_string());
+        let options = connect_options(url, &config).unwrap();
+        assert_eq!(options.get_socket().and_then(|p| p.to_str()), Some("/var/run/mysqld/mysqld.sock"));
+        config.socket = None;
+
+        config.ssl_mode = Some(SslMode::VerifyIdentity);
+        config.ssl_ca = Some("/etc/ssl/rds-ca.pem".to_string());
+        assert!(matches!(connect_options(url, &config).unwrap().get_ssl_mode(), MySqlSslMode::VerifyIdentity));
+        config.ssl_cert = Some("/etc/ssl/client.pem".to_string());
+        assert!(connect_options(url, &config).is_err(), "a client certificate needs its key");
+        config.ssl_key = Some("/etc/ssl/client-key.pem".to_string());
+        assert!(connect_options(url, &config).is_ok());
+
+        let config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"\nssl_mode = \"verify_ca\"").unwrap();
+        assert_eq!(config.ssl_mode, Some(SslMode::VerifyCa));
+    }
+
+    #[test]
+    fn test_session_init_statements() {
+        let mut config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"").unwrap();
+        assert_eq!(session_init_statements(&config), ["SET SESSION TRANSACTION READ ONLY", "SET SESSION time_zone = '+00:00'"]);
+
+        config.charset = Some("utf8mb4".to_string());
+        config.collation = Some("utf8mb4_0900_ai_ci".to_string());
+        config.time_zone = Some("Europe/Berlin".to_string());
+        config.sql_mode = Some("STRICT_ALL_TABLES,NO_ZERO_DATE".to_string());
+        config.init_statements = vec!["SET SESSION group_concat_max_len = 1000000".to_string()];
+        assert_eq!(
+            session_init_statements(&config),
+            [
+                // First, so the statements after it cannot write in read-only mode
+                "SET SESSION TRANSACTION READ ONLY",
+                "SET NAMES 'utf8mb4' COLLATE 'utf8mb4_0900_ai_ci'",
+                "SET SESSION time_zone = 'Europe/Berlin'",
+                "SET SESSION sql_mode = 'STRICT_ALL_TABLES,NO_ZERO_DATE'",
+                "SET SESSION group_concat_max_len = 1000000",
+            ]
+        );
+        config.allow_insert = true;
+        config.charset = None;
+        config.time_zone = Some("it's".to_string());
+        assert_eq!(session_init_statements(&config)[..2], ["SET SESSION collation_connection = 'utf8mb4_0900_ai_ci'", "SET SESSION time_zone = 'it''s'"]);
+    }
+
+    #[test]
+    fn test_max_execution_time_statement() {
+        let mut config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"").unwrap();
+        assert_eq!(max_execution_time_statement(&config), None);
+        config.query_timeout = Some(0);
+        assert_eq!(max_execution_time_statement(&config), None);
+        config.query_timeout = Some(45);
+        assert_eq!(max_execution_time_statement(&config).as_deref(), Some("SET SESSION max_execution_time = 45000"));
+    }
+
+    #[test]
+    fn test_pool_options() {
+        let mut config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"").unwrap();
+        let options = pool_options(&config);
+        assert_eq!((options.get_max_connections(), options.get_min_connections()), (5, 0));
+        assert_eq!(options.get_idle_timeout(), Some(Duration::from_secs(600)));
+        assert!(options.get_test_before_acquire());
+
+        config.pool_max_connections = 20;
+        config.pool_min_connections = 2;
+        config.pool_acquire_timeout_secs = 5;
+        config.pool_idle_timeout_secs = 0;
+        config.pool_max_lifetime_secs = 0;
+        config.pool_test_before_acquire = false;
+        let options = pool_options(&config);
+        assert_eq!((options.get_max_connections(), options.get_min_connections()), (20, 2));
+        assert_eq!(options.get_acquire_timeout(), Duration::from_secs(5));
+        assert_eq!((options.get_idle_timeout(), options.get_max_lifetime()), (None, None));
+        assert!(!options.get_test_before_acquire());
+    }
+
+    #[test]
+    fn test_ensure_keyed_conditions() {
+        let keys = vec![vec!["id".to_string()], vec!["tenant_id".to_string(), "email".to_string()]];
+        let conditions = |value: Value| value.as_object().cloned().unwrap();
+        assert!(ensure_keyed_conditions("users", &conditions(json!({ "ID": 7 })), &keys).is_ok());
+        assert!(ensure_keyed_conditions("users", &conditions(json!({ "email": "a@b.c", "status": "x" })), &keys).is_ok());
+        let Err(DbError::InvalidInput(message)) = ensure_keyed_conditions("users", &conditions(json!({ "status": "inactive" })), &keys) else {
+            panic!("a delete on a non-key column should be refused");
+        };
+        assert!(message.contains("email, id, tenant_id"), "{message}");
+        let Err(DbError::InvalidInput(message)) = ensure_keyed_conditions("logs", &conditions(json!({ "level": "debug" })), &[]) else {
+            panic!("a table without keys cannot be deleted from by key");
+        };
+        assert!(message.contains("'logs' has no primary or unique key"), "{message}");
+
+
+        assert!(ensure_keyed_conditions("users", &conditions(json!({ "id": { "op": "=", "value": 7 } })), &keys).is_ok());
+        assert!(ensure_keyed_conditions("users", &conditions(json!({ "id": { "op": "in", "value": [1, 2] } })), &keys).is_ok());
+        for unpinned in [
+            json!({ "id": { "op": "!=", "value": 0 } }),
+            json!({ "id": { "op": "<>", "value": 0 } }),
+            json!({ "id": { "op": "not in", "value": [1] } }),
+            json!({ "id": { "op": ">", "value": 0 } }),
+            json!({ "id": { "op": "between", "value": [1, 1000000] } }),
+            json!({ "id": { "op": "like", "value": "%" } }),
+            json!({ "id": { "op": "is not null" } }),
+            json!({ "id": { "op": "=", "value": null } }),
+            json!({ "id": null }),
+        ] {
+            assert!(ensure_keyed_conditions("users", &conditions(unpinned.clone()), &keys).is_err(), "{unpinned} matches too many rows");
+        }
+    }
+
+    #[test]
+    fn test_conditions_clause() {
+        let conditions = json!({ "id": 7, "status": "open" });
+        let (clause, params) = conditions_clause(conditions.as_object().unwrap()).unwrap();
+        assert_eq!(clause, "`id` = ? AND `status` = ?");
+        assert_eq!(params, vec![json!(7), json!("open")]);
+        let conditions = json!({ "id`; --": 1 });
+        assert_eq!(conditions_clause(conditions.as_object().unwrap()).unwrap().0, "`id``; --` = ?");
+
+        let conditions = json!({
+            "created_at": { "op": ">", "value": "2024-01-01" },
+            "deleted_at": null,
+            "name": { "op": "like", "value": "a%" },
+            "score": { "op": "between", "value": [1, 5] },
+            "status": { "op": "in", "value": ["a", "b"] },
+            "tag": { "op": "is not null" }
+        });
+        let (clause, params) = conditions_clause(conditions.as_object().unwrap()).unwrap();
+        assert_eq!(
+            clause,
+            "`created_at` > ? AND `deleted_at` IS NULL AND `name` LIKE ? AND `score` BETWEEN ? AND ? \
+             AND `status` IN (?, ?) AND `tag` IS NOT NULL"
+        );
+        assert_eq!(params, vec![json!("2024-01-01"), json!("a%"), json!(1), json!(5), json!("a"), json!("b")]);
+
+        for bad in [
+            json!({ "id": { "op": "in", "value": [] } }),
+            json!({ "id": { "op": "between", "value": [1] } }),
+            json!({ "id": { "op": "=", "value": [1] } }),
+            json!({ "id": { "op": "; DROP" , "value": 1 } }),
+        ] {
+            assert!(conditions_clause(bad.as_object().unwrap()).is_err());
+        }
+        assert_eq!(where_clause(None).unwrap().0, "");
+        assert_eq!(where_clause(Some(&json!({}))).unwrap().0, "");
+    }
+
+    #[test]
+    fn test_condition_sql() {
+        let sql = |condition: Value| {
+            let mut params = Vec::new();
+            condition_sql("`c`", &condition, &mut params).map(|sql| (sql, params))
+        };
+        assert_eq!(sql(json!(3)).unwrap(), ("`c` = ?".to_string(), vec![json!(3)]));
+        assert_eq!(sql(json!({ "a": 1 })).unwrap(), ("`c` = ?".to_string(), vec![json!({ "a": 1 })]), "objects without op are values");
+        for op in ["=", "!=", "<>", "<", "<=", ">", ">=", "LIKE", "NOT LIKE"] {
+            assert_eq!(sql(json!({ "op": op, "value": "x" })).unwrap(), (format!("`c` {op} ?"), vec![json!("x")]));
+        }
+        assert_eq!(sql(json!({ "op": " not in ", "value": [1, 2, 3] })).unwrap(), ("`c` NOT IN (?, ?, ?)".to_string(), vec![json!(1), json!(2), json!(3)]));
+        assert_eq!(sql(json!({ "op": "not between", "value": [1, 9] })).unwrap(), ("`c` NOT BETWEEN ? AND ?".to_string(), vec![json!(1), json!(9)]));
+        assert_eq!(sql(json!({ "op": "is null", "value": 5 })).unwrap(), ("`c` IS NULL".to_string(), vec![]), "the value is ignored");
+
+        assert_eq!(sql(json!(null)).unwrap(), ("`c` IS NULL".to_string(), vec![]));
+        assert_eq!(sql(json!({ "op": "=", "value": null })).unwrap(), ("`c` IS NULL".to_string(), vec![]));
+        assert_eq!(sql(json!({ "op": "<>", "value": null })).unwrap(), ("`c` IS NOT NULL".to_string(), vec![]));
+        assert!(sql(json!({ "op": ">", "value": null })).is_err());
+
+        for bad in [
+            json!({ "op": "=" }),
+            json!({ "op": "<", "value": { "a": 1 } }),
+            json!({ "op": "in", "value": 1 }),
+            json!({ "op": "not in", "value": [] }),
+            json!({ "op": "between", "value": [1, 2, 3] }),
+            json!({ "op": "between", "value": 1 }),
+            json!({ "op": "regexp", "value": "a.*" }),
+            json!({ "op": 1, "value": 1 }),
+        ] {
+            assert!(matches!(sql(bad.clone()), Err(DbError::InvalidInput(_))), "{bad} should be refused");
+        }
+    }
+
+    #[test]
+    fn test_select_sql() {
+        let args: SelectArguments = serde_json::from_value(json!({
+            "table_name": "orders",
+            "database": "shop",
+            "columns": ["id", "total"],
+            "conditions": { "status": "open" },
+            "order_by": [{ "column": "created_at", "direction": "desc" }, { "column": "id" }],
+            "offset": 20
+        }))
+        .unwrap();
+        let (query, params) = select_sql(&args, 10).unwrap();
+        assert_eq!(
+            query,
+            "SELECT `id`, `total` FROM `shop`.`orders` WHERE `status` = ? ORDER BY `created_at` DESC, `id` ASC LIMIT 11 OFFSET 20"
+        );
+        assert_eq!(params, vec![json!("open")]);
+
+        // Identifiers are always quoted, so odd names cannot break out
+        let args: SelectArguments =
+            serde_json::from_value(json!({ "table_name": "orders", "order_by": [{ "column": "id; DROP" }] })).unwrap();
+        assert_eq!(select_sql(&args, 5).unwrap().0, "SELECT * FROM `orders` ORDER BY `id; DROP` ASC LIMIT 6");
+        let args: SelectArguments =
+            serde_json::from_value(json!({ "table_name": "orders", "columns": ["a`b"] })).unwrap();
+        assert_eq!(select_sql(&args, 10).unwrap().0, "SELECT `a``b` FROM `orders` LIMIT 11");
+    }
+
+    #[test]
+    fn test_aggregate_sql() {
+        let args: AggregateArguments = serde_json::from_value(json!({
+            "table_name": "orders",
+            "group_by": ["status"],
+            "aggregations": [
+                { "function": "count" },
+                { "function": "sum", "column": "total", "alias": "revenue" },
+                { "function": "count", "column": "customer_id", "distinct": true }
+            ],
+            "conditions": { "year": 2024 },
+            "order_by": [{ "column": "revenue", "direction": "desc" }]
+        }))
+        .unwrap();
+        let (query, params) = aggregate_sql(&args, 100).unwrap();
+        assert_eq!(
+            query,
+            "SELECT `status`, COUNT(*) AS `count`, SUM(`total`) AS `revenue`, \
+             COUNT(DISTINCT `customer_id`) AS `count_distinct_customer_id` FROM `orders` \
+             WHERE `year` = ? GROUP BY `status` ORDER BY `revenue` DESC LIMIT 101"
+        );
+        assert_eq!(params, vec![json!(2024)]);
+
+        let args: AggregateArguments = serde_json::from_value(json!({
+            "table_name": "orders",
+            "aggregations": [{ "function": "avg" }]
+        }))
+        .unwrap();
+        assert!(aggregate_sql(&args, 100).is_err());
+
+        let args: AggregateArguments = serde_json::from_value(json!({
+            "table_name": "orders",
+            "aggregations": [{ "function": "max", "column": "total" }],
+            "order_by": [{ "column": "total" }]
+        }))
+        .unwrap();
+        assert!(aggregate_sql(&args, 100).is_err());
+    }
+
+    #[test]
+    fn test_duplicates_sql() {
+        let args: DuplicatesArguments = serde_json::from_value(json!({
+            "table_name": "users",
+            "columns": ["email", "tenant_id"],
+            "conditions": { "deleted_at": null },
+            "offset": 20
+        }))
+        .unwrap();
+        let (query, params) = duplicates_sql(&args, 20).unwrap();
+        assert_eq!(
+            query,
+            "SELECT `email`, `tenant_id`, COUNT(*) AS `duplicate_count` FROM `users` WHERE `deleted_at` IS NULL \
+             GROUP BY `email`, `tenant_id` HAVING COUNT(*) > 1 ORDER BY `duplicate_count` DESC, `email`, `tenant_id` \
+             LIMIT 21 OFFSET 20"
+        );
+        assert!(params.is_empty());
+
+        let args: DuplicatesArguments = serde_json::from_value(json!({ "table_name": "users", "columns": [] })).unwrap();
+        assert!(duplicates_sql(&args, 20).is_err());
+    }
+
+    #[test]
+    fn test_partitioning_info() {
+        assert_eq!(partitioning_info(&[]), Value::Null);
+        let row = |partition: &str, sub: Value, rows: u64| {
+            json!({
+                "partition_name": partition,
+                "subpartition_name": sub,
+                "partition_method": "RANGE",
+                "partition_expression": "year(`created_at`)",
+                "subpartition_method": "HASH",
+                "subpartition_expression": "`id`",
+                "partition_description": if partition == "p2023" { "2024" } else { "MAXVALUE" },
+                "table_rows": rows
+            })
+        };
+        let info = partitioning_info(&[
+            row("p2023", json!("p2023sp0"), 10),
+            row("p2023", json!("p2023sp1"), 5),
+            row("pmax", json!("pmaxsp0"), 1),
+        ]);
+        assert_eq!(info["method"], "RANGE");
+        assert_eq!(info["expression"], "year(`created_at`)");
+        assert_eq!(
+            info["partitions"],
+            json!([
+                { "name": "p2023", "description": "2024", "rows": 15, "subpartitions": ["p2023sp0", "p2023sp1"] },
+                { "name": "pmax", "description": "MAXVALUE", "rows": 1, "subpartitions": ["pmaxsp0"] }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_insert_rows() {
+        assert_eq!(insert_rows(json!({ "id": 1 })).unwrap().len(), 1);
+        assert_eq!(insert_rows(json!([{ "id": 1 }, { "id": 2, "name": "b" }])).unwrap().len(), 2);
+        assert!(insert_rows(json!([])).is_err());
+        assert!(insert_rows(json!([{ "id": 1 }, 2])).is_err());
+        assert!(insert_rows(json!("row")).is_err());
+    }
+
+    #[test]
+    fn test_inserted_by_key_sql() {
+        let rows = insert_rows(json!([
+            { "tenant": 1, "sku": "A", "name": "x" },
+            { "tenant": 1, "sku": "B", "name": "y" }
+        ]))
+        .unwrap();
+        let keys = vec!["tenant".to_string(), "sku".to_string()];
+        let (query, params) = inserted_by_key_sql("`items`", &keys, &rows, 100).unwrap();
+        assert_eq!(
+            query,
+            "SELECT * FROM `items` WHERE (`sku` = ? AND `tenant` = ?) OR (`sku` = ? AND `tenant` = ?) LIMIT 100"
+        );
+        assert_eq!(params, vec![json!("A"), json!(1), json!("B"), json!(1)]);
+        assert_eq!(inserted_by_key_sql("`items`", &keys, &rows, 1).unwrap().1.len(), 2);
+    }
+
+    #[test]
+    fn test_mutation_sql() {
+        let args: UpdateArguments = serde_json::from_value(json!({
+            "table_name": "users",
+            "data": { "email": "a@b.c", "active": false },
+            "conditions": { "id": 7 }
+        }))
+        .unwrap();
+        let (query, params) = update_sql(&args).unwrap();
+        assert_eq!(query, "UPDATE `users` SET `active` = ?, `email` = ? WHERE `id` = ?");
+        assert_eq!(params, vec![json!(false), json!("a@b.c"), json!(7)]);
+
+        let args: DeleteArguments = serde_json::from_value(json!({
+            "table_name": "events",
+            "conditions": { "archived": true, "tenant": "x" },
+            "order_by": [{ "column": "created_at" }],
+            "limit": 1000
+        }))
+        .unwrap();
+        let (query, params) = delete_sql(&args).unwrap();
+        assert_eq!(
+            query,
+            "DELETE FROM `events` WHERE `archived` = ? AND `tenant` = ? ORDER BY `created_at` ASC LIMIT 1000"
+        );
+        assert_eq!(params, vec![json!(true), json!("x")]);
+
+        let args: DeleteArguments = serde_json::from_value(json!({
+            "table_name": "events",
+            "conditions": { "id": 1 },
+            "order_by": [{ "column": "created_at" }]
+        }))
+        .unwrap();
+        assert!(delete_sql(&args).is_err());
+        let args: DeleteArguments = serde_json::from_value(json!({ "table_name": "users", "conditions": {} })).unwrap();
+        assert!(delete_sql(&args).is_err());
+    }
+
+    #[test]
+    fn test_upsert_sql() {
+        let data = json!({ "sku": "A-1", "name": "Widget", "stock": 5 });
+        let data = data.as_object().unwrap();
+        let columns = vec!["name".to_string(), "stock".to_string()];
+        assert_eq!(
+            upsert_sql("`products`", data, &columns).unwrap(),
+            "INSERT INTO `products` (`name`, `sku`, `stock`) VALUES (?, ?, ?) \
+             ON DUPLICATE KEY UPDATE `name` = VALUES(`name`), `stock` = VALUES(`stock`)"
+        );
+        assert!(upsert_sql("`products`", data, &[]).is_err());
+        assert!(upsert_sql("`products`", data, &["price".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("users"), "users");
+        assert_eq!(escape_like("my_table"), "my\\_table");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a\\b"), "a\\\\b");
+    }
+    #[test]
+    fn test_ensure_within_affected_limit() {
+        assert!(ensure_within_affected_limit("delete", 100, 100).is_ok());
+        let err = ensure_within_affected_limit("delete", 101, 100).unwrap_err().to_string();
+        assert!(err.contains("101 rows") && err.contains("dry_run") && err.contains("force"), "{err}");
     }
 }
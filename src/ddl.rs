@@ -0,0 +1,389 @@
+//! Structured DDL: statements built from validated arguments instead of
+//! free-form SQL, for the tools enabled by `allow_ddl`.
+
+use log::debug;
+use serde_json::Value;
+use sqlx::{Executor, MySql, Pool};
+use std::time::Instant;
+
+use crate::db::{is_valid_identifier, record_statement};
+use crate::error::DbError;
+use crate::rpc::{
+    AlterOperation, AlterTableArguments, ColumnDefinition, CreateIndexArguments, CreateTableArguments, DropIndexArguments,
+    DropTableArguments, IndexType,
+};
+
+pub struct DdlResult {
+    pub statement: String,
+    pub elapsed_ms: u128,
+}
+
+pub async fn execute_ddl(statement: String, pool: &Pool<MySql>) -> Result<DdlResult, DbError> {
+    debug!("Executing DDL: {statement}");
+    record_statement(&statement);
+    let started = Instant::now();
+    // Text protocol: not every DDL statement can be prepared
+    pool.execute(statement.as_str()).await?;
+    Ok(DdlResult {
+        statement,
+        elapsed_ms: started.elapsed().as_millis(),
+    })
+}
+
+/// Backtick-quote a name, doubling any backtick inside it as MySQL expects.
+pub fn quote_name(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+pub fn quote_identifier(name: &str) -> Result<String, DbError> {
+    if !is_valid_identifier(name) {
+        return Err(DbError::InvalidIdentifier(name.to_string()));
+    }
+    Ok(quote_name(name))
+}
+
+/// `` `db`.`table` `` or just `` `table` ``.
+pub fn qualified_name(database: Option<&str>, table: &str) -> Result<String, DbError> {
+    match database {
+        Some(db) => Ok(format!("{}.{}", quote_identifier(db)?, quote_identifier(table)?)),
+        None => quote_identifier(table),
+    }
+}
+
+/// Quote a string as a SQL literal.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+/// Column types are free text (`DECIMAL(10,2) UNSIGNED`, `ENUM('a','b')`), so
+/// check they cannot smuggle in anything but a type: words, numbers, commas,
+/// balanced parentheses, and quoted strings.
+pub fn is_valid_column_type(data_type: &str) -> bool {
+    let mut depth = 0i32;
+    let mut chars = data_type.chars();
+    if !data_type.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return false;
+    }
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+            }
+            '\'' => loop {
+                match chars.next() {
+                    // '' is an escaped quote inside the literal; anything else ends it
+                    Some('\'') => {
+                        if chars.clone().next() == Some('\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    Some('\\') | Some('\n') | None => return false,
+                    Some(_) => {}
+                }
+            },
+            c if c.is_ascii_alphanumeric() || c == '_' || c == ' ' || c == ',' || c == '.' => {}
+            _ => return false,
+        }
+    }
+    depth == 0
+}
+
+/// Literal for a column `DEFAULT`. `CURRENT_TIMESTAMP` (optionally with a
+/// precision) is passed through as an expression; other strings are quoted.
+fn default_literal(value: &Value) -> Result<String, DbError> {
+    Ok(match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => {
+            let upper = s.trim().to_uppercase();
+            let is_timestamp = upper == "CURRENT_TIMESTAMP"
+                || upper
+                    .strip_prefix("CURRENT_TIMESTAMP(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .is_some_and(|p| p.len() == 1 && p.chars().all(|c| c.is_ascii_digit()));
+            if is_timestamp {
+                upper
+            } else {
+                quote_literal(s)
+            }
+        }
+        Value::Array(_) | Value::Object(_) => {
+            return Err(DbError::InvalidInput("Column defaults must be scalar values".to_string()))
+        }
+    })
+}
+
+fn column_sql(column: &ColumnDefinition) -> Result<String, DbError> {
+    if !is_valid_column_type(&column.data_type) {
+        return Err(DbError::InvalidInput(format!("Invalid column type: {}", column.data_type)));
+    }
+    let mut sql = format!("{} {}", quote_identifier(&column.name)?, column.data_type);
+    sql.push_str(if column.nullable { " NULL" } else { " NOT NULL" });
+    if let Some(default) = &column.default {
+        sql.push_str(&format!(" DEFAULT {}", default_literal(default)?));
+    }
+    if column.auto_increment {
+        sql.push_str(" AUTO_INCREMENT");
+    }
+    if column.unique {
+        sql.push_str(" UNIQUE");
+    }
+    if let Some(comment) = &column.comment {
+        sql.push_str(&format!(" COMMENT {}", quote_literal(comment)));
+    }
+    Ok(sql)
+}
+
+fn identifier_list(names: &[String]) -> Result<String, DbError> {
+    Ok(names
+        .iter()
+        .map(|n| quote_identifier(n))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", "))
+}
+
+pub fn create_table_sql(args: &CreateTableArguments) -> Result<String, DbError> {
+    if args.columns.is_empty() {
+        return Err(DbError::InvalidInput("A table needs at least one column".to_string()));
+    }
+    let mut definitions = args.columns.iter().map(column_sql).collect::<Result<Vec<_>, _>>()?;
+
+    // Columns flagged primary_key form the key unless an explicit list is given
+    let primary_key = args.primary_key.clone().unwrap_or_else(|| {
+        args.columns.iter().filter(|c| c.primary_key).map(|c| c.name.clone()).collect()
+    });
+    if !primary_key.is_empty() {
+        definitions.push(format!("PRIMARY KEY ({})", identifier_list(&primary_key)?));
+    }
+
+    let mut sql = format!(
+        "CREATE TABLE {}{} (\n  {}\n)",
+        if args.if_not_exists { "IF NOT EXISTS " } else { "" },
+        qualified_name(args.database.as_deref(), &args.table_name)?,
+        definitions.join(",\n  ")
+    );
+    if let Some(engine) = &args.engine {
+        sql.push_str(&format!(" ENGINE={}", word(engine, "engine")?));
+    }
+    if let Some(charset) = &args.charset {
+        sql.push_str(&format!(" DEFAULT CHARSET={}", word(charset, "charset")?));
+    }
+    if let Some(collation) = &args.collation {
+        sql.push_str(&format!(" COLLATE={}", word(collation, "collation")?));
+    }
+    if let Some(comment) = &args.comment {
+        sql.push_str(&format!(" COMMENT={}", quote_literal(comment)));
+    }
+    Ok(sql)
+}
+
+/// Engine, charset, and collation names are bare words.
+fn word<'a>(value: &'a str, what: &str) -> Result<&'a str, DbError> {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(DbError::InvalidInput(format!("Invalid {what}: {value}")));
+    }
+    Ok(value)
+}
+
+pub fn alter_table_sql(args: &AlterTableArguments) -> Result<String, DbError> {
+    if args.operations.is_empty() {
+        return Err(DbError::InvalidInput("No operations given".to_string()));
+    }
+    let operations = args
+        .operations
+        .iter()
+        .map(|op| {
+            Ok(match op {
+                AlterOperation::Add { column, after, first } => {
+                    format!("ADD COLUMN {}{}", column_sql(column)?, position_sql(after.as_deref(), *first)?)
+                }
+                AlterOperation::Modify { column, after, first } => {
+                    format!("MODIFY COLUMN {}{}", column_sql(column)?, position_sql(after.as_deref(), *first)?)
+                }
+                AlterOperation::Drop { name } => format!("DROP COLUMN {}", quote_identifier(name)?),
+            })
+        })
+        .collect::<Result<Vec<_>, DbError>>()?;
+    Ok(format!(
+        "ALTER TABLE {} {}",
+        qualified_name(args.database.as_deref(), &args.table_name)?,
+        operations.join(", ")
+    ))
+}
+
+fn position_sql(after: Option<&str>, first: bool) -> Result<String, DbError> {
+    match (after, first) {
+        (Some(_), true) => Err(DbError::InvalidInput("Use either after or first, not both".to_string())),
+        (Some(column), false) => Ok(format!(" AFTER {}", quote_identifier(column)?)),
+        (None, true) => Ok(" FIRST".to_string()),
+        (None, false) => Ok(String::new()),
+    }
+}
+
+pub fn drop_table_sql(args: &DropTableArguments) -> Result<String, DbError> {
+    if !args.confirm {
+        return Err(DbError::InvalidInput(format!(
+            "Dropping '{}' deletes the table and all its data. Call again with confirm: true to proceed.",
+            args.table_name
+        )));
+    }
+    Ok(format!(
+        "DROP TABLE {}{}",
+        if args.if_exists { "IF EXISTS " } else { "" },
+        qualified_name(args.database.as_deref(), &args.table_name)?
+    ))
+}
+
+/// MySQL's limit on identifier length.
+const MAX_IDENTIFIER_LENGTH: usize = 64;
+
+pub fn create_index_sql(args: &CreateIndexArguments) -> Result<String, DbError> {
+    if args.columns.is_empty() {
+        return Err(DbError::InvalidInput("An index needs at least one column".to_string()));
+    }
+    let kind = match (args.index_type, args.unique) {
+        (Some(IndexType::Fulltext | IndexType::Spatial), true) => {
+            return Err(DbError::InvalidInput("FULLTEXT and SPATIAL indexes cannot be unique".to_string()))
+        }
+        (Some(IndexType::Fulltext), false) => "FULLTEXT ",
+        (Some(IndexType::Spatial), false) => "SPATIAL ",
+        (_, true) => "UNIQUE ",
+        (_, false) => "",
+    };
+    let using = match args.index_type {
+        Some(IndexType::Btree) => " USING BTREE",
+        Some(IndexType::Hash) => " USING HASH",
+        _ => "",
+    };
+    let name = match &args.index_name {
+        Some(name) => name.clone(),
+        None => {
+            let mut name = format!("idx_{}_{}", args.table_name, args.columns.join("_"));
+            name.truncate(MAX_IDENTIFIER_LENGTH);
+            name
+        }
+    };
+    Ok(format!(
+        "CREATE {kind}INDEX {} ON {} ({}){using}",
+        quote_identifier(&name)?,
+        qualified_name(args.database.as_deref(), &args.table_name)?,
+        identifier_list(&args.columns)?
+    ))
+}
+
+pub fn drop_index_sql(args: &DropIndexArguments) -> Result<String, DbError> {
+    Ok(format!(
+        "DROP INDEX {} ON {}",
+        quote_identifier(&args.index_name)?,
+        qualified_name(args.database.as_deref(), &args.table_name)?
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_valid_column_type() {
+        assert!(is_valid_column_type("INT"));
+        assert!(is_valid_column_type("decimal(10,2) unsigned"));
+        assert!(is_valid_column_type("ENUM('a', 'it''s', 'c,d)')"));
+        assert!(is_valid_column_type("DATETIME(3)"));
+
+        assert!(!is_valid_column_type(""));
+        assert!(!is_valid_column_type("INT); DROP TABLE users; --"));
+        assert!(!is_valid_column_type("INT -- comment"));
+        assert!(!is_valid_column_type("VARCHAR(10"));
+        assert!(!is_valid_column_type("ENUM('a)"));
+        assert!(!is_valid_column_type("INT /* x */"));
+        assert!(!is_valid_column_type("INT`"));
+    }
+
+    #[test]
+    fn test_create_table_sql() {
+        let args: CreateTableArguments = serde_json::from_value(json!({
+            "table_name": "orders",
+            "database": "shop",
+            "columns": [
+                { "name": "id", "type": "BIGINT UNSIGNED", "nullable": false, "auto_increment": true, "primary_key": true },
+                { "name": "note", "type": "VARCHAR(255)", "default": "it's new", "comment": "free text" },
+                { "name": "created_at", "type": "DATETIME(3)", "nullable": false, "default": "current_timestamp(3)" }
+            ],
+            "engine": "InnoDB"
+        }))
+        .unwrap();
+        assert_eq!(
+            create_table_sql(&args).unwrap(),
+            "CREATE TABLE `shop`.`orders` (\n  \
+             `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,\n  \
+             `note` VARCHAR(255) NULL DEFAULT 'it''s new' COMMENT 'free text',\n  \
+             `created_at` DATETIME(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),\n  \
+             PRIMARY KEY (`id`)\n) ENGINE=InnoDB"
+        );
+    }
+
+    #[test]
+    fn test_alter_and_drop_table_sql() {
+        let args: AlterTableArguments = serde_json::from_value(json!({
+            "table_name": "orders",
+            "operations": [
+                { "op": "add_column", "column": { "name": "status", "type": "TINYINT", "nullable": false, "default": 0 }, "after": "id" },
+                { "op": "modify_column", "column": { "name": "note", "type": "TEXT" } },
+                { "op": "drop_column", "name": "legacy" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            alter_table_sql(&args).unwrap(),
+            "ALTER TABLE `orders` ADD COLUMN `status` TINYINT NOT NULL DEFAULT 0 AFTER `id`, \
+             MODIFY COLUMN `note` TEXT NULL, DROP COLUMN `legacy`"
+        );
+
+        let mut drop: DropTableArguments =
+            serde_json::from_value(json!({ "table_name": "orders" })).unwrap();
+        assert!(drop_table_sql(&drop).is_err());
+        drop.confirm = true;
+        assert_eq!(drop_table_sql(&drop).unwrap(), "DROP TABLE `orders`");
+    }
+
+    #[test]
+    fn test_index_sql() {
+        let args: CreateIndexArguments = serde_json::from_value(json!({
+            "table_name": "orders",
+            "columns": ["customer_id", "created_at"]
+        }))
+        .unwrap();
+        assert_eq!(
+            create_index_sql(&args).unwrap(),
+            "CREATE INDEX `idx_orders_customer_id_created_at` ON `orders` (`customer_id`, `created_at`)"
+        );
+
+        let args: CreateIndexArguments = serde_json::from_value(json!({
+            "table_name": "users", "database": "app", "columns": ["email"],
+            "index_name": "uq_email", "unique": true, "type": "btree"
+        }))
+        .unwrap();
+        assert_eq!(
+            create_index_sql(&args).unwrap(),
+            "CREATE UNIQUE INDEX `uq_email` ON `app`.`users` (`email`) USING BTREE"
+        );
+
+        let args: CreateIndexArguments = serde_json::from_value(json!({
+            "table_name": "posts", "columns": ["body"], "unique": true, "type": "fulltext"
+        }))
+        .unwrap();
+        assert!(create_index_sql(&args).is_err());
+
+        let args: DropIndexArguments =
+            serde_json::from_value(json!({ "table_name": "users", "index_name": "uq_email" })).unwrap();
+        assert_eq!(drop_index_sql(&args).unwrap(), "DROP INDEX `uq_email` ON `users`");
+    }
+}
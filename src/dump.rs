@@ -0,0 +1,302 @@
+//! SQL dumps in the format `mysqldump` writes, so they can be restored with
+//! the `mysql` client.
+
+use log::debug;
+use serde_json::Value;
+use sqlx::{Column, Executor, Row, TypeInfo};
+use tokio_stream::StreamExt;
+
+use crate::db::{is_valid_identifier, record_statement, report_progress, track_connection};
+use crate::ddl::quote_name;
+use crate::error::DbError;
+use crate::export::Sink;
+use crate::masking::{ColumnMasks, Masks};
+use crate::rpc::DumpArguments;
+
+/// Length at which an extended INSERT is closed and a new one started, as
+/// with mysqldump's default `net_buffer_length`.
+const MAX_INSERT_BYTES: usize = 1024 * 1024;
+
+pub struct DumpResult {
+    pub database: String,
+    pub tables: Vec<String>,
+    pub views: Vec<String>,
+    pub rows: u64,
+    pub bytes: u64,
+    pub path: Option<String>,
+    pub content: Option<String>,
+}
+
+/// Dump the schema (and unless `include_data` is false, the rows) of the
+/// selected tables from one consistent snapshot. Views follow the tables
+/// they may depend on. Masked columns are dumped masked.
+pub async fn dump(
+    args: DumpArguments,
+    export_dir: Option<&str>,
+    masks: &Masks,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<DumpResult, DbError> {
+    let database = match args.database {
+        Some(db) if !is_valid_identifier(&db) => return Err(DbError::InvalidIdentifier(db)),
+        Some(db) => db,
+        None => conn
+            .fetch_one("SELECT DATABASE()")
+            .await?
+            .try_get::<Option<String>, _>(0)?
+            .ok_or(DbError::NoDatabaseSelected)?,
+    };
+    let mut sink = Sink::open(export_dir, args.path.as_deref(), args.overwrite).await?;
+
+    track_connection(conn).await?;
+    // TIMESTAMP values are dumped in UTC and the dump restores with the same
+    // zone, as mysqldump --tz-utc does
+    conn.execute("SET @mcp_dump_time_zone = @@time_zone, time_zone = '+00:00'").await?;
+    conn.execute("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ").await?;
+    conn.execute("START TRANSACTION WITH CONSISTENT SNAPSHOT, READ ONLY").await?;
+    let result = write_dump(&database, args.tables.as_deref(), args.include_data, masks, &mut sink, conn).await;
+    let _ = conn.execute("ROLLBACK").await;
+    let _ = conn.execute("SET time_zone = @mcp_dump_time_zone").await;
+    let (tables, views, rows, bytes) = result?;
+    let (path, content) = sink.finish().await?;
+
+    Ok(DumpResult { database, tables, views, rows, bytes, path, content })
+}
+
+async fn write_dump(
+    database: &str,
+    only: Option<&[String]>,
+    include_data: bool,
+    masks: &Masks,
+    sink: &mut Sink,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<(Vec<String>, Vec<String>, u64, u64), DbError> {
+    let objects = sqlx::query(
+        "SELECT table_name AS table_name, table_type AS table_type
+         FROM information_schema.tables
+         WHERE table_schema = ?
+         ORDER BY table_name",
+    )
+    .bind(database)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut tables = Vec::new();
+    let mut views = Vec::new();
+    for row in objects {
+        let name: String = row.try_get("table_name")?;
+        if only.is_some_and(|only| !only.contains(&name)) {
+            continue;
+        }
+        if row.try_get::<String, _>("table_type")? == "VIEW" {
+            views.push(name);
+        } else {
+            tables.push(name);
+        }
+    }
+    if let Some(only) = only {
+        if let Some(missing) = only.iter().find(|t| !tables.contains(t) && !views.contains(t)) {
+            return Err(DbError::NotFound(format!("Table '{missing}' not found in database '{database}'")));
+        }
+    }
+
+    let version: String = conn.fetch_one("SELECT VERSION()").await?.try_get(0)?;
+    let mut bytes = 0u64;
+    let mut out = dump_header(database, &version);
+
+    let mut rows = 0u64;
+    for (i, table) in tables.iter().enumerate() {
+        let quoted = quote_name(table);
+        let qualified = format!("{}.{quoted}", quote_name(database));
+        let create = show_create(conn, &format!("SHOW CREATE TABLE {qualified}")).await?;
+        let comment = comment_name(&quoted);
+        out.push_str(&format!(
+            "\n--\n-- Table structure for table {comment}\n--\n\nDROP TABLE IF EXISTS {quoted};\n{create};\n"
+        ));
+        if include_data {
+            out.push_str(&format!(
+                "\n--\n-- Dumping data for table {comment}\n--\n\nLOCK TABLES {quoted} WRITE;\n/*!40000 ALTER TABLE {quoted} DISABLE KEYS */;\n"
+            ));
+            sink.write(&out).await?;
+            bytes += out.len() as u64;
+            out.clear();
+
+            let (table_rows, table_bytes) = write_table_data(database, table, &qualified, masks, sink, conn).await?;
+            rows += table_rows;
+            bytes += table_bytes;
+            out.push_str(&format!("/*!40000 ALTER TABLE {quoted} ENABLE KEYS */;\nUNLOCK TABLES;\n"));
+        }
+        report_progress(i as u64 + 1, Some(tables.len() as u64), &format!("Dumped table {table}"));
+    }
+
+    for view in &views {
+        let quoted = quote_name(view);
+        let create = show_create(conn, &format!("SHOW CREATE VIEW {}.{quoted}", quote_name(database))).await?;
+        out.push_str(&format!(
+            "\n--\n-- View structure for view {}\n--\n\nDROP VIEW IF EXISTS {quoted};\n{create};\n",
+            comment_name(&quoted)
+        ));
+    }
+    out.push_str(DUMP_FOOTER);
+    sink.write(&out).await?;
+    bytes += out.len() as u64;
+
+    Ok((tables, views, rows, bytes))
+}
+
+/// A quoted name for a `--` comment line, where a line break would end the
+/// comment and turn the rest of the name into SQL.
+fn comment_name(quoted: &str) -> String {
+    quoted.replace(['\n', '\r'], " ")
+}
+
+/// Rows of one table as extended INSERTs. The SELECT uses the text protocol,
+/// so every value arrives as the server's own textual representation.
+/// `qualified` is the quoted `database`.`table`.
+async fn write_table_data(
+    database: &str,
+    table: &str,
+    qualified: &str,
+    masks: &Masks,
+    sink: &mut Sink,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<(u64, u64), DbError> {
+    let select = format!("SELECT * FROM {qualified}");
+    debug!("Dumping: {select}");
+    record_statement(&select);
+    let mut stream = conn.fetch(select.as_str());
+    let mut statement = String::new();
+    let (mut rows, mut bytes) = (0u64, 0u64);
+    let mut column_masks = None;
+    while let Some(row) = stream.next().await {
+        let row = row?;
+        let column_masks = column_masks.get_or_insert_with(|| {
+            let columns: Vec<String> = row.columns().iter().map(|c| c.name().to_string()).collect();
+            masks.for_table(&columns, Some(database), table)
+        });
+        let values = (0..row.columns().len())
+            .map(|i| match column_masks.is_masked(i) {
+                true => masked_literal(&row, i, column_masks),
+                false => sql_literal(&row, i),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if statement.is_empty() {
+            statement.push_str(&format!("INSERT INTO {} VALUES ", quote_name(table)));
+        } else {
+            statement.push(',');
+        }
+        statement.push_str(&format!("({})", values.join(",")));
+        rows += 1;
+        if statement.len() >= MAX_INSERT_BYTES {
+            statement.push_str(";\n");
+            sink.write(&statement).await?;
+            bytes += statement.len() as u64;
+            statement.clear();
+        }
+    }
+    if !statement.is_empty() {
+        statement.push_str(";\n");
+        sink.write(&statement).await?;
+        bytes += statement.len() as u64;
+    }
+    Ok((rows, bytes))
+}
+
+async fn show_create(conn: &mut sqlx::MySqlConnection, statement: &str) -> Result<String, DbError> {
+    let row = conn.fetch_one(statement).await?;
+    // Some servers return the DDL as a binary string
+    let ddl: Option<Vec<u8>> = row.try_get_unchecked(1)?;
+    Ok(String::from_utf8_lossy(&ddl.unwrap_or_default()).into_owned())
+}
+
+/// A value from a text-protocol row as a SQL literal: numbers bare, binary
+/// data as hex, everything else as an escaped string.
+fn sql_literal(row: &sqlx::mysql::MySqlRow, index: usize) -> Result<String, DbError> {
+    let Some(bytes) = row.try_get_unchecked::<Option<Vec<u8>>, _>(index)? else {
+        return Ok("NULL".to_string());
+    };
+    let type_name = row.columns()[index].type_info().name();
+    let is_numeric = ["TINYINT", "SMALLINT", "MEDIUMINT", "INT", "BIGINT", "DECIMAL", "FLOAT", "DOUBLE", "YEAR"]
+        .iter()
+        .any(|t| type_name.split(' ').next() == Some(t));
+    let is_binary = ["BLOB", "BINARY", "BIT", "GEOMETRY"].iter().any(|t| type_name.contains(t));
+    Ok(if is_binary {
+        if bytes.is_empty() {
+            "''".to_string()
+        } else {
+            let hex: String = bytes.iter().map(|b| format!("{b:02X}")).collect();
+            format!("0x{hex}")
+        }
+    } else if is_numeric {
+        String::from_utf8_lossy(&bytes).into_owned()
+    } else {
+        escape_string(&String::from_utf8_lossy(&bytes))
+    })
+}
+
+/// The masked value of a text-protocol column as a SQL literal.
+fn masked_literal(row: &sqlx::mysql::MySqlRow, index: usize, masks: &ColumnMasks) -> Result<String, DbError> {
+    let value = match row.try_get_unchecked::<Option<Vec<u8>>, _>(index)? {
+        Some(bytes) => Value::String(String::from_utf8_lossy(&bytes).into_owned()),
+        None => Value::Null,
+    };
+    Ok(match masks.mask(index, value) {
+        Value::String(text) => escape_string(&text),
+        _ => "NULL".to_string(),
+    })
+}
+
+/// Quote a string the way mysqldump does, escaping with backslashes.
+fn escape_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('\'');
+    for c in value.chars() {
+        match c {
+            '\0' => escaped.push_str("\\0"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\x1a' => escaped.push_str("\\Z"),
+            '\'' => escaped.push_str("\\'"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('\'');
+    escaped
+}
+
+fn dump_header(database: &str, version: &str) -> String {
+    format!(
+        "-- MySQL dump written by mcp-server-mysql\n\
+         --\n\
+         -- Database: {database}\n\
+         -- ------------------------------------------------------\n\
+         -- Server version\t{version}\n\
+         \n\
+         /*!40101 SET @OLD_CHARACTER_SET_CLIENT=@@CHARACTER_SET_CLIENT */;\n\
+         /*!50503 SET NAMES utf8mb4 */;\n\
+         /*!40103 SET @OLD_TIME_ZONE=@@TIME_ZONE */;\n\
+         /*!40103 SET TIME_ZONE='+00:00' */;\n\
+         /*!40014 SET @OLD_UNIQUE_CHECKS=@@UNIQUE_CHECKS, UNIQUE_CHECKS=0 */;\n\
+         /*!40014 SET @OLD_FOREIGN_KEY_CHECKS=@@FOREIGN_KEY_CHECKS, FOREIGN_KEY_CHECKS=0 */;\n\
+         /*!40101 SET @OLD_SQL_MODE=@@SQL_MODE, SQL_MODE='NO_AUTO_VALUE_ON_ZERO' */;\n"
+    )
+}
+
+const DUMP_FOOTER: &str = "\n/*!40101 SET SQL_MODE=@OLD_SQL_MODE */;\n\
+/*!40014 SET FOREIGN_KEY_CHECKS=@OLD_FOREIGN_KEY_CHECKS */;\n\
+/*!40014 SET UNIQUE_CHECKS=@OLD_UNIQUE_CHECKS */;\n\
+/*!40103 SET TIME_ZONE=@OLD_TIME_ZONE */;\n\
+/*!40101 SET CHARACTER_SET_CLIENT=@OLD_CHARACTER_SET_CLIENT */;\n\
+\n-- Dump completed\n";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_string() {
+        assert_eq!(escape_string("plain"), "'plain'");
+        assert_eq!(escape_string("it's \"quoted\""), "'it\\'s \\\"quoted\\\"'");
+        assert_eq!(escape_string("a\\b\nc\r\0\x1a"), "'a\\\\b\\nc\\r\\0\\Z'");
+    }
+}
@@ -9,6 +9,14 @@ pub enum DbError {
     NotFound(String),
     NoDatabaseSelected,
     ReadOnlyViolation(String),
+    AccessDenied(String),
+    /// A tool call ran longer than `query_timeout`.
+    Timeout(std::time::Duration),
+    /// A `rate_limits` entry is exhausted; `key` is the tool or bucket.
+    RateLimited { key: String, limit: u32, retry_after: std::time::Duration },
+    /// A high-impact call under `require_confirmation`; repeating it with
+    /// `token` runs it.
+    ConfirmationRequired { token: String, reason: String },
 }
 
 impl fmt::Display for DbError {
@@ -21,6 +29,18 @@ impl fmt::Display for DbError {
             DbError::NotFound(msg) => write!(f, "Not found: {msg}"),
             DbError::NoDatabaseSelected => write!(f, "No database selected"),
             DbError::ReadOnlyViolation(msg) => write!(f, "Read-only violation: {msg}"),
+            DbError::AccessDenied(msg) => write!(f, "Access denied: {msg}"),
+            DbError::Timeout(limit) => write!(f, "Timed out: the call ran longer than query_timeout ({}s) and was stopped", limit.as_secs()),
+            DbError::RateLimited { key, limit, retry_after } => write!(
+                f,
+                "Rate limited: at most {limit} '{key}' calls per minute; retry after {}s",
+                retry_after.as_secs_f64().ceil()
+            ),
+            DbError::ConfirmationRequired { token, reason } => write!(
+                f,
+                "Confirmation required: {reason}. Nothing was changed; to proceed, repeat the call with the same arguments \
+                 and confirmation_token: \"{token}\""
+            ),
         }
     }
 }
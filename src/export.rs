@@ -0,0 +1,298 @@
+//! Bulk export of a query result or table as CSV or newline-delimited JSON,
+//! written to a file under `export_dir` or returned as an embedded resource.
+
+use log::debug;
+use serde_json::Value;
+use sqlx::{Column, Executor, Row};
+use std::path::{Component, Path, PathBuf};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio_stream::StreamExt;
+
+use crate::db::{bind_param, ensure_within_guardrails, is_valid_identifier, record_statement, report_progress, row_to_json, track_connection, where_clause, PROGRESS_INTERVAL_ROWS};
+use crate::ddl::{qualified_name, quote_name};
+use crate::error::DbError;
+use crate::masking::{ColumnMasks, Masks};
+use crate::policy::{self, Guardrails, StatementKind};
+use crate::rpc::{ExportArguments, DataFormat};
+
+pub struct ExportResult {
+    pub format: DataFormat,
+    pub rows: u64,
+    pub bytes: u64,
+    /// File written, when the export went to `export_dir`.
+    pub path: Option<String>,
+    /// The exported data, when it is returned inline.
+    pub content: Option<String>,
+}
+
+pub fn mime_type(format: DataFormat) -> &'static str {
+    match format {
+        DataFormat::Csv => "text/csv",
+        DataFormat::Ndjson => "application/x-ndjson",
+    }
+}
+
+pub fn extension(format: DataFormat) -> &'static str {
+    match format {
+        DataFormat::Csv => "csv",
+        DataFormat::Ndjson => "ndjson",
+    }
+}
+
+/// Where exported data goes: a file under `export_dir`, or a string returned
+/// with the result.
+pub enum Sink {
+    File { writer: BufWriter<tokio::fs::File>, path: PathBuf },
+    Inline(String),
+}
+
+impl Sink {
+    /// A file sink for `path` (relative to `export_dir`), or an inline one
+    /// when no path is given.
+    pub async fn open(export_dir: Option<&str>, path: Option<&str>, overwrite: bool) -> Result<Self, DbError> {
+        let Some(path) = path else {
+            return Ok(Sink::Inline(String::new()));
+        };
+        let dir = export_dir.ok_or_else(|| {
+            DbError::InvalidInput("Writing to a file requires export_dir to be set in config.toml".to_string())
+        })?;
+        let path = resolve_path_in(Path::new(dir), path)?;
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true);
+        if overwrite {
+            options.create(true).truncate(true);
+        } else {
+            options.create_new(true);
+        }
+        let file = options
+            .open(&path)
+            .await
+            .map_err(|e| DbError::InvalidInput(format!("Cannot create {}: {e}", path.display())))?;
+        Ok(Sink::File { writer: BufWriter::new(file), path })
+    }
+
+    pub async fn write(&mut self, data: &str) -> Result<(), DbError> {
+        match self {
+            Sink::File { writer, path } => writer
+                .write_all(data.as_bytes())
+                .await
+                .map_err(|e| DbError::InvalidInput(format!("Failed to write {}: {e}", path.display()))),
+            Sink::Inline(buffer) => {
+                buffer.push_str(data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Flush a file sink. Returns the file written, or the inline data.
+    pub async fn finish(self) -> Result<(Option<String>, Option<String>), DbError> {
+        match self {
+            Sink::File { mut writer, path } => {
+                writer
+                    .flush()
+                    .await
+                    .map_err(|e| DbError::InvalidInput(format!("Failed to write {}: {e}", path.display())))?;
+                Ok((Some(path.display().to_string()), None))
+            }
+            Sink::Inline(buffer) => Ok((None, Some(buffer))),
+        }
+    }
+}
+
+/// Run the export inside a read-only transaction, streaming rows to the sink
+/// so large tables are never held in memory as JSON, with masked columns
+/// masked. A caller's query is held to the same guardrails as `query`;
+/// whole-table exports are what the tool is for, so `select_star_max_rows`
+/// does not apply to them.
+pub async fn export(
+    args: ExportArguments,
+    export_dir: Option<&str>,
+    masks: &Masks,
+    guardrails: Guardrails,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<ExportResult, DbError> {
+    let (sql, params) = export_query(&args)?;
+    if let (Some(query), true) = (&args.query, guardrails.is_active()) {
+        ensure_within_guardrails(query, args.database.as_deref(), guardrails, conn).await?;
+    }
+    let mut sink = Sink::open(export_dir, args.path.as_deref(), args.overwrite).await?;
+
+    track_connection(conn).await?;
+    if let Some(db) = &args.database {
+        if args.query.is_some() {
+            if !is_valid_identifier(db) {
+                return Err(DbError::InvalidIdentifier(db.clone()));
+            }
+            conn.execute(format!("USE {}", quote_name(db)).as_str()).await?;
+        }
+    }
+
+    debug!("Exporting: {sql}");
+    record_statement(&sql);
+    conn.execute("START TRANSACTION READ ONLY").await?;
+    let masks_for = |columns: &[String]| match &args.table_name {
+        Some(table) => masks.for_table(columns, args.database.as_deref(), table),
+        None => masks.for_query(columns, &sql, args.database.as_deref()),
+    };
+    let result = write_rows(&sql, &params, args.format, masks_for, &mut sink, conn).await;
+    let _ = conn.execute("ROLLBACK").await;
+    let (rows, bytes) = result?;
+    let (path, content) = sink.finish().await?;
+
+    Ok(ExportResult { format: args.format, rows, bytes, path, content })
+}
+
+/// The SELECT to export and its bound values: the caller's query, or all
+/// rows of a table matching `conditions`.
+fn export_query(args: &ExportArguments) -> Result<(String, Vec<Value>), DbError> {
+    match (&args.query, &args.table_name) {
+        (Some(query), None) => {
+            // The READ ONLY transaction does not stop SELECT ... INTO OUTFILE
+            if policy::classify(query)?.iter().any(|kind| *kind != StatementKind::Read) {
+                return Err(DbError::ReadOnlyViolation("Only SELECT queries can be exported".to_string()));
+            }
+            Ok((query.clone(), args.params.clone()))
+        }
+        (None, Some(table_name)) => {
+            let (clause, params) = where_clause(args.conditions.as_ref())?;
+            let sql = format!("SELECT * FROM {}{clause}", qualified_name(args.database.as_deref(), table_name)?);
+            Ok((sql, params))
+        }
+        _ => Err(DbError::InvalidInput("Provide either query or table_name".to_string())),
+    }
+}
+
+async fn write_rows(
+    sql: &str,
+    params: &[Value],
+    format: DataFormat,
+    masks_for: impl Fn(&[String]) -> ColumnMasks,
+    sink: &mut Sink,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<(u64, u64), DbError> {
+    let mut query = sqlx::query(sql);
+    for param in params {
+        query = bind_param(query, param);
+    }
+    let mut stream = query.fetch(&mut *conn);
+    let (mut rows, mut bytes) = (0u64, 0u64);
+    let mut column_masks = None;
+    while let Some(row) = stream.next().await {
+        let row = row?;
+        let columns: Vec<String> = row.columns().iter().map(|c| c.name().to_string()).collect();
+        let column_masks = column_masks.get_or_insert_with(|| masks_for(&columns));
+        let line = export_line(row_to_json(&row), &columns, column_masks, format, rows == 0);
+        sink.write(&line).await?;
+        rows += 1;
+        bytes += line.len() as u64;
+        if rows % PROGRESS_INTERVAL_ROWS as u64 == 0 {
+            report_progress(rows, None, &format!("Exported {rows} rows"));
+        }
+    }
+    Ok((rows, bytes))
+}
+
+/// One exported row, masked, preceded by the CSV header when it is the first.
+fn export_line(mut values: Value, columns: &[String], masks: &ColumnMasks, format: DataFormat, first: bool) -> String {
+    masks.apply(std::slice::from_mut(&mut values));
+    let mut line = String::new();
+    match format {
+        DataFormat::Csv => {
+            if first {
+                line.push_str(&csv_record(columns.iter().map(|c| Value::String(c.clone()))));
+            }
+            line.push_str(&csv_record(columns.iter().map(|c| values[c.as_str()].clone())));
+        }
+        DataFormat::Ndjson => {
+            line.push_str(&values.to_string());
+            line.push('\n');
+        }
+    }
+    line
+}
+
+/// One CSV line (RFC 4180): fields containing a comma, quote, or line break
+/// are quoted, and NULL is an empty field.
+fn csv_record(fields: impl Iterator<Item = Value>) -> String {
+    let fields: Vec<String> = fields
+        .map(|value| {
+            let text = match value {
+                Value::Null => return String::new(),
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            if text.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            } else {
+                text
+            }
+        })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Resolve a caller-supplied relative path inside `dir`, refusing absolute
+/// paths and `..` so callers cannot escape the configured directory.
+pub fn resolve_path_in(dir: &Path, path: &str) -> Result<PathBuf, DbError> {
+    let relative = Path::new(path);
+    let is_plain = !path.is_empty() && relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !is_plain {
+        return Err(DbError::InvalidInput(format!(
+            "Path must be relative to {} without '..': {path}",
+            dir.display()
+        )));
+    }
+    Ok(dir.join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_csv_record() {
+        assert_eq!(
+            csv_record(vec![json!(1), json!("plain"), json!(null), json!(true)].into_iter()),
+            "1,plain,,true\r\n"
+        );
+        assert_eq!(
+            csv_record(vec![json!("a,b"), json!("say \"hi\""), json!("two\nlines")].into_iter()),
+            "\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_export_query() {
+        let query = |sql: &str| export_query(&serde_json::from_value(json!({ "query": sql })).unwrap());
+        assert_eq!(query("WITH c AS (SELECT 1 AS n) SELECT n FROM c").unwrap().0, "WITH c AS (SELECT 1 AS n) SELECT n FROM c");
+        assert!(matches!(query("SELECT * FROM orders INTO OUTFILE '/tmp/orders.csv'"), Err(DbError::InvalidInput(_))));
+        assert!(matches!(query("SELECT id INTO @id FROM orders"), Err(DbError::ReadOnlyViolation(_))));
+        assert!(matches!(query("SELECT 1; DELETE FROM orders"), Err(DbError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_export_line() {
+        let config: crate::config::Config = toml::from_str("username = \"u\"\ndatabase = \"d\"\ncolumn_masks = { \"users.email\" = \"partial\" }").unwrap();
+        let columns = vec!["id".to_string(), "contact".to_string()];
+        let masks = Masks::new(&config).for_query(&columns, "SELECT id, email AS contact FROM users", None);
+        let row = json!({ "id": 7, "contact": "jane@example.com" });
+        assert_eq!(export_line(row.clone(), &columns, &masks, DataFormat::Csv, true), "id,contact\r\n7,j***@example.com\r\n");
+        assert_eq!(export_line(row, &columns, &masks, DataFormat::Ndjson, false), "{\"contact\":\"j***@example.com\",\"id\":7}\n");
+
+        let masks = Masks::new(&config).for_table(&columns, Some("shop"), "users");
+        assert_eq!(export_line(json!({ "id": 7, "contact": "x" }), &columns, &masks, DataFormat::Csv, false), "7,x\r\n");
+    }
+
+    #[test]
+    fn test_resolve_path_in() {
+        let dir = Path::new("/var/exports");
+        assert_eq!(resolve_path_in(dir, "orders.csv").unwrap(), PathBuf::from("/var/exports/orders.csv"));
+        assert_eq!(resolve_path_in(dir, "2024/orders.csv").unwrap(), PathBuf::from("/var/exports/2024/orders.csv"));
+        assert!(resolve_path_in(dir, "../etc/passwd").is_err());
+        assert!(resolve_path_in(dir, "a/../../b").is_err());
+        assert!(resolve_path_in(dir, "/etc/passwd").is_err());
+        assert_eq!(resolve_path_in(dir, "./orders.csv").unwrap(), PathBuf::from("/var/exports/./orders.csv"));
+        assert!(resolve_path_in(dir, "").is_err());
+    }
+}
@@ -0,0 +1,498 @@
+//! Synthetic rows for the `generate_data` tool: values are chosen from each
+//! column's type and name, foreign keys point at existing rows, and unique
+//! keys are kept unique.
+
+use serde_json::{json, Map, Value};
+use sqlx::{Executor, Row};
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::db::{foreign_keys, insert_data, is_valid_identifier, row_to_json, unique_keys, InsertResult};
+use crate::ddl::quote_name;
+use crate::error::DbError;
+use crate::rpc::{GenerateDataArguments, InsertArguments};
+
+/// Rows one call may generate.
+pub const MAX_GENERATED_ROWS: usize = 10_000;
+
+/// Referenced keys sampled per foreign key.
+const FK_SAMPLE_SIZE: usize = 1000;
+
+/// Attempts at a row that does not collide with a unique key before giving up.
+const MAX_ATTEMPTS: usize = 100;
+
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Bob", "Carol", "David", "Emma", "Frank", "Grace", "Henry", "Isla", "James", "Kate", "Liam", "Maria",
+    "Noah", "Olivia", "Peter", "Quinn", "Ruby", "Sam", "Tara",
+];
+const LAST_NAMES: &[&str] = &[
+    "Smith", "Jones", "Brown", "Taylor", "Wilson", "Davies", "Evans", "Thomas", "Johnson", "Roberts", "Walker",
+    "Wright", "Robinson", "Thompson", "White", "Hughes",
+];
+const CITIES: &[&str] = &[
+    "London", "Paris", "Berlin", "Madrid", "Rome", "Lisbon", "Dublin", "Vienna", "Prague", "Oslo", "Toronto",
+    "Sydney", "Tokyo", "Cape Town", "Austin", "Seattle",
+];
+const COUNTRIES: &[&str] = &[
+    "United Kingdom", "France", "Germany", "Spain", "Italy", "Portugal", "Ireland", "Austria", "Norway", "Canada",
+    "Australia", "Japan", "South Africa", "United States",
+];
+const WORDS: &[&str] = &[
+    "alpha", "bright", "cloud", "delta", "ember", "forest", "granite", "harbor", "island", "jade", "kernel", "lumen",
+    "meadow", "nova", "orbit", "pixel", "quartz", "river", "summit", "timber", "umbra", "vector", "willow", "zenith",
+];
+const STATUSES: &[&str] = &["active", "pending", "inactive", "archived"];
+
+pub struct GenerateResult {
+    pub table: String,
+    pub insert: InsertResult,
+    /// The first few generated rows.
+    pub sample: Vec<Value>,
+    pub seed: u64,
+}
+
+/// A small deterministic generator (SplitMix64), so a `seed` reproduces a data set.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `low..=high`.
+    pub fn range(&mut self, low: i64, high: i64) -> i64 {
+        let span = (high - low) as u64 + 1;
+        low + (self.next_u64() % span) as i64
+    }
+
+    pub fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.next_u64() as usize % items.len()]
+    }
+
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ColumnSpec {
+    pub name: String,
+    pub data_type: String,
+    pub column_type: String,
+    pub nullable: bool,
+    pub max_length: Option<i64>,
+    pub precision: Option<i64>,
+    pub scale: Option<i64>,
+    /// Part of a single-column unique key, so values must not repeat.
+    pub unique: bool,
+}
+
+/// Existing referenced keys for one foreign key, as values of its columns.
+struct ForeignKeySample {
+    columns: Vec<String>,
+    keys: Vec<Vec<Value>>,
+}
+
+/// Generate `count` rows for the table and insert them with `insert_data`.
+pub async fn generate_data(
+    args: GenerateDataArguments,
+    conn: &mut sqlx::MySqlConnection,
+    pool: &sqlx::Pool<sqlx::MySql>,
+    in_transaction: bool,
+    max_rows: usize,
+) -> Result<GenerateResult, DbError> {
+    if !is_valid_identifier(&args.table_name) {
+        return Err(DbError::InvalidIdentifier(args.table_name));
+    }
+    if args.count == 0 || args.count > MAX_GENERATED_ROWS {
+        return Err(DbError::InvalidInput(format!("count must be between 1 and {MAX_GENERATED_ROWS}")));
+    }
+    let database: String = conn
+        .fetch_one("SELECT DATABASE()")
+        .await?
+        .try_get::<Option<String>, _>(0)?
+        .ok_or(DbError::NoDatabaseSelected)?;
+    let table = args.table_name.clone();
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+    });
+    let mut rng = Rng::new(seed);
+
+    let unique_keys = unique_keys(&database, &table, conn).await?;
+    let mut columns = column_specs(&database, &table, conn).await?;
+    if columns.is_empty() {
+        return Err(DbError::NotFound(format!("Table '{table}' not found in database '{database}'")));
+    }
+    for column in &mut columns {
+        column.unique = unique_keys.iter().any(|key| key.len() == 1 && key[0] == column.name);
+    }
+
+    let mut samples = Vec::new();
+    for key in foreign_keys(pool, &database, Some(&table)).await? {
+        let select = format!(
+            "SELECT DISTINCT {} FROM {}.{} LIMIT {FK_SAMPLE_SIZE}",
+            key.referenced_columns.iter().map(|c| quote_name(c)).collect::<Vec<_>>().join(", "),
+            quote_name(&key.referenced_database),
+            quote_name(&key.referenced_table)
+        );
+        let keys: Vec<Vec<Value>> = conn
+            .fetch_all(select.as_str())
+            .await?
+            .iter()
+            .map(|row| {
+                let values = row_to_json(row);
+                key.referenced_columns.iter().map(|c| values[c].clone()).collect()
+            })
+            .collect();
+        let nullable = key.columns.iter().all(|c| columns.iter().any(|s| s.name == *c && s.nullable));
+        if keys.is_empty() && !nullable {
+            return Err(DbError::InvalidInput(format!(
+                "Foreign key {} references {}.{}, which has no rows; generate data for it first",
+                key.name, key.referenced_database, key.referenced_table
+            )));
+        }
+        samples.push(ForeignKeySample { columns: key.columns, keys });
+    }
+
+    // Sequential values for unique integer columns, starting above the current maximum
+    let mut next_ints = HashMap::new();
+    for column in columns.iter().filter(|c| c.unique && is_integer(&c.data_type)) {
+        let max: Option<i64> = sqlx::query_scalar(&format!("SELECT CAST(MAX({}) AS SIGNED) FROM {}", quote_name(&column.name), quote_name(&table)))
+            .fetch_one(&mut *conn)
+            .await?;
+        next_ints.insert(column.name.clone(), max.unwrap_or(0) + 1);
+    }
+
+    let mut seen: Vec<HashSet<String>> = vec![HashSet::new(); unique_keys.len()];
+    let mut rows = Vec::with_capacity(args.count);
+    for index in 0..args.count {
+        let mut attempts = 0;
+        let row = loop {
+            let row = generate_row(&columns, &samples, &mut next_ints, index, &mut rng);
+            let tuples: Vec<String> = unique_keys
+                .iter()
+                .map(|key| key.iter().map(|c| row.get(c).map_or("".to_string(), |v| v.to_string())).collect::<Vec<_>>().join("\u{1f}"))
+                .collect();
+            if tuples.iter().zip(&seen).all(|(tuple, seen)| !seen.contains(tuple)) {
+                for (tuple, seen) in tuples.into_iter().zip(&mut seen) {
+                    seen.insert(tuple);
+                }
+                break row;
+            }
+            attempts += 1;
+            if attempts == MAX_ATTEMPTS {
+                return Err(DbError::InvalidInput(format!(
+                    "Could only generate {index} rows with unique keys; the referenced tables may have too few rows"
+                )));
+            }
+        };
+        rows.push(Value::Object(row));
+    }
+
+    let sample = rows.iter().take(5).cloned().collect();
+    let insert = insert_data(
+        InsertArguments {
+            table_name: table.clone(),
+            data: Value::Array(rows),
+            batch_size: args.batch_size,
+            return_rows: false,
+            key_columns: None,
+            dry_run: false,
+            transaction_id: None,
+        },
+        conn,
+        in_transaction,
+        max_rows,
+    )
+    .await?;
+    Ok(GenerateResult { table, insert, sample, seed })
+}
+
+async fn column_specs(database: &str, table: &str, conn: &mut sqlx::MySqlConnection) -> Result<Vec<ColumnSpec>, DbError> {
+    let rows = sqlx::query(
+        "SELECT column_name AS column_name, data_type AS data_type, column_type AS column_type,
+                is_nullable AS is_nullable, extra AS extra,
+                CAST(character_maximum_length AS SIGNED) AS max_length,
+                CAST(numeric_precision AS SIGNED) AS numeric_precision,
+                CAST(numeric_scale AS SIGNED) AS numeric_scale
+         FROM information_schema.columns
+         WHERE table_schema = ? AND table_name = ?
+         ORDER BY ordinal_position",
+    )
+    .bind(database)
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut columns = Vec::new();
+    for row in rows {
+        let extra: String = row.try_get("extra")?;
+        // The server fills these in
+        if extra.contains("auto_increment") || extra.contains("GENERATED") && !extra.contains("DEFAULT_GENERATED") {
+            continue;
+        }
+        columns.push(ColumnSpec {
+            name: row.try_get("column_name")?,
+            data_type: row.try_get::<String, _>("data_type")?.to_lowercase(),
+            column_type: row.try_get::<String, _>("column_type")?.to_lowercase(),
+            nullable: row.try_get::<String, _>("is_nullable")? == "YES",
+            max_length: row.try_get("max_length")?,
+            precision: row.try_get("numeric_precision")?,
+            scale: row.try_get("numeric_scale")?,
+            unique: false,
+        });
+    }
+    Ok(columns)
+}
+
+fn generate_row(
+    columns: &[ColumnSpec],
+    samples: &[ForeignKeySample],
+    next_ints: &mut HashMap<String, i64>,
+    index: usize,
+    rng: &mut Rng,
+) -> Map<String, Value> {
+    let mut row = Map::new();
+    for sample in samples {
+        let key = match sample.keys.len() {
+            0 => None,
+            n => Some(&sample.keys[rng.next_u64() as usize % n]),
+        };
+        for (i, column) in sample.columns.iter().enumerate() {
+            row.insert(column.clone(), key.map_or(Value::Null, |k| k[i].clone()));
+        }
+    }
+    for column in columns {
+        if row.contains_key(&column.name) {
+            continue;
+        }
+        let value = match next_ints.get_mut(&column.name) {
+            Some(next) => {
+                *next += 1;
+                json!(*next - 1)
+            }
+            None if column.nullable && !column.unique && rng.chance(10) => Value::Null,
+            None => column_value(column, index, rng),
+        };
+        row.insert(column.name.clone(), value);
+    }
+    row
+}
+
+fn is_integer(data_type: &str) -> bool {
+    matches!(data_type, "tinyint" | "smallint" | "mediumint" | "int" | "integer" | "bigint")
+}
+
+/// A plausible value for the column, from its type and, for strings, its name.
+pub fn column_value(column: &ColumnSpec, index: usize, rng: &mut Rng) -> Value {
+    let unsigned = column.column_type.contains("unsigned");
+    match column.data_type.as_str() {
+        "tinyint" if column.column_type.starts_with("tinyint(1)") => json!(rng.range(0, 1)),
+        "tinyint" => json!(rng.range(if unsigned { 0 } else { 1 }, 100)),
+        "smallint" | "mediumint" | "int" | "integer" | "bigint" => json!(rng.range(1, 1000)),
+        "bit" => json!(rng.range(0, 1)),
+        "decimal" | "numeric" => {
+            let scale = column.scale.unwrap_or(0).clamp(0, 4) as u32;
+            let digits = (column.precision.unwrap_or(10) - column.scale.unwrap_or(0)).clamp(1, 5) as u32;
+            let max = 10i64.pow(digits + scale) - 1;
+            let value = rng.range(0, max.min(1_000_000));
+            if scale == 0 {
+                json!(value.to_string())
+            } else {
+                let divisor = 10i64.pow(scale);
+                json!(format!("{}.{:0width$}", value / divisor, value % divisor, width = scale as usize))
+            }
+        }
+        "float" | "double" | "real" => json!(rng.range(0, 100_000) as f64 / 100.0),
+        "date" => json!(random_date(rng)),
+        "datetime" | "timestamp" => json!(format!("{} {}", random_date(rng), random_time(rng))),
+        "time" => json!(random_time(rng)),
+        "year" => json!(rng.range(2000, 2025)),
+        "enum" | "set" => {
+            let values = enum_values(&column.column_type);
+            match values.len() {
+                0 => json!(""),
+                n => json!(values[rng.next_u64() as usize % n]),
+            }
+        }
+        "json" => json!(json!({ "id": index + 1, "tag": rng.pick(WORDS) }).to_string()),
+        "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob" => {
+            let length = column.max_length.unwrap_or(16).min(16) as usize;
+            json!((0..length).map(|_| char::from(b'a' + (rng.next_u64() % 26) as u8)).collect::<String>())
+        }
+        _ => json!(truncate(text_value(column, index, rng), column.max_length, column.unique)),
+    }
+}
+
+/// Text picked by what the column name suggests it holds; unique columns get
+/// a suffix so values never repeat.
+fn text_value(column: &ColumnSpec, index: usize, rng: &mut Rng) -> String {
+    let name = column.name.to_lowercase();
+    let first = rng.pick(FIRST_NAMES);
+    let last = rng.pick(LAST_NAMES);
+    let suffix = format!("{}{:04x}", index + 1, rng.next_u64() & 0xffff);
+    if name.contains("email") {
+        return format!("{}.{}{suffix}@example.com", first.to_lowercase(), last.to_lowercase());
+    }
+    if name.contains("uuid") || name.contains("guid") {
+        return uuid::Uuid::from_u64_pair(rng.next_u64(), rng.next_u64()).to_string();
+    }
+    let value = if name.contains("first") {
+        first.to_string()
+    } else if name.contains("last") || name.contains("surname") {
+        last.to_string()
+    } else if name.contains("user") || name.contains("login") {
+        format!("{}{}", first.to_lowercase(), last.to_lowercase())
+    } else if name.contains("name") {
+        format!("{first} {last}")
+    } else if name.contains("phone") || name.contains("mobile") {
+        format!("+1-555-{:04}", rng.range(0, 9999))
+    } else if name.contains("city") {
+        rng.pick(CITIES).to_string()
+    } else if name.contains("country") {
+        rng.pick(COUNTRIES).to_string()
+    } else if name.contains("url") || name.contains("website") || name.contains("link") {
+        format!("https://example.com/{}/{}", rng.pick(WORDS), rng.pick(WORDS))
+    } else if name.contains("status") || name.contains("state") {
+        rng.pick(STATUSES).to_string()
+    } else if name.contains("code") || name.contains("sku") {
+        format!("{}-{:04}", rng.pick(WORDS).to_uppercase(), rng.range(0, 9999))
+    } else {
+        let words = if column.data_type.ends_with("text") { 8 } else { 2 };
+        let sentence: Vec<&str> = (0..words).map(|_| rng.pick(WORDS)).collect();
+        let sentence = sentence.join(" ");
+        sentence[..1].to_uppercase() + &sentence[1..]
+    };
+    if column.unique {
+        format!("{value} {suffix}")
+    } else {
+        value
+    }
+}
+
+/// Cut a value to the column length, keeping the tail of unique values,
+/// where their suffix sits.
+fn truncate(value: String, max_length: Option<i64>, keep_tail: bool) -> String {
+    match max_length {
+        Some(max) if value.chars().count() > max as usize => {
+            let skip = if keep_tail { value.chars().count() - max as usize } else { 0 };
+            value.chars().skip(skip).take(max as usize).collect()
+        }
+        _ => value,
+    }
+}
+
+/// The members of `enum('a','b')` or `set('a','b')`.
+pub fn enum_values(column_type: &str) -> Vec<String> {
+    let Some(inner) = column_type.find('(').and_then(|start| column_type.rfind(')').map(|end| &column_type[start + 1..end])) else {
+        return Vec::new();
+    };
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut chars = inner.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' if quoted && chars.peek() == Some(&'\'') => {
+                current.push('\'');
+                chars.next();
+            }
+            '\'' if quoted => {
+                values.push(std::mem::take(&mut current));
+                quoted = false;
+            }
+            '\'' => quoted = true,
+            c if quoted => current.push(c),
+            _ => {}
+        }
+    }
+    values
+}
+
+/// A date between 2020-01-01 and 2025-12-31.
+fn random_date(rng: &mut Rng) -> String {
+    // Days since 1970-01-01
+    let (year, month, day) = civil_from_days(rng.range(18262, 20453));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn random_time(rng: &mut Rng) -> String {
+    format!("{:02}:{:02}:{:02}", rng.range(0, 23), rng.range(0, 59), rng.range(0, 59))
+}
+
+/// Calendar date of a day count since the Unix epoch (Howard Hinnant's algorithm).
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(name: &str, data_type: &str, column_type: &str, max_length: Option<i64>) -> ColumnSpec {
+        ColumnSpec {
+            name: name.into(),
+            data_type: data_type.into(),
+            column_type: column_type.into(),
+            nullable: false,
+            max_length,
+            precision: Some(10),
+            scale: Some(2),
+            unique: false,
+        }
+    }
+
+    #[test]
+    fn test_enum_values_and_dates() {
+        assert_eq!(enum_values("enum('new','it''s','done')"), vec!["new", "it's", "done"]);
+        assert_eq!(enum_values("set('a,b','c')"), vec!["a,b", "c"]);
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(18262), (2020, 1, 1));
+        assert_eq!(civil_from_days(20453), (2025, 12, 31));
+    }
+
+    #[test]
+    fn test_column_value() {
+        let mut rng = Rng::new(42);
+        for _ in 0..50 {
+            let status = column_value(&spec("status", "enum", "enum('open','closed')", None), 0, &mut rng);
+            assert!(status == "open" || status == "closed");
+            let code = column_value(&spec("country_code", "char", "char(2)", Some(2)), 0, &mut rng);
+            assert_eq!(code.as_str().unwrap().chars().count(), 2);
+            let price = column_value(&spec("price", "decimal", "decimal(10,2)", None), 0, &mut rng);
+            let (whole, fraction) = price.as_str().unwrap().split_once('.').unwrap();
+            assert!(whole.parse::<u64>().is_ok() && fraction.len() == 2);
+            let flag = column_value(&spec("active", "tinyint", "tinyint(1)", None), 0, &mut rng);
+            assert!(flag == 0 || flag == 1);
+        }
+        let email = column_value(&spec("email", "varchar", "varchar(255)", Some(255)), 7, &mut rng);
+        assert!(email.as_str().unwrap().ends_with("@example.com"));
+
+        let mut unique = spec("username", "varchar", "varchar(40)", Some(40));
+        unique.unique = true;
+        let values: HashSet<String> =
+            (0..500).map(|i| column_value(&unique, i, &mut rng).as_str().unwrap().to_string()).collect();
+        assert_eq!(values.len(), 500);
+
+        // The same seed reproduces the same values
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        let name = spec("name", "varchar", "varchar(100)", Some(100));
+        assert_eq!(column_value(&name, 0, &mut a), column_value(&name, 0, &mut b));
+    }
+}
@@ -0,0 +1,131 @@
+//! A per-session ring buffer of recent tool calls and the SQL they ran, so
+//! the model and the user can review what was actually executed.
+
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::rpc::{HistoryStatus, QueryHistoryArguments};
+
+/// Calls remembered per session; the oldest are dropped first.
+pub const HISTORY_CAPACITY: usize = 200;
+
+/// Statements kept for one call, since an import can run thousands of batches.
+pub const MAX_STATEMENTS_PER_CALL: usize = 20;
+
+pub struct HistoryEntry {
+    pub id: u64,
+    /// Unix time in milliseconds when the call finished.
+    pub finished_at: u128,
+    pub tool: String,
+    pub statements: Vec<String>,
+    pub duration: Duration,
+    /// Rows returned or affected, when the tool reports a count.
+    pub row_count: Option<u64>,
+    /// Error text of a failed call.
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct QueryHistory {
+    entries: std::sync::Mutex<(u64, VecDeque<HistoryEntry>)>,
+}
+
+impl QueryHistory {
+    pub fn record(
+        &self,
+        tool: &str,
+        statements: Vec<String>,
+        duration: Duration,
+        row_count: Option<u64>,
+        error: Option<String>,
+    ) {
+        let finished_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+        let mut guard = self.entries.lock().unwrap();
+        let (next_id, entries) = &mut *guard;
+        *next_id += 1;
+        if entries.len() == HISTORY_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(HistoryEntry {
+            id: *next_id,
+            finished_at,
+            tool: tool.to_string(),
+            statements,
+            duration,
+            row_count,
+            error,
+        });
+    }
+
+    /// Matching entries, newest first.
+    pub fn list(&self, args: &QueryHistoryArguments) -> Vec<Value> {
+        let guard = self.entries.lock().unwrap();
+        guard
+            .1
+            .iter()
+            .rev()
+            .filter(|entry| matches(entry, args))
+            .take(args.limit)
+            .map(|entry| {
+                json!({
+                    "id": entry.id,
+                    "finishedAt": entry.finished_at as u64,
+                    "tool": entry.tool,
+                    "statements": entry.statements,
+                    "durationMs": entry.duration.as_millis() as u64,
+                    "rowCount": entry.row_count,
+                    "status": if entry.error.is_some() { "error" } else { "ok" },
+                    "error": entry.error,
+                })
+            })
+            .collect()
+    }
+}
+
+fn matches(entry: &HistoryEntry, args: &QueryHistoryArguments) -> bool {
+    if args.tool.as_ref().is_some_and(|tool| *tool != entry.tool) {
+        return false;
+    }
+    match args.status {
+        Some(HistoryStatus::Ok) if entry.error.is_some() => return false,
+        Some(HistoryStatus::Error) if entry.error.is_none() => return false,
+        _ => {}
+    }
+    match &args.contains {
+        Some(text) => {
+            let text = text.to_lowercase();
+            entry.statements.iter().any(|s| s.to_lowercase().contains(&text))
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_ring_and_filters() {
+        let history = QueryHistory::default();
+        for i in 0..HISTORY_CAPACITY + 5 {
+            history.record("query", vec![format!("SELECT {i}")], Duration::from_millis(3), Some(1), None);
+        }
+        history.record("delete", vec!["DELETE FROM `t` WHERE `id` = ?".into()], Duration::ZERO, None, Some("denied".into()));
+
+        let all: QueryHistoryArguments = serde_json::from_value(json!({ "limit": 1000 })).unwrap();
+        let entries = history.list(&all);
+        assert_eq!(entries.len(), HISTORY_CAPACITY);
+        assert_eq!(entries[0]["tool"], "delete");
+        assert_eq!(entries[0]["status"], "error");
+        assert_eq!(entries[1]["statements"][0], format!("SELECT {}", HISTORY_CAPACITY + 4));
+
+        let errors: QueryHistoryArguments = serde_json::from_value(json!({ "status": "error" })).unwrap();
+        assert_eq!(history.list(&errors).len(), 1);
+        let search: QueryHistoryArguments =
+            serde_json::from_value(json!({ "tool": "query", "contains": "select 20" })).unwrap();
+        let found = history.list(&search);
+        assert!(!found.is_empty());
+        assert!(found.iter().all(|e| e["statements"][0].as_str().unwrap().starts_with("SELECT 20")));
+    }
+}
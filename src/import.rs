@@ -0,0 +1,313 @@
+//! Bulk loading of rows with batched multi-row INSERTs, from inline JSON or
+//! a CSV / NDJSON / JSON file under `import_dir`.
+
+use log::debug;
+use serde_json::{Map, Value};
+use sqlx::Executor;
+use std::path::Path;
+
+use crate::db::{bind_param, is_valid_identifier, record_statement, report_progress, track_connection};
+use crate::ddl::{qualified_name, quote_name};
+use crate::error::DbError;
+use crate::export::resolve_path_in;
+use crate::rpc::{DataFormat, ImportArguments, OnDuplicate};
+
+/// Placeholders MySQL accepts in one prepared statement.
+pub const MAX_PLACEHOLDERS: usize = 65_535;
+
+pub struct ImportResult {
+    pub rows: u64,
+    pub batches: u64,
+    pub affected_rows: u64,
+    /// Rows left out by `on_duplicate: ignore`.
+    pub skipped: Option<u64>,
+}
+
+/// Insert every row in batches of `batch_size`. Outside an explicit
+/// transaction the whole import runs in one, so a failing batch leaves the
+/// table untouched.
+pub async fn import(
+    args: ImportArguments,
+    import_dir: Option<&str>,
+    conn: &mut sqlx::MySqlConnection,
+    in_transaction: bool,
+) -> Result<ImportResult, DbError> {
+    let table = qualified_name(args.database.as_deref(), &args.table_name)?;
+    let rows = load_rows(&args, import_dir).await?;
+    if rows.is_empty() {
+        return Err(DbError::InvalidInput("No rows to import".to_string()));
+    }
+
+    // Union of all keys in order of first appearance
+    let mut columns: Vec<String> = Vec::new();
+    for row in &rows {
+        for key in row.keys() {
+            if !columns.contains(key) {
+                if !is_valid_identifier(key) {
+                    return Err(DbError::InvalidIdentifier(key.clone()));
+                }
+                columns.push(key.clone());
+            }
+        }
+    }
+    let batch_size = args.batch_size.clamp(1, (MAX_PLACEHOLDERS / columns.len()).max(1));
+
+    track_connection(conn).await?;
+    if !in_transaction {
+        conn.execute("START TRANSACTION").await?;
+    }
+    let result = insert_batches(&table, &columns, &rows, batch_size, args.on_duplicate, conn).await;
+    if !in_transaction {
+        let end = if result.is_ok() { "COMMIT" } else { "ROLLBACK" };
+        let finished = conn.execute(end).await;
+        if result.is_ok() {
+            finished?;
+        }
+    }
+    let (batches, affected_rows) = result?;
+
+    let total = rows.len() as u64;
+    Ok(ImportResult {
+        rows: total,
+        batches,
+        affected_rows,
+        skipped: (args.on_duplicate == OnDuplicate::Ignore).then(|| total.saturating_sub(affected_rows)),
+    })
+}
+
+async fn insert_batches(
+    table: &str,
+    columns: &[String],
+    rows: &[Map<String, Value>],
+    batch_size: usize,
+    on_duplicate: OnDuplicate,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<(u64, u64), DbError> {
+    let total = rows.len() as u64;
+    let mut affected_rows = 0;
+    let mut batches = 0;
+    for (i, batch) in rows.chunks(batch_size).enumerate() {
+        let (sql, params) = insert_statement(table, columns, batch, on_duplicate);
+        let mut query = sqlx::query(&sql);
+        for param in &params {
+            query = bind_param(query, param);
+        }
+        let first_row = i * batch_size + 1;
+        debug!("Importing rows {first_row}..{} into {table}", first_row + batch.len() - 1);
+        record_statement(&sql);
+        let result = query.execute(&mut *conn).await.map_err(|e| {
+            DbError::InvalidInput(format!(
+                "Batch {} (rows {first_row}-{}) failed: {e}",
+                i + 1,
+                first_row + batch.len() - 1
+            ))
+        })?;
+        affected_rows += result.rows_affected();
+        batches += 1;
+        let done = (first_row + batch.len() - 1) as u64;
+        report_progress(done, Some(total), &format!("Imported {done} of {total} rows"));
+    }
+    Ok((batches, affected_rows))
+}
+
+/// Multi-row INSERT for one batch. Columns a row does not mention get their
+/// `DEFAULT`.
+pub fn insert_statement(
+    table: &str,
+    columns: &[String],
+    batch: &[Map<String, Value>],
+    on_duplicate: OnDuplicate,
+) -> (String, Vec<Value>) {
+    let mut params = Vec::new();
+    let tuples: Vec<String> = batch
+        .iter()
+        .map(|row| {
+            let values: Vec<&str> = columns
+                .iter()
+                .map(|column| match row.get(column) {
+                    Some(value) => {
+                        params.push(value.clone());
+                        "?"
+                    }
+                    None => "DEFAULT",
+                })
+                .collect();
+            format!("({})", values.join(", "))
+        })
+        .collect();
+    let column_list: Vec<String> = columns.iter().map(|c| quote_name(c)).collect();
+    let mut sql = format!(
+        "INSERT {}INTO {table} ({}) VALUES {}",
+        if on_duplicate == OnDuplicate::Ignore { "IGNORE " } else { "" },
+        column_list.join(", "),
+        tuples.join(", ")
+    );
+    if on_duplicate == OnDuplicate::Update {
+        let updates: Vec<String> = column_list.iter().map(|c| format!("{c} = VALUES({c})")).collect();
+        sql.push_str(&format!(" ON DUPLICATE KEY UPDATE {}", updates.join(", ")));
+    }
+    (sql, params)
+}
+
+/// Rows from the inline `rows` argument or the file at `path`.
+async fn load_rows(args: &ImportArguments, import_dir: Option<&str>) -> Result<Vec<Map<String, Value>>, DbError> {
+    let values = match (&args.rows, &args.path) {
+        (Some(rows), None) => rows.clone(),
+        (None, Some(path)) => {
+            let dir = import_dir.ok_or_else(|| {
+                DbError::InvalidInput("Importing from a file requires import_dir to be set in config.toml".to_string())
+            })?;
+            let file = resolve_path_in(Path::new(dir), path)?;
+            let text = tokio::fs::read_to_string(&file)
+                .await
+                .map_err(|e| DbError::InvalidInput(format!("Cannot read {}: {e}", file.display())))?;
+            let format = args.format.or_else(|| match file.extension().and_then(|e| e.to_str()) {
+                Some("csv") => Some(DataFormat::Csv),
+                Some("ndjson") | Some("jsonl") => Some(DataFormat::Ndjson),
+                _ => None,
+            });
+            match format {
+                Some(DataFormat::Csv) => csv_rows(&text)?,
+                Some(DataFormat::Ndjson) => text
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .enumerate()
+                    .map(|(i, line)| {
+                        serde_json::from_str(line)
+                            .map_err(|e| DbError::InvalidInput(format!("Line {}: {e}", i + 1)))
+                    })
+                    .collect::<Result<_, _>>()?,
+                // A .json file holding an array of row objects
+                None => serde_json::from_str(&text)
+                    .map_err(|e| DbError::InvalidInput(format!("Expected a JSON array of rows: {e}")))?,
+            }
+        }
+        _ => return Err(DbError::InvalidInput("Provide either rows or path".to_string())),
+    };
+
+    values
+        .into_iter()
+        .map(|value| match value {
+            Value::Object(row) => Ok(row),
+            _ => Err(DbError::InvalidInput("Every row must be an object".to_string())),
+        })
+        .collect()
+}
+
+/// CSV with a header row as row objects. An unquoted empty field is NULL,
+/// matching what `export` writes.
+fn csv_rows(text: &str) -> Result<Vec<Value>, DbError> {
+    let mut records = parse_csv(text)?.into_iter();
+    let header = records
+        .next()
+        .ok_or_else(|| DbError::InvalidInput("CSV file is empty".to_string()))?;
+    let header: Vec<String> = header.into_iter().map(Option::unwrap_or_default).collect();
+    records
+        .enumerate()
+        .map(|(i, record)| {
+            if record.len() != header.len() {
+                return Err(DbError::InvalidInput(format!(
+                    "CSV record {} has {} fields, expected {}",
+                    i + 1,
+                    record.len(),
+                    header.len()
+                )));
+            }
+            Ok(Value::Object(
+                header.iter().cloned().zip(record.into_iter().map(|f| f.map_or(Value::Null, Value::String))).collect(),
+            ))
+        })
+        .collect()
+}
+
+/// Parse RFC 4180 CSV into records. Fields are `None` when empty and
+/// unquoted. Blank lines are skipped.
+fn parse_csv(text: &str) -> Result<Vec<Vec<Option<String>>>, DbError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    let end_field = |field: &mut String, quoted: &mut bool, record: &mut Vec<Option<String>>| {
+        let value = std::mem::take(field);
+        record.push(if value.is_empty() && !*quoted { None } else { Some(value) });
+        *quoted = false;
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => field.push(c),
+                        None => return Err(DbError::InvalidInput("Unterminated quoted CSV field".to_string())),
+                    }
+                }
+            }
+            ',' => end_field(&mut field, &mut quoted, &mut record),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if !(record.is_empty() && field.is_empty() && !quoted) {
+                    end_field(&mut field, &mut quoted, &mut record);
+                    records.push(std::mem::take(&mut record));
+                }
+            }
+            c => field.push(c),
+        }
+    }
+    if !(record.is_empty() && field.is_empty() && !quoted) {
+        end_field(&mut field, &mut quoted, &mut record);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_csv() {
+        let records = parse_csv("id,name,note\r\n1,\"Smith, J\",\n2,\"say \"\"hi\"\"\",\"\"\n\n").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec![Some("id".into()), Some("name".into()), Some("note".into())],
+                vec![Some("1".into()), Some("Smith, J".into()), None],
+                vec![Some("2".into()), Some("say \"hi\"".into()), Some(String::new())],
+            ]
+        );
+        assert_eq!(parse_csv("a,\"multi\nline\"").unwrap(), vec![vec![Some("a".into()), Some("multi\nline".into())]]);
+        assert!(parse_csv("a,\"open").is_err());
+    }
+
+    #[test]
+    fn test_insert_statement() {
+        let rows: Vec<Map<String, Value>> = vec![
+            json!({ "id": 1, "name": "a" }).as_object().unwrap().clone(),
+            json!({ "id": 2 }).as_object().unwrap().clone(),
+        ];
+        let columns = vec!["id".to_string(), "name".to_string()];
+
+        let (sql, params) = insert_statement("`t`", &columns, &rows, OnDuplicate::Error);
+        assert_eq!(sql, "INSERT INTO `t` (`id`, `name`) VALUES (?, ?), (?, DEFAULT)");
+        assert_eq!(params, vec![json!(1), json!("a"), json!(2)]);
+
+        let (sql, _) = insert_statement("`t`", &columns, &rows[..1], OnDuplicate::Ignore);
+        assert_eq!(sql, "INSERT IGNORE INTO `t` (`id`, `name`) VALUES (?, ?)");
+
+        let (sql, _) = insert_statement("`t`", &columns, &rows[..1], OnDuplicate::Update);
+        assert_eq!(
+            sql,
+            "INSERT INTO `t` (`id`, `name`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `id` = VALUES(`id`), `name` = VALUES(`name`)"
+        );
+    }
+}
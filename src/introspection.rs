@@ -0,0 +1,790 @@
+//! Introspection of database objects beyond tables and columns.
+
+use serde_json::{json, Value};
+use sqlx::{MySql, Pool, Row};
+use std::collections::HashMap;
+
+use crate::db::{acquire_with_fresh_stats, current_database, foreign_keys, is_valid_identifier, ForeignKey};
+use crate::ddl::quote_name;
+use crate::error::DbError;
+use crate::masking::Masks;
+use crate::rpc::Verbosity;
+
+/// Resolve an optional database argument to a validated name, defaulting to the current one.
+pub async fn resolve_database(database: Option<String>, pool: &Pool<MySql>) -> Result<String, DbError> {
+    match database {
+        Some(db) if !is_valid_identifier(&db) => Err(DbError::InvalidIdentifier(db)),
+        Some(db) => Ok(db),
+        None => current_database(pool).await,
+    }
+}
+
+/// Stored procedures and functions of `database` with their signatures and bodies.
+pub async fn list_routines(
+    database: Option<String>,
+    routine_type: Option<String>,
+    include_body: bool,
+    pool: &Pool<MySql>,
+) -> Result<(String, Vec<Value>), DbError> {
+    let database = resolve_database(database, pool).await?;
+    let routine_type = match routine_type.map(|t| t.to_uppercase()) {
+        None => None,
+        Some(t) if t == "PROCEDURE" || t == "FUNCTION" => Some(t),
+        Some(t) => {
+            return Err(DbError::InvalidInput(format!(
+                "Unknown routine type '{t}', expected procedure or function"
+            )))
+        }
+    };
+
+    let routines = sqlx::query(
+        "SELECT routine_name AS routine_name, routine_type AS routine_type,
+                dtd_identifier AS return_type, routine_definition AS definition,
+                is_deterministic AS is_deterministic, sql_data_access AS sql_data_access,
+                security_type AS security_type, routine_comment AS routine_comment
+         FROM information_schema.routines
+         WHERE routine_schema = ? AND (? IS NULL OR routine_type = ?)
+         ORDER BY routine_name",
+    )
+    .bind(&database)
+    .bind(&routine_type)
+    .bind(&routine_type)
+    .fetch_all(pool)
+    .await?;
+
+    // Parameters of every routine in one round trip, keyed by (type, name)
+    let parameters = sqlx::query(
+        "SELECT specific_name AS specific_name, routine_type AS routine_type,
+                CAST(ordinal_position AS SIGNED) AS ordinal_position, parameter_mode AS parameter_mode,
+                parameter_name AS parameter_name, dtd_identifier AS data_type
+         FROM information_schema.parameters
+         WHERE specific_schema = ?
+         ORDER BY specific_name, ordinal_position",
+    )
+    .bind(&database)
+    .fetch_all(pool)
+    .await?;
+    let mut params_by_routine: HashMap<(String, String), Vec<Value>> = HashMap::new();
+    for row in parameters {
+        // Position 0 is a function's return value, already reported as `returns`
+        if row.try_get::<i64, _>("ordinal_position").unwrap_or(0) == 0 {
+            continue;
+        }
+        let key = (
+            row.try_get::<String, _>("routine_type").unwrap_or_default(),
+            row.try_get::<String, _>("specific_name").unwrap_or_default(),
+        );
+        params_by_routine.entry(key).or_default().push(json!({
+            "name": row.try_get::<Option<String>, _>("parameter_name").unwrap_or_default(),
+            "mode": row.try_get::<Option<String>, _>("parameter_mode").unwrap_or_default(),
+            "type": row.try_get::<Option<String>, _>("data_type").unwrap_or_default(),
+        }));
+    }
+
+    let routines = routines
+        .into_iter()
+        .map(|row| {
+            let name: String = row.try_get("routine_name").unwrap_or_default();
+            let kind: String = row.try_get("routine_type").unwrap_or_default();
+            let parameters = params_by_routine.remove(&(kind.clone(), name.clone())).unwrap_or_default();
+            let signature = format!(
+                "{}({})",
+                name,
+                parameters
+                    .iter()
+                    .map(|p| {
+                        let mode = p["mode"].as_str().map(|m| format!("{m} ")).unwrap_or_default();
+                        format!("{mode}{} {}", p["name"].as_str().unwrap_or(""), p["type"].as_str().unwrap_or(""))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            let mut routine = json!({
+                "name": name,
+                "type": kind.to_lowercase(),
+                "signature": signature,
+                "parameters": parameters,
+                "returns": row.try_get::<Option<String>, _>("return_type").unwrap_or_default(),
+                "deterministic": row.try_get::<String, _>("is_deterministic").unwrap_or_default() == "YES",
+                "sql_data_access": row.try_get::<String, _>("sql_data_access").unwrap_or_default(),
+                "security": row.try_get::<String, _>("security_type").unwrap_or_default(),
+                "comment": row.try_get::<String, _>("routine_comment").unwrap_or_default(),
+            });
+            if include_body {
+                // NULL unless the user created the routine or has SHOW_ROUTINE / SELECT on mysql.proc
+                routine["body"] = json!(row.try_get::<Option<String>, _>("definition").unwrap_or_default());
+            }
+            routine
+        })
+        .collect();
+
+    Ok((database, routines))
+}
+
+pub struct EntityColumn {
+    pub name: String,
+    pub data_type: String,
+    pub primary_key: bool,
+    pub nullable: bool,
+}
+
+/// Mermaid `erDiagram` of the base tables of `database` (or just `tables`)
+/// and the foreign keys between them.
+pub async fn er_diagram(
+    database: Option<String>,
+    tables: Option<Vec<String>>,
+    pool: &Pool<MySql>,
+) -> Result<(String, usize, usize), DbError> {
+    let database = resolve_database(database, pool).await?;
+    if let Some(invalid) = tables.iter().flatten().find(|t| !is_valid_identifier(t)) {
+        return Err(DbError::InvalidIdentifier(invalid.clone()));
+    }
+
+    let rows = sqlx::query(
+        "SELECT c.table_name AS table_name, c.column_name AS column_name,
+                c.data_type AS data_type, c.column_key AS column_key,
+                c.is_nullable AS is_nullable
+         FROM information_schema.columns c
+         JOIN information_schema.tables t
+           ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+         WHERE c.table_schema = ? AND t.table_type = 'BASE TABLE'
+         ORDER BY c.table_name, c.ordinal_position",
+    )
+    .bind(&database)
+    .fetch_all(pool)
+    .await?;
+
+    let wanted = |table: &str| tables.as_ref().is_none_or(|t| t.iter().any(|w| w == table));
+    let mut entities: Vec<(String, Vec<EntityColumn>)> = Vec::new();
+    for row in rows {
+        let table: String = row.try_get("table_name")?;
+        if !wanted(&table) {
+            continue;
+        }
+        let column = EntityColumn {
+            name: row.try_get("column_name")?,
+            data_type: row.try_get("data_type")?,
+            primary_key: row.try_get::<String, _>("column_key")? == "PRI",
+            nullable: row.try_get::<String, _>("is_nullable")? == "YES",
+        };
+        match entities.last_mut() {
+            Some((name, columns)) if *name == table => columns.push(column),
+            _ => entities.push((table, vec![column])),
+        }
+    }
+    if let Some(missing) = tables.iter().flatten().find(|t| !entities.iter().any(|(name, _)| name == *t)) {
+        return Err(DbError::NotFound(format!("Table '{missing}' not found in '{database}'")));
+    }
+
+    let keys: Vec<ForeignKey> = foreign_keys(pool, &database, None)
+        .await?
+        .into_iter()
+        .filter(|k| k.referenced_database == database && wanted(&k.table_name) && wanted(&k.referenced_table))
+        .collect();
+
+    let diagram = render_er_diagram(&entities, &keys);
+    Ok((diagram, entities.len(), keys.len()))
+}
+
+/// Mermaid entity names and attribute types only allow word characters and hyphens.
+fn mermaid_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+pub fn render_er_diagram(entities: &[(String, Vec<EntityColumn>)], keys: &[ForeignKey]) -> String {
+    let mut out = String::from("erDiagram\n");
+    for (table, columns) in entities {
+        out.push_str(&format!("    {} {{\n", mermaid_name(table)));
+        for column in columns {
+            let foreign = keys
+                .iter()
+                .any(|k| k.table_name == *table && k.columns.contains(&column.name));
+            let markers = match (column.primary_key, foreign) {
+                (true, true) => " PK, FK",
+                (true, false) => " PK",
+                (false, true) => " FK",
+                (false, false) => "",
+            };
+            out.push_str(&format!(
+                "        {} {}{}\n",
+                mermaid_name(&column.data_type),
+                mermaid_name(&column.name),
+                markers
+            ));
+        }
+        out.push_str("    }\n");
+    }
+    for key in keys {
+        // A nullable foreign key means the child row may exist without a parent
+        let optional_parent = entities
+            .iter()
+            .find(|(name, _)| *name == key.table_name)
+            .is_some_and(|(_, columns)| {
+                columns.iter().any(|c| c.nullable && key.columns.contains(&c.name))
+            });
+        out.push_str(&format!(
+            "    {} {}--o{{ {} : \"{}\"\n",
+            mermaid_name(&key.referenced_table),
+            if optional_parent { "|o" } else { "||" },
+            mermaid_name(&key.table_name),
+            key.name.replace('"', "'")
+        ));
+    }
+    out
+}
+
+pub struct SummaryColumn {
+    pub name: String,
+    pub column_type: String,
+    pub primary_key: bool,
+    pub nullable: bool,
+    pub default: Option<String>,
+    pub extra: String,
+    pub comment: String,
+}
+
+pub struct SummaryTable {
+    pub name: String,
+    pub view: bool,
+    pub estimated_rows: Option<u64>,
+    pub comment: String,
+    pub columns: Vec<SummaryColumn>,
+    /// Columns of each unique key other than the primary key.
+    pub unique_keys: Vec<Vec<String>>,
+}
+
+/// One line per table and view of `database` (or just `tables`), condensed
+/// for a model's context rather than for programs.
+pub async fn schema_summary(
+    database: Option<String>,
+    tables: Option<Vec<String>>,
+    verbosity: Verbosity,
+    pool: &Pool<MySql>,
+) -> Result<(String, Vec<String>), DbError> {
+    let database = resolve_database(database, pool).await?;
+    if let Some(invalid) = tables.iter().flatten().find(|t| !is_valid_identifier(t)) {
+        return Err(DbError::InvalidIdentifier(invalid.clone()));
+    }
+
+    let rows = sqlx::query(
+        "SELECT c.table_name AS table_name, t.table_type AS table_type,
+                CAST(t.table_rows AS UNSIGNED) AS table_rows, t.table_comment AS table_comment,
+                c.column_name AS column_name, c.column_type AS column_type,
+                c.column_key AS column_key, c.is_nullable AS is_nullable,
+                c.column_default AS column_default, c.extra AS extra, c.column_comment AS column_comment
+         FROM information_schema.columns c
+         JOIN information_schema.tables t
+           ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+         WHERE c.table_schema = ?
+         ORDER BY c.table_name, c.ordinal_position",
+    )
+    .bind(&database)
+    .fetch_all(pool)
+    .await?;
+
+    let wanted = |table: &str| tables.as_ref().is_none_or(|t| t.iter().any(|w| w == table));
+    let mut summary: Vec<SummaryTable> = Vec::new();
+    for row in rows {
+        let table: String = row.try_get("table_name")?;
+        if !wanted(&table) {
+            continue;
+        }
+        let column = SummaryColumn {
+            name: row.try_get("column_name")?,
+            column_type: row.try_get("column_type")?,
+            primary_key: row.try_get::<String, _>("column_key")? == "PRI",
+            nullable: row.try_get::<String, _>("is_nullable")? == "YES",
+            default: row.try_get("column_default")?,
+            extra: row.try_get("extra")?,
+            comment: row.try_get("column_comment")?,
+        };
+        match summary.last_mut() {
+            Some(entry) if entry.name == table => entry.columns.push(column),
+            _ => summary.push(SummaryTable {
+                view: row.try_get::<String, _>("table_type")? == "VIEW",
+                estimated_rows: row.try_get("table_rows")?,
+                comment: row.try_get::<Option<String>, _>("table_comment")?.unwrap_or_default(),
+                name: table,
+                columns: vec![column],
+                unique_keys: Vec::new(),
+            }),
+        }
+    }
+    if let Some(missing) = tables.iter().flatten().find(|t| !summary.iter().any(|entry| entry.name == **t)) {
+        return Err(DbError::NotFound(format!("Table '{missing}' not found in '{database}'")));
+    }
+
+    if verbosity == Verbosity::Full {
+        let rows = sqlx::query(
+            "SELECT table_name AS table_name, index_name AS index_name, column_name AS column_name
+             FROM information_schema.statistics
+             WHERE table_schema = ? AND non_unique = 0 AND index_name <> 'PRIMARY'
+             ORDER BY table_name, index_name, seq_in_index",
+        )
+        .bind(&database)
+        .fetch_all(pool)
+        .await?;
+        let mut last_index = String::new();
+        for row in rows {
+            let table: String = row.try_get("table_name")?;
+            let index: String = row.try_get("index_name")?;
+            // Functional key parts have no column
+            let column: Option<String> = row.try_get("column_name")?;
+            let Some(entry) = summary.iter_mut().find(|entry| entry.name == table) else {
+                continue;
+            };
+            let key = format!("{table}.{index}");
+            match entry.unique_keys.last_mut() {
+                Some(columns) if key == last_index => columns.extend(column),
+                _ => entry.unique_keys.push(column.into_iter().collect()),
+            }
+            last_index = key;
+        }
+    }
+
+    let keys = foreign_keys(pool, &database, None).await?;
+    let lines = summary.iter().map(|table| summary_line(table, &keys, &database, verbosity)).collect();
+    Ok((database, lines))
+}
+
+/// `orders: id int PK, customer_id int FK->customers.id, status varchar(20)`
+pub fn summary_line(table: &SummaryTable, keys: &[ForeignKey], database: &str, verbosity: Verbosity) -> String {
+    let mut line = table.name.clone();
+    let mut notes = Vec::new();
+    if table.view {
+        notes.push("view".to_string());
+    }
+    if verbosity == Verbosity::Full {
+        if let Some(rows) = table.estimated_rows.filter(|_| !table.view) {
+            notes.push(format!("~{rows} rows"));
+        }
+        if !table.comment.is_empty() {
+            notes.push(format!("\"{}\"", table.comment));
+        }
+    }
+    if !notes.is_empty() {
+        line.push_str(&format!(" ({})", notes.join(", ")));
+    }
+
+    let columns: Vec<String> = table
+        .columns
+        .iter()
+        .map(|column| {
+            let mut parts = vec![column.name.clone()];
+            if verbosity != Verbosity::Names {
+                parts.push(column.column_type.clone());
+            }
+            if column.primary_key {
+                parts.push("PK".to_string());
+            }
+            for key in keys.iter().filter(|k| k.table_name == table.name) {
+                if let Some(position) = key.columns.iter().position(|c| *c == column.name) {
+                    let referenced = if key.referenced_database == database {
+                        key.referenced_table.clone()
+                    } else {
+                        format!("{}.{}", key.referenced_database, key.referenced_table)
+                    };
+                    parts.push(match verbosity {
+                        Verbosity::Names => format!("FK->{referenced}"),
+                        _ => format!("FK->{referenced}.{}", key.referenced_columns[position]),
+                    });
+                }
+            }
+            if verbosity == Verbosity::Full {
+                if !column.nullable && !column.primary_key {
+                    parts.push("NOT NULL".to_string());
+                }
+                if let Some(default) = &column.default {
+                    parts.push(format!("DEFAULT {default}"));
+                }
+                if !column.extra.is_empty() && !column.extra.eq_ignore_ascii_case("DEFAULT_GENERATED") {
+                    parts.push(column.extra.replace("DEFAULT_GENERATED ", "").to_lowercase());
+                }
+                if !column.comment.is_empty() {
+                    parts.push(format!("\"{}\"", column.comment));
+                }
+            }
+            parts.join(" ")
+        })
+        .collect();
+    line.push_str(": ");
+    line.push_str(&columns.join(", "));
+    for unique in table.unique_keys.iter().filter(|_| verbosity == Verbosity::Full) {
+        line.push_str(&format!("; UNIQUE({})", unique.join(", ")));
+    }
+    line
+}
+
+/// Storage statistics for one table, or every base table, of `database`.
+/// `exact` adds a `COUNT(*)`, which is only allowed for a single table.
+pub async fn table_stats(
+    table_name: Option<String>,
+    database: Option<String>,
+    exact: bool,
+    pool: &Pool<MySql>,
+) -> Result<(String, Vec<Value>), DbError> {
+    let database = resolve_database(database, pool).await?;
+    if let Some(table) = &table_name {
+        if !is_valid_identifier(table) {
+            return Err(DbError::InvalidIdentifier(table.clone()));
+        }
+    } else if exact {
+        return Err(DbError::InvalidInput("exact counts require a table_name".to_string()));
+    }
+
+    let mut conn = acquire_with_fresh_stats(pool).await?;
+    let rows = sqlx::query(
+        "SELECT table_name AS table_name, engine AS engine, row_format AS row_format,
+                table_rows AS table_rows, avg_row_length AS avg_row_length,
+                data_length AS data_length, index_length AS index_length,
+                data_free AS data_free, auto_increment AS auto_increment,
+                CAST(create_time AS CHAR) AS create_time, CAST(update_time AS CHAR) AS update_time
+         FROM information_schema.tables
+         WHERE table_schema = ? AND table_type = 'BASE TABLE' AND (? IS NULL OR table_name = ?)
+         ORDER BY table_name",
+    )
+    .bind(&database)
+    .bind(&table_name)
+    .bind(&table_name)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if let (Some(table), true) = (&table_name, rows.is_empty()) {
+        return Err(DbError::NotFound(format!("Table '{table}' not found in '{database}'")));
+    }
+
+    let mut stats = Vec::with_capacity(rows.len());
+    for row in rows {
+        let name: String = row.try_get("table_name")?;
+        let data_length: Option<u64> = row.try_get("data_length")?;
+        let index_length: Option<u64> = row.try_get("index_length")?;
+        let data_free: Option<u64> = row.try_get("data_free")?;
+        let mut entry = json!({
+            "name": name,
+            "engine": row.try_get::<Option<String>, _>("engine")?,
+            "rowFormat": row.try_get::<Option<String>, _>("row_format")?,
+            "estimatedRows": row.try_get::<Option<u64>, _>("table_rows")?,
+            "avgRowLength": row.try_get::<Option<u64>, _>("avg_row_length")?,
+            "dataBytes": data_length,
+            "indexBytes": index_length,
+            "freeBytes": data_free,
+            "fragmentationPercent": fragmentation_percent(data_length, index_length, data_free),
+            "autoIncrement": row.try_get::<Option<u64>, _>("auto_increment")?,
+            "createTime": row.try_get::<Option<String>, _>("create_time")?,
+            "updateTime": row.try_get::<Option<String>, _>("update_time")?,
+        });
+        if exact {
+            let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}.{}", quote_name(&database), quote_name(&name)))
+                .fetch_one(&mut *conn)
+                .await?;
+            entry["exactRows"] = json!(count);
+        }
+        stats.push(entry);
+    }
+    Ok((database, stats))
+}
+
+/// Rows profiled at most; larger tables are sampled down to about this many.
+const PROFILE_SAMPLE_ROWS: u64 = 100_000;
+
+/// Most frequent values reported per column at most.
+pub const PROFILE_MAX_TOP_K: usize = 20;
+
+/// Per-column statistics for a table: null and distinct counts, min/max,
+/// average length of string values, and the most frequent values. Tables
+/// estimated above `PROFILE_SAMPLE_ROWS` are profiled on a random sample, so
+/// counts then describe the sample. Values of masked columns are masked.
+pub async fn profile_table(
+    table_name: String,
+    database: Option<String>,
+    columns: Option<Vec<String>>,
+    top_k: usize,
+    masks: &Masks,
+    pool: &Pool<MySql>,
+) -> Result<Value, DbError> {
+    let database = resolve_database(database, pool).await?;
+    if !is_valid_identifier(&table_name) {
+        return Err(DbError::InvalidIdentifier(table_name));
+    }
+    let top_k = top_k.min(PROFILE_MAX_TOP_K);
+
+    let column_rows = sqlx::query(
+        "SELECT column_name AS column_name, data_type AS data_type
+         FROM information_schema.columns
+         WHERE table_schema = ? AND table_name = ?
+         ORDER BY ordinal_position",
+    )
+    .bind(&database)
+    .bind(&table_name)
+    .fetch_all(pool)
+    .await?;
+    if column_rows.is_empty() {
+        return Err(DbError::NotFound(format!("Table '{table_name}' not found in '{database}'")));
+    }
+    let mut profiled = Vec::new();
+    for row in column_rows {
+        let name: String = row.try_get("column_name")?;
+        if columns.as_ref().is_some_and(|only| !only.contains(&name)) {
+            continue;
+        }
+        if !is_valid_identifier(&name) {
+            return Err(DbError::InvalidIdentifier(name));
+        }
+        profiled.push((name, row.try_get::<String, _>("data_type")?.to_lowercase()));
+    }
+    if let Some(missing) = columns.iter().flatten().find(|c| !profiled.iter().any(|(name, _)| name == *c)) {
+        return Err(DbError::NotFound(format!("Column '{missing}' not found in '{table_name}'")));
+    }
+
+    let estimated_rows: Option<u64> = sqlx::query_scalar(
+        "SELECT CAST(table_rows AS UNSIGNED) AS table_rows FROM information_schema.tables
+         WHERE table_schema = ? AND table_name = ?",
+    )
+    .bind(&database)
+    .bind(&table_name)
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+    let sampled = estimated_rows.unwrap_or(0) > PROFILE_SAMPLE_ROWS;
+    let source = profile_source(&database, &table_name, estimated_rows.unwrap_or(0));
+
+    let stats = sqlx::query(&profile_stats_sql(&source, &profiled)).fetch_one(pool).await?;
+    let rows_profiled: i64 = stats.try_get("total")?;
+
+    let mut column_profiles = Vec::with_capacity(profiled.len());
+    for (i, (name, data_type)) in profiled.iter().enumerate() {
+        let mut profile = json!({
+            "name": name,
+            "dataType": data_type,
+            "nulls": stats.try_get::<Option<u64>, _>(format!("nulls_{i}").as_str())?.unwrap_or(0),
+            "distinct": stats.try_get::<i64, _>(format!("distinct_{i}").as_str())?,
+        });
+        if !is_opaque_type(data_type) {
+            profile["min"] = json!(stats.try_get::<Option<String>, _>(format!("min_{i}").as_str())?);
+            profile["max"] = json!(stats.try_get::<Option<String>, _>(format!("max_{i}").as_str())?);
+        }
+        if is_string_type(data_type) {
+            let avg: Option<String> = stats.try_get(format!("avg_length_{i}").as_str())?;
+            profile["avgLength"] = json!(avg.and_then(|a| a.parse::<f64>().ok()));
+        }
+        if top_k > 0 && !is_opaque_type(data_type) {
+            let column = quote_name(name);
+            let top = sqlx::query(&format!(
+                "SELECT CAST({column} AS CHAR) AS value, COUNT(*) AS count FROM {source}
+                 GROUP BY {column} ORDER BY count DESC LIMIT {top_k}"
+            ))
+            .fetch_all(pool)
+            .await?;
+            profile["topValues"] = top
+                .iter()
+                .map(|row| {
+                    Ok(json!({
+                        "value": row.try_get::<Option<String>, _>("value")?,
+                        "count": row.try_get::<i64, _>("count")?,
+                    }))
+                })
+                .collect::<Result<Vec<_>, sqlx::Error>>()?
+                .into();
+        }
+        // Min, max, and the frequent values are values of the column too
+        for key in ["min", "max"] {
+            if let Some(value) = profile.get_mut(key) {
+                masks.apply_to_value(value, Some(&database), &table_name, name);
+            }
+        }
+        for entry in profile.get_mut("topValues").and_then(Value::as_array_mut).into_iter().flatten() {
+            masks.apply_to_value(&mut entry["value"], Some(&database), &table_name, name);
+        }
+        column_profiles.push(profile);
+    }
+
+    Ok(json!({
+        "database": database,
+        "table": table_name,
+        "rowsProfiled": rows_profiled,
+        "estimatedRows": estimated_rows,
+        "sampled": sampled,
+        "columns": column_profiles,
+    }))
+}
+
+/// Rows to profile as a FROM clause: the table itself, or for big tables a
+/// random sample. `RAND()` is seeded so every query sees the same sample.
+fn profile_source(database: &str, table_name: &str, estimated_rows: u64) -> String {
+    let table = format!("{}.{}", quote_name(database), quote_name(table_name));
+    if estimated_rows <= PROFILE_SAMPLE_ROWS {
+        return table;
+    }
+    let fraction = PROFILE_SAMPLE_ROWS as f64 / estimated_rows as f64;
+    format!("(SELECT * FROM {table} WHERE RAND(1) < {fraction}) AS sample")
+}
+
+/// One pass computing every column's aggregate statistics.
+fn profile_stats_sql(source: &str, columns: &[(String, String)]) -> String {
+    let mut select = vec!["COUNT(*) AS total".to_string()];
+    for (i, (name, data_type)) in columns.iter().enumerate() {
+        let column = quote_name(name);
+        select.push(format!("CAST(SUM({column} IS NULL) AS UNSIGNED) AS nulls_{i}"));
+        select.push(format!("COUNT(DISTINCT {column}) AS distinct_{i}"));
+        if !is_opaque_type(data_type) {
+            select.push(format!("CAST(MIN({column}) AS CHAR) AS min_{i}"));
+            select.push(format!("CAST(MAX({column}) AS CHAR) AS max_{i}"));
+        }
+        if is_string_type(data_type) {
+            select.push(format!("CAST(AVG(CHAR_LENGTH({column})) AS CHAR) AS avg_length_{i}"));
+        }
+    }
+    format!("SELECT {} FROM {source}", select.join(", "))
+}
+
+fn is_string_type(data_type: &str) -> bool {
+    matches!(data_type, "char" | "varchar" | "tinytext" | "text" | "mediumtext" | "longtext" | "enum" | "set")
+}
+
+/// Types whose raw values are not useful as min/max or frequent values.
+fn is_opaque_type(data_type: &str) -> bool {
+    data_type.contains("blob") || data_type.contains("binary") || matches!(data_type, "json" | "geometry" | "point" | "linestring" | "polygon" | "multipoint" | "multilinestring" | "multipolygon" | "geometrycollection")
+}
+
+/// Share of the tablespace allocated to the table that is unused, as a
+/// rough signal that `OPTIMIZE TABLE` would reclaim space.
+fn fragmentation_percent(data: Option<u64>, index: Option<u64>, free: Option<u64>) -> Option<f64> {
+    let free = free?;
+    let total = data.unwrap_or(0) + index.unwrap_or(0) + free;
+    if total == 0 {
+        return Some(0.0);
+    }
+    Some((free as f64 * 1000.0 / total as f64).round() / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, primary_key: bool, nullable: bool) -> EntityColumn {
+        EntityColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            primary_key,
+            nullable,
+        }
+    }
+
+    #[test]
+    fn test_render_er_diagram() {
+        let entities = vec![
+            ("customers".to_string(), vec![column("id", "int", true, false)]),
+            (
+                "orders".to_string(),
+                vec![
+                    column("id", "int", true, false),
+                    column("customer_id", "int", false, false),
+                    column("coupon id", "int", false, true),
+                ],
+            ),
+            ("coupons".to_string(), vec![column("id", "int", true, false)]),
+        ];
+        let fk = |name: &str, column: &str, parent: &str| ForeignKey {
+            name: name.to_string(),
+            table_name: "orders".to_string(),
+            columns: vec![column.to_string()],
+            referenced_database: "shop".to_string(),
+            referenced_table: parent.to_string(),
+            referenced_columns: vec!["id".to_string()],
+            on_update: "RESTRICT".to_string(),
+            on_delete: "CASCADE".to_string(),
+        };
+        let keys = vec![
+            fk("fk_customer", "customer_id", "customers"),
+            fk("fk_coupon", "coupon id", "coupons"),
+        ];
+
+        let diagram = render_er_diagram(&entities, &keys);
+        assert!(diagram.starts_with("erDiagram\n    customers {\n        int id PK\n    }\n"));
+        assert!(diagram.contains("        int customer_id FK\n"));
+        assert!(diagram.contains("        int coupon_id FK\n"));
+        assert!(diagram.contains("    customers ||--o{ orders : \"fk_customer\"\n"));
+        assert!(diagram.contains("    coupons |o--o{ orders : \"fk_coupon\"\n"));
+    }
+
+    #[test]
+    fn test_summary_line() {
+        let summary_column = |name: &str, column_type: &str, primary_key: bool| SummaryColumn {
+            name: name.to_string(),
+            column_type: column_type.to_string(),
+            primary_key,
+            nullable: name == "note",
+            default: (name == "status").then(|| "new".to_string()),
+            extra: if primary_key { "auto_increment".to_string() } else { String::new() },
+            comment: String::new(),
+        };
+        let orders = SummaryTable {
+            name: "orders".to_string(),
+            view: false,
+            estimated_rows: Some(48000),
+            comment: "Customer orders".to_string(),
+            columns: vec![
+                summary_column("id", "int unsigned", true),
+                summary_column("customer_id", "int unsigned", false),
+                summary_column("status", "varchar(20)", false),
+                summary_column("note", "text", false),
+            ],
+            unique_keys: vec![vec!["customer_id".to_string(), "status".to_string()]],
+        };
+        let keys = vec![ForeignKey {
+            name: "fk_customer".to_string(),
+            table_name: "orders".to_string(),
+            columns: vec!["customer_id".to_string()],
+            referenced_database: "shop".to_string(),
+            referenced_table: "customers".to_string(),
+            referenced_columns: vec!["id".to_string()],
+            on_update: "RESTRICT".to_string(),
+            on_delete: "CASCADE".to_string(),
+        }];
+
+        assert_eq!(
+            summary_line(&orders, &keys, "shop", Verbosity::Names),
+            "orders: id PK, customer_id FK->customers, status, note"
+        );
+        assert_eq!(
+            summary_line(&orders, &keys, "shop", Verbosity::Types),
+            "orders: id int unsigned PK, customer_id int unsigned FK->customers.id, status varchar(20), note text"
+        );
+        assert_eq!(
+            summary_line(&orders, &keys, "other", Verbosity::Full),
+            "orders (~48000 rows, \"Customer orders\"): id int unsigned PK auto_increment, \
+             customer_id int unsigned FK->shop.customers.id NOT NULL, status varchar(20) NOT NULL DEFAULT new, \
+             note text; UNIQUE(customer_id, status)"
+        );
+    }
+
+    #[test]
+    fn test_profile_sql() {
+        assert_eq!(profile_source("shop", "orders", 500), "`shop`.`orders`");
+        assert_eq!(
+            profile_source("shop", "orders", 1_000_000),
+            "(SELECT * FROM `shop`.`orders` WHERE RAND(1) < 0.1) AS sample"
+        );
+
+        let columns = vec![("email".to_string(), "varchar".to_string()), ("photo".to_string(), "blob".to_string())];
+        assert_eq!(
+            profile_stats_sql("`t`", &columns),
+            "SELECT COUNT(*) AS total, \
+             CAST(SUM(`email` IS NULL) AS UNSIGNED) AS nulls_0, COUNT(DISTINCT `email`) AS distinct_0, \
+             CAST(MIN(`email`) AS CHAR) AS min_0, CAST(MAX(`email`) AS CHAR) AS max_0, \
+             CAST(AVG(CHAR_LENGTH(`email`)) AS CHAR) AS avg_length_0, \
+             CAST(SUM(`photo` IS NULL) AS UNSIGNED) AS nulls_1, COUNT(DISTINCT `photo`) AS distinct_1 \
+             FROM `t`"
+        );
+    }
+
+    #[test]
+    fn test_fragmentation_percent() {
+        assert_eq!(fragmentation_percent(Some(600), Some(200), Some(200)), Some(20.0));
+        assert_eq!(fragmentation_percent(Some(0), Some(0), Some(0)), Some(0.0));
+        assert_eq!(fragmentation_percent(Some(2), None, Some(1)), Some(33.3));
+        assert_eq!(fragmentation_percent(Some(100), Some(0), None), None);
+    }
+}
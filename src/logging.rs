@@ -0,0 +1,204 @@
+//! Logger that writes to stderr through env_logger and mirrors this crate's
+//! records to clients that enabled MCP logging with `logging/setLevel`.
+//! A record only goes to the session it was logged for ([`SESSION_ID`]), so
+//! clients sharing a network transport never see each other's SQL; records
+//! logged outside any session stay on stderr. Every record has its
+//! credentials masked by [`redact::text`] first.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::redact;
+use crate::server::Notifier;
+
+struct ClientSink {
+    session_id: u64,
+    level: LevelFilter,
+    notifier: Notifier,
+}
+
+/// Sessions that asked for log messages, with the minimum level each wants.
+static CLIENT_SINKS: Mutex<Vec<ClientSink>> = Mutex::new(Vec::new());
+
+/// Level configured for stderr through `RUST_LOG`.
+static STDERR_LEVEL: AtomicU64 = AtomicU64::new(0);
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+tokio::task_local! {
+    /// The session the current task works for, whose sink gets its records.
+    pub static SESSION_ID: u64;
+}
+
+struct ClientLogger {
+    stderr: env_logger::Logger,
+}
+
+impl Log for ClientLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata) || metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        let message = redact::text(&record.args().to_string());
+        let emit = |record: &Record| {
+            if self.stderr.enabled(record.metadata()) {
+                self.stderr.log(record);
+            }
+            // Dependency output (sqlx, hyper) is too noisy to forward
+            if record.target().starts_with(env!("CARGO_CRATE_NAME")) {
+                forward(record);
+            }
+        };
+        emit(
+            &Record::builder()
+                .args(format_args!("{message}"))
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+pub fn init() {
+    let stderr = env_logger::Builder::from_default_env().build();
+    let level = stderr.filter();
+    STDERR_LEVEL.store(level as u64, Ordering::SeqCst);
+    if log::set_boxed_logger(Box::new(ClientLogger { stderr })).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Identifier used to register a session's log sink.
+pub fn next_session_id() -> u64 {
+    NEXT_SESSION_ID.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Map an MCP log level name to the most verbose `log` level it includes.
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level {
+        "debug" => Some(LevelFilter::Debug),
+        "info" | "notice" => Some(LevelFilter::Info),
+        "warning" => Some(LevelFilter::Warn),
+        "error" | "critical" | "alert" | "emergency" => Some(LevelFilter::Error),
+        _ => None,
+    }
+}
+
+fn mcp_level(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warn => "warning",
+        Level::Info => "info",
+        Level::Debug | Level::Trace => "debug",
+    }
+}
+
+/// Start (or change the level of) log forwarding to a session.
+pub fn set_client_level(session_id: u64, level: LevelFilter, notifier: Notifier) {
+    let mut sinks = CLIENT_SINKS.lock().unwrap();
+    sinks.retain(|s| s.session_id != session_id);
+    sinks.push(ClientSink {
+        session_id,
+        level,
+        notifier,
+    });
+    update_max_level(&sinks);
+}
+
+pub fn remove_client(session_id: u64) {
+    let mut sinks = CLIENT_SINKS.lock().unwrap();
+    sinks.retain(|s| s.session_id != session_id);
+    update_max_level(&sinks);
+}
+
+/// The `log` macros drop records above `log::max_level()` before reaching the
+/// logger, so it has to cover both stderr and the most verbose client.
+fn update_max_level(sinks: &[ClientSink]) {
+    let stderr_level = match STDERR_LEVEL.load(Ordering::SeqCst) {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    let client_level = sinks.iter().map(|s| s.level).max().unwrap_or(LevelFilter::Off);
+    log::set_max_level(stderr_level.max(client_level));
+}
+
+fn forward(record: &Record) {
+    let Ok(session_id) = SESSION_ID.try_with(|id| *id) else {
+        return;
+    };
+    // Never log from here: the logger would re-enter these locks.
+    let Ok(sinks) = CLIENT_SINKS.try_lock() else {
+        return;
+    };
+    let Some(sink) = sinks.iter().find(|s| s.session_id == session_id && record.level() <= s.level) else {
+        return;
+    };
+    let message = json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/message",
+        "params": {
+            "level": mcp_level(record.level()),
+            "logger": record.target(),
+            "data": record.args().to_string(),
+        }
+    })
+    .to_string();
+    if let Ok(notifier) = sink.notifier.try_lock() {
+        if let Some(tx) = notifier.as_ref() {
+            let _ = tx.send(message);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug"), Some(LevelFilter::Debug));
+        assert_eq!(parse_level("notice"), Some(LevelFilter::Info));
+        assert_eq!(parse_level("warning"), Some(LevelFilter::Warn));
+        assert_eq!(parse_level("emergency"), Some(LevelFilter::Error));
+        assert_eq!(parse_level("verbose"), None);
+    }
+
+    #[test]
+    fn test_forward_to_own_session() {
+        let sink = || {
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            (Notifier::new(Mutex::new(Some(tx))), rx)
+        };
+        let (first, second) = (next_session_id(), next_session_id());
+        let (first_notifier, mut first_rx) = sink();
+        let (second_notifier, mut second_rx) = sink();
+        set_client_level(first, LevelFilter::Debug, first_notifier);
+        set_client_level(second, LevelFilter::Debug, second_notifier);
+
+        let log = |message: &str| {
+            forward(&Record::builder().args(format_args!("{message}")).level(Level::Debug).target("mcp_server_mysql::server").build())
+        };
+        SESSION_ID.sync_scope(first, || log("Received message: SELECT secret FROM first"));
+        let received: serde_json::Value = serde_json::from_str(&first_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(received["params"]["data"], "Received message: SELECT secret FROM first");
+        assert!(second_rx.try_recv().is_err(), "other sessions do not get it");
+
+        log("Outside any session");
+        assert!(first_rx.try_recv().is_err() && second_rx.try_recv().is_err());
+        remove_client(first);
+        remove_client(second);
+    }
+}
@@ -12,16 +12,44 @@
 //! - Robust error handling and logging
 //! - Configurable security settings
 
+mod admin;
+mod analysis;
+mod audit;
+mod cdc;
 mod config;
+mod confirm;
+mod credentials;
 mod db;
+mod ddl;
+mod dump;
 mod error;
+mod export;
+mod generate;
+mod history;
+mod import;
+mod introspection;
+mod logging;
+mod masking;
+mod migrations;
+mod policy;
+mod prompts;
+mod ratelimit;
+mod rds_iam;
+mod redact;
 mod rpc;
+mod saved_queries;
+mod schema_diff;
+mod script;
 mod server;
+mod tools;
+mod transaction;
+mod transport;
+mod tunnel;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logger
-    env_logger::init();
+    // Initialize logger (stderr, plus MCP clients that enable logging)
+    logging::init();
 
     let config = config::load()?;
     server::run(config).await
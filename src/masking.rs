@@ -0,0 +1,434 @@
+//! Column masking: values of columns matched by the `column_masks` rules are
+//! hidden before results leave the server. For free-form SQL the source
+//! columns of each result column are found from the parsed statement, so
+//! aliases (`SELECT email AS contact`), derived column lists
+//! (`WITH c(contact) AS ...`), and expressions over a masked column are
+//! masked too. A result column whose source cannot be traced is masked as if
+//! it were any masked column of the tables the query reads.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+
+use serde_json::Value;
+use sqlparser::ast::{
+    visit_expressions, Expr, Query, Select, SelectItem, SetExpr, Statement, TableAlias, TableFactor, Visit, Visitor,
+};
+use sqlparser::dialect::MySqlDialect;
+use sqlparser::parser::Parser;
+
+use crate::config::{Config, MaskStrategy};
+use crate::db::QueryResult;
+use crate::policy::{self, glob_matches};
+
+#[derive(Debug, Clone, Default)]
+pub struct Masks {
+    rules: Vec<(String, MaskStrategy)>,
+}
+
+impl Masks {
+    pub fn new(config: &Config) -> Self {
+        Masks { rules: config.column_masks.iter().map(|(pattern, strategy)| (pattern.clone(), *strategy)).collect() }
+    }
+
+    /// The mask for `column`, where an unknown database or table matches any
+    /// pattern for it.
+    fn strategy(&self, database: Option<&str>, table: Option<&str>, column: &str) -> Option<MaskStrategy> {
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| rule_matches(pattern, database, table, column))
+            .map(|(_, strategy)| *strategy)
+            .max()
+    }
+
+    /// The strongest mask of any column of `table`.
+    fn table_strategy(&self, database: Option<&str>, table: &str) -> Option<MaskStrategy> {
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| rule_parts(pattern).is_some_and(|(db, t, _)| part_matches(db, database) && part_matches(t, Some(table))))
+            .map(|(_, strategy)| *strategy)
+            .max()
+    }
+
+    /// Whether values of `column` of `table` are masked.
+    pub fn is_masked(&self, database: Option<&str>, table: &str, column: &str) -> bool {
+        self.strategy(database, Some(table), column).is_some()
+    }
+
+    /// Mask one value read from `column` of `table`.
+    pub fn apply_to_value(&self, value: &mut Value, database: Option<&str>, table: &str, column: &str) {
+        if let Some(strategy) = self.strategy(database, Some(table), column).filter(|_| !value.is_null()) {
+            *value = mask_value(value, strategy);
+        }
+    }
+
+    /// Mask a result read from one table.
+    pub fn apply_to_table(&self, result: &mut QueryResult, database: Option<&str>, table: &str) {
+        self.for_table(&result.columns, database, table).apply(&mut result.rows);
+    }
+
+    /// Mask rows read from one table, as returned by `row_to_json`.
+    pub fn apply_to_rows(&self, rows: &mut [Value], database: Option<&str>, table: &str) {
+        let columns: Vec<String> = rows.first().and_then(Value::as_object).map(|row| row.keys().cloned().collect()).unwrap_or_default();
+        self.for_table(&columns, database, table).apply(rows);
+    }
+
+    /// Mask the result of `sql`, whose unqualified tables are in `database`.
+    /// SQL that cannot be parsed is masked by column name alone.
+    pub fn apply_to_query(&self, result: &mut QueryResult, sql: &str, database: Option<&str>) {
+        self.for_query(&result.columns, sql, database).apply(&mut result.rows);
+    }
+
+    /// The masks of `columns` read from one table, resolved once for results
+    /// that are streamed a row at a time.
+    pub fn for_table(&self, columns: &[String], database: Option<&str>, table: &str) -> ColumnMasks {
+        let strategies = columns.iter().map(|c| self.strategy(database, Some(table), c)).collect();
+        ColumnMasks { columns: columns.to_vec(), strategies }
+    }
+
+    /// The masks of the result `columns` of `sql`, like [`Masks::apply_to_query`].
+    pub fn for_query(&self, columns: &[String], sql: &str, database: Option<&str>) -> ColumnMasks {
+        if self.rules.is_empty() {
+            return ColumnMasks { columns: columns.to_vec(), strategies: vec![None; columns.len()] };
+        }
+        let strategies: Vec<_> = match Parser::parse_sql(&MySqlDialect {}, sql) {
+            Ok(statements) => {
+                let (_, tables) = policy::references(&statements);
+                let sources = column_sources(statements.last(), columns);
+                sources
+                    .iter()
+                    .map(|sources| {
+                        let Some(sources) = sources else {
+                            return tables.iter().filter_map(|(db, table)| self.table_strategy(db.as_deref().or(database), table)).max();
+                        };
+                        sources
+                            .iter()
+                            .filter_map(|source| {
+                                if tables.is_empty() {
+                                    return self.strategy(None, None, source);
+                                }
+                                tables
+                                    .iter()
+                                    .filter_map(|(db, table)| self.strategy(db.as_deref().or(database), Some(table), source))
+                                    .max()
+                            })
+                            .max()
+                    })
+                    .collect()
+            }
+            Err(_) => columns.iter().map(|c| self.strategy(None, None, c)).collect(),
+        };
+        ColumnMasks { columns: columns.to_vec(), strategies }
+    }
+}
+
+/// The masks of the columns of one result.
+pub struct ColumnMasks {
+    columns: Vec<String>,
+    strategies: Vec<Option<MaskStrategy>>,
+}
+
+impl ColumnMasks {
+    /// Mask `rows`, JSON objects keyed by column name.
+    pub fn apply(&self, rows: &mut [Value]) {
+        mask_rows(rows, &self.columns, &self.strategies);
+    }
+
+    /// Whether the column at `index` is masked.
+    pub fn is_masked(&self, index: usize) -> bool {
+        self.strategies.get(index).is_some_and(Option::is_some)
+    }
+
+    /// `value`, read from the column at `index`, masked.
+    pub fn mask(&self, index: usize, value: Value) -> Value {
+        match self.strategies.get(index).copied().flatten() {
+            Some(strategy) if !value.is_null() => mask_value(&value, strategy),
+            _ => value,
+        }
+    }
+}
+
+/// Whether a `column`, `table.column`, or `db.table.column` pattern matches.
+fn rule_matches(pattern: &str, database: Option<&str>, table: Option<&str>, column: &str) -> bool {
+    let Some((database_pattern, table_pattern, column_pattern)) = rule_parts(pattern) else {
+        return false;
+    };
+    glob_matches(column_pattern, column)
+        && part_matches(table_pattern, table)
+        && part_matches(database_pattern, database)
+}
+
+/// The database, table, and column patterns of a rule.
+fn rule_parts(pattern: &str) -> Option<(Option<&str>, Option<&str>, &str)> {
+    let parts: Vec<&str> = pattern.split('.').collect();
+    match parts.as_slice() {
+        [column] => Some((None, None, *column)),
+        [table, column] => Some((None, Some(*table), *column)),
+        [database, table, column] => Some((Some(*database), Some(*table), *column)),
+        _ => None,
+    }
+}
+
+/// Whether a rule's database or table pattern matches, where a missing pattern or name matches anything.
+fn part_matches(pattern: Option<&str>, name: Option<&str>) -> bool {
+    match (pattern, name) {
+        (Some(pattern), Some(name)) => glob_matches(pattern, name),
+        _ => true,
+    }
+}
+
+fn mask_rows(rows: &mut [Value], columns: &[String], strategies: &[Option<MaskStrategy>]) {
+    for (column, strategy) in columns.iter().zip(strategies) {
+        let Some(strategy) = strategy else { continue };
+        for row in rows.iter_mut() {
+            if let Some(value) = row.get_mut(column).filter(|v| !v.is_null()) {
+                *value = mask_value(value, *strategy);
+            }
+        }
+    }
+}
+
+fn mask_value(value: &Value, strategy: MaskStrategy) -> Value {
+    match strategy {
+        MaskStrategy::Redact => Value::String("[REDACTED]".to_string()),
+        MaskStrategy::Null => Value::Null,
+        MaskStrategy::Partial => {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            let masked = match text.split_once('@') {
+                Some((local, domain)) if !local.is_empty() => {
+                    format!("{}***@{domain}", local.chars().next().unwrap_or_default())
+                }
+                _ if text.chars().count() > 8 => {
+                    let tail: String = text.chars().skip(text.chars().count() - 4).collect();
+                    format!("***{tail}")
+                }
+                _ => "***".to_string(),
+            };
+            Value::String(masked)
+        }
+    }
+}
+
+/// For each result column, the lowercased names of the columns its values
+/// may come from: itself, the select-list expression at its position, and
+/// what any alias of those names was defined as. `None` when one of those
+/// aliases names a column whose expression is unknown.
+fn column_sources(statement: Option<&Statement>, columns: &[String]) -> Vec<Option<Vec<String>>> {
+    let mut collector = Aliases::default();
+    if let Some(statement) = statement {
+        let _ = statement.visit(&mut collector);
+    }
+    let mut positional: Vec<Vec<String>> = vec![Vec::new(); columns.len()];
+    if let Some(Statement::Query(query)) = statement {
+        let mut branches = Vec::new();
+        selects(&query.body, &mut branches);
+        for select in branches {
+            let has_wildcard = select
+                .projection
+                .iter()
+                .any(|item| matches!(item, SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)));
+            if has_wildcard || select.projection.len() != columns.len() {
+                continue;
+            }
+            for (sources, item) in positional.iter_mut().zip(&select.projection) {
+                if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item {
+                    sources.extend(identifiers(expr));
+                }
+            }
+        }
+    }
+    columns
+        .iter()
+        .zip(positional)
+        .map(|(column, names)| {
+            let mut sources = Vec::new();
+            for name in std::iter::once(column.clone()).chain(names) {
+                resolve(&name, &collector.aliases, &mut sources);
+            }
+            Some(sources).filter(|sources| !sources.iter().any(|source| collector.unresolved.contains(source)))
+        })
+        .collect()
+}
+
+fn resolve(name: &str, aliases: &HashMap<String, Vec<String>>, out: &mut Vec<String>) {
+    let name = name.to_lowercase();
+    if out.contains(&name) {
+        return;
+    }
+    out.push(name.clone());
+    for source in aliases.get(&name).into_iter().flatten() {
+        resolve(source, aliases, out);
+    }
+}
+
+/// The SELECT branches of a query body, through set operations.
+fn selects<'a>(body: &'a SetExpr, out: &mut Vec<&'a Select>) {
+    match body {
+        SetExpr::Select(select) => out.push(select),
+        SetExpr::Query(query) => selects(&query.body, out),
+        SetExpr::SetOperation { left, right, .. } => {
+            selects(left, out);
+            selects(right, out);
+        }
+        _ => {}
+    }
+}
+
+/// Column names referenced in an expression.
+fn identifiers(expr: &Expr) -> Vec<String> {
+    let mut names = Vec::new();
+    let _ = visit_expressions(expr, |e| {
+        match e {
+            Expr::Identifier(ident) => names.push(ident.value.to_lowercase()),
+            Expr::CompoundIdentifier(parts) => names.extend(parts.last().map(|ident| ident.value.to_lowercase())),
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    names
+}
+
+/// Select-list aliases and derived column lists of every (sub)query, mapped
+/// to the columns they read.
+#[derive(Default)]
+struct Aliases {
+    aliases: HashMap<String, Vec<String>>,
+    /// Derived column names whose position in the query they name is taken
+    /// by a wildcard or missing.
+    unresolved: HashSet<String>,
+}
+
+impl Aliases {
+    /// Map the column list of `alias` onto the select list of `query`, by position.
+    fn add_column_list(&mut self, alias: &TableAlias, query: &Query) {
+        let mut branches = Vec::new();
+        selects(&query.body, &mut branches);
+        for (index, column) in alias.columns.iter().enumerate() {
+            let name = column.name.value.to_lowercase();
+            for select in &branches {
+                let has_wildcard = select
+                    .projection
+                    .iter()
+                    .any(|item| matches!(item, SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)));
+                match select.projection.get(index) {
+                    Some(SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. }) if !has_wildcard => {
+                        self.aliases.entry(name.clone()).or_default().extend(identifiers(expr));
+                    }
+                    _ => {
+                        self.unresolved.insert(name.clone());
+                    }
+                }
+            }
+            if branches.is_empty() {
+                self.unresolved.insert(name);
+            }
+        }
+    }
+}
+
+impl Visitor for Aliases {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        let mut branches = Vec::new();
+        selects(&query.body, &mut branches);
+        for item in branches.iter().flat_map(|select| &select.projection) {
+            if let SelectItem::ExprWithAlias { expr, alias } = item {
+                self.aliases.entry(alias.value.to_lowercase()).or_default().extend(identifiers(expr));
+            }
+        }
+        for cte in query.with.iter().flat_map(|with| &with.cte_tables) {
+            self.add_column_list(&cte.alias, &cte.query);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<()> {
+        if let TableFactor::Derived { subquery, alias: Some(alias), .. } = table_factor {
+            self.add_column_list(alias, subquery);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(columns: &[&str], row: Value) -> QueryResult {
+        QueryResult {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: vec![row],
+            row_count: 1,
+            truncated: false,
+            next_cursor: None,
+        }
+    }
+
+    #[test]
+    fn test_masks() {
+        let masks = Masks {
+            rules: vec![
+                ("users.email".to_string(), MaskStrategy::Partial),
+                ("*.password".to_string(), MaskStrategy::Redact),
+                ("shop.cards.number".to_string(), MaskStrategy::Partial),
+            ],
+        };
+
+        let mut r = result(&["id", "email", "password"], json!({ "id": 1, "email": "jane@example.com", "password": "hunter2" }));
+        masks.apply_to_table(&mut r, Some("shop"), "users");
+        assert_eq!(r.rows[0], json!({ "id": 1, "email": "j***@example.com", "password": "[REDACTED]" }));
+
+        let mut r = result(&["email"], json!({ "email": "jane@example.com" }));
+        masks.apply_to_table(&mut r, Some("shop"), "orders");
+        assert_eq!(r.rows[0]["email"], "jane@example.com");
+
+        let mut r = result(&["number"], json!({ "number": "4111111111111111" }));
+        masks.apply_to_table(&mut r, Some("shop"), "cards");
+        assert_eq!(r.rows[0]["number"], "***1111");
+
+        let mut r = result(&["contact", "n"], json!({ "contact": "jane@example.com", "n": 3 }));
+        masks.apply_to_query(&mut r, "SELECT email AS contact, COUNT(*) AS n FROM users GROUP BY email", None);
+        assert_eq!(r.rows[0], json!({ "contact": "j***@example.com", "n": 3 }));
+
+        let mut r = result(&["CONCAT(u.password, '')"], json!({ "CONCAT(u.password, '')": "hunter2" }));
+        masks.apply_to_query(&mut r, "SELECT CONCAT(u.password, '') FROM accounts u", Some("shop"));
+        assert_eq!(r.rows[0]["CONCAT(u.password, '')"], "[REDACTED]");
+
+        let mut r = result(&["x"], json!({ "x": "hunter2" }));
+        masks.apply_to_query(&mut r, "SELECT x FROM (SELECT password AS x FROM accounts) s", None);
+        assert_eq!(r.rows[0]["x"], "[REDACTED]");
+
+        let mut r = result(&["id"], json!({ "id": "hunter2" }));
+        masks.apply_to_query(&mut r, "SELECT id FROM orders UNION SELECT password FROM accounts", None);
+        assert_eq!(r.rows[0]["id"], "[REDACTED]");
+
+        let mut r = result(&["x"], json!({ "x": "hunter2" }));
+        masks.apply_to_query(&mut r, "WITH c(x) AS (SELECT password FROM users) SELECT x FROM c", None);
+        assert_eq!(r.rows[0]["x"], "[REDACTED]");
+
+        let mut r = result(&["x"], json!({ "x": "hunter2" }));
+        masks.apply_to_query(&mut r, "SELECT x FROM (SELECT password FROM users) AS s(x)", None);
+        assert_eq!(r.rows[0]["x"], "[REDACTED]");
+
+        let mut r = result(&["id", "x"], json!({ "id": 1, "x": "jane@example.com" }));
+        masks.apply_to_query(&mut r, "WITH c(id, x) AS (SELECT id, name FROM users) SELECT id, x FROM c", None);
+        assert_eq!(r.rows[0], json!({ "id": 1, "x": "jane@example.com" }), "columns traced to unmasked ones stay readable");
+
+        // Whatever `x` stands in for is unknown, so it is masked like the most masked column of users
+        let mut r = result(&["x"], json!({ "x": "hunter2" }));
+        masks.apply_to_query(&mut r, "WITH c(x) AS (SELECT * FROM users) SELECT x FROM c", None);
+        assert_eq!(r.rows[0]["x"], "[REDACTED]");
+
+        let mut r = result(&["x"], json!({ "x": "hunter2" }));
+        masks.apply_to_query(&mut r, "WITH c(x) AS (SELECT * FROM orders) SELECT x FROM c", None);
+        assert_eq!(r.rows[0]["x"], "[REDACTED]", "`*.password` covers orders too");
+
+        let mut r = result(&["email"], json!({ "email": null }));
+        masks.apply_to_query(&mut r, "SELECT email FROM users", None);
+        assert_eq!(r.rows[0]["email"], Value::Null);
+    }
+}
@@ -0,0 +1,290 @@
+//! Versioned `.sql` migrations from `migrations_dir`, applied in order and
+//! recorded in a `_mcp_migrations` table of the target database.
+//!
+//! Files are named `<version>_<description>.sql`, e.g. `001_create_users.sql`
+//! or `20240501120000_add_order_index.sql`; versions are compared as numbers.
+
+use log::{debug, info};
+use sqlx::{Executor, Row};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::db::{is_valid_identifier, record_statement, report_progress, track_connection};
+use crate::ddl::quote_name;
+use crate::error::DbError;
+use crate::rpc::RunMigrationsArguments;
+use crate::script::split_statements;
+
+pub const MIGRATIONS_TABLE: &str = "_mcp_migrations";
+
+/// Seconds to wait for another session applying migrations to the same database.
+const LOCK_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub version: u64,
+    pub name: String,
+    pub sql: String,
+}
+
+pub struct AppliedMigration {
+    pub version: u64,
+    pub name: String,
+    pub statements: usize,
+    pub elapsed_ms: u128,
+}
+
+pub struct FailedMigration {
+    pub version: u64,
+    pub name: String,
+    pub statement: String,
+    pub error: String,
+}
+
+pub struct MigrationResult {
+    pub database: String,
+    /// Migrations run by this call, in order.
+    pub applied: Vec<AppliedMigration>,
+    /// Migrations still to run: all of them with `dry_run`, or those after a failure.
+    pub pending: Vec<(u64, String)>,
+    /// Migrations recorded as applied before this call.
+    pub already_applied: usize,
+    /// Applied migrations whose file has changed since.
+    pub modified: Vec<(u64, String)>,
+    pub failed: Option<FailedMigration>,
+}
+
+/// Apply the pending migrations in `dir` to the target database, stopping at
+/// the first failing statement. DDL commits implicitly, so a failed
+/// migration is not recorded and may need cleaning up by hand.
+pub async fn run_migrations(
+    args: RunMigrationsArguments,
+    dir: &str,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<MigrationResult, DbError> {
+    let migrations = load_migrations(Path::new(dir)).await?;
+
+    track_connection(conn).await?;
+    let database = match args.database {
+        Some(db) if !is_valid_identifier(&db) => return Err(DbError::InvalidIdentifier(db)),
+        Some(db) => db,
+        None => conn
+            .fetch_one("SELECT DATABASE()")
+            .await?
+            .try_get::<Option<String>, _>(0)?
+            .ok_or(DbError::NoDatabaseSelected)?,
+    };
+    conn.execute(format!("USE {}", quote_name(&database)).as_str()).await?;
+
+    // Serialize runners on the same database, across sessions and servers
+    let lock_name = format!("{MIGRATIONS_TABLE}.{database}");
+    let locked: Option<i64> = sqlx::query_scalar("SELECT GET_LOCK(?, ?)")
+        .bind(&lock_name)
+        .bind(LOCK_TIMEOUT_SECS)
+        .fetch_one(&mut *conn)
+        .await?;
+    if locked != Some(1) {
+        return Err(DbError::InvalidInput(format!(
+            "Another session is applying migrations to '{database}'; try again later"
+        )));
+    }
+    let result = apply(&database, migrations, args.target_version, args.dry_run, conn).await;
+    let _ = sqlx::query("SELECT RELEASE_LOCK(?)").bind(&lock_name).execute(&mut *conn).await;
+    result
+}
+
+async fn apply(
+    database: &str,
+    migrations: Vec<Migration>,
+    target_version: Option<u64>,
+    dry_run: bool,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<MigrationResult, DbError> {
+    let create = format!(
+        "CREATE TABLE IF NOT EXISTS `{MIGRATIONS_TABLE}` (
+  `version` BIGINT UNSIGNED NOT NULL PRIMARY KEY,
+  `name` VARCHAR(255) NOT NULL,
+  `checksum` CHAR(16) NOT NULL,
+  `applied_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  `execution_ms` BIGINT UNSIGNED NOT NULL
+)"
+    );
+    if !dry_run {
+        record_statement(&create);
+        conn.execute(create.as_str()).await?;
+    }
+
+    let table_exists: Option<i64> = sqlx::query_scalar(
+        "SELECT 1 FROM information_schema.tables WHERE table_schema = ? AND table_name = ?",
+    )
+    .bind(database)
+    .bind(MIGRATIONS_TABLE)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let mut applied_checksums = HashMap::new();
+    if table_exists.is_some() {
+        let rows = sqlx::query(&format!(
+            "SELECT CAST(version AS UNSIGNED) AS version, checksum AS checksum FROM `{MIGRATIONS_TABLE}`"
+        ))
+        .fetch_all(&mut *conn)
+        .await?;
+        for row in rows {
+            applied_checksums.insert(row.try_get::<u64, _>("version")?, row.try_get::<String, _>("checksum")?);
+        }
+    }
+
+    let modified = migrations
+        .iter()
+        .filter(|m| applied_checksums.get(&m.version).is_some_and(|c| *c != checksum(&m.sql)))
+        .map(|m| (m.version, m.name.clone()))
+        .collect();
+    let pending = pending_migrations(migrations, &applied_checksums, target_version);
+    let mut result = MigrationResult {
+        database: database.to_string(),
+        applied: Vec::new(),
+        pending: Vec::new(),
+        already_applied: applied_checksums.len(),
+        modified,
+        failed: None,
+    };
+    if dry_run {
+        result.pending = pending.into_iter().map(|m| (m.version, m.name)).collect();
+        return Ok(result);
+    }
+
+    let total = pending.len() as u64;
+    let mut remaining = pending.into_iter();
+    while let Some(migration) = remaining.next() {
+        info!("Applying migration {} {}", migration.version, migration.name);
+        let started = Instant::now();
+        let statements = split_statements(&migration.sql);
+        for statement in &statements {
+            debug!("Executing migration statement: {statement}");
+            record_statement(statement);
+            // Text protocol, as not every DDL statement can be prepared
+            if let Err(e) = conn.execute(statement.as_str()).await {
+                result.failed = Some(FailedMigration {
+                    version: migration.version,
+                    name: migration.name.clone(),
+                    statement: statement.clone(),
+                    error: e.to_string(),
+                });
+                result.pending = std::iter::once(migration)
+                    .chain(remaining)
+                    .map(|m| (m.version, m.name))
+                    .collect();
+                return Ok(result);
+            }
+        }
+        let elapsed_ms = started.elapsed().as_millis();
+        sqlx::query(&format!(
+            "INSERT INTO `{MIGRATIONS_TABLE}` (version, name, checksum, execution_ms) VALUES (?, ?, ?, ?)"
+        ))
+        .bind(migration.version)
+        .bind(&migration.name)
+        .bind(checksum(&migration.sql))
+        .bind(elapsed_ms as u64)
+        .execute(&mut *conn)
+        .await?;
+        result.applied.push(AppliedMigration {
+            version: migration.version,
+            name: migration.name,
+            statements: statements.len(),
+            elapsed_ms,
+        });
+        let done = result.applied.len() as u64;
+        report_progress(done, Some(total), &format!("Applied migration {done} of {total}"));
+    }
+    Ok(result)
+}
+
+/// The migrations in `dir`, ordered by version.
+async fn load_migrations(dir: &Path) -> Result<Vec<Migration>, DbError> {
+    let unreadable = |e: std::io::Error| DbError::InvalidInput(format!("Cannot read {}: {e}", dir.display()));
+    let mut entries = tokio::fs::read_dir(dir).await.map_err(unreadable)?;
+    let mut migrations: Vec<Migration> = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(unreadable)? {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if !file_name.ends_with(".sql") {
+            continue;
+        }
+        let (version, name) = parse_file_name(&file_name).ok_or_else(|| {
+            DbError::InvalidInput(format!("Migration file name must start with a version number: {file_name}"))
+        })?;
+        if let Some(other) = migrations.iter().find(|m| m.version == version) {
+            return Err(DbError::InvalidInput(format!(
+                "Migrations '{}' and '{name}' share version {version}",
+                other.name
+            )));
+        }
+        let sql = tokio::fs::read_to_string(entry.path())
+            .await
+            .map_err(|e| DbError::InvalidInput(format!("Cannot read {file_name}: {e}")))?;
+        migrations.push(Migration { version, name, sql });
+    }
+    migrations.sort_by_key(|m| m.version);
+    Ok(migrations)
+}
+
+/// `(version, name)` of `<version>_<name>.sql`; the name is the file name
+/// without the extension.
+pub fn parse_file_name(file_name: &str) -> Option<(u64, String)> {
+    let stem = file_name.strip_suffix(".sql")?;
+    let digits = stem.chars().take_while(|c| c.is_ascii_digit()).count();
+    let version = stem[..digits].parse().ok()?;
+    match stem[digits..].chars().next() {
+        None | Some('_' | '-' | '.') => Some((version, stem.to_string())),
+        Some(_) => None,
+    }
+}
+
+/// Migrations not yet applied, up to and including `target_version`.
+pub fn pending_migrations(
+    migrations: Vec<Migration>,
+    applied: &HashMap<u64, String>,
+    target_version: Option<u64>,
+) -> Vec<Migration> {
+    migrations
+        .into_iter()
+        .filter(|m| !applied.contains_key(&m.version))
+        .filter(|m| target_version.is_none_or(|target| m.version <= target))
+        .collect()
+}
+
+/// FNV-1a of the file contents, to notice applied migrations being edited.
+/// Stable across builds, unlike `DefaultHasher`.
+pub fn checksum(sql: &str) -> String {
+    let hash = sql.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(parse_file_name("001_create_users.sql"), Some((1, "001_create_users".into())));
+        assert_eq!(parse_file_name("20240501120000-add-index.sql"), Some((20240501120000, "20240501120000-add-index".into())));
+        assert_eq!(parse_file_name("7.sql"), Some((7, "7".into())));
+        assert_eq!(parse_file_name("create_users.sql"), None);
+        assert_eq!(parse_file_name("001create.sql"), None);
+        assert_eq!(parse_file_name("001_create.txt"), None);
+    }
+
+    #[test]
+    fn test_pending_migrations() {
+        let migration = |version: u64| Migration { version, name: format!("{version:03}_m"), sql: String::new() };
+        let applied = HashMap::from([(1, checksum("")), (3, checksum(""))]);
+        let versions = |pending: Vec<Migration>| pending.iter().map(|m| m.version).collect::<Vec<_>>();
+        let all = || vec![migration(1), migration(2), migration(3), migration(4), migration(5)];
+        assert_eq!(versions(pending_migrations(all(), &applied, None)), vec![2, 4, 5]);
+        assert_eq!(versions(pending_migrations(all(), &applied, Some(4))), vec![2, 4]);
+        assert_eq!(checksum("SELECT 1"), checksum("SELECT 1"));
+        assert_ne!(checksum("SELECT 1"), checksum("SELECT 2"));
+        assert_eq!(checksum("").len(), 16);
+    }
+}
@@ -0,0 +1,883 @@
+//! Statement classification from the parsed SQL, for the policies applied
+//! to free-form SQL. Matching keywords misjudges statements such as
+//! `SELECT updated_at FROM t` or a write hidden behind a comment; the AST
+//! says what a statement does.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    Expr, JoinConstraint, JoinOperator, LimitClause, ObjectName, Query, Select, SelectItem, SetExpr, Statement, TableFactor, Use, Value,
+    Visit, Visitor,
+};
+use sqlparser::dialect::MySqlDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Location, Token, Tokenizer};
+
+use crate::config::Config;
+use crate::error::DbError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// SELECT, SHOW, DESCRIBE, and EXPLAIN without ANALYZE.
+    Read,
+    /// INSERT, REPLACE, and LOAD DATA.
+    Insert,
+    Update,
+    Delete,
+    /// CREATE, ALTER, DROP, TRUNCATE, and RENAME of schema objects.
+    Ddl,
+    /// Anything else that may change data or server state: CALL, SET,
+    /// GRANT, KILL, transaction control, and unrecognised statements.
+    Admin,
+}
+
+impl StatementKind {
+    pub fn category(self) -> &'static str {
+        match self {
+            StatementKind::Read => "read",
+            StatementKind::Insert | StatementKind::Update | StatementKind::Delete => "write",
+            StatementKind::Ddl => "DDL",
+            StatementKind::Admin => "admin",
+        }
+    }
+}
+
+/// The kind of each statement in `sql`.
+///
+/// MySQL-only statements the parser does not know, such as `SHOW ENGINE
+/// INNODB STATUS`, are still classified as reads when they start with SHOW,
+/// DESCRIBE, or EXPLAIN (without ANALYZE); anything else that fails to parse
+/// is an error, as what it does is unknown. So is text after a `;`: queries
+/// and script pieces are one statement each.
+pub fn classify(sql: &str) -> Result<Vec<StatementKind>, DbError> {
+    // `/*! ... */` is executed by MySQL but skipped by the parser as a comment
+    if sql.contains("/*!") {
+        return Ok(vec![StatementKind::Admin]);
+    }
+    let separator = single_statement(sql)?;
+    match Parser::parse_sql(&MySqlDialect {}, sql) {
+        Ok(statements) => {
+            for statement in &statements {
+                // A generic SHOW takes in every word up to the end, past any `;`
+                if let (Statement::ShowVariable { variable }, Some(separator)) = (statement, separator) {
+                    if variable.iter().any(|ident| ident.span.start > separator) {
+                        return Err(DbError::InvalidInput("SHOW is followed by another statement".to_string()));
+                    }
+                }
+            }
+            Ok(statements.iter().map(statement_kind).collect())
+        }
+        Err(e) => {
+            let mut words = sql.split_whitespace().map(str::to_uppercase);
+            match (words.next().as_deref(), words.next().as_deref()) {
+                (Some("SHOW" | "DESC" | "DESCRIBE"), _) => Ok(vec![StatementKind::Read]),
+                (Some("EXPLAIN"), next) if next != Some("ANALYZE") => Ok(vec![StatementKind::Read]),
+                _ => Err(DbError::InvalidInput(format!("Cannot parse the statement to check what it does: {e}"))),
+            }
+        }
+    }
+}
+
+/// Refuse `sql` when anything but comments follows a `;` in it, and return
+/// where its trailing `;` is, if it has one. The parser reads some
+/// statements, such as a generic SHOW, past a `;`, so the statement after it
+/// would be classified as part of the first; the server runs both.
+fn single_statement(sql: &str) -> Result<Option<Location>, DbError> {
+    let tokens = Tokenizer::new(&MySqlDialect {}, sql)
+        .tokenize_with_location()
+        .map_err(|e| DbError::InvalidInput(format!("Cannot read the statement: {e}")))?;
+    let mut separator = None;
+    for token in tokens.iter().filter(|token| !matches!(token.token, Token::Whitespace(_) | Token::EOF)) {
+        match (&token.token, separator) {
+            (Token::SemiColon, None) => separator = Some(token.span.start),
+            (Token::SemiColon, Some(_)) => {}
+            (_, Some(_)) => {
+                return Err(DbError::InvalidInput("Only one statement can be run at a time; remove the text after `;`".to_string()));
+            }
+            (_, None) => {}
+        }
+    }
+    Ok(separator)
+}
+
+fn statement_kind(statement: &Statement) -> StatementKind {
+    match statement {
+        Statement::Query(query) => set_expr_kind(&query.body),
+        Statement::Explain { analyze: true, statement, .. } => statement_kind(statement),
+        Statement::Explain { .. }
+        | Statement::ExplainTable { .. }
+        | Statement::ShowFunctions { .. }
+        | Statement::ShowVariable { .. }
+        | Statement::ShowStatus { .. }
+        | Statement::ShowVariables { .. }
+        | Statement::ShowCreate { .. }
+        | Statement::ShowColumns { .. }
+        | Statement::ShowDatabases { .. }
+        | Statement::ShowSchemas { .. }
+        | Statement::ShowCharset { .. }
+        | Statement::ShowTables { .. }
+        | Statement::ShowViews { .. }
+        | Statement::ShowCollation { .. } => StatementKind::Read,
+        Statement::Insert { .. } | Statement::LoadData { .. } => StatementKind::Insert,
+        Statement::Update { .. } => StatementKind::Update,
+        Statement::Delete { .. } => StatementKind::Delete,
+        Statement::CreateTable { .. }
+        | Statement::CreateView { .. }
+        | Statement::CreateIndex { .. }
+        | Statement::CreateDatabase { .. }
+        | Statement::CreateSchema { .. }
+        | Statement::CreateFunction { .. }
+        | Statement::CreateProcedure { .. }
+        | Statement::CreateTrigger { .. }
+        | Statement::AlterTable { .. }
+        | Statement::AlterIndex { .. }
+        | Statement::AlterView { .. }
+        | Statement::Drop { .. }
+        | Statement::DropFunction { .. }
+        | Statement::DropProcedure { .. }
+        | Statement::DropTrigger { .. }
+        | Statement::Truncate { .. }
+        | Statement::RenameTable { .. } => StatementKind::Ddl,
+        _ => StatementKind::Admin,
+    }
+}
+
+fn set_expr_kind(body: &SetExpr) -> StatementKind {
+    match body {
+        // SELECT ... INTO writes a variable or file
+        SetExpr::Select(select) if select.into.is_some() => StatementKind::Admin,
+        SetExpr::Select(_) | SetExpr::Values(_) | SetExpr::Table(_) => StatementKind::Read,
+        SetExpr::Query(query) => set_expr_kind(&query.body),
+        SetExpr::SetOperation { left, right, .. } => {
+            let kinds = [set_expr_kind(left), set_expr_kind(right)];
+            kinds.into_iter().find(|k| *k != StatementKind::Read).unwrap_or(StatementKind::Read)
+        }
+        SetExpr::Insert(statement) | SetExpr::Update(statement) | SetExpr::Delete(statement) | SetExpr::Merge(statement) => {
+            statement_kind(statement)
+        }
+    }
+}
+
+/// `DROP` or `TRUNCATE` when `sql` contains such a statement. SQL that does
+/// not parse is judged by its words.
+pub fn destructive_keyword(sql: &str) -> Option<&'static str> {
+    match Parser::parse_sql(&MySqlDialect {}, sql) {
+        Ok(statements) => statements.iter().find_map(|statement| match statement {
+            Statement::Drop { .. }
+            | Statement::DropFunction { .. }
+            | Statement::DropProcedure { .. }
+            | Statement::DropTrigger { .. } => Some("DROP"),
+            Statement::Truncate { .. } => Some("TRUNCATE"),
+            _ => None,
+        }),
+        Err(_) => {
+            let lowered = sql.to_lowercase();
+            [("drop", "DROP"), ("truncate", "TRUNCATE")]
+                .into_iter()
+                .find_map(|(word, keyword)| mentions_word(&lowered, word).then_some(keyword))
+        }
+    }
+}
+
+/// `sql` with its row limit capped at `limit`: a query without a LIMIT gets
+/// one and a larger literal LIMIT is lowered. `None` when `sql` needs no
+/// change or is not a single SELECT.
+pub fn cap_limit(sql: &str, limit: u64) -> Option<String> {
+    let mut statements = Parser::parse_sql(&MySqlDialect {}, sql).ok()?;
+    let [Statement::Query(query)] = statements.as_mut_slice() else { return None };
+    if set_expr_kind(&query.body) != StatementKind::Read {
+        return None;
+    }
+    let capped = Expr::value(Value::Number(limit.to_string(), false));
+    match &mut query.limit_clause {
+        None => {
+            query.limit_clause = Some(LimitClause::LimitOffset { limit: Some(capped), offset: None, limit_by: Vec::new() })
+        }
+        Some(LimitClause::LimitOffset { limit: existing @ None, .. }) => *existing = Some(capped),
+        Some(LimitClause::LimitOffset { limit: Some(existing), .. } | LimitClause::OffsetCommaLimit { limit: existing, .. }) => {
+            let Expr::Value(value) = existing else { return None };
+            let Value::Number(n, _) = &value.value else { return None };
+            if n.parse::<u64>().ok()? <= limit {
+                return None;
+            }
+            *existing = capped;
+        }
+    }
+    Some(query.to_string())
+}
+
+/// Which kinds of statements and tools beyond reads are enabled, from the
+/// `allow_insert`, `allow_update`, `allow_delete`, `allow_ddl`, and
+/// `allow_admin` flags. The legacy `allow_dangerous_queries` enables them all.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Permissions {
+    pub insert: bool,
+    pub update: bool,
+    pub delete: bool,
+    pub ddl: bool,
+    pub admin: bool,
+}
+
+impl Permissions {
+    pub fn new(config: &Config) -> Self {
+        let all = config.allow_dangerous_queries;
+        Permissions {
+            insert: all || config.allow_insert,
+            update: all || config.allow_update,
+            delete: all || config.allow_delete,
+            ddl: all || config.allow_ddl,
+            admin: all || config.allow_admin,
+        }
+    }
+
+    pub fn allows(&self, kind: StatementKind) -> bool {
+        match kind {
+            StatementKind::Read => true,
+            StatementKind::Insert => self.insert,
+            StatementKind::Update => self.update,
+            StatementKind::Delete => self.delete,
+            StatementKind::Ddl => self.ddl,
+            StatementKind::Admin => self.admin,
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        !(self.insert || self.update || self.delete || self.ddl || self.admin)
+    }
+
+    pub fn allows_all(&self) -> bool {
+        self.insert && self.update && self.delete && self.ddl && self.admin
+    }
+
+    /// The enabled statement kinds beyond reads, e.g. "INSERT, UPDATE".
+    pub fn describe(&self) -> String {
+        let kinds = [
+            (self.insert, "INSERT"),
+            (self.update, "UPDATE"),
+            (self.delete, "DELETE"),
+            (self.ddl, "DDL"),
+            (self.admin, "admin"),
+        ];
+        kinds.iter().filter(|(enabled, _)| *enabled).map(|(_, name)| *name).collect::<Vec<_>>().join(", ")
+    }
+
+    /// The config flag a tool needs that is not set, or `None` if the tool is
+    /// enabled.
+    pub fn missing_flag(&self, tool: &str) -> Option<&'static str> {
+        let required: &[(bool, &'static str)] = match tool {
+            "insert" | "generate_data" | "import" => &[(self.insert, "allow_insert")],
+            "upsert" => &[(self.insert, "allow_insert"), (self.update, "allow_update")],
+            "update" => &[(self.update, "allow_update")],
+            "delete" => &[(self.delete, "allow_delete")],
+            "create_table" | "alter_table" | "drop_table" | "create_index" | "drop_index" | "run_migrations" => {
+                &[(self.ddl, "allow_ddl")]
+            }
+            "call_procedure" | "kill_query" => &[(self.admin, "allow_admin")],
+            "run_script" | "begin_transaction" | "commit" | "rollback" if self.is_read_only() => {
+                return Some("one of the allow_* flags");
+            }
+            _ => &[],
+        };
+        required.iter().find(|(enabled, _)| !enabled).map(|(_, flag)| *flag)
+    }
+}
+
+fn flag_for(kind: StatementKind) -> &'static str {
+    match kind {
+        StatementKind::Read => "",
+        StatementKind::Insert => "allow_insert",
+        StatementKind::Update => "allow_update",
+        StatementKind::Delete => "allow_delete",
+        StatementKind::Ddl => "allow_ddl",
+        StatementKind::Admin => "allow_admin",
+    }
+}
+
+/// Fail unless every statement in `sql` is of a kind `permissions` enables.
+pub fn ensure_permitted(sql: &str, permissions: Permissions) -> Result<(), DbError> {
+    if let Some(kind) = classify(sql)?.into_iter().find(|k| !permissions.allows(*k)) {
+        let enabled = match permissions.describe() {
+            enabled if enabled.is_empty() => String::new(),
+            enabled => format!(" and {enabled}"),
+        };
+        return Err(DbError::ReadOnlyViolation(format!(
+            "This is a {} statement; only SELECT, SHOW, DESCRIBE, and EXPLAIN{enabled} queries are allowed. Set {} = true to execute it.",
+            kind.category(),
+            flag_for(kind)
+        )));
+    }
+    Ok(())
+}
+
+/// The databases and tables tools may touch, from the `allowed_databases`,
+/// `denied_databases`, `allowed_tables`, and `denied_tables` patterns. A
+/// table pattern is `table` (in any database) or `database.table`; `*` and
+/// `?` are wildcards in both. An empty allow list allows everything that is
+/// not denied.
+#[derive(Debug, Clone, Default)]
+pub struct TableAccess {
+    allowed: Vec<String>,
+    denied: Vec<String>,
+    allowed_databases: Vec<String>,
+    denied_databases: Vec<String>,
+    hide_system_schemas: bool,
+}
+
+/// Schemas `hide_system_schemas` keeps out of reach: grant tables and server internals.
+pub const SYSTEM_SCHEMAS: &[&str] = &["mysql", "sys", "performance_schema", "information_schema"];
+
+impl TableAccess {
+    pub fn new(config: &Config) -> Self {
+        TableAccess {
+            allowed: config.allowed_tables.clone(),
+            denied: config.denied_tables.clone(),
+            allowed_databases: config.allowed_databases.clone(),
+            denied_databases: config.denied_databases.clone(),
+            hide_system_schemas: config.hide_system_schemas,
+        }
+    }
+
+    /// Whether any check applies, including the system schema one.
+    pub fn is_active(&self) -> bool {
+        self.is_restricted() || self.hide_system_schemas
+    }
+
+    /// Whether allow or deny lists are configured, so table names have to be checked.
+    pub fn is_restricted(&self) -> bool {
+        !self.allowed.is_empty()
+            || !self.denied.is_empty()
+            || !self.allowed_databases.is_empty()
+            || !self.denied_databases.is_empty()
+    }
+
+    pub fn permits_database(&self, database: &str) -> bool {
+        if self.is_system_schema(database) {
+            return false;
+        }
+        let matches = |pattern: &String| glob_matches(pattern, database);
+        (self.allowed_databases.is_empty() || self.allowed_databases.iter().any(matches))
+            && !self.denied_databases.iter().any(matches)
+    }
+
+    pub fn permits(&self, database: &str, table: &str) -> bool {
+        let matches = |pattern: &String| match pattern.split_once('.') {
+            Some((db, name)) => glob_matches(db, database) && glob_matches(name, table),
+            None => glob_matches(pattern, table),
+        };
+        self.permits_database(database)
+            && (self.allowed.is_empty() || self.allowed.iter().any(matches))
+            && !self.denied.iter().any(matches)
+    }
+
+    fn is_system_schema(&self, database: &str) -> bool {
+        self.hide_system_schemas && SYSTEM_SCHEMAS.iter().any(|schema| schema.eq_ignore_ascii_case(database))
+    }
+
+    pub fn check_database(&self, database: &str) -> Result<(), DbError> {
+        if self.permits_database(database) {
+            return Ok(());
+        }
+        if self.is_system_schema(database) {
+            return Err(DbError::AccessDenied(format!("'{database}' is a system schema, hidden by hide_system_schemas")));
+        }
+        Err(DbError::AccessDenied(format!("database '{database}' is excluded by allowed_databases/denied_databases")))
+    }
+
+    pub fn check(&self, database: &str, table: &str) -> Result<(), DbError> {
+        self.check_database(database)?;
+        if self.permits(database, table) {
+            return Ok(());
+        }
+        let name = if database.is_empty() { table.to_string() } else { format!("{database}.{table}") };
+        Err(DbError::AccessDenied(format!("table '{name}' is excluded by allowed_tables/denied_tables")))
+    }
+
+    /// Check every table `sql` refers to, resolving unqualified names against
+    /// `database`, and every database it switches to with USE. SQL that cannot
+    /// be parsed is refused under allow or deny lists, since the tables it
+    /// reads are unknown; with only system schemas hidden, it is refused if it
+    /// mentions one of them.
+    pub fn check_sql(&self, sql: &str, database: &str) -> Result<(), DbError> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        let statements = match Parser::parse_sql(&MySqlDialect {}, sql) {
+            Ok(statements) => statements,
+            Err(e) if self.is_restricted() => {
+                return Err(DbError::AccessDenied(format!("cannot parse the statement to check which tables it uses: {e}")));
+            }
+            Err(_) => {
+                let lowered = sql.to_lowercase();
+                return match SYSTEM_SCHEMAS.iter().find(|schema| mentions_word(&lowered, schema)) {
+                    Some(schema) => self.check_database(schema),
+                    None => Ok(()),
+                };
+            }
+        };
+        // Unqualified names resolve against the database the statements before switched to
+        let mut database = database.to_string();
+        for statement in &statements {
+            let (databases, tables) = references(std::slice::from_ref(statement));
+            for db in databases {
+                self.check_database(&db)?;
+            }
+            for (db, table) in tables {
+                self.check(db.as_deref().unwrap_or(&database), &table)?;
+            }
+            database = used_database(statement).unwrap_or(database);
+        }
+        Ok(())
+    }
+}
+
+/// The database a `USE` statement switches to.
+fn used_database(statement: &Statement) -> Option<String> {
+    match statement {
+        Statement::Use(Use::Object(name) | Use::Database(name) | Use::Schema(name)) => object_name_parts(name).pop(),
+        _ => None,
+    }
+}
+
+/// The database the last `USE` in `sql` switches to, if any.
+pub fn switched_database(sql: &str) -> Option<String> {
+    let statements = Parser::parse_sql(&MySqlDialect {}, sql).ok()?;
+    statements.iter().rev().find_map(used_database)
+}
+
+/// Whether `word` occurs in `text` not as part of a longer identifier.
+fn mentions_word(text: &str, word: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    text.match_indices(word).any(|(start, _)| {
+        !text[..start].chars().next_back().is_some_and(is_ident)
+            && !text[start + word.len()..].chars().next().is_some_and(is_ident)
+    })
+}
+
+/// Case-insensitive glob match supporting `*` and `?`.
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match (pattern.first(), name.first()) {
+            (Some('*'), _) => matches(&pattern[1..], name) || (!name.is_empty() && matches(pattern, &name[1..])),
+            (Some('?'), Some(_)) => matches(&pattern[1..], &name[1..]),
+            (Some(p), Some(n)) if p == n => matches(&pattern[1..], &name[1..]),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    matches(&pattern, &name)
+}
+
+/// Tables, CTE names, and databases found while walking statements.
+#[derive(Default)]
+struct Relations {
+    tables: Vec<Vec<String>>,
+    ctes: Vec<String>,
+    databases: Vec<String>,
+}
+
+impl Visitor for Relations {
+    type Break = ();
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<()> {
+        self.tables.push(object_name_parts(relation));
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        if let Some(with) = &query.with {
+            self.ctes.extend(with.cte_tables.iter().map(|cte| cte.alias.name.value.to_lowercase()));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<()> {
+        // Object names the visitor does not report as relations
+        match statement {
+            Statement::ShowCreate { obj_name, .. } => self.tables.push(object_name_parts(obj_name)),
+            Statement::Drop { names, .. } => self.tables.extend(names.iter().map(object_name_parts)),
+            Statement::Truncate { table_names, .. } => {
+                self.tables.extend(table_names.iter().map(|t| object_name_parts(&t.name)))
+            }
+            // SHOW INDEX FROM t [FROM db] is parsed as a generic SHOW
+            Statement::ShowVariable { variable } => {
+                let is_keyword = |word: &str, keywords: &[&str]| keywords.iter().any(|k| word.eq_ignore_ascii_case(k));
+                let words: Vec<&str> = variable.iter().map(|ident| ident.value.as_str()).collect();
+                if let [kind, from, table, rest @ ..] = words.as_slice() {
+                    if is_keyword(kind, &["INDEX", "INDEXES", "KEYS"]) && is_keyword(from, &["FROM", "IN"]) {
+                        let mut parts = match rest {
+                            [from, database, ..] if is_keyword(from, &["FROM", "IN"]) => vec![database.to_string()],
+                            _ => Vec::new(),
+                        };
+                        parts.push(table.to_string());
+                        self.tables.push(parts);
+                    }
+                }
+            }
+            Statement::Use(Use::Object(name) | Use::Database(name) | Use::Schema(name)) => {
+                self.databases.extend(object_name_parts(name).pop())
+            }
+            // The visitor reports the database of SHOW TABLES FROM db as a relation
+            Statement::ShowTables { show_options, .. } | Statement::ShowViews { show_options, .. } => {
+                if let Some(name) = show_options.show_in.as_ref().and_then(|s| s.parent_name.as_ref()) {
+                    self.databases.extend(object_name_parts(name).pop());
+                }
+            }
+            Statement::RenameTable(renames) => {
+                for rename in renames {
+                    self.tables.push(object_name_parts(&rename.old_name));
+                    self.tables.push(object_name_parts(&rename.new_name));
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn object_name_parts(name: &ObjectName) -> Vec<String> {
+    name.0.iter().filter_map(|part| part.as_ident()).map(|ident| ident.value.clone()).collect()
+}
+
+/// The databases `statements` switch to or list, and the `(database, table)`
+/// pairs they refer to, without CTEs.
+pub fn references(statements: &[Statement]) -> (Vec<String>, Vec<(Option<String>, String)>) {
+    let mut relations = Relations::default();
+    for statement in statements {
+        let _ = statement.visit(&mut relations);
+    }
+    let mut tables = Vec::new();
+    for mut parts in relations.tables {
+        let Some(table) = parts.pop() else { continue };
+        let database = parts.pop();
+        if database.is_none() && (relations.ctes.contains(&table.to_lowercase()) || relations.databases.contains(&table)) {
+            continue;
+        }
+        if !tables.contains(&(database.clone(), table.clone())) {
+            tables.push((database, table));
+        }
+    }
+    (relations.databases, tables)
+}
+
+/// Limits on the shape of free-form queries, from `max_joins`,
+/// `deny_cross_joins`, and `select_star_max_rows`, checked on the parsed SQL
+/// before it runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Guardrails {
+    pub max_joins: Option<usize>,
+    pub deny_cross_joins: bool,
+    /// Estimated rows above which a `SELECT *` without LIMIT is refused.
+    pub select_star_max_rows: Option<u64>,
+}
+
+impl Guardrails {
+    pub fn new(config: &Config) -> Self {
+        Guardrails {
+            max_joins: config.max_joins,
+            deny_cross_joins: config.deny_cross_joins,
+            select_star_max_rows: config.select_star_max_rows,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.max_joins.is_some() || self.deny_cross_joins || self.select_star_max_rows.is_some()
+    }
+
+    /// Check the joins in `sql`, and return the `(database, table)` pairs a
+    /// `SELECT *` without LIMIT reads, for the caller to compare their size
+    /// with `select_star_max_rows`. SQL that does not parse is left to the
+    /// other policies.
+    pub fn check_sql(&self, sql: &str) -> Result<Vec<(Option<String>, String)>, DbError> {
+        let Ok(statements) = Parser::parse_sql(&MySqlDialect {}, sql) else {
+            return Ok(Vec::new());
+        };
+        let mut joins = Joins::default();
+        for statement in &statements {
+            let _ = statement.visit(&mut joins);
+        }
+        if let Some(max) = self.max_joins.filter(|max| joins.count > *max) {
+            return Err(DbError::InvalidInput(format!(
+                "This query joins {} times, more than max_joins ({max}). Split it into smaller queries, \
+                 for example by fetching the ids from one join and filtering the next query with them.",
+                joins.count
+            )));
+        }
+        if let Some(cross) = joins.unconditioned.first().filter(|_| self.deny_cross_joins) {
+            return Err(DbError::InvalidInput(format!(
+                "{cross} has no join condition, so it returns every combination of rows. Add ON or USING \
+                 (or a WHERE condition relating the tables); deny_cross_joins refuses joins without one."
+            )));
+        }
+        if self.select_star_max_rows.is_none() {
+            return Ok(Vec::new());
+        }
+        let mut tables = Vec::new();
+        for statement in &statements {
+            let Statement::Query(query) = statement else { continue };
+            let SetExpr::Select(select) = query.body.as_ref() else { continue };
+            let is_star = select.projection.iter().any(|item| matches!(item, SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)));
+            if query.limit_clause.is_some() || !is_star {
+                continue;
+            }
+            for relation in select.from.iter().flat_map(|from| std::iter::once(&from.relation).chain(from.joins.iter().map(|j| &j.relation))) {
+                if let TableFactor::Table { name, .. } = relation {
+                    let mut parts = object_name_parts(name);
+                    if let Some(table) = parts.pop() {
+                        tables.push((parts.pop(), table));
+                    }
+                }
+            }
+        }
+        Ok(tables)
+    }
+}
+
+/// Joins found while walking statements: how many, and a description of
+/// each one without a join condition.
+#[derive(Default)]
+struct Joins {
+    count: usize,
+    unconditioned: Vec<String>,
+}
+
+impl Joins {
+    fn add_set_expr(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => self.add_select(select),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.add_set_expr(left);
+                self.add_set_expr(right);
+            }
+            // Nested queries are visited on their own
+            _ => {}
+        }
+    }
+
+    fn add_select(&mut self, select: &Select) {
+        self.count += select.from.len().saturating_sub(1);
+        if select.from.len() > 1 && select.selection.is_none() {
+            let names: Vec<String> = select.from.iter().map(|from| from.relation.to_string()).collect();
+            self.unconditioned.push(format!("FROM {} without a WHERE clause", names.join(", ")));
+        }
+        for from in &select.from {
+            self.count += from.joins.len();
+            for join in &from.joins {
+                let constraint = match &join.join_operator {
+                    JoinOperator::CrossApply | JoinOperator::OuterApply => None,
+                    JoinOperator::Join(c)
+                    | JoinOperator::Inner(c)
+                    | JoinOperator::CrossJoin(c)
+                    | JoinOperator::StraightJoin(c)
+                    | JoinOperator::Left(c)
+                    | JoinOperator::LeftOuter(c)
+                    | JoinOperator::Right(c)
+                    | JoinOperator::RightOuter(c)
+                    | JoinOperator::FullOuter(c)
+                    | JoinOperator::Semi(c)
+                    | JoinOperator::LeftSemi(c)
+                    | JoinOperator::RightSemi(c)
+                    | JoinOperator::Anti(c)
+                    | JoinOperator::LeftAnti(c)
+                    | JoinOperator::RightAnti(c)
+                    | JoinOperator::AsOf { constraint: c, .. } => Some(c),
+                };
+                if matches!(constraint, None | Some(JoinConstraint::None)) {
+                    self.unconditioned.push(format!("The join of {} with {}", from.relation, join.relation));
+                }
+            }
+        }
+    }
+}
+
+impl Visitor for Joins {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.add_set_expr(&query.body);
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let kind = |sql: &str| classify(sql).unwrap();
+        assert_eq!(kind("SELECT updated_at, deleted FROM t WHERE `update` = 1"), vec![StatementKind::Read]);
+        assert_eq!(kind("WITH recent AS (SELECT * FROM orders) SELECT * FROM recent"), vec![StatementKind::Read]);
+        assert_eq!(kind("SELECT 1 UNION SELECT 2"), vec![StatementKind::Read]);
+        assert_eq!(kind("SHOW TABLES"), vec![StatementKind::Read]);
+        assert_eq!(kind("SHOW ENGINE INNODB STATUS"), vec![StatementKind::Read]);
+        assert_eq!(kind("EXPLAIN SELECT * FROM t"), vec![StatementKind::Read]);
+        assert_eq!(kind("EXPLAIN ANALYZE DELETE FROM t"), vec![StatementKind::Delete]);
+        assert_eq!(kind("/* harmless */ DELETE FROM t"), vec![StatementKind::Delete]);
+        assert_eq!(kind("REPLACE INTO t (id) VALUES (1)"), vec![StatementKind::Insert]);
+        assert_eq!(kind("UPDATE t SET a = 1"), vec![StatementKind::Update]);
+        assert_eq!(kind("TRUNCATE TABLE t"), vec![StatementKind::Ddl]);
+        assert_eq!(kind("DROP TABLE t"), vec![StatementKind::Ddl]);
+        assert_eq!(kind("GRANT ALL ON *.* TO 'x'@'%'"), vec![StatementKind::Admin]);
+        assert_eq!(kind("SELECT 1 /*!, (DELETE FROM t) */"), vec![StatementKind::Admin]);
+        assert_eq!(kind("SELECT 1; -- done"), vec![StatementKind::Read]);
+        assert_eq!(kind("SELECT ';' AS semicolon"), vec![StatementKind::Read]);
+        assert!(classify("SELECT 1; DELETE FROM t").is_err());
+        // Generic SHOWs are parsed up to the end of the text, past the `;`
+        assert!(classify("SHOW PROCESSLIST; DROP TABLE users").is_err());
+        assert!(classify("SHOW WARNINGS; DELETE FROM users").is_err());
+        assert!(classify("SHOW WARNINGS; /* x */ DELETE FROM users").is_err());
+        assert!(classify("EXPLAIN ANALYZE FORMAT=TREE gibberish (").is_err());
+        // Trailing INTO OUTFILE/DUMPFILE does not parse, which refuses it too
+        assert_eq!(kind("SELECT a INTO @v FROM t"), vec![StatementKind::Admin]);
+        assert!(classify("SELECT * FROM t INTO OUTFILE '/tmp/t.csv'").is_err());
+        assert!(classify("WITH c AS (SELECT 1) SELECT * FROM c INTO DUMPFILE '/tmp/c'").is_err());
+
+        let read_only = Permissions::default();
+        assert!(ensure_permitted("SELECT `update` FROM t", read_only).is_ok());
+        assert!(ensure_permitted("SELECT 1; DROP TABLE t", read_only).is_err());
+    }
+
+    #[test]
+    fn test_guardrails() {
+        let guardrails = Guardrails { max_joins: Some(2), deny_cross_joins: true, select_star_max_rows: Some(1000) };
+        assert!(guardrails.check_sql("SELECT o.id FROM orders o JOIN users u ON u.id = o.user_id JOIN items i USING (order_id)").is_ok());
+        let Err(DbError::InvalidInput(message)) =
+            guardrails.check_sql("SELECT 1 FROM a JOIN b ON a.id = b.a_id JOIN c ON c.id = b.c_id WHERE a.id IN (SELECT a_id FROM d JOIN e ON d.id = e.d_id)")
+        else {
+            panic!("joins in subqueries count too");
+        };
+        assert!(message.contains("joins 3 times, more than max_joins (2)"), "{message}");
+        assert!(guardrails.check_sql("SELECT * FROM a CROSS JOIN b LIMIT 5").is_err());
+        assert!(guardrails.check_sql("SELECT * FROM a JOIN b LIMIT 5").is_err());
+        assert!(guardrails.check_sql("SELECT * FROM a, b LIMIT 5").is_err());
+        assert!(guardrails.check_sql("SELECT * FROM a, b WHERE a.id = b.a_id LIMIT 5").is_ok());
+        assert!(Guardrails { deny_cross_joins: false, ..guardrails }.check_sql("SELECT 1 FROM a CROSS JOIN b").is_ok());
+
+        assert_eq!(
+            guardrails.check_sql("SELECT * FROM shop.orders o JOIN users u ON u.id = o.user_id").unwrap(),
+            vec![(Some("shop".to_string()), "orders".to_string()), (None, "users".to_string())]
+        );
+        assert_eq!(guardrails.check_sql("SELECT * FROM orders LIMIT 10").unwrap(), vec![]);
+        assert_eq!(guardrails.check_sql("SELECT id FROM orders").unwrap(), vec![]);
+        assert!(!Guardrails::default().is_active());
+    }
+
+    #[test]
+    fn test_cap_limit() {
+        assert_eq!(cap_limit("SELECT * FROM events", 1001).as_deref(), Some("SELECT * FROM events LIMIT 1001"));
+        assert_eq!(
+            cap_limit("SELECT * FROM events ORDER BY id LIMIT 5000 OFFSET 10", 1001).as_deref(),
+            Some("SELECT * FROM events ORDER BY id LIMIT 1001 OFFSET 10")
+        );
+        assert_eq!(cap_limit("SELECT * FROM events LIMIT 20, 5000", 1001).as_deref(), Some("SELECT * FROM events LIMIT 20, 1001"));
+        assert_eq!(
+            cap_limit("SELECT a FROM t UNION SELECT a FROM u", 11).as_deref(),
+            Some("SELECT a FROM t UNION SELECT a FROM u LIMIT 11")
+        );
+        assert_eq!(
+            cap_limit("SELECT * FROM `order items` WHERE id = ?", 11).as_deref(),
+            Some("SELECT * FROM `order items` WHERE id = ? LIMIT 11")
+        );
+        assert_eq!(cap_limit("SELECT * FROM events LIMIT 10", 1001), None);
+        assert_eq!(cap_limit("SELECT * FROM events LIMIT ?", 1001), None);
+        assert_eq!(cap_limit("SHOW TABLES", 1001), None);
+        assert_eq!(cap_limit("SELECT 1; SELECT 2", 1001), None);
+        assert_eq!(cap_limit("DELETE FROM events", 1001), None);
+    }
+
+    #[test]
+    fn test_permissions() {
+        let writes = Permissions { insert: true, update: true, ..Default::default() };
+        assert!(ensure_permitted("INSERT INTO t (id) VALUES (1)", writes).is_ok());
+        assert!(ensure_permitted("UPDATE t SET a = 1", writes).is_ok());
+        let err = ensure_permitted("DELETE FROM t", writes).unwrap_err().to_string();
+        assert!(err.contains("allow_delete") && err.contains("INSERT, UPDATE"), "{err}");
+        assert!(ensure_permitted("DROP TABLE t", writes).is_err());
+        assert!(ensure_permitted("DROP TABLE t", Permissions { ddl: true, ..Default::default() }).is_ok());
+
+        assert_eq!(writes.missing_flag("insert"), None);
+        assert_eq!(writes.missing_flag("upsert"), None);
+        assert_eq!(writes.missing_flag("delete"), Some("allow_delete"));
+        assert_eq!(writes.missing_flag("run_script"), None);
+        assert_eq!(writes.missing_flag("select"), None);
+        assert_eq!(Permissions::default().missing_flag("commit"), Some("one of the allow_* flags"));
+        assert!(!writes.allows_all() && !writes.is_read_only());
+    }
+
+    #[test]
+    fn test_table_access() {
+        let access = TableAccess {
+            allowed: vec!["shop.*".to_string(), "reporting.daily_?".to_string()],
+            denied: vec!["users_credentials".to_string(), "*.audit_*".to_string()],
+            ..Default::default()
+        };
+        assert!(access.permits("shop", "orders"));
+        assert!(access.permits("SHOP", "Orders"));
+        assert!(access.permits("reporting", "daily_1"));
+        assert!(!access.permits("reporting", "daily_10"));
+        assert!(!access.permits("shop", "users_credentials"));
+        assert!(!access.permits("shop", "audit_log"));
+        assert!(!access.permits("other", "orders"));
+        assert!(TableAccess::default().permits("any", "table"));
+
+        assert!(access.check_sql("SELECT * FROM orders o JOIN shop.customers c ON c.id = o.customer_id", "shop").is_ok());
+        assert!(access.check_sql("WITH audit_log AS (SELECT 1) SELECT * FROM audit_log", "shop").is_ok());
+        assert!(access.check_sql("SELECT * FROM orders WHERE id IN (SELECT order_id FROM audit_log)", "shop").is_err());
+        assert!(access.check_sql("SELECT * FROM shop.audit_log", "other").is_err());
+        assert!(access.check_sql("SELECT * FROM orders", "other").is_err());
+        assert!(access.check_sql("USE other; SELECT * FROM orders", "shop").is_err(), "names after USE resolve in the new database");
+        assert!(access.check_sql("USE shop; SELECT * FROM orders", "other").is_ok());
+        assert_eq!(switched_database("USE reporting; SELECT 1"), Some("reporting".to_string()));
+        assert_eq!(switched_database("SELECT 1"), None);
+        assert!(access.check_sql("INSERT INTO users_credentials (id) VALUES (1)", "shop").is_err());
+        assert!(access.check_sql("UPDATE orders SET total = 0", "shop").is_ok());
+        assert!(access.check_sql("UPDATE orders o JOIN audit_log a ON a.id = o.id SET o.total = 0", "shop").is_err());
+        assert!(access.check_sql("DELETE FROM users_credentials", "shop").is_err());
+        assert!(access.check_sql("SHOW CREATE TABLE users_credentials", "shop").is_err());
+        assert!(access.check_sql("DESCRIBE users_credentials", "shop").is_err());
+        assert!(access.check_sql("SHOW COLUMNS FROM users_credentials", "shop").is_err());
+        assert!(access.check_sql("DROP TABLE orders, users_credentials", "shop").is_err());
+        assert!(access.check_sql("TRUNCATE TABLE audit_2024", "shop").is_err());
+        assert!(access.check_sql("SELECT * FROM orders; SELECT * FROM users_credentials", "shop").is_err());
+        assert!(access.check_sql("SHOW INDEX FROM users_credentials", "shop").is_err());
+        assert!(access.check_sql("SHOW KEYS IN orders FROM shop", "other").is_ok());
+        assert!(access.check_sql("SELECT * FROM orders WHERE (", "shop").is_err());
+        assert!(TableAccess::default().check_sql("SELECT * FROM orders WHERE (", "shop").is_ok());
+    }
+
+    #[test]
+    fn test_database_access() {
+        let access = TableAccess {
+            allowed_databases: vec!["shop".to_string(), "reporting_*".to_string()],
+            denied_databases: vec!["reporting_raw".to_string()],
+            ..Default::default()
+        };
+        assert!(access.permits_database("shop"));
+        assert!(access.permits_database("reporting_daily"));
+        assert!(!access.permits_database("reporting_raw"));
+        assert!(!access.permits("mysql", "user"));
+
+        assert!(access.check_sql("SELECT * FROM orders JOIN reporting_daily.totals USING (id)", "shop").is_ok());
+        assert!(access.check_sql("SELECT * FROM mysql.user", "shop").is_err());
+        assert!(access.check_sql("SELECT * FROM orders", "crm").is_err());
+        assert!(access.check_sql("USE reporting_raw", "shop").is_err());
+        assert!(access.check_sql("SHOW TABLES FROM reporting_daily", "shop").is_ok());
+        assert!(access.check_sql("SHOW TABLES FROM crm", "shop").is_err());
+        assert!(access.check_database("crm").is_err());
+
+        let access = TableAccess { hide_system_schemas: true, ..Default::default() };
+        assert!(!access.is_restricted());
+        assert!(access.permits_database("shop"));
+        assert!(!access.permits_database("Performance_Schema"));
+        assert!(access.check_database("sys").is_err());
+        assert!(access.check_sql("SELECT * FROM orders", "").is_ok());
+        assert!(access.check_sql("SELECT host, user FROM mysql.user", "").is_err());
+        assert!(access.check_sql("SELECT * FROM information_schema.tables", "").is_err());
+        assert!(access.check_sql("USE sys", "").is_err());
+        assert!(access.check_sql("SHOW ENGINE INNODB STATUS", "").is_ok());
+        assert!(access.check_sql("HANDLER mysql.user OPEN", "").is_err());
+        assert!(access.check_sql("SELECT mysql_version FROM builds", "").is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+//! Built-in MCP prompts. Each one embeds live schema data from
+//! [`crate::db::get_schema`] so the model starts from the real table definitions.
+
+use serde_json::json;
+use sqlx::{MySql, Pool};
+use std::collections::HashMap;
+
+use crate::db::get_schema;
+use crate::error::DbError;
+use crate::policy::TableAccess;
+use crate::rpc::{Prompt, PromptArgument, PromptGetResult, PromptMessage};
+
+fn argument(name: &str, description: &str, required: bool) -> PromptArgument {
+    PromptArgument {
+        name: name.to_string(),
+        description: description.to_string(),
+        required,
+    }
+}
+
+pub fn list() -> Vec<Prompt> {
+    let table_argument = || {
+        argument("table_name", "Table to include the schema of (defaults to all tables)", false)
+    };
+    vec![
+        Prompt {
+            name: "explain-schema".to_string(),
+            description: "Explain the structure and relationships of the database schema".to_string(),
+            arguments: vec![table_argument()],
+        },
+        Prompt {
+            name: "write-query-for".to_string(),
+            description: "Write a SQL query that accomplishes a task against the actual schema".to_string(),
+            arguments: vec![
+                argument("task", "What the query should do, in plain language", true),
+                table_argument(),
+            ],
+        },
+        Prompt {
+            name: "optimize-query".to_string(),
+            description: "Review a SQL query for performance problems and suggest improvements".to_string(),
+            arguments: vec![
+                argument("query", "The SQL query to optimize", true),
+                table_argument(),
+            ],
+        },
+    ]
+}
+
+fn required<'a>(arguments: &'a HashMap<String, String>, name: &str) -> Result<&'a str, DbError> {
+    arguments
+        .get(name)
+        .map(String::as_str)
+        .filter(|v| !v.trim().is_empty())
+        .ok_or_else(|| DbError::InvalidInput(format!("Missing required argument '{name}'")))
+}
+
+pub async fn get(
+    name: &str,
+    arguments: &HashMap<String, String>,
+    access: &TableAccess,
+    pool: &Pool<MySql>,
+) -> Result<PromptGetResult, DbError> {
+    let table_name = arguments
+        .get("table_name")
+        .filter(|t| !t.is_empty())
+        .cloned()
+        .unwrap_or_else(|| "all-tables".to_string());
+
+    let (description, instructions) = match name {
+        "explain-schema" => (
+            format!("Explain the schema of {table_name}"),
+            "Explain the following MySQL schema. Describe what each table is for, \
+             the meaning of its important columns, and how the tables relate to each other."
+                .to_string(),
+        ),
+        "write-query-for" => {
+            let task = required(arguments, "task")?;
+            (
+                format!("Write a query for: {task}"),
+                format!(
+                    "Write a MySQL query for the following task, using only the tables and \
+                     columns in the schema below. Explain any assumptions.\n\nTask: {task}"
+                ),
+            )
+        }
+        "optimize-query" => {
+            let query = required(arguments, "query")?;
+            (
+                "Optimize a SQL query".to_string(),
+                format!(
+                    "Review the following MySQL query for performance problems. Use the schema \
+                     and existing indexes below to suggest a rewritten query and any indexes \
+                     worth adding.\n\nQuery:\n```sql\n{query}\n```"
+                ),
+            )
+        }
+        _ => return Err(DbError::InvalidInput(format!("Unknown prompt: {name}"))),
+    };
+
+    let schema = get_schema(table_name, access, pool).await?;
+    let schema_json = serde_json::to_string_pretty(&schema.schemas).unwrap_or_default();
+
+    Ok(PromptGetResult {
+        description,
+        messages: vec![PromptMessage {
+            role: "user".to_string(),
+            content: json!({
+                "type": "text",
+                "text": format!("{instructions}\n\nSchema:\n```json\n{schema_json}\n```"),
+            }),
+        }],
+    })
+}
@@ -0,0 +1,96 @@
+//! Per-session limits on tool calls per minute, configured with
+//! `rate_limits`. Keys are tool names or the buckets `reads`, `writes`, and
+//! `all`; a call counts against its tool, its bucket, and `all`, and is
+//! refused while any of them is at its limit within the last 60 seconds.
+//! Limits are read from the config on every call, so a reload applies them
+//! to the calls already counted.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::error::DbError;
+use crate::policy::{self, Permissions, StatementKind};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+pub struct RateLimiter {
+    /// Start times of the calls counted against each limited key.
+    calls: std::sync::Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    /// Count a call to `tool` with `arguments` against `config`'s
+    /// `rate_limits`, or refuse it with the time until the limit it hit frees
+    /// a slot. Refused calls are not counted.
+    pub fn check(&self, config: &Config, tool: &str, arguments: &Value) -> Result<(), DbError> {
+        if config.rate_limits.is_empty() {
+            return Ok(());
+        }
+        let bucket = if is_write(tool, arguments) { "writes" } else { "reads" };
+        self.check_at(&config.rate_limits, &[tool, bucket, "all"], Instant::now())
+    }
+
+    fn check_at(&self, limits: &BTreeMap<String, u32>, keys: &[&str], now: Instant) -> Result<(), DbError> {
+        let mut calls = self.calls.lock().unwrap();
+        let limited: Vec<(&str, u32)> =
+            keys.iter().filter_map(|key| limits.get(*key).map(|limit| (*key, *limit))).collect();
+        for (key, limit) in &limited {
+            let window = calls.entry(key.to_string()).or_default();
+            while window.front().is_some_and(|start| now.duration_since(*start) >= WINDOW) {
+                window.pop_front();
+            }
+            if window.len() >= *limit as usize {
+                let retry_after = window.front().map_or(WINDOW, |start| WINDOW - now.duration_since(*start));
+                return Err(DbError::RateLimited { key: key.to_string(), limit: *limit, retry_after });
+            }
+        }
+        for (key, _) in limited {
+            calls.entry(key.to_string()).or_default().push_back(now);
+        }
+        Ok(())
+    }
+}
+
+/// Whether a call may change data: a tool that needs an `allow_*` flag, or
+/// `query` with SQL that is not a read.
+fn is_write(tool: &str, arguments: &Value) -> bool {
+    if Permissions::default().missing_flag(tool).is_some() {
+        return true;
+    }
+    match (tool, arguments["query"].as_str()) {
+        ("query", Some(sql)) => policy::classify(sql).map_or(true, |kinds| kinds.iter().any(|k| *k != StatementKind::Read)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::default();
+        let limits = BTreeMap::from([("query".to_string(), 3), ("writes".to_string(), 1)]);
+        let start = Instant::now();
+        limiter.check_at(&limits, &["query", "writes", "all"], start).unwrap();
+        let Err(DbError::RateLimited { key, retry_after, .. }) = limiter.check_at(&limits, &["delete", "writes", "all"], start + Duration::from_secs(20)) else {
+            panic!("second write should be limited");
+        };
+        assert_eq!(key, "writes");
+        assert_eq!(retry_after, Duration::from_secs(40));
+        limiter.check_at(&limits, &["query", "reads", "all"], start + Duration::from_secs(20)).unwrap();
+        limiter.check_at(&limits, &["query", "reads", "all"], start + Duration::from_secs(30)).unwrap();
+        assert!(limiter.check_at(&limits, &["query", "reads", "all"], start + Duration::from_secs(40)).is_err());
+        limiter.check_at(&limits, &["query", "reads", "all"], start + Duration::from_secs(60)).unwrap();
+        limiter.check_at(&limits, &["delete", "writes", "all"], start + Duration::from_secs(60)).unwrap();
+
+        assert!(is_write("query", &json!({ "query": "UPDATE t SET a = 1" })));
+        assert!(!is_write("query", &json!({ "query": "SELECT 1" })));
+        assert!(is_write("insert", &json!({})));
+        assert!(!is_write("preview", &json!({})));
+    }
+}
@@ -31,6 +31,7 @@ pub struct JsonRpcError {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InitializeParams {
+    pub protocol_version: Option<String>,
     pub initialization_options: Option<InitializationOptions>,
 }
 
@@ -45,6 +46,13 @@ pub struct ServerSettings {
     pub database_url: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledParams {
+    pub request_id: Value,
+    pub reason: Option<String>,
+}
+
 // MCP specific structures
 #[derive(Debug, Serialize)]
 pub struct ServerInfo {
@@ -64,6 +72,27 @@ pub struct InitializeResult {
 #[derive(Debug, Serialize)]
 pub struct ServerCapabilities {
     pub tools: Option<ToolsCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourcesCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<PromptsCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completions: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptsCapability {
+    #[serde(rename = "listChanged")]
+    pub list_changed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourcesCapability {
+    pub subscribe: bool,
+    #[serde(rename = "listChanged")]
+    pub list_changed: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -78,11 +107,190 @@ pub struct Tool {
     pub description: String,
     #[serde(rename = "inputSchema")]
     pub input_schema: Value,
+    #[serde(rename = "outputSchema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// Behavioral hints clients can use to decide how much confirmation a tool call needs.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    pub title: String,
+    pub read_only_hint: bool,
+    pub destructive_hint: bool,
+    pub idempotent_hint: bool,
+    pub open_world_hint: bool,
+}
+
+impl ToolAnnotations {
+    pub fn read_only(title: &str) -> Self {
+        ToolAnnotations {
+            title: title.to_string(),
+            read_only_hint: true,
+            destructive_hint: false,
+            idempotent_hint: true,
+            open_world_hint: false,
+        }
+    }
+
+    /// Changes state without overwriting or removing existing data.
+    pub fn non_destructive(title: &str) -> Self {
+        ToolAnnotations {
+            title: title.to_string(),
+            read_only_hint: false,
+            destructive_hint: false,
+            idempotent_hint: false,
+            open_world_hint: false,
+        }
+    }
+
+    pub fn destructive(title: &str) -> Self {
+        ToolAnnotations {
+            title: title.to_string(),
+            read_only_hint: false,
+            destructive_hint: true,
+            idempotent_hint: false,
+            open_world_hint: false,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
 pub struct ToolsList {
     pub tools: Vec<Tool>,
+    #[serde(rename = "nextCursor", skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourcesList {
+    pub resources: Vec<Resource>,
+    #[serde(rename = "nextCursor", skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceTemplate {
+    #[serde(rename = "uriTemplate")]
+    pub uri_template: String,
+    pub name: String,
+    pub description: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceTemplatesList {
+    #[serde(rename = "resourceTemplates")]
+    pub resource_templates: Vec<ResourceTemplate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResourceReadParams {
+    pub uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResourceSubscribeParams {
+    pub uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceReadResult {
+    pub contents: Vec<ResourceContents>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptArgument {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Prompt {
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptsList {
+    pub prompts: Vec<Prompt>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PromptGetParams {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptMessage {
+    pub role: String,
+    pub content: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptGetResult {
+    pub description: String,
+    pub messages: Vec<PromptMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetLevelParams {
+    pub level: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompleteParams {
+    pub argument: CompleteArgument,
+    pub context: Option<CompleteContext>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompleteArgument {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompleteContext {
+    #[serde(default)]
+    pub arguments: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Completion {
+    pub values: Vec<String>,
+    #[serde(rename = "hasMore")]
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompleteResult {
+    pub completion: Completion,
 }
 
 #[derive(Debug, Deserialize)]
@@ -98,6 +306,551 @@ pub struct SchemaArguments {
 
 #[derive(Debug, Deserialize)]
 pub struct QueryArguments {
+    pub query: String,
+    /// Values bound to the query's `?` placeholders, in order.
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+    pub database: Option<String>,
+    /// `nextCursor` of the previous page of the same query and params.
+    pub cursor: Option<String>,
+    /// Run inside this open transaction instead of on a fresh connection.
+    pub transaction_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListTablesArguments {
+    pub database: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListRoutinesArguments {
+    pub database: Option<String>,
+    /// procedure or function; both when omitted.
+    #[serde(rename = "type")]
+    pub routine_type: Option<String>,
+    #[serde(default = "default_true")]
+    pub include_body: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallProcedureArguments {
+    pub name: String,
+    /// Values bound to the procedure's IN parameters, in order.
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+    pub database: Option<String>,
+    pub transaction_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ErDiagramArguments {
+    pub database: Option<String>,
+    /// Restrict the diagram to these tables and the keys between them.
+    pub tables: Option<Vec<String>>,
+}
+
+/// How much `schema_summary` says about each column.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// Column names with PK/FK markers.
+    Names,
+    /// Also column types.
+    #[default]
+    Types,
+    /// Also nullability, defaults, comments, unique keys, and row estimates.
+    Full,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchemaSummaryArguments {
+    pub database: Option<String>,
+    /// Only these tables.
+    pub tables: Option<Vec<String>>,
+    #[serde(default)]
+    pub verbosity: Verbosity,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TableStatsArguments {
+    /// Omit for every base table of the database.
+    pub table_name: Option<String>,
+    pub database: Option<String>,
+    /// Also run `COUNT(*)` (single table only).
+    #[serde(default)]
+    pub exact: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProcesslistArguments {
+    /// Also list idle (`Sleep`) connections.
+    #[serde(default)]
+    pub include_idle: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KillQueryArguments {
+    pub thread_id: u64,
+    /// Terminate the whole connection instead of just its running statement.
+    #[serde(default)]
+    pub connection: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShowGrantsArguments {
+    /// Account as `name` or `name@host`; the connected user when absent.
+    pub user: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServerStatusArguments {
+    /// SQL LIKE pattern over status and variable names.
+    pub pattern: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ColumnDefinition {
+    pub name: String,
+    /// SQL type, e.g. `VARCHAR(255)` or `DECIMAL(10,2) UNSIGNED`.
+    #[serde(rename = "type")]
+    pub data_type: String,
+    #[serde(default = "default_true")]
+    pub nullable: bool,
+    pub default: Option<serde_json::Value>,
+    #[serde(default)]
+    pub auto_increment: bool,
+    #[serde(default)]
+    pub primary_key: bool,
+    #[serde(default)]
+    pub unique: bool,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTableArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    pub columns: Vec<ColumnDefinition>,
+    /// Composite primary key; overrides per-column `primary_key` flags.
+    pub primary_key: Option<Vec<String>>,
+    #[serde(default)]
+    pub if_not_exists: bool,
+    pub engine: Option<String>,
+    pub charset: Option<String>,
+    pub collation: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op")]
+pub enum AlterOperation {
+    #[serde(rename = "add_column")]
+    Add {
+        column: ColumnDefinition,
+        after: Option<String>,
+        #[serde(default)]
+        first: bool,
+    },
+    #[serde(rename = "modify_column")]
+    Modify {
+        column: ColumnDefinition,
+        after: Option<String>,
+        #[serde(default)]
+        first: bool,
+    },
+    #[serde(rename = "drop_column")]
+    Drop {
+        name: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlterTableArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    pub operations: Vec<AlterOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DropTableArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    /// Must be true; guards against dropping a table by accident.
+    #[serde(default)]
+    pub confirm: bool,
+    #[serde(default)]
+    pub if_exists: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexType {
+    Btree,
+    Hash,
+    Fulltext,
+    Spatial,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateIndexArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    pub columns: Vec<String>,
+    /// Defaults to `idx_<table>_<columns>`.
+    pub index_name: Option<String>,
+    #[serde(default)]
+    pub unique: bool,
+    #[serde(rename = "type")]
+    pub index_type: Option<IndexType>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DropIndexArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    pub index_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShowCreateArguments {
+    pub name: String,
+    /// table (default), view, procedure, function, trigger, or event.
+    #[serde(rename = "type", default = "default_show_create_type")]
+    pub object_type: String,
+    pub database: Option<String>,
+}
+
+fn default_show_create_type() -> String {
+    "table".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    #[default]
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportArguments {
+    /// SELECT to export; alternatively give `table_name` (and `conditions`).
+    pub query: Option<String>,
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+    pub table_name: Option<String>,
+    pub database: Option<String>,
+    pub conditions: Option<serde_json::Value>,
+    #[serde(default)]
+    pub format: DataFormat,
+    /// File to write, relative to `export_dir`. Returned inline when absent.
+    pub path: Option<String>,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DumpArguments {
+    pub database: Option<String>,
+    /// Tables and views to dump; all of them when absent.
+    pub tables: Option<Vec<String>>,
+    #[serde(default = "default_true")]
+    pub include_data: bool,
+    /// File to write, relative to `export_dir`. Returned inline when absent.
+    pub path: Option<String>,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    #[serde(default = "default_preview_limit")]
+    pub limit: usize,
+    /// Return a random sample instead of the first rows.
+    #[serde(default)]
+    pub sample: bool,
+}
+
+fn default_preview_limit() -> usize {
+    10
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchArguments {
+    pub value: String,
+    pub database: Option<String>,
+    /// Tables to search; all base tables when absent.
+    pub tables: Option<Vec<String>>,
+    /// Match whole column values instead of substrings.
+    #[serde(default)]
+    pub exact: bool,
+    #[serde(default = "default_search_rows_per_table")]
+    pub rows_per_table: usize,
+}
+
+fn default_search_rows_per_table() -> usize {
+    5
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FulltextMode {
+    #[default]
+    Natural,
+    Boolean,
+    /// Natural language with blind query expansion.
+    Expansion,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FulltextSearchArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    pub query: String,
+    /// Columns of the FULLTEXT index to search; needed when the table has several.
+    pub columns: Option<Vec<String>>,
+    #[serde(default)]
+    pub mode: FulltextMode,
+    /// Extra filters, as for select.
+    pub conditions: Option<serde_json::Value>,
+    pub min_score: Option<f64>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProfileTableArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    /// Columns to profile; all of them when absent.
+    pub columns: Option<Vec<String>>,
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+}
+
+fn default_top_k() -> usize {
+    5
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CountArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    pub conditions: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderBy {
+    pub column: String,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SelectArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    /// Columns to return; all of them when absent.
+    pub columns: Option<Vec<String>>,
+    pub conditions: Option<serde_json::Value>,
+    #[serde(default)]
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Aggregation {
+    pub function: AggregateFunction,
+    /// Column to aggregate; `count` without one counts rows.
+    pub column: Option<String>,
+    #[serde(default)]
+    pub distinct: bool,
+    /// Result column name; defaults to e.g. `sum_total`.
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AggregateArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    #[serde(default)]
+    pub group_by: Vec<String>,
+    pub aggregations: Vec<Aggregation>,
+    pub conditions: Option<serde_json::Value>,
+    /// Group-by columns or aggregation aliases to sort by.
+    #[serde(default)]
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicatesArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    /// Key columns whose shared values make rows duplicates.
+    pub columns: Vec<String>,
+    pub conditions: Option<serde_json::Value>,
+    /// Groups per page.
+    #[serde(default = "default_duplicate_groups")]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+    /// Rows of each group to return alongside its count.
+    #[serde(default = "default_rows_per_group")]
+    pub rows_per_group: usize,
+}
+
+fn default_duplicate_groups() -> usize {
+    20
+}
+
+fn default_rows_per_group() -> usize {
+    5
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveQueryArguments {
+    pub name: String,
+    pub description: Option<String>,
+    pub query: String,
+    /// Names of the `?` placeholders in order.
+    #[serde(default)]
+    pub parameters: Vec<String>,
+    /// Replace an existing query of the same name.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunSavedQueryArguments {
+    pub name: String,
+    /// Values for the placeholders: an array in order, or an object keyed by
+    /// parameter name.
+    pub params: Option<serde_json::Value>,
+    pub database: Option<String>,
+    pub cursor: Option<String>,
+    /// Run inside this open transaction instead of on a fresh connection.
+    pub transaction_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchemaDiffArguments {
+    /// Database whose schema is the reference.
+    pub source_database: String,
+    /// Database compared against it; the current database when absent.
+    pub target_database: Option<String>,
+    /// Tables to compare; all base tables when absent.
+    pub tables: Option<Vec<String>>,
+    /// Also return statements that bring the target in line with the source.
+    #[serde(default)]
+    pub generate_sql: bool,
+    /// Let the statements drop columns, indexes, keys, and tables the source lacks.
+    #[serde(default)]
+    pub include_drops: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateDataArguments {
+    pub table_name: String,
+    /// Rows to generate.
+    #[serde(default = "default_generate_count")]
+    pub count: usize,
+    /// Makes the generated values reproducible.
+    pub seed: Option<u64>,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Run inside this open transaction instead of on a fresh connection.
+    pub transaction_id: Option<String>,
+}
+
+fn default_generate_count() -> usize {
+    10
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollChangesArguments {
+    /// `cursor` of the previous poll; the current end of the binary log when absent.
+    pub cursor: Option<String>,
+    /// Changes to return before stopping at the next transaction boundary.
+    #[serde(default = "default_poll_limit")]
+    pub limit: usize,
+}
+
+fn default_poll_limit() -> usize {
+    500
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunMigrationsArguments {
+    /// Database to migrate; the current database when absent.
+    pub database: Option<String>,
+    /// Apply migrations up to and including this version only.
+    pub target_version: Option<u64>,
+    /// List the pending migrations without running them.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStatus {
+    Ok,
+    Error,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueryHistoryArguments {
+    /// Only calls of this tool.
+    pub tool: Option<String>,
+    pub status: Option<HistoryStatus>,
+    /// Case-insensitive text one of the call's statements must contain.
+    pub contains: Option<String>,
+    #[serde(default = "default_history_limit")]
+    pub limit: usize,
+}
+
+fn default_history_limit() -> usize {
+    20
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExplainArguments {
+    pub query: String,
+    pub database: Option<String>,
+    /// Also run `EXPLAIN ANALYZE` (MySQL 8.0.18+), which executes the query.
+    #[serde(default)]
+    pub analyze: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdviseIndexesArguments {
     pub query: String,
     pub database: Option<String>,
 }
@@ -105,7 +858,68 @@ pub struct QueryArguments {
 #[derive(Debug, Deserialize)]
 pub struct InsertArguments {
     pub table_name: String,
+    /// One row object, or an array of them.
     pub data: serde_json::Value,
+    /// Rows per multi-row INSERT when `data` is an array.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Read the inserted rows back and return them.
+    #[serde(default)]
+    pub return_rows: bool,
+    /// Unique key identifying the inserted rows for `return_rows`; defaults to
+    /// the auto-increment column.
+    pub key_columns: Option<Vec<String>>,
+    /// Return the statement instead of running it.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Run inside this open transaction instead of on a fresh connection.
+    pub transaction_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    pub data: serde_json::Value,
+    /// Columns overwritten from `data` when the row already exists.
+    pub update_columns: Vec<String>,
+    /// Run inside this open transaction instead of on a fresh connection.
+    pub transaction_id: Option<String>,
+}
+
+/// What `import` does with a row whose key already exists.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnDuplicate {
+    /// Fail the import.
+    #[default]
+    Error,
+    /// Skip the row (`INSERT IGNORE`).
+    Ignore,
+    /// Overwrite the existing row (`ON DUPLICATE KEY UPDATE`).
+    Update,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportArguments {
+    pub table_name: String,
+    pub database: Option<String>,
+    /// Row objects to insert; alternatively give `path`.
+    pub rows: Option<Vec<serde_json::Value>>,
+    /// File to read, relative to `import_dir`.
+    pub path: Option<String>,
+    /// File format; inferred from the extension when absent (`.json` is an array of rows).
+    pub format: Option<DataFormat>,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default)]
+    pub on_duplicate: OnDuplicate,
+    /// Run inside this open transaction instead of on a fresh connection.
+    pub transaction_id: Option<String>,
+}
+
+fn default_batch_size() -> usize {
+    500
 }
 
 #[derive(Debug, Deserialize)]
@@ -113,12 +927,54 @@ pub struct UpdateArguments {
     pub table_name: String,
     pub data: serde_json::Value,
     pub conditions: serde_json::Value,
+    /// Order in which rows are changed, so `limit` picks e.g. the oldest.
+    #[serde(default)]
+    pub order_by: Vec<OrderBy>,
+    /// Change at most this many matching rows.
+    pub limit: Option<u64>,
+    /// Return the statement and the matching row count instead of running it.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Run even when more rows match than `max_affected_rows`.
+    #[serde(default)]
+    pub force: bool,
+    /// Run inside this open transaction instead of on a fresh connection.
+    pub transaction_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct DeleteArguments {
     pub table_name: String,
     pub conditions: serde_json::Value,
+    /// Order in which rows are changed, so `limit` picks e.g. the oldest.
+    #[serde(default)]
+    pub order_by: Vec<OrderBy>,
+    /// Change at most this many matching rows.
+    pub limit: Option<u64>,
+    /// Return the statement and the matching row count instead of running it.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Run even when more rows match than `max_affected_rows`, or without a
+    /// key column under `require_keyed_deletes`.
+    #[serde(default)]
+    pub force: bool,
+    /// Run inside this open transaction instead of on a fresh connection.
+    pub transaction_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunScriptArguments {
+    pub script: String,
+    pub database: Option<String>,
+    /// Keep executing after a failed statement instead of skipping the rest.
+    #[serde(default)]
+    pub continue_on_error: bool,
+    pub transaction_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionArguments {
+    pub transaction_id: String,
 }
 
 impl JsonRpcResponse {
@@ -143,4 +999,17 @@ impl JsonRpcResponse {
             }),
         }
     }
+
+    pub fn error_with_data(id: Option<Value>, code: i32, message: String, data: Value) -> Self {
+        JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(JsonRpcError {
+                code,
+                message,
+                data: Some(data),
+            }),
+        }
+    }
 }
@@ -0,0 +1,177 @@
+//! Named, parameterized queries kept in the file `saved_queries_file` points
+//! to, so vetted SQL can be listed and run by name. Files ending in `.json`
+//! are JSON; anything else is TOML with one `[[queries]]` table per query.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use tokio::sync::Mutex;
+
+use crate::error::DbError;
+use crate::rpc::SaveQueryArguments;
+
+/// Serializes read-modify-write cycles on the file across sessions.
+static FILE_LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedQuery {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub query: String,
+    /// Names of the `?` placeholders in order, so callers can pass params by name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct SavedQueryFile {
+    #[serde(default)]
+    queries: Vec<SavedQuery>,
+}
+
+pub async fn list(path: &str) -> Result<Vec<SavedQuery>, DbError> {
+    Ok(read_file(Path::new(path)).await?.queries)
+}
+
+pub async fn find(path: &str, name: &str) -> Result<SavedQuery, DbError> {
+    list(path)
+        .await?
+        .into_iter()
+        .find(|q| q.name == name)
+        .ok_or_else(|| DbError::NotFound(format!("No saved query named '{name}'")))
+}
+
+/// Add a query to the file, replacing one of the same name only when
+/// `overwrite` is set. Returns whether an existing query was replaced.
+pub async fn save(path: &str, args: SaveQueryArguments) -> Result<bool, DbError> {
+    if args.name.is_empty() || !args.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(DbError::InvalidInput(format!(
+            "Saved query names may only contain letters, digits, '_' and '-': {}",
+            args.name
+        )));
+    }
+    if args.query.trim().is_empty() {
+        return Err(DbError::InvalidInput("Query is empty".to_string()));
+    }
+
+    let _guard = FILE_LOCK.lock().await;
+    let path = Path::new(path);
+    let mut file = read_file(path).await?;
+    let saved = SavedQuery { name: args.name, description: args.description, query: args.query, parameters: args.parameters };
+    let replaced = match file.queries.iter_mut().find(|q| q.name == saved.name) {
+        Some(_) if !args.overwrite => {
+            return Err(DbError::InvalidInput(format!(
+                "A query named '{}' already exists; pass overwrite to replace it",
+                saved.name
+            )))
+        }
+        Some(existing) => {
+            *existing = saved;
+            true
+        }
+        None => {
+            file.queries.push(saved);
+            false
+        }
+    };
+
+    let text = if is_json(path) {
+        serde_json::to_string_pretty(&file).map_err(|e| DbError::InvalidInput(e.to_string()))?
+    } else {
+        toml::to_string(&file).map_err(|e| DbError::InvalidInput(e.to_string()))?
+    };
+    tokio::fs::write(path, text)
+        .await
+        .map_err(|e| DbError::InvalidInput(format!("Cannot write {}: {e}", path.display())))?;
+    Ok(replaced)
+}
+
+/// Positional values for the query's placeholders from an array, or from an
+/// object keyed by the saved parameter names.
+pub fn bind_values(saved: &SavedQuery, params: Option<Value>) -> Result<Vec<Value>, DbError> {
+    match params {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::Array(values)) => Ok(values),
+        Some(Value::Object(mut named)) => {
+            let values = saved
+                .parameters
+                .iter()
+                .map(|name| {
+                    named
+                        .remove(name)
+                        .ok_or_else(|| DbError::InvalidInput(format!("Missing parameter '{name}'")))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(unknown) = named.keys().next() {
+                return Err(DbError::InvalidInput(format!(
+                    "Unknown parameter '{unknown}'; '{}' takes {}",
+                    saved.name,
+                    if saved.parameters.is_empty() { "none".to_string() } else { saved.parameters.join(", ") }
+                )));
+            }
+            Ok(values)
+        }
+        Some(_) => Err(DbError::InvalidInput("Params must be an array or an object".to_string())),
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "json")
+}
+
+/// The file's queries; a file that does not exist yet holds none.
+async fn read_file(path: &Path) -> Result<SavedQueryFile, DbError> {
+    let text = match tokio::fs::read_to_string(path).await {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(SavedQueryFile::default()),
+        Err(e) => return Err(DbError::InvalidInput(format!("Cannot read {}: {e}", path.display()))),
+    };
+    let parsed = if is_json(path) {
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&text).map_err(|e| e.to_string())
+    };
+    parsed.map_err(|e| DbError::InvalidInput(format!("Invalid saved queries file {}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bind_values() {
+        let saved = SavedQuery {
+            name: "revenue".into(),
+            description: None,
+            query: "SELECT SUM(total) FROM orders WHERE created_at >= ? AND created_at < ?".into(),
+            parameters: vec!["from".into(), "to".into()],
+        };
+        assert_eq!(bind_values(&saved, None).unwrap(), Vec::<Value>::new());
+        assert_eq!(bind_values(&saved, Some(json!(["a", "b"]))).unwrap(), vec![json!("a"), json!("b")]);
+        assert_eq!(
+            bind_values(&saved, Some(json!({ "to": "2024-02-01", "from": "2024-01-01" }))).unwrap(),
+            vec![json!("2024-01-01"), json!("2024-02-01")]
+        );
+        assert!(bind_values(&saved, Some(json!({ "from": "2024-01-01" }))).is_err());
+        assert!(bind_values(&saved, Some(json!({ "from": 1, "to": 2, "extra": 3 }))).is_err());
+        assert!(bind_values(&saved, Some(json!("x"))).is_err());
+    }
+
+    #[test]
+    fn test_saved_query_file_round_trip() {
+        let file = SavedQueryFile {
+            queries: vec![SavedQuery {
+                name: "active_users".into(),
+                description: Some("Users seen in the last 30 days".into()),
+                query: "SELECT * FROM users WHERE last_seen > NOW() - INTERVAL 30 DAY".into(),
+                parameters: vec![],
+            }],
+        };
+        let text = toml::to_string(&file).unwrap();
+        assert!(text.contains("[[queries]]"));
+        let parsed: SavedQueryFile = toml::from_str(&text).unwrap();
+        assert_eq!(parsed.queries, file.queries);
+    }
+}
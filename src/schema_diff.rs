@@ -0,0 +1,485 @@
+//! Structural comparison of two databases: tables, columns, indexes, and
+//! foreign keys, with optional ALTER statements that bring the target in line
+//! with the source.
+
+use serde_json::{json, Value};
+use sqlx::{MySql, Pool, Row};
+use std::collections::BTreeMap;
+
+use crate::db::{foreign_keys, ForeignKey};
+use crate::ddl::{quote_literal, quote_name};
+use crate::error::DbError;
+use crate::introspection::resolve_database;
+use crate::rpc::SchemaDiffArguments;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    /// Everything after the name in a column definition, e.g.
+    /// `varchar(255) NOT NULL DEFAULT ''`.
+    pub definition: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexInfo {
+    pub unique: bool,
+    /// `BTREE`, `FULLTEXT`, `SPATIAL`, or `HASH`.
+    pub index_type: String,
+    /// Columns with their prefix length, e.g. `` `name`(10) ``.
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableSchema {
+    pub columns: Vec<ColumnInfo>,
+    pub indexes: BTreeMap<String, IndexInfo>,
+    /// Constraint name to its `FOREIGN KEY ... REFERENCES ...` clause.
+    pub foreign_keys: BTreeMap<String, String>,
+}
+
+/// Differences of one kind of object (columns, indexes, or foreign keys)
+/// within a table present in both databases.
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.changed.is_empty()
+    }
+
+    fn to_json(&self) -> Value {
+        json!({ "missing": self.missing, "extra": self.extra, "changed": self.changed })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TableDiff {
+    pub table: String,
+    pub columns: Changes,
+    pub indexes: Changes,
+    pub foreign_keys: Changes,
+}
+
+#[derive(Debug, Default)]
+pub struct SchemaDiff {
+    /// Tables in the source but not the target.
+    pub missing_tables: Vec<String>,
+    /// Tables in the target but not the source.
+    pub extra_tables: Vec<String>,
+    pub tables: Vec<TableDiff>,
+}
+
+/// Compare two databases and report what the target lacks or has in
+/// addition, plus statements to reconcile them when asked.
+pub async fn schema_diff(args: SchemaDiffArguments, pool: &Pool<MySql>) -> Result<Value, DbError> {
+    let target_db = resolve_database(args.target_database, pool).await?;
+    let source_db = resolve_database(Some(args.source_database), pool).await?;
+    if source_db == target_db {
+        return Err(DbError::InvalidInput("source_database and target_database are the same".to_string()));
+    }
+    let wanted = |table: &String| args.tables.as_ref().is_none_or(|t| t.contains(table));
+    let mut source = load_schema(&source_db, pool).await?;
+    let mut target = load_schema(&target_db, pool).await?;
+    source.retain(|name, _| wanted(name));
+    target.retain(|name, _| wanted(name));
+    if source.is_empty() {
+        return Err(DbError::NotFound(format!("No tables to compare in '{source_db}'")));
+    }
+
+    let diff = diff_schemas(&source, &target);
+    let mut result = json!({
+        "sourceDatabase": source_db,
+        "targetDatabase": target_db,
+        "identical": diff.missing_tables.is_empty() && diff.extra_tables.is_empty() && diff.tables.is_empty(),
+        "missingTables": diff.missing_tables,
+        "extraTables": diff.extra_tables,
+        "tables": diff.tables.iter().map(|t| json!({
+            "table": t.table,
+            "columns": t.columns.to_json(),
+            "indexes": t.indexes.to_json(),
+            "foreignKeys": t.foreign_keys.to_json(),
+        })).collect::<Vec<_>>(),
+    });
+
+    if args.generate_sql {
+        let mut statements = Vec::new();
+        for table in &diff.missing_tables {
+            let row = sqlx::query(&format!("SHOW CREATE TABLE {}.{}", quote_name(&source_db), quote_name(table)))
+                .fetch_one(pool)
+                .await?;
+            // Some servers return the DDL as a binary string
+            let create: Option<Vec<u8>> = row.try_get_unchecked(1)?;
+            let create = String::from_utf8_lossy(&create.unwrap_or_default()).into_owned();
+            // Pin the new table to the target database
+            statements.push(create.replacen("CREATE TABLE ", &format!("CREATE TABLE {}.", quote_name(&target_db)), 1) + ";");
+        }
+        statements.extend(alter_statements(&target_db, &source_db, &diff, &source, args.include_drops));
+        result["statements"] = json!(statements);
+    }
+    Ok(result)
+}
+
+/// Columns, indexes, and foreign keys of every base table in `database`.
+async fn load_schema(database: &str, pool: &Pool<MySql>) -> Result<BTreeMap<String, TableSchema>, DbError> {
+    let mut tables: BTreeMap<String, TableSchema> = BTreeMap::new();
+
+    let columns = sqlx::query(
+        "SELECT c.table_name AS table_name, c.column_name AS column_name, c.column_type AS column_type,
+                c.is_nullable AS is_nullable, c.column_default AS column_default, c.extra AS extra,
+                c.collation_name AS collation_name, c.column_comment AS column_comment,
+                c.generation_expression AS generation_expression
+         FROM information_schema.columns c
+         JOIN information_schema.tables t
+           ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+         WHERE c.table_schema = ? AND t.table_type = 'BASE TABLE'
+         ORDER BY c.table_name, c.ordinal_position",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    for row in columns {
+        let table: String = row.try_get("table_name")?;
+        let definition = column_definition(
+            &row.try_get::<String, _>("column_type")?,
+            row.try_get::<String, _>("is_nullable")? == "YES",
+            row.try_get::<Option<String>, _>("column_default")?.as_deref(),
+            &row.try_get::<String, _>("extra")?,
+            row.try_get::<Option<String>, _>("collation_name")?.as_deref(),
+            &row.try_get::<String, _>("column_comment")?,
+            row.try_get::<Option<String>, _>("generation_expression")?.as_deref(),
+        );
+        tables
+            .entry(table)
+            .or_default()
+            .columns
+            .push(ColumnInfo { name: row.try_get("column_name")?, definition });
+    }
+
+    let indexes = sqlx::query(
+        "SELECT table_name AS table_name, index_name AS index_name, CAST(non_unique AS SIGNED) AS non_unique,
+                column_name AS column_name, CAST(sub_part AS SIGNED) AS sub_part, index_type AS index_type
+         FROM information_schema.statistics
+         WHERE table_schema = ?
+         ORDER BY table_name, index_name, seq_in_index",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    for row in indexes {
+        let Some(table) = tables.get_mut(&row.try_get::<String, _>("table_name")?) else {
+            continue;
+        };
+        let column = match (row.try_get::<Option<String>, _>("column_name")?, row.try_get::<Option<i64>, _>("sub_part")?) {
+            (Some(column), Some(length)) => format!("{}({length})", quote_name(&column)),
+            (Some(column), None) => quote_name(&column),
+            // Functional key part (MySQL 8.0.13+)
+            (None, _) => "(expression)".to_string(),
+        };
+        let index = table.indexes.entry(row.try_get("index_name")?).or_insert_with(|| IndexInfo {
+            unique: row.try_get::<i64, _>("non_unique").unwrap_or(1) == 0,
+            index_type: row.try_get("index_type").unwrap_or_default(),
+            columns: Vec::new(),
+        });
+        index.columns.push(column);
+    }
+
+    for key in foreign_keys(pool, database, None).await? {
+        if let Some(table) = tables.get_mut(&key.table_name) {
+            table.foreign_keys.insert(key.name.clone(), foreign_key_clause(&key, database));
+        }
+    }
+    Ok(tables)
+}
+
+/// A column definition in the form `SHOW CREATE TABLE` would print it, so
+/// equal columns compare equal and changed ones can be re-declared as is.
+pub fn column_definition(
+    column_type: &str,
+    nullable: bool,
+    default: Option<&str>,
+    extra: &str,
+    collation: Option<&str>,
+    comment: &str,
+    generation_expression: Option<&str>,
+) -> String {
+    let mut definition = column_type.to_string();
+    if let Some(collation) = collation {
+        definition.push_str(&format!(" COLLATE {collation}"));
+    }
+    let generated = extra.contains("VIRTUAL GENERATED") || extra.contains("STORED GENERATED");
+    if let (true, Some(expression)) = (generated, generation_expression) {
+        let storage = if extra.contains("STORED") { "STORED" } else { "VIRTUAL" };
+        definition.push_str(&format!(" GENERATED ALWAYS AS ({expression}) {storage}"));
+    }
+    if !nullable {
+        definition.push_str(" NOT NULL");
+    }
+    let expression_default = extra.contains("DEFAULT_GENERATED");
+    match default {
+        // MySQL 5.7 reports CURRENT_TIMESTAMP without DEFAULT_GENERATED
+        Some(value) if value.to_uppercase().starts_with("CURRENT_TIMESTAMP") => {
+            definition.push_str(&format!(" DEFAULT {value}"))
+        }
+        Some(value) if expression_default => definition.push_str(&format!(" DEFAULT ({value})")),
+        Some(value) => definition.push_str(&format!(" DEFAULT {}", quote_literal(value))),
+        None if nullable && !generated => definition.push_str(" DEFAULT NULL"),
+        None => {}
+    }
+    let extra = extra.replace("DEFAULT_GENERATED", "");
+    let extra = extra.trim();
+    if !extra.is_empty() && !generated {
+        definition.push_str(&format!(" {}", extra.to_uppercase()));
+    }
+    if !comment.is_empty() {
+        definition.push_str(&format!(" COMMENT {}", quote_literal(comment)));
+    }
+    definition
+}
+
+/// `FOREIGN KEY (...) REFERENCES ...`, naming the referenced database only
+/// when it is not the table's own, so keys compare equal across databases.
+fn foreign_key_clause(key: &ForeignKey, database: &str) -> String {
+    let columns: Vec<String> = key.columns.iter().map(|c| quote_name(c)).collect();
+    let referenced: Vec<String> = key.referenced_columns.iter().map(|c| quote_name(c)).collect();
+    let table = if key.referenced_database == database {
+        quote_name(&key.referenced_table)
+    } else {
+        format!("{}.{}", quote_name(&key.referenced_database), quote_name(&key.referenced_table))
+    };
+    format!(
+        "FOREIGN KEY ({}) REFERENCES {table} ({}) ON DELETE {} ON UPDATE {}",
+        columns.join(", "),
+        referenced.join(", "),
+        key.on_delete,
+        key.on_update
+    )
+}
+
+/// What the target lacks, has in addition, or has differently, table by table.
+pub fn diff_schemas(source: &BTreeMap<String, TableSchema>, target: &BTreeMap<String, TableSchema>) -> SchemaDiff {
+    let mut diff = SchemaDiff {
+        missing_tables: source.keys().filter(|t| !target.contains_key(*t)).cloned().collect(),
+        extra_tables: target.keys().filter(|t| !source.contains_key(*t)).cloned().collect(),
+        tables: Vec::new(),
+    };
+    for (name, source_table) in source {
+        let Some(target_table) = target.get(name) else {
+            continue;
+        };
+        let source_columns: BTreeMap<_, _> = source_table.columns.iter().map(|c| (c.name.clone(), &c.definition)).collect();
+        let target_columns: BTreeMap<_, _> = target_table.columns.iter().map(|c| (c.name.clone(), &c.definition)).collect();
+        let table = TableDiff {
+            table: name.clone(),
+            columns: compare(&source_columns, &target_columns),
+            indexes: compare(&source_table.indexes, &target_table.indexes),
+            foreign_keys: compare(&source_table.foreign_keys, &target_table.foreign_keys),
+        };
+        if !(table.columns.is_empty() && table.indexes.is_empty() && table.foreign_keys.is_empty()) {
+            diff.tables.push(table);
+        }
+    }
+    diff
+}
+
+fn compare<T: PartialEq>(source: &BTreeMap<String, T>, target: &BTreeMap<String, T>) -> Changes {
+    let mut changes = Changes::default();
+    for (name, value) in source {
+        match target.get(name) {
+            None => changes.missing.push(name.clone()),
+            Some(other) if other != value => changes.changed.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    changes.extra = target.keys().filter(|name| !source.contains_key(*name)).cloned().collect();
+    changes
+}
+
+/// One ALTER TABLE per differing table, then the foreign keys to add, since
+/// they may reference tables created or changed earlier. Extra columns and
+/// tables are only dropped with `include_drops`.
+pub fn alter_statements(
+    target_db: &str,
+    source_db: &str,
+    diff: &SchemaDiff,
+    source: &BTreeMap<String, TableSchema>,
+    include_drops: bool,
+) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut foreign_key_statements = Vec::new();
+    for table_diff in &diff.tables {
+        let source_table = &source[&table_diff.table];
+        let qualified = format!("{}.{}", quote_name(target_db), quote_name(&table_diff.table));
+        let mut clauses = Vec::new();
+
+        for name in table_diff.foreign_keys.extra.iter().chain(&table_diff.foreign_keys.changed) {
+            if include_drops || table_diff.foreign_keys.changed.contains(name) {
+                clauses.push(format!("DROP FOREIGN KEY {}", quote_name(name)));
+            }
+        }
+        for name in table_diff.indexes.extra.iter().chain(&table_diff.indexes.changed) {
+            if include_drops || table_diff.indexes.changed.contains(name) {
+                clauses.push(if name == "PRIMARY" { "DROP PRIMARY KEY".to_string() } else { format!("DROP INDEX {}", quote_name(name)) });
+            }
+        }
+        for (i, column) in source_table.columns.iter().enumerate() {
+            let position = match i {
+                0 => " FIRST".to_string(),
+                _ => format!(" AFTER {}", quote_name(&source_table.columns[i - 1].name)),
+            };
+            if table_diff.columns.missing.contains(&column.name) {
+                clauses.push(format!("ADD COLUMN {} {}{position}", quote_name(&column.name), column.definition));
+            } else if table_diff.columns.changed.contains(&column.name) {
+                clauses.push(format!("MODIFY COLUMN {} {}", quote_name(&column.name), column.definition));
+            }
+        }
+        if include_drops {
+            for name in &table_diff.columns.extra {
+                clauses.push(format!("DROP COLUMN {}", quote_name(name)));
+            }
+        }
+        for name in table_diff.indexes.missing.iter().chain(&table_diff.indexes.changed) {
+            clauses.push(add_index_clause(name, &source_table.indexes[name]));
+        }
+        for name in table_diff.foreign_keys.missing.iter().chain(&table_diff.foreign_keys.changed) {
+            let clause = source_table.foreign_keys[name].clone();
+            // Keys into other tables of the source database point at the target's copy
+            let clause = clause.replace(&format!("REFERENCES {}.", quote_name(source_db)), &format!("REFERENCES {}.", quote_name(target_db)));
+            foreign_key_statements.push(format!("ALTER TABLE {qualified} ADD CONSTRAINT {} {clause};", quote_name(name)));
+        }
+
+        if !clauses.is_empty() {
+            statements.push(format!("ALTER TABLE {qualified}\n  {};", clauses.join(",\n  ")));
+        }
+    }
+    if include_drops {
+        for table in &diff.extra_tables {
+            statements.push(format!("DROP TABLE {}.{};", quote_name(target_db), quote_name(table)));
+        }
+    }
+    statements.extend(foreign_key_statements);
+    statements
+}
+
+fn add_index_clause(name: &str, index: &IndexInfo) -> String {
+    let columns = index.columns.join(", ");
+    if name == "PRIMARY" {
+        return format!("ADD PRIMARY KEY ({columns})");
+    }
+    let kind = match index.index_type.as_str() {
+        "FULLTEXT" => "FULLTEXT INDEX",
+        "SPATIAL" => "SPATIAL INDEX",
+        _ if index.unique => "UNIQUE INDEX",
+        _ => "INDEX",
+    };
+    format!("ADD {kind} {} ({columns})", quote_name(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(columns: &[(&str, &str)], indexes: &[(&str, bool, &[&str])]) -> TableSchema {
+        TableSchema {
+            columns: columns
+                .iter()
+                .map(|(name, definition)| ColumnInfo { name: name.to_string(), definition: definition.to_string() })
+                .collect(),
+            indexes: indexes
+                .iter()
+                .map(|(name, unique, columns)| {
+                    (
+                        name.to_string(),
+                        IndexInfo {
+                            unique: *unique,
+                            index_type: "BTREE".into(),
+                            columns: columns.iter().map(|c| format!("`{c}`")).collect(),
+                        },
+                    )
+                })
+                .collect(),
+            foreign_keys: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_column_definition() {
+        assert_eq!(
+            column_definition("varchar(255)", false, Some(""), "", Some("utf8mb4_0900_ai_ci"), "", None),
+            "varchar(255) COLLATE utf8mb4_0900_ai_ci NOT NULL DEFAULT ''"
+        );
+        assert_eq!(
+            column_definition("int unsigned", false, None, "auto_increment", None, "row id", None),
+            "int unsigned NOT NULL AUTO_INCREMENT COMMENT 'row id'"
+        );
+        assert_eq!(
+            column_definition(
+                "timestamp",
+                true,
+                Some("CURRENT_TIMESTAMP"),
+                "DEFAULT_GENERATED on update CURRENT_TIMESTAMP",
+                None,
+                "",
+                None
+            ),
+            "timestamp DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP"
+        );
+        assert_eq!(
+            column_definition("datetime", false, Some("CURRENT_TIMESTAMP"), "", None, "", None),
+            "datetime NOT NULL DEFAULT CURRENT_TIMESTAMP"
+        );
+        assert_eq!(column_definition("date", true, None, "", None, "", None), "date DEFAULT NULL");
+        assert_eq!(
+            column_definition("int", true, None, "VIRTUAL GENERATED", None, "", Some("(`a` + 1)")),
+            "int GENERATED ALWAYS AS ((`a` + 1)) VIRTUAL"
+        );
+    }
+
+    #[test]
+    fn test_diff_and_alter_statements() {
+        let source = BTreeMap::from([
+            (
+                "users".to_string(),
+                table(
+                    &[("id", "int NOT NULL"), ("email", "varchar(255) NOT NULL"), ("name", "varchar(100) DEFAULT NULL")],
+                    &[("PRIMARY", true, &["id"]), ("email", true, &["email"])],
+                ),
+            ),
+            ("orders".to_string(), table(&[("id", "int NOT NULL")], &[])),
+        ]);
+        let target = BTreeMap::from([
+            (
+                "users".to_string(),
+                table(
+                    &[("id", "int NOT NULL"), ("email", "varchar(100) NOT NULL"), ("legacy", "int DEFAULT NULL")],
+                    &[("PRIMARY", true, &["id"]), ("old_idx", false, &["legacy"])],
+                ),
+            ),
+            ("audit".to_string(), table(&[("id", "int NOT NULL")], &[])),
+        ]);
+
+        let diff = diff_schemas(&source, &target);
+        assert_eq!(diff.missing_tables, vec!["orders"]);
+        assert_eq!(diff.extra_tables, vec!["audit"]);
+        assert_eq!(diff.tables.len(), 1);
+        let users = &diff.tables[0];
+        assert_eq!(users.columns, Changes { missing: vec!["name".into()], extra: vec!["legacy".into()], changed: vec!["email".into()] });
+        assert_eq!(users.indexes, Changes { missing: vec!["email".into()], extra: vec!["old_idx".into()], changed: vec![] });
+
+        let statements = alter_statements("prod", "staging", &diff, &source, false);
+        assert_eq!(
+            statements,
+            vec![
+                "ALTER TABLE `prod`.`users`\n  MODIFY COLUMN `email` varchar(255) NOT NULL,\n  \
+                 ADD COLUMN `name` varchar(100) DEFAULT NULL AFTER `email`,\n  ADD UNIQUE INDEX `email` (`email`);"
+            ]
+        );
+        let statements = alter_statements("prod", "staging", &diff, &source, true);
+        assert!(statements[0].starts_with("ALTER TABLE `prod`.`users`\n  DROP INDEX `old_idx`,"));
+        assert!(statements[0].contains("DROP COLUMN `legacy`"));
+        assert_eq!(statements[1], "DROP TABLE `prod`.`audit`;");
+    }
+}
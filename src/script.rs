@@ -0,0 +1,274 @@
+//! Multi-statement SQL scripts for the `run_script` tool.
+
+use log::debug;
+use sqlx::Executor;
+
+use crate::db::{is_valid_identifier, record_statement, report_progress, track_connection};
+use crate::ddl::quote_name;
+use crate::error::DbError;
+use crate::policy::{self, Permissions};
+
+pub enum StatementStatus {
+    Ok { affected_rows: u64 },
+    Error(String),
+    /// Not run because an earlier statement failed.
+    Skipped,
+}
+
+pub struct StatementOutcome {
+    pub statement: String,
+    pub status: StatementStatus,
+}
+
+pub struct ScriptResult {
+    pub statements: Vec<StatementOutcome>,
+}
+
+impl ScriptResult {
+    pub fn failed(&self) -> usize {
+        self.statements
+            .iter()
+            .filter(|s| matches!(s.status, StatementStatus::Error(_)))
+            .count()
+    }
+}
+
+/// Run each statement of `script` in order on one connection. When a
+/// statement fails, the rest are skipped unless `continue_on_error` is set.
+/// Unless every `allow_*` flag is set, each statement is checked against
+/// `permissions` before any of them runs.
+pub async fn run_script(
+    script: &str,
+    database: Option<String>,
+    continue_on_error: bool,
+    permissions: Permissions,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<ScriptResult, DbError> {
+    let statements = checked_statements(script, permissions)?;
+
+    track_connection(conn).await?;
+    if let Some(db) = database {
+        if !is_valid_identifier(&db) {
+            return Err(DbError::InvalidIdentifier(db));
+        }
+        sqlx::query(&format!("USE {}", quote_name(&db))).execute(&mut *conn).await?;
+    }
+
+    let total = statements.len() as u64;
+    let mut outcomes = Vec::with_capacity(statements.len());
+    let mut stopped = false;
+    for (i, statement) in statements.into_iter().enumerate() {
+        if stopped {
+            outcomes.push(StatementOutcome { statement, status: StatementStatus::Skipped });
+            continue;
+        }
+        debug!("Executing script statement {}: {}", i + 1, statement);
+        record_statement(&statement);
+        let status = match execute(&statement, permissions, conn).await {
+            Ok(result) => StatementStatus::Ok { affected_rows: result.rows_affected() },
+            Err(e) => {
+                stopped = !continue_on_error;
+                StatementStatus::Error(e.to_string())
+            }
+        };
+        report_progress(i as u64 + 1, Some(total), &format!("Executed statement {} of {total}", i + 1));
+        outcomes.push(StatementOutcome { statement, status });
+    }
+
+    Ok(ScriptResult { statements: outcomes })
+}
+
+/// The statements of `script`, each checked against `permissions` unless
+/// every `allow_*` flag is set.
+fn checked_statements(script: &str, permissions: Permissions) -> Result<Vec<String>, DbError> {
+    let statements = split_statements(script);
+    if statements.is_empty() {
+        return Err(DbError::InvalidInput("Script contains no statements".to_string()));
+    }
+    if !permissions.allows_all() {
+        for statement in &statements {
+            policy::ensure_permitted(statement, permissions)?;
+        }
+    }
+    Ok(statements)
+}
+
+/// Run one script piece as a prepared statement, which the server refuses to
+/// treat as several. Statements that cannot be prepared (CREATE PROCEDURE,
+/// CREATE TRIGGER) are sent over the text protocol instead, which runs every
+/// statement in the text, so only when every `allow_*` flag is set.
+async fn execute(
+    statement: &str,
+    permissions: Permissions,
+    conn: &mut sqlx::MySqlConnection,
+) -> Result<sqlx::mysql::MySqlQueryResult, sqlx::Error> {
+    match sqlx::query(statement).execute(&mut *conn).await {
+        Err(sqlx::Error::Database(e)) if is_unpreparable(e.as_ref()) && permissions.allows_all() => conn.execute(statement).await,
+        result => result,
+    }
+}
+
+/// `ER_UNSUPPORTED_PS`: the statement has to go over the text protocol.
+fn is_unpreparable(error: &dyn sqlx::error::DatabaseError) -> bool {
+    error.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>().map(|e| e.number()) == Some(1295)
+}
+
+/// Split a script into statements on `;` (or the current `DELIMITER`),
+/// ignoring delimiters inside quotes, backtick identifiers, and comments.
+/// Statements consisting only of comments are dropped.
+pub fn split_statements(script: &str) -> Vec<String> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut statements = Vec::new();
+    let mut delimiter: Vec<char> = vec![';'];
+    let mut current = String::new();
+    let mut has_code = false;
+    let mut at_line_start = true;
+    let mut i = 0;
+
+    let mut finish = |current: &mut String, has_code: &mut bool| {
+        if *has_code {
+            statements.push(current.trim().to_string());
+        }
+        current.clear();
+        *has_code = false;
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // mysql client DELIMITER directive, only recognised at the start of a line
+        if at_line_start && !has_code {
+            let rest: String = chars[i..].iter().take_while(|&&c| c != '\n').collect();
+            let trimmed = rest.trim_start();
+            if trimmed.get(..10).is_some_and(|d| d.eq_ignore_ascii_case("DELIMITER ")) {
+                let new_delimiter = trimmed[10..].trim();
+                if !new_delimiter.is_empty() {
+                    delimiter = new_delimiter.chars().collect();
+                }
+                current.clear();
+                i += rest.chars().count();
+                continue;
+            }
+        }
+
+        if c == '\n' {
+            at_line_start = true;
+            current.push(c);
+            i += 1;
+            continue;
+        }
+        if !c.is_whitespace() {
+            at_line_start = false;
+        }
+
+        match c {
+            '\'' | '"' | '`' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '\\' && c != '`' {
+                        i += 2;
+                        continue;
+                    }
+                    if chars[i] == c {
+                        break;
+                    }
+                    i += 1;
+                }
+                i = (i + 1).min(chars.len());
+                current.extend(&chars[start..i]);
+                has_code = true;
+                continue;
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    current.push(chars[i]);
+                    i += 1;
+                }
+                continue;
+            }
+            '-' if chars.get(i + 1) == Some(&'-')
+                && chars.get(i + 2).is_none_or(|c| c.is_whitespace()) =>
+            {
+                while i < chars.len() && chars[i] != '\n' {
+                    current.push(chars[i]);
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let start = i;
+                // `/*! ... */` is executable on MySQL, so it counts as code
+                if chars.get(i + 2) == Some(&'!') {
+                    has_code = true;
+                }
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i = (i + 2).min(chars.len());
+                current.extend(&chars[start..i]);
+                continue;
+            }
+            _ => {}
+        }
+
+        if chars[i..].starts_with(&delimiter) {
+            finish(&mut current, &mut has_code);
+            i += delimiter.len();
+            continue;
+        }
+
+        if !c.is_whitespace() {
+            has_code = true;
+        }
+        current.push(c);
+        i += 1;
+    }
+    finish(&mut current, &mut has_code);
+
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split_statements("CREATE TABLE a (id INT);\nINSERT INTO a VALUES (1);"),
+            vec!["CREATE TABLE a (id INT)", "INSERT INTO a VALUES (1)"]
+        );
+        // Delimiters inside strings, identifiers and comments do not split
+        assert_eq!(
+            split_statements("INSERT INTO `x;y` VALUES ('a;b', \"c\\\";d\"); -- done; really\nSELECT 1 /* ; */;"),
+            vec!["INSERT INTO `x;y` VALUES ('a;b', \"c\\\";d\")", "-- done; really\nSELECT 1 /* ; */"]
+        );
+        // Comment-only and empty statements are dropped
+        assert_eq!(split_statements("SELECT 1;;\n# trailing comment\n"), vec!["SELECT 1"]);
+        assert!(split_statements("  -- nothing here\n").is_empty());
+        // Missing final delimiter
+        assert_eq!(split_statements("SELECT 1; SELECT 2"), vec!["SELECT 1", "SELECT 2"]);
+    }
+
+    #[test]
+    fn test_split_statements_delimiter() {
+        let script = "DELIMITER //\nCREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  SELECT 2;\nEND//\nDELIMITER ;\nCALL p();\n";
+        assert_eq!(
+            split_statements(script),
+            vec!["CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  SELECT 2;\nEND", "CALL p()"]
+        );
+    }
+
+    #[test]
+    fn test_checked_statements() {
+        let read_only = Permissions::default();
+        assert_eq!(checked_statements("SHOW PROCESSLIST; SHOW WARNINGS", read_only).unwrap(), vec!["SHOW PROCESSLIST", "SHOW WARNINGS"]);
+        // With another delimiter a `;` stays inside the piece; the statement after it is not a read
+        for script in ["DELIMITER //\nSHOW PROCESSLIST; DROP TABLE users//", "DELIMITER //\nSHOW WARNINGS; DELETE FROM users//"] {
+            assert!(checked_statements(script, read_only).is_err(), "{script}");
+        }
+        assert!(checked_statements("-- nothing", read_only).is_err());
+    }
+}
@@ -0,0 +1,2284 @@
+//! Tool definitions advertised by `tools/list`.
+
+use serde_json::{json, Value};
+
+use crate::cdc;
+use crate::config::Config;
+use crate::confirm;
+use crate::generate;
+use crate::history;
+use crate::migrations;
+use crate::policy::Permissions;
+use crate::rpc::{Tool, ToolAnnotations};
+
+/// Tools advertised to clients: the default connection's, plus any that only
+/// a named connection enables. With named connections, every tool takes a
+/// `connection` argument selecting one.
+pub fn list(config: &Config) -> Vec<Tool> {
+    let mut tools = list_for(config);
+    if config.connections.is_empty() {
+        return tools;
+    }
+    for connection in config.connections.values() {
+        for tool in list_for(connection) {
+            if !tools.iter().any(|listed| listed.name == tool.name) {
+                tools.push(tool);
+            }
+        }
+    }
+    let names: Vec<&String> = config.connections.keys().collect();
+    for tool in &mut tools {
+        tool.input_schema["properties"]["connection"] = json!({
+            "type": "string",
+            "enum": names,
+            "description": "Named connection from config.toml to run on, with its own permissions; the default connection when omitted"
+        });
+    }
+    tools
+}
+
+fn list_for(config: &Config) -> Vec<Tool> {
+    let permissions = Permissions::new(config);
+    let query_description = if permissions.allows_all() {
+        "Execute any SQL query on the database (unrestricted)".to_string()
+    } else if permissions.is_read_only() {
+        "Execute a SELECT query on the database (read-only)".to_string()
+    } else {
+        format!("Execute a SELECT, SHOW, DESCRIBE, EXPLAIN, or {} query on the database", permissions.describe())
+    };
+
+    let mut tools = vec![
+        Tool {
+            name: "mysql".to_string(),
+            description: "Retrieve MySQL database schema information for tables and views (views include their definition)".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": {
+                        "type": "string",
+                        "description": "Name of the table or view to inspect, or 'all-tables' to get all table and view schemas"
+                    }
+                },
+                "required": ["table_name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "schemas": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "One entry per table or view, with its columns (including charset and collation), indexes, foreign keys, and triggers; tables add engine, row_format, charset, collation, and partitioning (null unless partitioned); views add definition, updatable, and check_option"
+                    }
+                },
+                "required": ["schemas"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Inspect table schema")),
+        },
+        Tool {
+            name: "query".to_string(),
+            description: query_description,
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": if permissions.is_read_only() {
+                            "SELECT query to execute"
+                        } else {
+                            "SQL query to execute"
+                        }
+                    },
+                    "params": {
+                        "type": "array",
+                        "description": "Optional values bound in order to ? placeholders in the query. Prefer this over inlining literals into the SQL."
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database name to use for this query. If specified, the query will be executed in the context of this database."
+                    },
+                    "cursor": {
+                        "type": "string",
+                        "description": "nextCursor from a truncated result, to fetch the following page. Pass the same query and params, with an ORDER BY on a unique key so pages are stable."
+                    }
+                },
+                "required": ["query"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Column names in select-list order"
+                    },
+                    "rows": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "Result rows keyed by column name"
+                    },
+                    "rowCount": { "type": "integer" },
+                    "truncated": {
+                        "type": "boolean",
+                        "description": "True when more than max_rows rows matched"
+                    },
+                    "nextCursor": {
+                        "type": ["string", "null"],
+                        "description": "Pass as cursor with the same query to fetch the next page"
+                    }
+                },
+                "required": ["columns", "rows", "rowCount", "truncated"]
+            })),
+            annotations: Some(if !permissions.is_read_only() {
+                ToolAnnotations::destructive("Run SQL")
+            } else {
+                ToolAnnotations::read_only("Run read-only SQL")
+            }),
+        },
+        Tool {
+            name: "preview".to_string(),
+            description: format!(
+                "Show what a table looks like: its first rows, or a random sample (at most {} rows). No SQL needed.",
+                crate::db::PREVIEW_MAX_ROWS
+            ),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table to preview"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Rows to return (default 10, at most {})", crate::db::PREVIEW_MAX_ROWS)
+                    },
+                    "sample": {
+                        "type": "boolean",
+                        "description": "Return randomly chosen rows instead of the first ones"
+                    }
+                },
+                "required": ["table_name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Column names in table order"
+                    },
+                    "rows": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "Rows keyed by column name"
+                    },
+                    "rowCount": { "type": "integer" },
+                    "truncated": {
+                        "type": "boolean",
+                        "description": "True when the table has more rows than were returned (first-rows mode only)"
+                    }
+                },
+                "required": ["columns", "rows", "rowCount", "truncated"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Preview table")),
+        },
+        Tool {
+            name: "search".to_string(),
+            description: "Find where a value lives: search the text columns of every table (or the given tables) for a string and report which tables, columns, and rows match".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "value": {
+                        "type": "string",
+                        "description": "Text to look for, e.g. an email address"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database to search (defaults to the current one)"
+                    },
+                    "tables": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only search these tables"
+                    },
+                    "exact": {
+                        "type": "boolean",
+                        "description": "Match whole column values instead of substrings"
+                    },
+                    "rows_per_table": {
+                        "type": "integer",
+                        "description": format!("Matching rows returned per table (default 5, at most {})", crate::db::SEARCH_MAX_ROWS_PER_TABLE)
+                    }
+                },
+                "required": ["value"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "database": { "type": "string" },
+                    "matches": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "table": { "type": "string" },
+                                "columns": {
+                                    "type": "array",
+                                    "items": { "type": "string" },
+                                    "description": "Columns containing the value in the returned rows"
+                                },
+                                "rows": { "type": "array", "items": { "type": "object" } }
+                            }
+                        }
+                    },
+                    "tablesSearched": { "type": "integer" },
+                    "skipped": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tables whose search timed out or failed"
+                    },
+                    "truncated": {
+                        "type": "boolean",
+                        "description": "True when there were too many tables to search them all"
+                    }
+                },
+                "required": ["database", "matches", "tablesSearched", "skipped", "truncated"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Search values")),
+        },
+        Tool {
+            name: "fulltext_search".to_string(),
+            description: "Search a table through its FULLTEXT index with MATCH ... AGAINST, in natural language, boolean (+word -word \"phrase\" prefix*), or query-expansion mode. Rows come back best match first with their relevance as _score. Much faster than LIKE on large text columns".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table with a FULLTEXT index"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "Search text, or a boolean-mode expression"
+                    },
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Columns of the FULLTEXT index to use; required when the table has more than one"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["natural", "boolean", "expansion"],
+                        "description": "natural (default): relevance ranking; boolean: operators such as + - * and quoted phrases; expansion: natural language with query expansion"
+                    },
+                    "conditions": {
+                        "type": "object",
+                        "description": "Extra filters as column-value pairs or column-operator objects, as for select"
+                    },
+                    "min_score": {
+                        "type": "number",
+                        "description": "Only rows scoring at least this"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Rows to return (default and maximum {})", config.max_rows)
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Rows to skip"
+                    }
+                },
+                "required": ["table_name", "query"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "index": { "type": "string", "description": "FULLTEXT index searched" },
+                    "indexColumns": { "type": "array", "items": { "type": "string" } },
+                    "columns": { "type": "array", "items": { "type": "string" } },
+                    "rows": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "Matching rows with their relevance in _score, best first"
+                    },
+                    "rowCount": { "type": "integer" },
+                    "truncated": {
+                        "type": "boolean",
+                        "description": "True when more rows match than were returned"
+                    }
+                },
+                "required": ["index", "indexColumns", "columns", "rows", "rowCount", "truncated"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Full-text search")),
+        },
+        Tool {
+            name: "profile_table".to_string(),
+            description: "Per-column data profile of a table: null and distinct counts, min/max, average string length, and the most frequent values. Large tables are profiled on a random sample.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table to profile"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table"
+                    },
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only profile these columns"
+                    },
+                    "top_k": {
+                        "type": "integer",
+                        "description": format!("Most frequent values to report per column (default 5, at most {}, 0 to skip)", crate::introspection::PROFILE_MAX_TOP_K)
+                    }
+                },
+                "required": ["table_name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "database": { "type": "string" },
+                    "table": { "type": "string" },
+                    "rowsProfiled": { "type": "integer" },
+                    "estimatedRows": { "type": ["integer", "null"] },
+                    "sampled": {
+                        "type": "boolean",
+                        "description": "True when the statistics describe a random sample"
+                    },
+                    "columns": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "dataType": { "type": "string" },
+                                "nulls": { "type": "integer" },
+                                "distinct": { "type": "integer" },
+                                "min": { "type": ["string", "null"] },
+                                "max": { "type": ["string", "null"] },
+                                "avgLength": { "type": ["number", "null"] },
+                                "topValues": {
+                                    "type": "array",
+                                    "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "value": { "type": ["string", "null"] },
+                                            "count": { "type": "integer" }
+                                        }
+                                    }
+                                }
+                            },
+                            "required": ["name", "dataType", "nulls", "distinct"]
+                        }
+                    }
+                },
+                "required": ["database", "table", "rowsProfiled", "sampled", "columns"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Profile table")),
+        },
+        Tool {
+            name: "count".to_string(),
+            description: "Count the rows of a table matching conditions, e.g. to check how many rows an update or delete would touch".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table to count rows in"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table"
+                    },
+                    "conditions": {
+                        "type": "object",
+                        "description": "Conditions as key-value pairs, as for update and delete. Omit to count every row."
+                    }
+                },
+                "required": ["table_name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "count": { "type": "integer" }
+                },
+                "required": ["count"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Count rows")),
+        },
+        Tool {
+            name: "select".to_string(),
+            description: "Read rows from a table using structured arguments (columns, conditions, ordering, limit, offset) instead of SQL. Values are bound as parameters.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table to read from"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table"
+                    },
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Columns to return (default: all)"
+                    },
+                    "conditions": {
+                        "type": "object",
+                        "description": "Conditions as column-value pairs (equality) or column-operator objects, as for update and delete"
+                    },
+                    "order_by": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "column": { "type": "string" },
+                                "direction": { "type": "string", "enum": ["asc", "desc"] }
+                            },
+                            "required": ["column"]
+                        }
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Rows to return (default and maximum {})", config.max_rows)
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Rows to skip"
+                    }
+                },
+                "required": ["table_name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Column names in select-list order"
+                    },
+                    "rows": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "Result rows keyed by column name"
+                    },
+                    "rowCount": { "type": "integer" },
+                    "truncated": {
+                        "type": "boolean",
+                        "description": "True when more rows match than were returned"
+                    }
+                },
+                "required": ["columns", "rows", "rowCount", "truncated"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Select rows")),
+        },
+        Tool {
+            name: "aggregate".to_string(),
+            description: "Group and summarize a table (count, sum, avg, min, max per group) using structured arguments instead of SQL".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table to aggregate"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table"
+                    },
+                    "group_by": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Columns to group by (omit for one summary row)"
+                    },
+                    "aggregations": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "function": { "type": "string", "enum": ["count", "sum", "avg", "min", "max"] },
+                                "column": { "type": "string", "description": "Column to aggregate; count without a column counts rows" },
+                                "distinct": { "type": "boolean" },
+                                "alias": { "type": "string", "description": "Result column name (default e.g. sum_total)" }
+                            },
+                            "required": ["function"]
+                        }
+                    },
+                    "conditions": {
+                        "type": "object",
+                        "description": "Conditions as column-value pairs (equality) or column-operator objects, as for update and delete"
+                    },
+                    "order_by": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "column": { "type": "string", "description": "A group_by column or aggregation alias" },
+                                "direction": { "type": "string", "enum": ["asc", "desc"] }
+                            },
+                            "required": ["column"]
+                        }
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Groups to return (default and maximum {})", config.max_rows)
+                    }
+                },
+                "required": ["table_name", "aggregations"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Group-by columns followed by aggregation aliases"
+                    },
+                    "rows": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "One row per group"
+                    },
+                    "rowCount": { "type": "integer" },
+                    "truncated": {
+                        "type": "boolean",
+                        "description": "True when there were more groups than were returned"
+                    }
+                },
+                "required": ["columns", "rows", "rowCount", "truncated"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Aggregate rows")),
+        },
+        Tool {
+            name: "find_duplicates".to_string(),
+            description: "Find groups of rows sharing the same values in key columns (GROUP BY ... HAVING COUNT(*) > 1), largest first, with counts and a few rows of each group. Pages with limit and offset".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": { "type": "string" },
+                    "database": { "type": "string", "description": "Optional database containing the table" },
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Key columns whose shared values make rows duplicates, e.g. [\"email\"]"
+                    },
+                    "conditions": {
+                        "type": "object",
+                        "description": "Only consider rows matching these conditions (same form as select)"
+                    },
+                    "limit": { "type": "integer", "description": "Groups per page (default 20)" },
+                    "offset": { "type": "integer", "description": "Groups to skip, e.g. nextOffset of the previous page" },
+                    "rows_per_group": { "type": "integer", "description": "Rows of each group to return (default 5, 0 for counts only)" }
+                },
+                "required": ["table_name", "columns"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "groups": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "key": { "type": "object", "description": "The shared key column values" },
+                                "count": { "type": "integer" },
+                                "rows": { "type": "array", "items": { "type": "object" } }
+                            }
+                        }
+                    },
+                    "totalGroups": { "type": "integer", "description": "Duplicate groups in the whole table" },
+                    "excessRows": { "type": "integer", "description": "Rows beyond the first of each group" },
+                    "nextOffset": { "type": ["integer", "null"], "description": "Offset of the next page, if any" }
+                },
+                "required": ["groups", "totalGroups", "excessRows", "nextOffset"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Find duplicate rows")),
+        },
+        Tool {
+            name: "query_history".to_string(),
+            description: "List recent tool calls of this session (newest first) with the SQL they ran, duration, row count, and status, to review what was executed".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "tool": {
+                        "type": "string",
+                        "description": "Only calls of this tool, e.g. delete"
+                    },
+                    "status": {
+                        "type": "string",
+                        "enum": ["ok", "error"]
+                    },
+                    "contains": {
+                        "type": "string",
+                        "description": "Case-insensitive text a statement must contain, e.g. a table name"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Entries to return (default 20; the last {} calls are kept)", history::HISTORY_CAPACITY)
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "entries": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "id": { "type": "integer" },
+                                "finishedAt": { "type": "integer", "description": "Unix time in milliseconds" },
+                                "tool": { "type": "string" },
+                                "statements": {
+                                    "type": "array",
+                                    "items": { "type": "string" },
+                                    "description": format!("SQL the call ran (at most {})", history::MAX_STATEMENTS_PER_CALL)
+                                },
+                                "durationMs": { "type": "integer" },
+                                "rowCount": { "type": ["integer", "null"] },
+                                "status": { "type": "string", "enum": ["ok", "error"] },
+                                "error": { "type": ["string", "null"] }
+                            }
+                        }
+                    }
+                },
+                "required": ["entries"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Query history")),
+        },
+        Tool {
+            name: "schema_diff".to_string(),
+            description: "Compare the schema of a target database against a source (e.g. staging vs prod on this server): missing and extra tables, and differing columns, indexes, and foreign keys. Optionally returns ALTER statements that bring the target in line with the source; they are not executed".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "source_database": {
+                        "type": "string",
+                        "description": "Database whose schema is the reference"
+                    },
+                    "target_database": {
+                        "type": "string",
+                        "description": "Database compared against the source (defaults to the current database)"
+                    },
+                    "tables": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tables to compare; all base tables when omitted"
+                    },
+                    "generate_sql": {
+                        "type": "boolean",
+                        "description": "Also return statements that make the target match the source (default false)"
+                    },
+                    "include_drops": {
+                        "type": "boolean",
+                        "description": "Let the statements drop columns, indexes, foreign keys, and tables the source lacks (default false)"
+                    }
+                },
+                "required": ["source_database"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "sourceDatabase": { "type": "string" },
+                    "targetDatabase": { "type": "string" },
+                    "identical": { "type": "boolean" },
+                    "missingTables": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tables in the source but not the target"
+                    },
+                    "extraTables": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tables in the target but not the source"
+                    },
+                    "tables": {
+                        "type": "array",
+                        "description": "Tables in both whose structure differs",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "table": { "type": "string" },
+                                "columns": schema_changes_schema(),
+                                "indexes": schema_changes_schema(),
+                                "foreignKeys": schema_changes_schema()
+                            }
+                        }
+                    },
+                    "statements": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "With generate_sql: statements to run on the target, in order"
+                    }
+                },
+                "required": ["sourceDatabase", "targetDatabase", "identical", "missingTables", "extraTables", "tables"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Compare schemas")),
+        },
+        Tool {
+            name: "list_databases".to_string(),
+            description: "List the databases (schemas) on the server with their default character set and collation".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "databases": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "charset": { "type": "string" },
+                                "collation": { "type": "string" }
+                            },
+                            "required": ["name", "charset", "collation"]
+                        }
+                    }
+                },
+                "required": ["databases"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("List databases")),
+        },
+        Tool {
+            name: "list_tables".to_string(),
+            description: "List the tables of a database with engine, estimated row count, data and index size, and comment. Much cheaper than fetching every table schema.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "database": {
+                        "type": "string",
+                        "description": "Database to list (defaults to the current database)"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "database": { "type": "string" },
+                    "tables": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "type": { "type": "string", "description": "BASE TABLE, VIEW, or SYSTEM VIEW" },
+                                "engine": { "type": ["string", "null"] },
+                                "estimatedRows": { "type": ["integer", "null"], "description": "Approximate for InnoDB" },
+                                "dataBytes": { "type": ["integer", "null"] },
+                                "indexBytes": { "type": ["integer", "null"] },
+                                "comment": { "type": "string" }
+                            },
+                            "required": ["name", "type"]
+                        }
+                    }
+                },
+                "required": ["database", "tables"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("List tables")),
+        },
+        Tool {
+            name: "list_routines".to_string(),
+            description: "List stored procedures and functions with their parameter signatures, return types, and bodies".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "database": {
+                        "type": "string",
+                        "description": "Database to list (defaults to the current database)"
+                    },
+                    "type": {
+                        "type": "string",
+                        "enum": ["procedure", "function"],
+                        "description": "Only list this kind of routine"
+                    },
+                    "include_body": {
+                        "type": "boolean",
+                        "description": "Include each routine's body (default true)"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "database": { "type": "string" },
+                    "routines": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "type": { "type": "string", "enum": ["procedure", "function"] },
+                                "signature": { "type": "string" },
+                                "parameters": { "type": "array", "items": { "type": "object" } },
+                                "returns": { "type": ["string", "null"] },
+                                "body": { "type": ["string", "null"] }
+                            },
+                            "required": ["name", "type", "signature", "parameters"]
+                        }
+                    }
+                },
+                "required": ["database", "routines"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("List stored routines")),
+        },
+        Tool {
+            name: "schema_summary".to_string(),
+            description: "Get a compact schema overview for context: one line per table and view with its columns, types, and PK/FK markers, e.g. `orders: id int PK, customer_id int FK->customers.id, status varchar(20)`. Far smaller than the full schema from the mysql tool on large databases".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "database": {
+                        "type": "string",
+                        "description": "Database to summarize (defaults to the current database)"
+                    },
+                    "tables": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only include these tables"
+                    },
+                    "verbosity": {
+                        "type": "string",
+                        "enum": ["names", "types", "full"],
+                        "description": "names: column names with PK/FK markers; types (default): also column types; full: also NOT NULL, defaults, extras, comments, unique keys, and row estimates"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "database": { "type": "string" },
+                    "lines": { "type": "array", "items": { "type": "string" }, "description": "One line per table" }
+                },
+                "required": ["database", "lines"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Schema summary")),
+        },
+        Tool {
+            name: "er_diagram".to_string(),
+            description: "Generate a Mermaid erDiagram of the tables of a database and the foreign keys between them".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "database": {
+                        "type": "string",
+                        "description": "Database to diagram (defaults to the current database)"
+                    },
+                    "tables": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only include these tables"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "mermaid": { "type": "string" },
+                    "tables": { "type": "integer" },
+                    "relationships": { "type": "integer" }
+                },
+                "required": ["mermaid", "tables", "relationships"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Draw ER diagram")),
+        },
+        Tool {
+            name: "table_stats".to_string(),
+            description: "Get storage statistics for a table (or all tables): estimated or exact row count, data and index size, free space and fragmentation, AUTO_INCREMENT value, and create/update time. Check this before running COUNT(*) on a large table.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table to inspect; omit for every table in the database"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Database containing the table (defaults to the current database)"
+                    },
+                    "exact": {
+                        "type": "boolean",
+                        "description": "Also run COUNT(*) for an exact row count (requires table_name; slow on large InnoDB tables)"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "database": { "type": "string" },
+                    "tables": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "engine": { "type": ["string", "null"] },
+                                "estimatedRows": { "type": ["integer", "null"] },
+                                "exactRows": { "type": "integer" },
+                                "dataBytes": { "type": ["integer", "null"] },
+                                "indexBytes": { "type": ["integer", "null"] },
+                                "freeBytes": { "type": ["integer", "null"] },
+                                "fragmentationPercent": { "type": ["number", "null"] },
+                                "autoIncrement": { "type": ["integer", "null"] },
+                                "createTime": { "type": ["string", "null"] },
+                                "updateTime": { "type": ["string", "null"] }
+                            },
+                            "required": ["name"]
+                        }
+                    }
+                },
+                "required": ["database", "tables"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Table statistics")),
+        },
+        Tool {
+            name: "show_processlist".to_string(),
+            description: "List the threads currently running on the server (SHOW FULL PROCESSLIST), longest-running first, to diagnose slow or stuck queries".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "include_idle": {
+                        "type": "boolean",
+                        "description": "Also list idle (Sleep) connections (default false)"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "threads": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "id": { "type": "integer" },
+                                "user": { "type": "string" },
+                                "host": { "type": "string" },
+                                "database": { "type": ["string", "null"] },
+                                "command": { "type": "string" },
+                                "timeSecs": { "type": "integer" },
+                                "state": { "type": ["string", "null"] },
+                                "query": { "type": ["string", "null"] }
+                            },
+                            "required": ["id", "user", "host", "command", "timeSecs"]
+                        }
+                    }
+                },
+                "required": ["threads"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Show running threads")),
+        },
+        Tool {
+            name: "server_status".to_string(),
+            description: "Get global status counters and server variables (connections, buffer pool, max_connections, sql_mode, version). Without a pattern a curated selection is returned.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "pattern": {
+                        "type": "string",
+                        "description": "SQL LIKE pattern for status/variable names, e.g. 'innodb_buffer_pool%'"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "status": {
+                        "type": "object",
+                        "description": "SHOW GLOBAL STATUS values by name"
+                    },
+                    "variables": {
+                        "type": "object",
+                        "description": "SHOW GLOBAL VARIABLES values by name"
+                    }
+                },
+                "required": ["status", "variables"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Server status")),
+        },
+        Tool {
+            name: "innodb_status".to_string(),
+            description: "Diagnose lock contention: transactions currently waiting for a row lock with the transaction blocking each (sys.innodb_lock_waits), and the latest deadlock from SHOW ENGINE INNODB STATUS with the queries and locks involved and which transaction was rolled back. Needs the PROCESS privilege".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "lockWaits": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "waitSecs": { "type": ["integer", "null"] },
+                                "lockedTable": { "type": ["string", "null"] },
+                                "lockedIndex": { "type": ["string", "null"] },
+                                "waiting": lock_transaction_schema(),
+                                "blocking": lock_transaction_schema()
+                            }
+                        }
+                    },
+                    "latestDeadlock": {
+                        "type": ["object", "null"],
+                        "description": "Null when no deadlock has been detected since the server started",
+                        "properties": {
+                            "time": { "type": ["string", "null"] },
+                            "transactions": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "trxId": { "type": "string" },
+                                        "state": { "type": "string" },
+                                        "threadId": { "type": ["integer", "null"] },
+                                        "query": { "type": "string" },
+                                        "holds": { "type": "array", "items": { "type": "object" } },
+                                        "waitingFor": { "type": "array", "items": { "type": "object" } }
+                                    }
+                                }
+                            },
+                            "rolledBack": { "type": ["integer", "null"], "description": "Number of the transaction InnoDB rolled back" }
+                        }
+                    }
+                },
+                "required": ["lockWaits", "latestDeadlock"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("InnoDB status")),
+        },
+        Tool {
+            name: "show_grants".to_string(),
+            description: "Show the privileges of the connected user (SHOW GRANTS), or of another account when permitted, parsed into privileges per database or table. Use it to explain permission errors instead of retrying".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "user": {
+                        "type": "string",
+                        "description": "Account as name or name@host (host defaults to %); the connected user when omitted. Needs SELECT on the mysql schema"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "account": { "type": "string" },
+                    "grants": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "privileges": { "type": "array", "items": { "type": "string" } },
+                                "on": { "type": "string", "description": "Object the privileges apply to, e.g. `shop`.* or *.*" },
+                                "roles": { "type": "array", "items": { "type": "string" }, "description": "For role grants, the roles granted" },
+                                "grantOption": { "type": "boolean" },
+                                "statement": { "type": "string" }
+                            }
+                        }
+                    }
+                },
+                "required": ["account", "grants"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Show grants")),
+        },
+        Tool {
+            name: "replication_status".to_string(),
+            description: "Report replication state: per channel whether the IO and SQL threads run, seconds behind the source, last errors, and GTID sets; the binary log position when this server is a source; and GTID variables. Answers \"is the replica caught up?\"".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "isReplica": { "type": "boolean" },
+                    "channels": {
+                        "type": "array",
+                        "description": "One entry per replication channel (SHOW REPLICA STATUS)",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "channel": { "type": ["string", "null"] },
+                                "sourceHost": { "type": ["string", "null"] },
+                                "ioThreadRunning": { "type": ["boolean", "null"] },
+                                "sqlThreadRunning": { "type": ["boolean", "null"] },
+                                "secondsBehindSource": { "type": ["integer", "null"], "description": "Null when the SQL thread is stopped" },
+                                "caughtUp": { "type": "boolean", "description": "Both threads running with no lag" },
+                                "lastIoError": { "type": ["string", "null"] },
+                                "lastSqlError": { "type": ["string", "null"] },
+                                "retrievedGtidSet": { "type": ["string", "null"] },
+                                "executedGtidSet": { "type": ["string", "null"] }
+                            }
+                        }
+                    },
+                    "source": {
+                        "type": ["object", "null"],
+                        "description": "Current binary log file and position (SHOW BINARY LOG STATUS); null without binary logging"
+                    },
+                    "variables": {
+                        "type": "object",
+                        "description": "server_id, read_only, log_bin, gtid_mode, gtid_executed, and related variables"
+                    }
+                },
+                "required": ["isReplica", "channels", "source", "variables"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Replication status")),
+        },
+        Tool {
+            name: "show_create".to_string(),
+            description: "Get the exact CREATE statement for a table, view, procedure, function, trigger, or event (SHOW CREATE), including constraints, charset, and partitioning".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "Name of the object"
+                    },
+                    "type": {
+                        "type": "string",
+                        "enum": ["table", "view", "procedure", "function", "trigger", "event"],
+                        "description": "Kind of object (default table)"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the object"
+                    }
+                },
+                "required": ["name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "type": { "type": "string" },
+                    "name": { "type": "string" },
+                    "ddl": { "type": "string" }
+                },
+                "required": ["type", "name", "ddl"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Show CREATE statement")),
+        },
+        Tool {
+            name: "export".to_string(),
+            description: "Export a SELECT result or a whole table as CSV or newline-delimited JSON, either to a file under the configured export_dir or returned as a resource. Use this instead of query for large results.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "SELECT to export (instead of table_name)"
+                    },
+                    "params": {
+                        "type": "array",
+                        "description": "Values bound to the query's ? placeholders, in order"
+                    },
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table to export (instead of query)"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table or to run the query in"
+                    },
+                    "conditions": {
+                        "type": "object",
+                        "description": "Conditions on table_name's columns, as for update and delete"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["csv", "ndjson"],
+                        "description": "Output format (default csv)"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "File to write, relative to export_dir. Omit to return the data as a resource."
+                    },
+                    "overwrite": {
+                        "type": "boolean",
+                        "description": "Replace the file if it already exists"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "format": { "type": "string" },
+                    "rows": { "type": "integer" },
+                    "bytes": { "type": "integer" },
+                    "path": {
+                        "type": ["string", "null"],
+                        "description": "File written, or null when the data is returned as a resource"
+                    }
+                },
+                "required": ["format", "rows", "bytes"]
+            })),
+            annotations: Some(ToolAnnotations::non_destructive("Export data")),
+        },
+        Tool {
+            name: "dump".to_string(),
+            description: "Write a mysqldump-compatible SQL dump (CREATE statements and, optionally, INSERTs) of a database or selected tables, to a file under the configured export_dir or returned as a resource. Useful as a backup before changing data.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "database": {
+                        "type": "string",
+                        "description": "Database to dump (defaults to the current one)"
+                    },
+                    "tables": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tables and views to include (default: all)"
+                    },
+                    "include_data": {
+                        "type": "boolean",
+                        "description": "Include the rows as INSERT statements (default true)"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "File to write, relative to export_dir. Omit to return the dump as a resource."
+                    },
+                    "overwrite": {
+                        "type": "boolean",
+                        "description": "Replace the file if it already exists"
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "database": { "type": "string" },
+                    "tables": { "type": "array", "items": { "type": "string" } },
+                    "views": { "type": "array", "items": { "type": "string" } },
+                    "rows": { "type": "integer" },
+                    "bytes": { "type": "integer" },
+                    "path": {
+                        "type": ["string", "null"],
+                        "description": "File written, or null when the dump is returned as a resource"
+                    }
+                },
+                "required": ["database", "tables", "views", "rows", "bytes"]
+            })),
+            annotations: Some(ToolAnnotations::non_destructive("Dump database")),
+        },
+        Tool {
+            name: "explain".to_string(),
+            description: "Show the optimizer's plan for a query (EXPLAIN FORMAT=JSON), optionally with measured timings from EXPLAIN ANALYZE. Use it to find out why a query is slow.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Statement to explain (SELECT, TABLE, WITH, INSERT, REPLACE, UPDATE, or DELETE)"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database to explain the query in"
+                    },
+                    "analyze": {
+                        "type": "boolean",
+                        "description": "Also run EXPLAIN ANALYZE (MySQL 8.0.18+). This executes the query, so in read-only mode only SELECTs are allowed."
+                    }
+                },
+                "required": ["query"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "plan": {
+                        "type": "object",
+                        "description": "EXPLAIN FORMAT=JSON output"
+                    },
+                    "analyze": {
+                        "type": ["string", "null"],
+                        "description": "EXPLAIN ANALYZE tree, when requested"
+                    }
+                },
+                "required": ["plan"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Explain query plan")),
+        },
+        Tool {
+            name: "advise_indexes".to_string(),
+            description: "Suggest indexes for a slow query: runs EXPLAIN, reads the columns the query filters, joins, and sorts on, checks them against the existing indexes of each table, and returns candidate indexes with the CREATE INDEX statement for each. Nothing is changed".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Statement to analyze (SELECT, TABLE, WITH, INSERT, REPLACE, UPDATE, or DELETE)"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Database the query runs in; the current one when omitted"
+                    }
+                },
+                "required": ["query"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "database": { "type": "string" },
+                    "tables": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "table": { "type": "string" },
+                                "alias": { "type": ["string", "null"] },
+                                "accessType": { "type": ["string", "null"], "description": "EXPLAIN access type; ALL is a full table scan" },
+                                "key": { "type": ["string", "null"], "description": "Index the plan uses" },
+                                "rowsExamined": { "type": ["integer", "null"] },
+                                "indexes": {
+                                    "type": "array",
+                                    "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "name": { "type": "string" },
+                                            "unique": { "type": "boolean" },
+                                            "columns": { "type": "array", "items": { "type": "string" } }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "suggestions": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "table": { "type": "string" },
+                                "columns": { "type": "array", "items": { "type": "string" } },
+                                "reason": { "type": "string" },
+                                "statement": { "type": "string", "description": "CREATE INDEX statement for the candidate" }
+                            }
+                        }
+                    },
+                    "plan": { "type": "object", "description": "EXPLAIN FORMAT=JSON output" }
+                },
+                "required": ["database", "tables", "suggestions", "plan"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Advise indexes")),
+        },
+    ];
+
+    if !permissions.is_read_only() {
+        tools[1].input_schema["properties"]["transaction_id"] = json!({
+            "type": "string",
+            "description": "Optional id from begin_transaction to run inside that transaction"
+        });
+        tools.push(Tool {
+            name: "insert".to_string(),
+            description: "Insert one row, or many rows at once as batched multi-row INSERTs, into a specified table".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    },
+                    "table_name": {
+                        "type": "string",
+                        "description": "Name of the table to insert data into"
+                    },
+                    "data": {
+                        "type": ["object", "array"],
+                        "items": { "type": "object" },
+                        "description": "Row to insert as key-value pairs, or an array of rows"
+                    },
+                    "batch_size": {
+                        "type": "integer",
+                        "description": "Rows per INSERT statement when data is an array (default 500)"
+                    },
+                    "return_rows": {
+                        "type": "boolean",
+                        "description": "Read the inserted rows back, including defaults, generated values, and trigger changes"
+                    },
+                    "key_columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Unique key columns (present in data) that identify the rows for return_rows; defaults to the auto-increment column"
+                    },
+                    "dry_run": {
+                        "type": "boolean",
+                        "description": "Return the SQL, bound parameters (of the first batch), and row count without executing"
+                    }
+                },
+                "required": ["table_name", "data"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "insertedRows": { "type": "integer" },
+                    "batches": { "type": "integer" },
+                    "firstInsertId": {
+                        "type": "integer",
+                        "description": "Auto-increment id of the first row, 0 when the table has none"
+                    },
+                    "lastInsertId": {
+                        "type": "integer",
+                        "description": "Auto-increment id of the last row"
+                    },
+                    "rows": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "The inserted rows as stored, with return_rows"
+                    },
+                    "dryRun": { "type": "boolean" },
+                    "sql": { "type": "string", "description": "Statement that would run, with dry_run" },
+                    "params": { "type": "array", "description": "Values it would bind, with dry_run" }
+                },
+                "required": ["insertedRows", "batches", "firstInsertId", "lastInsertId"]
+            })),
+            annotations: Some(ToolAnnotations::destructive("Insert rows")),
+        });
+        tools.push(Tool {
+            name: "upsert".to_string(),
+            description: "Insert a row, or update chosen columns of the existing row when it collides with a primary or unique key (INSERT ... ON DUPLICATE KEY UPDATE)".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    },
+                    "table_name": {
+                        "type": "string",
+                        "description": "Name of the table to upsert into"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table"
+                    },
+                    "data": {
+                        "type": "object",
+                        "description": "Row as key-value pairs, including the key columns"
+                    },
+                    "update_columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Columns of data to overwrite when the row already exists"
+                    }
+                },
+                "required": ["table_name", "data", "update_columns"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "affectedRows": {
+                        "type": "integer",
+                        "description": "1 when inserted or unchanged, 2 when an existing row was updated"
+                    },
+                    "lastInsertId": { "type": "integer" },
+                    "updated": { "type": "boolean" }
+                },
+                "required": ["affectedRows", "lastInsertId", "updated"]
+            })),
+            annotations: Some(ToolAnnotations::destructive("Upsert a row")),
+        });
+        tools.push(Tool {
+            name: "generate_data".to_string(),
+            description: format!("Insert plausible synthetic rows into a table for demos and dev databases. Values follow each column's type and name (emails, names, dates, enum members), foreign keys reference existing rows, and unique keys stay unique. Up to {} rows per call", generate::MAX_GENERATED_ROWS),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    },
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table in the current database to fill"
+                    },
+                    "count": {
+                        "type": "integer",
+                        "description": "Rows to generate (default 10)"
+                    },
+                    "seed": {
+                        "type": "integer",
+                        "description": "Seed for reproducible values; random when omitted"
+                    },
+                    "batch_size": {
+                        "type": "integer",
+                        "description": "Rows per multi-row INSERT (default 500)"
+                    }
+                },
+                "required": ["table_name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "insertedRows": { "type": "integer" },
+                    "batches": { "type": "integer" },
+                    "firstInsertId": { "type": "integer" },
+                    "lastInsertId": { "type": "integer" },
+                    "seed": { "type": "integer", "description": "Pass back as seed to generate the same values" },
+                    "sample": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "The first generated rows"
+                    }
+                },
+                "required": ["insertedRows", "batches", "firstInsertId", "lastInsertId", "seed", "sample"]
+            })),
+            annotations: Some(ToolAnnotations::destructive("Generate test data")),
+        });
+        tools.push(Tool {
+            name: "import".to_string(),
+            description: "Bulk load rows into a table with batched multi-row INSERTs, from inline rows or a CSV, NDJSON, or JSON file under the configured import_dir".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    },
+                    "table_name": {
+                        "type": "string",
+                        "description": "Table to load into"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the table"
+                    },
+                    "rows": {
+                        "type": "array",
+                        "items": { "type": "object" },
+                        "description": "Rows as key-value objects (instead of path)"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "File to read, relative to import_dir (instead of rows)"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["csv", "ndjson"],
+                        "description": "File format; inferred from the extension by default, and .json files hold an array of rows"
+                    },
+                    "batch_size": {
+                        "type": "integer",
+                        "description": "Rows per INSERT statement (default 500)"
+                    },
+                    "on_duplicate": {
+                        "type": "string",
+                        "enum": ["error", "ignore", "update"],
+                        "description": "Fail on duplicate keys (default), skip those rows, or update the existing rows"
+                    }
+                },
+                "required": ["table_name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "rows": { "type": "integer" },
+                    "batches": { "type": "integer" },
+                    "affectedRows": { "type": "integer" },
+                    "skipped": {
+                        "type": ["integer", "null"],
+                        "description": "Rows skipped as duplicates (on_duplicate: ignore)"
+                    }
+                },
+                "required": ["rows", "batches", "affectedRows"]
+            })),
+            annotations: Some(ToolAnnotations::destructive("Import rows")),
+        });
+        tools.push(Tool {
+            name: "update".to_string(),
+            description: "Update data in a specified table based on conditions".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    },
+                    "table_name": {
+                        "type": "string",
+                        "description": "Name of the table to update data in"
+                    },
+                    "data": {
+                        "type": "object",
+                        "description": "Data to update as key-value pairs"
+                    },
+                    "conditions": {
+                        "type": "object",
+                        "description": "Conditions for update: column-value pairs for equality (null matches IS NULL), or {\"op\": \">\", \"value\": ...} objects with =, !=, <, <=, >, >=, like, not like, in, not in, between, not between, is null, is not null"
+                    },
+                    "order_by": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "column": { "type": "string" },
+                                "direction": { "type": "string", "enum": ["asc", "desc"] }
+                            },
+                            "required": ["column"]
+                        },
+                        "description": "Order in which rows are updated; requires limit"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Update at most this many matching rows, e.g. to work through a large cleanup in chunks"
+                    },
+                    "dry_run": {
+                        "type": "boolean",
+                        "description": "Return the SQL, bound parameters, and number of rows that would be updated without executing"
+                    },
+                    "force": {
+                        "type": "boolean",
+                        "description": "Run even if more rows match than the server's max_affected_rows limit, or the conditions use no key column under require_keyed_deletes"
+                    }
+                },
+                "required": ["table_name", "data", "conditions"]
+            }),
+            output_schema: Some(affected_rows_schema()),
+            annotations: Some(ToolAnnotations::destructive("Update rows")),
+        });
+        tools.push(Tool {
+            name: "delete".to_string(),
+            description: "Delete data from a specified table based on conditions".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    },
+                    "table_name": {
+                        "type": "string",
+                        "description": "Name of the table to delete data from"
+                    },
+                    "conditions": {
+                        "type": "object",
+                        "description": "Conditions for deletion: column-value pairs for equality (null matches IS NULL), or {\"op\": \">\", \"value\": ...} objects with =, !=, <, <=, >, >=, like, not like, in, not in, between, not between, is null, is not null"
+                    },
+                    "order_by": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "column": { "type": "string" },
+                                "direction": { "type": "string", "enum": ["asc", "desc"] }
+                            },
+                            "required": ["column"]
+                        },
+                        "description": "Order in which rows are deleted; requires limit"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Delete at most this many matching rows, e.g. to work through a large cleanup in chunks"
+                    },
+                    "dry_run": {
+                        "type": "boolean",
+                        "description": "Return the SQL, bound parameters, and number of rows that would be deleted without executing"
+                    },
+                    "force": {
+                        "type": "boolean",
+                        "description": "Run even if more rows match than the server's max_affected_rows limit"
+                    }
+                },
+                "required": ["table_name", "conditions"]
+            }),
+            output_schema: Some(affected_rows_schema()),
+            annotations: Some(ToolAnnotations::destructive("Delete rows")),
+        });
+        tools.push(Tool {
+            name: "run_script".to_string(),
+            description: "Execute a multi-statement SQL script (e.g. schema setup) sequentially on one connection, reporting the status of each statement. Supports mysql client DELIMITER lines for procedure and trigger bodies.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "script": {
+                        "type": "string",
+                        "description": "SQL statements separated by ; (or the delimiter set with DELIMITER)"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database to run the script in"
+                    },
+                    "continue_on_error": {
+                        "type": "boolean",
+                        "description": "Keep running after a failed statement instead of skipping the rest (default false)"
+                    },
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    }
+                },
+                "required": ["script"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "statements": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "index": { "type": "integer" },
+                                "statement": { "type": "string" },
+                                "status": { "type": "string", "enum": ["ok", "error", "skipped"] },
+                                "affectedRows": { "type": "integer" },
+                                "error": { "type": "string" }
+                            },
+                            "required": ["index", "statement", "status"]
+                        }
+                    },
+                    "failed": { "type": "integer" }
+                },
+                "required": ["statements", "failed"]
+            })),
+            annotations: Some(ToolAnnotations::destructive("Run SQL script")),
+        });
+        tools.push(Tool {
+            name: "call_procedure".to_string(),
+            description: "Call a stored procedure with bound arguments and return every result set it produces".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "Name of the procedure"
+                    },
+                    "params": {
+                        "type": "array",
+                        "description": "Values for the procedure's parameters, in order"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database containing the procedure"
+                    },
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    }
+                },
+                "required": ["name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "resultSets": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "columns": { "type": "array", "items": { "type": "string" } },
+                                "rows": { "type": "array", "items": { "type": "object" } },
+                                "rowCount": { "type": "integer" },
+                                "truncated": { "type": "boolean" }
+                            },
+                            "required": ["columns", "rows", "rowCount", "truncated"]
+                        }
+                    },
+                    "affectedRows": { "type": "integer" }
+                },
+                "required": ["resultSets", "affectedRows"]
+            })),
+            annotations: Some(ToolAnnotations::destructive("Call stored procedure")),
+        });
+        tools.push(Tool {
+            name: "begin_transaction".to_string(),
+            description: "Start a transaction on a dedicated connection and return its id. Pass the id as transaction_id to query, insert, update, and delete so the writes are atomic, then call commit or rollback.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "transactionId": { "type": "string" }
+                },
+                "required": ["transactionId"]
+            })),
+            annotations: Some(ToolAnnotations::non_destructive("Begin transaction")),
+        });
+        tools.push(Tool {
+            name: "commit".to_string(),
+            description: "Commit a transaction started with begin_transaction".to_string(),
+            input_schema: transaction_id_schema(),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "committed": { "type": "boolean" }
+                },
+                "required": ["committed"]
+            })),
+            annotations: Some(ToolAnnotations::destructive("Commit transaction")),
+        });
+        tools.push(Tool {
+            name: "rollback".to_string(),
+            description: "Roll back a transaction started with begin_transaction, discarding its changes".to_string(),
+            input_schema: transaction_id_schema(),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "rolledBack": { "type": "boolean" }
+                },
+                "required": ["rolledBack"]
+            })),
+            annotations: Some(ToolAnnotations::non_destructive("Roll back transaction")),
+        });
+    }
+
+    if permissions.ddl {
+        tools.push(Tool {
+            name: "create_table".to_string(),
+            description: "Create a table from a structured list of column definitions".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": { "type": "string", "description": "Name of the new table" },
+                    "database": { "type": "string", "description": "Optional database to create it in" },
+                    "columns": {
+                        "type": "array",
+                        "items": column_definition_schema(),
+                        "description": "Column definitions, in order"
+                    },
+                    "primary_key": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Columns of a composite primary key (instead of per-column primary_key flags)"
+                    },
+                    "if_not_exists": { "type": "boolean" },
+                    "engine": { "type": "string", "description": "Storage engine, e.g. InnoDB" },
+                    "charset": { "type": "string" },
+                    "collation": { "type": "string" },
+                    "comment": { "type": "string" }
+                },
+                "required": ["table_name", "columns"]
+            }),
+            output_schema: Some(ddl_output_schema()),
+            annotations: Some(ToolAnnotations::non_destructive("Create table")),
+        });
+        tools.push(Tool {
+            name: "alter_table".to_string(),
+            description: "Add, modify, or drop columns of a table".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": { "type": "string", "description": "Table to alter" },
+                    "database": { "type": "string", "description": "Optional database containing the table" },
+                    "operations": {
+                        "type": "array",
+                        "description": "Operations applied in one ALTER TABLE statement",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": { "type": "string", "enum": ["add_column", "modify_column", "drop_column"] },
+                                "column": column_definition_schema(),
+                                "name": { "type": "string", "description": "Column to drop (drop_column)" },
+                                "after": { "type": "string", "description": "Place the column after this one" },
+                                "first": { "type": "boolean", "description": "Place the column first" }
+                            },
+                            "required": ["op"]
+                        }
+                    }
+                },
+                "required": ["table_name", "operations"]
+            }),
+            output_schema: Some(ddl_output_schema()),
+            annotations: Some(ToolAnnotations::destructive("Alter table")),
+        });
+        tools.push(Tool {
+            name: "drop_table".to_string(),
+            description: "Drop a table and all of its data. Requires confirm: true.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": { "type": "string", "description": "Table to drop" },
+                    "database": { "type": "string", "description": "Optional database containing the table" },
+                    "confirm": { "type": "boolean", "description": "Must be true to drop the table" },
+                    "if_exists": { "type": "boolean" }
+                },
+                "required": ["table_name", "confirm"]
+            }),
+            output_schema: Some(ddl_output_schema()),
+            annotations: Some(ToolAnnotations::destructive("Drop table")),
+        });
+        tools.push(Tool {
+            name: "create_index".to_string(),
+            description: "Create an index on one or more columns of a table".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": { "type": "string", "description": "Table to index" },
+                    "database": { "type": "string", "description": "Optional database containing the table" },
+                    "columns": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Indexed columns, in order"
+                    },
+                    "index_name": { "type": "string", "description": "Defaults to idx_<table>_<columns>" },
+                    "unique": { "type": "boolean" },
+                    "type": { "type": "string", "enum": ["btree", "hash", "fulltext", "spatial"] }
+                },
+                "required": ["table_name", "columns"]
+            }),
+            output_schema: Some(ddl_output_schema()),
+            annotations: Some(ToolAnnotations::non_destructive("Create index")),
+        });
+        tools.push(Tool {
+            name: "drop_index".to_string(),
+            description: "Drop an index from a table".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "table_name": { "type": "string", "description": "Table the index belongs to" },
+                    "database": { "type": "string", "description": "Optional database containing the table" },
+                    "index_name": { "type": "string", "description": "Index to drop (PRIMARY for the primary key)" }
+                },
+                "required": ["table_name", "index_name"]
+            }),
+            output_schema: Some(ddl_output_schema()),
+            annotations: Some(ToolAnnotations::destructive("Drop index")),
+        });
+        if let Some(dir) = &config.migrations_dir {
+            tools.push(Tool {
+                name: "run_migrations".to_string(),
+                description: format!("Apply the pending <version>_<name>.sql migrations from {dir} in version order, recording each in the {} table of the database, and report which ran. Stops at the first failing statement", migrations::MIGRATIONS_TABLE),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "database": { "type": "string", "description": "Database to migrate (defaults to the current database)" },
+                        "target_version": { "type": "integer", "description": "Apply migrations up to and including this version only" },
+                        "dry_run": { "type": "boolean", "description": "List the pending migrations without running them (default false)" }
+                    }
+                }),
+                output_schema: Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "database": { "type": "string" },
+                        "applied": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "version": { "type": "integer" },
+                                    "name": { "type": "string" },
+                                    "statements": { "type": "integer" },
+                                    "elapsedMs": { "type": "integer" }
+                                }
+                            },
+                            "description": "Migrations run by this call, in order"
+                        },
+                        "pending": {
+                            "type": "array",
+                            "items": { "type": "object", "properties": { "version": { "type": "integer" }, "name": { "type": "string" } } },
+                            "description": "Migrations not run: all pending ones with dry_run, or those from a failed one on"
+                        },
+                        "alreadyApplied": { "type": "integer" },
+                        "modified": {
+                            "type": "array",
+                            "items": { "type": "object", "properties": { "version": { "type": "integer" }, "name": { "type": "string" } } },
+                            "description": "Applied migrations whose file has changed since"
+                        },
+                        "failed": {
+                            "type": ["object", "null"],
+                            "properties": {
+                                "version": { "type": "integer" },
+                                "name": { "type": "string" },
+                                "statement": { "type": "string" },
+                                "error": { "type": "string" }
+                            }
+                        }
+                    },
+                    "required": ["database", "applied", "pending", "alreadyApplied", "modified", "failed"]
+                })),
+                annotations: Some(ToolAnnotations::destructive("Run migrations")),
+            });
+        }
+    }
+
+    if permissions.admin {
+        tools.push(Tool {
+            name: "kill_query".to_string(),
+            description: "Stop the statement running on a thread (KILL QUERY), or close the thread's connection entirely (KILL CONNECTION). Find thread ids with show_processlist.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "thread_id": {
+                        "type": "integer",
+                        "description": "Thread id from show_processlist"
+                    },
+                    "connection": {
+                        "type": "boolean",
+                        "description": "Kill the whole connection instead of only its current statement (default false)"
+                    }
+                },
+                "required": ["thread_id"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "threadId": { "type": "integer" },
+                    "existed": { "type": "boolean", "description": "False if no thread had this id" }
+                },
+                "required": ["threadId", "existed"]
+            })),
+            annotations: Some(ToolAnnotations::destructive("Kill query")),
+        });
+    }
+
+    if config.saved_queries_file.is_some() {
+        tools.push(Tool {
+            name: "list_saved_queries".to_string(),
+            description: "List the saved queries that run_saved_query can run, with their SQL and parameter names".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "queries": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "description": { "type": "string" },
+                                "query": { "type": "string" },
+                                "parameters": { "type": "array", "items": { "type": "string" } }
+                            },
+                            "required": ["name", "query"]
+                        }
+                    }
+                },
+                "required": ["queries"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("List saved queries")),
+        });
+        tools.push(Tool {
+            name: "run_saved_query".to_string(),
+            description: "Run a saved query by name with values for its parameters. Prefer this over writing new SQL when a saved query answers the question.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "Name from list_saved_queries"
+                    },
+                    "params": {
+                        "type": ["array", "object"],
+                        "description": "Placeholder values: an array in order, or an object keyed by parameter name"
+                    },
+                    "database": {
+                        "type": "string",
+                        "description": "Optional database to run the query in"
+                    },
+                    "cursor": {
+                        "type": "string",
+                        "description": "nextCursor from a truncated result, to fetch the following page"
+                    },
+                    "transaction_id": {
+                        "type": "string",
+                        "description": "Optional id from begin_transaction to run inside that transaction"
+                    }
+                },
+                "required": ["name"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "columns": { "type": "array", "items": { "type": "string" } },
+                    "rows": { "type": "array", "items": { "type": "object" } },
+                    "rowCount": { "type": "integer" },
+                    "truncated": { "type": "boolean" },
+                    "nextCursor": { "type": ["string", "null"] }
+                },
+                "required": ["columns", "rows", "rowCount", "truncated"]
+            })),
+            annotations: Some(if !permissions.is_read_only() {
+                ToolAnnotations::destructive("Run a saved query")
+            } else {
+                ToolAnnotations::read_only("Run a saved query")
+            }),
+        });
+        tools.push(Tool {
+            name: "save_query".to_string(),
+            description: "Save a named, parameterized query (with ? placeholders) so it can be listed and run by name later".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "Letters, digits, '_' and '-', e.g. monthly_revenue"
+                    },
+                    "description": {
+                        "type": "string",
+                        "description": "What the query answers"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "SQL with ? placeholders"
+                    },
+                    "parameters": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Names of the placeholders in order"
+                    },
+                    "overwrite": {
+                        "type": "boolean",
+                        "description": "Replace an existing query of the same name"
+                    }
+                },
+                "required": ["name", "query"]
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "replaced": { "type": "boolean" }
+                },
+                "required": ["name", "replaced"]
+            })),
+            annotations: Some(ToolAnnotations::non_destructive("Save a query")),
+        });
+    }
+
+    if !config.cdc_tables.is_empty() {
+        tools.push(Tool {
+            name: "poll_changes".to_string(),
+            description: format!("Report inserts, updates, and deletes committed to the watched tables ({}) since a cursor, read from the binary log. Call without a cursor to start from now, then pass back the returned cursor. Changes name the table and operation, not the row values", config.cdc_tables.join(", ")),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "cursor": {
+                        "type": "string",
+                        "description": "cursor from the previous poll (binlog file:position)"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Changes to return before stopping at a transaction boundary (default 500, at most {})", cdc::MAX_CHANGES)
+                    }
+                }
+            }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "changes": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "table": { "type": "string", "description": "db.table" },
+                                "operation": { "type": "string", "enum": ["insert", "update", "delete"] },
+                                "position": { "type": "string", "description": "Binlog file:position of the row event, which may cover several rows" }
+                            }
+                        }
+                    },
+                    "tables": {
+                        "type": "object",
+                        "description": "Change counts by table and operation"
+                    },
+                    "cursor": { "type": "string", "description": "Pass to the next poll" },
+                    "hasMore": { "type": "boolean", "description": "More of the log is waiting; poll again right away" },
+                    "binlogFormat": { "type": ["string", "null"] }
+                },
+                "required": ["changes", "tables", "cursor", "hasMore", "binlogFormat"]
+            })),
+            annotations: Some(ToolAnnotations::read_only("Poll table changes")),
+        });
+    }
+
+    // Write tools whose allow_* flag is not set, and disabled tools, are not advertised
+    tools.retain(|tool| permissions.missing_flag(&tool.name).is_none() && !config.is_tool_disabled(&tool.name));
+    if config.require_confirmation {
+        for tool in tools.iter_mut().filter(|tool| confirm::TOOLS.contains(&tool.name.as_str())) {
+            tool.input_schema["properties"]["confirmation_token"] = json!({
+                "type": "string",
+                "description": "Token from a refused call's confirmationToken, to confirm running that same call"
+            });
+        }
+    }
+    tools
+}
+
+fn affected_rows_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "affectedRows": { "type": "integer", "description": "Rows affected, or with dry_run the rows that would be" },
+            "dryRun": { "type": "boolean" },
+            "sql": { "type": "string", "description": "Statement that would run, with dry_run" },
+            "params": { "type": "array", "description": "Values it would bind, with dry_run" }
+        },
+        "required": ["affectedRows"]
+    })
+}
+
+fn transaction_id_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "transaction_id": {
+                "type": "string",
+                "description": "Id returned by begin_transaction"
+            }
+        },
+        "required": ["transaction_id"]
+    })
+}
+
+fn column_definition_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" },
+            "type": { "type": "string", "description": "SQL type, e.g. VARCHAR(255), DECIMAL(10,2) UNSIGNED" },
+            "nullable": { "type": "boolean", "description": "Default true" },
+            "default": { "description": "Default value; CURRENT_TIMESTAMP is passed through as an expression" },
+            "auto_increment": { "type": "boolean" },
+            "primary_key": { "type": "boolean" },
+            "unique": { "type": "boolean" },
+            "comment": { "type": "string" }
+        },
+        "required": ["name", "type"]
+    })
+}
+
+fn ddl_output_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "statement": { "type": "string", "description": "The DDL that was executed" },
+            "elapsedMs": { "type": "integer" }
+        },
+        "required": ["statement", "elapsedMs"]
+    })
+}
+
+fn schema_changes_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "missing": { "type": "array", "items": { "type": "string" }, "description": "In the source only" },
+            "extra": { "type": "array", "items": { "type": "string" }, "description": "In the target only" },
+            "changed": { "type": "array", "items": { "type": "string" }, "description": "In both with different definitions" }
+        }
+    })
+}
+
+fn lock_transaction_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "trxId": { "type": ["string", "null"] },
+            "threadId": { "type": ["integer", "null"], "description": "Connection id, as used by kill_query" },
+            "query": { "type": ["string", "null"] },
+            "lockMode": { "type": ["string", "null"] }
+        }
+    })
+}
@@ -0,0 +1,158 @@
+//! Transactions that span several tool calls. Each one pins a pooled
+//! connection to an id returned by `begin_transaction`, so later `query`,
+//! `insert`, `update`, and `delete` calls can run inside it. A transaction
+//! belongs to the named connection it was begun on.
+
+use log::debug;
+use sqlx::pool::PoolConnection;
+use sqlx::{MySql, MySqlConnection, Pool, Transaction};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMappedMutexGuard, OwnedMutexGuard};
+
+use crate::error::DbError;
+
+/// Open transactions a single session may hold, so a forgetful client cannot
+/// drain the connection pool.
+const MAX_OPEN_TRANSACTIONS: usize = 4;
+
+/// `None` once the transaction has been committed or rolled back by a
+/// concurrent call that removed it from the map.
+type Slot = Arc<Mutex<Option<Transaction<'static, MySql>>>>;
+
+/// Exclusive access to an open transaction's connection.
+type TransactionGuard = OwnedMappedMutexGuard<Option<Transaction<'static, MySql>>, Transaction<'static, MySql>>;
+
+#[derive(Default)]
+pub struct TransactionManager {
+    /// Each transaction with the named connection it was begun on.
+    transactions: std::sync::Mutex<HashMap<String, (Option<String>, Slot)>>,
+}
+
+impl TransactionManager {
+    /// Begin a transaction on `pool`, the pool of `connection` (`None` for the default one).
+    pub async fn begin(&self, pool: &Pool<MySql>, connection: Option<String>) -> Result<String, DbError> {
+        if self.transactions.lock().unwrap().len() >= MAX_OPEN_TRANSACTIONS {
+            return Err(DbError::InvalidInput(format!(
+                "Too many open transactions (limit {MAX_OPEN_TRANSACTIONS}); commit or roll back one first"
+            )));
+        }
+        let transaction = pool.begin().await.map_err(DbError::ConnectionError)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        debug!("Began transaction {id}");
+        self.transactions
+            .lock()
+            .unwrap()
+            .insert(id.clone(), (connection, Arc::new(Mutex::new(Some(transaction)))));
+        Ok(id)
+    }
+
+    /// Refuse a call naming another `connection` than the transaction was
+    /// begun on: its statements would run on that transaction's server under
+    /// the other connection's permissions. Unknown ids are left to fail when
+    /// the transaction is used.
+    pub fn check_connection(&self, id: &str, connection: Option<&str>) -> Result<(), DbError> {
+        match self.transactions.lock().unwrap().get(id) {
+            Some((begun_on, _)) if begun_on.as_deref() != connection => {
+                let begun_on = match begun_on {
+                    Some(name) => format!("connection '{name}'"),
+                    None => "the default connection".to_string(),
+                };
+                Err(DbError::InvalidInput(format!("Transaction '{id}' was begun on {begun_on}; use it with the same connection")))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn commit(&self, id: &str) -> Result<(), DbError> {
+        let transaction = self.take(id).await?;
+        debug!("Committing transaction {id}");
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn rollback(&self, id: &str) -> Result<(), DbError> {
+        let transaction = self.take(id).await?;
+        debug!("Rolling back transaction {id}");
+        transaction.rollback().await?;
+        Ok(())
+    }
+
+    /// Remove a transaction, waiting for any statement still running on it.
+    async fn take(&self, id: &str) -> Result<Transaction<'static, MySql>, DbError> {
+        let slot = self.transactions.lock().unwrap().remove(id).map(|(_, slot)| slot);
+        let slot = slot.ok_or_else(|| unknown_transaction(id))?;
+        let transaction = slot.lock().await.take();
+        transaction.ok_or_else(|| unknown_transaction(id))
+    }
+
+    /// Lock a transaction's connection for the duration of one statement.
+    async fn connection(&self, id: &str) -> Result<TransactionGuard, DbError> {
+        let slot = self.transactions.lock().unwrap().get(id).map(|(_, slot)| slot.clone());
+        let slot = slot.ok_or_else(|| unknown_transaction(id))?;
+        OwnedMutexGuard::try_map(slot.lock_owned().await, |t| t.as_mut())
+            .map_err(|_| unknown_transaction(id))
+    }
+}
+
+fn unknown_transaction(id: &str) -> DbError {
+    DbError::NotFound(format!("Transaction '{id}' does not exist or has already ended"))
+}
+
+/// Connection a tool runs its statement on: a fresh one from the pool, or the
+/// one pinned to the transaction named in its arguments.
+pub enum Connection {
+    Pooled(PoolConnection<MySql>),
+    Transaction(TransactionGuard),
+}
+
+impl Connection {
+    pub async fn acquire(
+        pool: &Pool<MySql>,
+        transactions: &TransactionManager,
+        transaction_id: Option<&str>,
+    ) -> Result<Self, DbError> {
+        match transaction_id {
+            Some(id) => Ok(Connection::Transaction(transactions.connection(id).await?)),
+            None => Ok(Connection::Pooled(pool.acquire().await.map_err(DbError::ConnectionError)?)),
+        }
+    }
+}
+
+impl Deref for Connection {
+    type Target = MySqlConnection;
+
+    fn deref(&self) -> &MySqlConnection {
+        match self {
+            Connection::Pooled(conn) => conn,
+            Connection::Transaction(transaction) => transaction,
+        }
+    }
+}
+
+impl DerefMut for Connection {
+    fn deref_mut(&mut self) -> &mut MySqlConnection {
+        match self {
+            Connection::Pooled(conn) => conn,
+            Connection::Transaction(transaction) => transaction,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_connection() {
+        let transactions = TransactionManager::default();
+        transactions.transactions.lock().unwrap().insert("t1".to_string(), (Some("replica".to_string()), Arc::new(Mutex::new(None))));
+        transactions.transactions.lock().unwrap().insert("t2".to_string(), (None, Arc::new(Mutex::new(None))));
+        assert!(transactions.check_connection("t1", Some("replica")).is_ok());
+        assert!(transactions.check_connection("t1", None).is_err());
+        assert!(transactions.check_connection("t2", None).is_ok());
+        assert!(transactions.check_connection("t2", Some("replica")).is_err());
+        assert!(transactions.check_connection("unknown", Some("replica")).is_ok());
+    }
+}
@@ -0,0 +1,194 @@
+//! MCP Streamable HTTP transport.
+//!
+//! Clients POST JSON-RPC messages to `/mcp`. The `initialize` request creates a
+//! session whose id is returned in the `Mcp-Session-Id` header; every later
+//! request must echo that header. `DELETE /mcp` ends the session, and sessions
+//! idle for longer than `session_idle_timeout_secs` are dropped. Sessions share
+//! a [`PoolCache`], so new ones reuse warm connections. The server does not
+//! open server-initiated streams, so `GET /mcp` is rejected with 405.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    middleware,
+    Router,
+};
+use log::{debug, info};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::config::SharedConfig;
+use crate::server::{PoolCache, Session};
+use crate::transport::validate_origin;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// How often sessions are checked for having gone idle.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+struct HttpSession {
+    session: Arc<Session>,
+    /// When the session last started or finished a request.
+    last_used: Instant,
+}
+
+#[derive(Clone)]
+struct AppState {
+    config: SharedConfig,
+    pool_cache: Arc<PoolCache>,
+    sessions: Arc<Mutex<HashMap<String, HttpSession>>>,
+}
+
+pub async fn serve(config: SharedConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = tokio::net::TcpListener::bind(config.get().listen_address()).await?;
+    info!(
+        "MCP MySQL Server started and ready to accept connections on http://{}/mcp",
+        listener.local_addr()?
+    );
+
+    let state = AppState {
+        config: config.clone(),
+        pool_cache: Arc::new(PoolCache::default()),
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+    };
+    tokio::spawn(expire_sessions(state.clone()));
+    let app = Router::new()
+        .route("/mcp", post(handle_post).get(handle_get).delete(handle_delete))
+        .layer(middleware::from_fn_with_state(config, validate_origin))
+        .with_state(state);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("Received Ctrl-C, shutting down HTTP transport");
+        })
+        .await?;
+    Ok(())
+}
+
+/// Drop sessions that have gone idle, checking the timeout afresh each round
+/// so a reload applies to it.
+async fn expire_sessions(state: AppState) {
+    let mut ticks = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        ticks.tick().await;
+        let timeout = state.config.get().session_idle_timeout_secs;
+        if timeout > 0 {
+            expire_idle(&mut state.sessions.lock().unwrap(), Duration::from_secs(timeout));
+        }
+    }
+}
+
+fn expire_idle(sessions: &mut HashMap<String, HttpSession>, timeout: Duration) {
+    sessions.retain(|id, entry| {
+        let idle = entry.last_used.elapsed() >= timeout;
+        if idle {
+            info!("Expired idle HTTP session {id}");
+        }
+        !idle
+    });
+}
+
+fn touch(state: &AppState, id: &str) -> Option<Arc<Session>> {
+    let mut sessions = state.sessions.lock().unwrap();
+    let entry = sessions.get_mut(id)?;
+    entry.last_used = Instant::now();
+    Some(entry.session.clone())
+}
+
+fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok())
+}
+
+fn is_initialize_request(body: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("method").and_then(|m| m.as_str()).map(|m| m == "initialize"))
+        .unwrap_or(false)
+}
+
+async fn handle_post(State(state): State<AppState>, headers: HeaderMap, body: String) -> Response {
+    let (id, session, is_new) = match session_id(&headers) {
+        Some(id) => {
+            match touch(&state, id) {
+                Some(session) => (id.to_string(), session, false),
+                None => return (StatusCode::NOT_FOUND, "Unknown or expired session").into_response(),
+            }
+        }
+        None if is_initialize_request(&body) => (
+            Uuid::new_v4().to_string(),
+            Arc::new(Session::with_pool_cache(state.config.clone(), state.pool_cache.clone())),
+            true,
+        ),
+        None => return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response(),
+    };
+
+    let response = session.handle_message(&body).await;
+
+    // Only keep sessions whose initialize actually connected to the database
+    if is_new {
+        if !session.is_initialized().await {
+            return json_response(response, None);
+        }
+        info!("Created HTTP session {id}");
+        state.sessions.lock().unwrap().insert(id.clone(), HttpSession { session, last_used: Instant::now() });
+    } else {
+        // A long request counts as activity until it finishes
+        touch(&state, &id);
+    }
+
+    json_response(response, Some(&id))
+}
+
+fn json_response(body: Option<String>, session_id: Option<&str>) -> Response {
+    let mut response = match body {
+        Some(body) => ([(header::CONTENT_TYPE, "application/json")], body).into_response(),
+        // Notifications and client responses are acknowledged without a body
+        None => StatusCode::ACCEPTED.into_response(),
+    };
+    if let Some(id) = session_id.and_then(|id| id.parse().ok()) {
+        response.headers_mut().insert(SESSION_HEADER, id);
+    }
+    response
+}
+
+async fn handle_get() -> Response {
+    (StatusCode::METHOD_NOT_ALLOWED, "Server-initiated streams are not supported").into_response()
+}
+
+async fn handle_delete(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(id) = session_id(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response();
+    };
+    match state.sessions.lock().unwrap().remove(id) {
+        Some(_) => {
+            debug!("Terminated HTTP session {id}");
+            StatusCode::OK.into_response()
+        }
+        None => (StatusCode::NOT_FOUND, "Unknown or expired session").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_expire_idle() {
+        let config = SharedConfig::new(toml::from_str("username = \"u\"\ndatabase = \"d\"\ntransport = \"http\"").unwrap());
+        let session = |idle: Duration| HttpSession {
+            session: Arc::new(Session::new(config.clone())),
+            last_used: Instant::now().checked_sub(idle).unwrap(),
+        };
+        let mut sessions = HashMap::new();
+        sessions.insert("active".to_string(), session(Duration::from_secs(5)));
+        sessions.insert("idle".to_string(), session(Duration::from_secs(3600)));
+        expire_idle(&mut sessions, Duration::from_secs(1800));
+        assert!(sessions.contains_key("active"));
+        assert!(!sessions.contains_key("idle"));
+    }
+}
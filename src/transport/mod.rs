@@ -0,0 +1,74 @@
+//! Network transports. Each one feeds raw JSON-RPC messages into a
+//! [`crate::server::Session`] and writes back whatever it returns.
+//!
+//! Every HTTP route, WebSocket upgrades included, first has its `Origin`
+//! checked, so a web page cannot drive a local server through the browser
+//! (DNS rebinding); see [`validate_origin`].
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use log::warn;
+
+use crate::config::SharedConfig;
+use crate::policy::glob_matches;
+
+pub mod http;
+pub mod socket;
+pub mod sse;
+pub mod ws;
+
+/// Middleware refusing requests whose `Origin` is not allowed, with 403.
+pub async fn validate_origin(State(config): State<SharedConfig>, request: Request, next: Next) -> Response {
+    let origin = request.headers().get(header::ORIGIN).map(|origin| origin.to_str().unwrap_or_default());
+    if !origin_allowed(&config.get().allowed_origins, origin) {
+        warn!("Refused a request from origin {}", origin.unwrap_or_default());
+        return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+    }
+    next.run(request).await
+}
+
+/// Requests without an `Origin` do not come from a web page and are allowed.
+/// Others must match one of `allowed`, or when it is empty be an http(s)
+/// origin on localhost.
+fn origin_allowed(allowed: &[String], origin: Option<&str>) -> bool {
+    let Some(origin) = origin else {
+        return true;
+    };
+    if !allowed.is_empty() {
+        return allowed.iter().any(|pattern| glob_matches(pattern, origin));
+    }
+    match url::Url::parse(origin) {
+        Ok(url) => {
+            matches!(url.scheme(), "http" | "https")
+                && matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"))
+        }
+        // Including "null", sent by sandboxed frames and file:// pages
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_allowed() {
+        assert!(origin_allowed(&[], None));
+        assert!(origin_allowed(&[], Some("http://localhost:6274")));
+        assert!(origin_allowed(&[], Some("http://127.0.0.1")));
+        assert!(origin_allowed(&[], Some("https://[::1]:8443")));
+        assert!(!origin_allowed(&[], Some("https://evil.example")));
+        assert!(!origin_allowed(&[], Some("http://localhost.evil.example")));
+        assert!(!origin_allowed(&[], Some("null")));
+
+        let allowed = vec!["https://app.example.com".to_string(), "https://*.internal.example".to_string()];
+        assert!(origin_allowed(&allowed, Some("https://app.example.com")));
+        assert!(origin_allowed(&allowed, Some("https://tools.internal.example")));
+        assert!(!origin_allowed(&allowed, Some("http://localhost:6274")));
+        assert!(!origin_allowed(&allowed, Some("https://app.example.com.evil.example")));
+    }
+}
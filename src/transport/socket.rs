@@ -0,0 +1,131 @@
+//! TCP and Unix domain socket transports for running the server as a daemon.
+//!
+//! Both speak the same newline-delimited JSON as stdio. Every connection is its
+//! own session, but sessions share a [`PoolCache`], so tools can connect and
+//! disconnect freely without reopening MySQL connections.
+
+use log::{info, warn};
+use std::sync::Arc;
+
+use crate::config::SharedConfig;
+use crate::server::{serve_lines, PoolCache, Session};
+
+pub async fn serve_tcp(config: SharedConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = tokio::net::TcpListener::bind(config.get().listen_address()).await?;
+    info!(
+        "MCP MySQL Server started and ready to accept connections on tcp://{}",
+        listener.local_addr()?
+    );
+    let pool_cache = Arc::new(PoolCache::default());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = match accepted {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!("Failed to accept TCP connection: {e}");
+                        continue;
+                    }
+                };
+                info!("TCP client connected from {peer}");
+                let session = Arc::new(Session::with_pool_cache(config.clone(), pool_cache.clone()));
+                tokio::spawn(async move {
+                    let (reader, writer) = stream.into_split();
+                    serve_lines(session, reader, writer).await;
+                    info!("TCP client {peer} disconnected");
+                });
+            }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Received Ctrl-C, shutting down TCP transport");
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+pub async fn serve_unix(config: SharedConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config.get().listen_address();
+    remove_stale_socket(std::path::Path::new(&path))?;
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    info!("MCP MySQL Server started and ready to accept connections on unix://{path}");
+    let pool_cache = Arc::new(PoolCache::default());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let stream = match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept Unix socket connection: {e}");
+                        continue;
+                    }
+                };
+                info!("Unix socket client connected");
+                let session = Arc::new(Session::with_pool_cache(config.clone(), pool_cache.clone()));
+                tokio::spawn(async move {
+                    let (reader, writer) = stream.into_split();
+                    serve_lines(session, reader, writer).await;
+                    info!("Unix socket client disconnected");
+                });
+            }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Received Ctrl-C, shutting down Unix socket transport");
+                let _ = std::fs::remove_file(&path);
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Remove a socket left behind by a previous run, which would make bind
+/// fail. Anything else at `path`, or a socket another server is still
+/// listening on, is left alone and reported.
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> Result<(), String> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Cannot check {}: {e}", path.display())),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(format!("{} exists and is not a socket; choose another listen path", path.display()));
+    }
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(format!("Another server is listening on {}", path.display()));
+    }
+    std::fs::remove_file(path).map_err(|e| format!("Cannot remove the stale socket {}: {e}", path.display()))
+}
+
+#[cfg(not(unix))]
+pub async fn serve_unix(_config: SharedConfig) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The unix transport is only available on Unix platforms".into())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_stale_socket() {
+        let dir = std::env::temp_dir().join(format!("mcp-socket-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("mcp.sock");
+        assert!(remove_stale_socket(&path).is_ok());
+
+        std::fs::write(&path, "not a socket").unwrap();
+        assert!(remove_stale_socket(&path).is_err());
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert!(remove_stale_socket(&path).is_err(), "the socket is still in use");
+        drop(listener);
+        assert!(remove_stale_socket(&path).is_ok());
+        assert!(!path.exists());
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -0,0 +1,128 @@
+//! Legacy MCP HTTP+SSE transport (protocol 2024-11-05).
+//!
+//! A client opens `GET /sse` and receives an `endpoint` event naming the URL to
+//! POST its JSON-RPC messages to (`/messages?sessionId=...`). Every response is
+//! delivered back over the event stream; the POST itself is answered with 202.
+//! Sessions share a [`PoolCache`], so new streams reuse warm connections.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    middleware,
+    Router,
+};
+use log::{debug, info};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
+use uuid::Uuid;
+
+use crate::config::SharedConfig;
+use crate::server::{PoolCache, Session};
+use crate::transport::validate_origin;
+
+struct SseSession {
+    session: Arc<Session>,
+    events: mpsc::UnboundedSender<String>,
+}
+
+#[derive(Clone)]
+struct AppState {
+    config: SharedConfig,
+    pool_cache: Arc<PoolCache>,
+    sessions: Arc<Mutex<HashMap<String, Arc<SseSession>>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageParams {
+    session_id: String,
+}
+
+pub async fn serve(config: SharedConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = tokio::net::TcpListener::bind(config.get().listen_address()).await?;
+    info!(
+        "MCP MySQL Server started and ready to accept connections on http://{}/sse",
+        listener.local_addr()?
+    );
+
+    let state = AppState {
+        config: config.clone(),
+        pool_cache: Arc::new(PoolCache::default()),
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+    };
+    let app = Router::new()
+        .route("/sse", get(handle_sse))
+        .route("/messages", post(handle_message))
+        .layer(middleware::from_fn_with_state(config, validate_origin))
+        .with_state(state);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("Received Ctrl-C, shutting down SSE transport");
+        })
+        .await?;
+    Ok(())
+}
+
+async fn handle_sse(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let id = Uuid::new_v4().to_string();
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let session = Arc::new(Session::with_pool_cache(state.config.clone(), state.pool_cache.clone()));
+    session.set_notifier(Some(tx.clone()));
+    let sse_session = Arc::new(SseSession {
+        session,
+        events: tx.clone(),
+    });
+    state.sessions.lock().unwrap().insert(id.clone(), sse_session);
+    info!("Opened SSE session {id}");
+
+    // Drop the session once the client goes away and the stream's receiver is released
+    let sessions = state.sessions.clone();
+    let cleanup_id = id.clone();
+    tokio::spawn(async move {
+        tx.closed().await;
+        sessions.lock().unwrap().remove(&cleanup_id);
+        info!("Closed SSE session {cleanup_id}");
+    });
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("/messages?sessionId={id}"));
+    let messages = UnboundedReceiverStream::new(rx)
+        .map(|message| Ok(Event::default().event("message").data(message)));
+    let stream = tokio_stream::once(Ok(endpoint)).chain(messages);
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn handle_message(
+    State(state): State<AppState>,
+    Query(params): Query<MessageParams>,
+    body: String,
+) -> Response {
+    let Some(sse_session) = state.sessions.lock().unwrap().get(&params.session_id).cloned() else {
+        return (StatusCode::NOT_FOUND, "Unknown or expired session").into_response();
+    };
+
+    debug!("SSE session {} received message", params.session_id);
+    tokio::spawn(async move {
+        if let Some(response) = sse_session.session.handle_message(&body).await {
+            let _ = sse_session.events.send(response);
+        }
+    });
+
+    StatusCode::ACCEPTED.into_response()
+}
@@ -0,0 +1,96 @@
+//! WebSocket transport.
+//!
+//! Each connection to `/ws` is an independent MCP session. Sessions share a
+//! [`PoolCache`], so clients that reconnect reuse warm connections.
+//! Every text frame carries one JSON-RPC message, and responses are sent back
+//! as text frames on the same socket.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+    routing::get,
+    middleware,
+    Router,
+};
+use log::{info, warn};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::config::SharedConfig;
+use crate::server::{PoolCache, Session};
+use crate::transport::validate_origin;
+
+#[derive(Clone)]
+struct AppState {
+    config: SharedConfig,
+    pool_cache: Arc<PoolCache>,
+}
+
+pub async fn serve(config: SharedConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = tokio::net::TcpListener::bind(config.get().listen_address()).await?;
+    info!(
+        "MCP MySQL Server started and ready to accept connections on ws://{}/ws",
+        listener.local_addr()?
+    );
+
+    let app = Router::new()
+        .route("/ws", get(handle_upgrade))
+        .layer(middleware::from_fn_with_state(config.clone(), validate_origin))
+        .with_state(AppState { config, pool_cache: Arc::new(PoolCache::default()) });
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("Received Ctrl-C, shutting down WebSocket transport");
+        })
+        .await?;
+    Ok(())
+}
+
+async fn handle_upgrade(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(mut socket: WebSocket, state: AppState) {
+    info!("WebSocket client connected");
+    let session = Arc::new(Session::with_pool_cache(state.config, state.pool_cache));
+    // Requests run concurrently; their responses are written back from this loop
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    session.set_notifier(Some(tx.clone()));
+
+    loop {
+        tokio::select! {
+            frame = socket.recv() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    // Ping/pong are answered by axum; binary frames are not part of MCP
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        warn!("WebSocket receive error: {e}");
+                        break;
+                    }
+                };
+
+                let session = session.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    if let Some(response) = session.handle_message(text.as_str()).await {
+                        let _ = tx.send(response);
+                    }
+                });
+            }
+            Some(response) = rx.recv() => {
+                if let Err(e) = socket.send(Message::Text(response.into())).await {
+                    warn!("Failed to write WebSocket response: {e}");
+                    break;
+                }
+            }
+        }
+    }
+
+    info!("WebSocket client disconnected");
+}
@@ -0,0 +1,190 @@
+//! SSH tunnels to MySQL servers only reachable through a bastion host,
+//! enabled with `ssh_host`. The system `ssh` client forwards a local port to
+//! the database, so keys, agents, and `~/.ssh/config` work as they do for
+//! `ssh -L`. A tunnel is opened before the first pool to its target connects,
+//! shared by later pools, and restarted whenever the `ssh` process exits;
+//! pooled connections lost with it are replaced through the new one.
+
+use log::{info, warn};
+use sqlx::mysql::MySqlConnectOptions;
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+
+/// How long a new `ssh` process may take to start forwarding.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Pause before restarting an `ssh` process that exited.
+const RESTART_DELAY: Duration = Duration::from_secs(2);
+
+/// Local port of the tunnel to each `(host, port)` database target.
+static TUNNELS: Mutex<BTreeMap<(String, u16), u16>> = Mutex::const_new(BTreeMap::new());
+
+/// `options` pointed at the local end of a tunnel to the server they name,
+/// opening the tunnel first if needed. Unchanged without `ssh_host`, or when
+/// they connect through a local socket.
+pub async fn route(options: MySqlConnectOptions, config: &Config) -> Result<MySqlConnectOptions, sqlx::Error> {
+    if config.ssh_host.is_none() || options.get_socket().is_some() {
+        return Ok(options);
+    }
+    let target = (options.get_host().to_string(), options.get_port());
+    if !is_configured_target(config, &target) {
+        return Err(sqlx::Error::Configuration(
+            format!("ssh_host only tunnels to the configured database server, not {}:{}", target.0, target.1).into(),
+        ));
+    }
+    let mut tunnels = TUNNELS.lock().await;
+    let local_port = match tunnels.get(&target) {
+        Some(port) => *port,
+        None => {
+            let port = open(config, &target).await.map_err(sqlx::Error::Io)?;
+            tunnels.insert(target, port);
+            port
+        }
+    };
+    Ok(options.host("127.0.0.1").port(local_port))
+}
+
+/// Whether `target` is the server `config` names itself. The bastion reaches
+/// hosts the client cannot, so a `database_url` sent in `initializationOptions`
+/// must not choose another one behind it.
+fn is_configured_target(config: &Config, (host, port): &(String, u16)) -> bool {
+    let configured = config.database_url().and_then(|url| url.parse::<MySqlConnectOptions>().map_err(|e| e.to_string()));
+    match configured {
+        Ok(configured) => configured.get_host().eq_ignore_ascii_case(host) && configured.get_port() == *port,
+        Err(_) => false,
+    }
+}
+
+async fn open(config: &Config, target: &(String, u16)) -> std::io::Result<u16> {
+    // Ask the OS for a free port; ssh binds it again right after
+    let local_port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    let args = ssh_args(config, local_port, target);
+    info!("Opening SSH tunnel: ssh {}", args.join(" "));
+    let mut child = spawn(&args)?;
+    wait_until_forwarding(local_port, &mut child).await?;
+    info!("SSH tunnel to {}:{} listening on 127.0.0.1:{local_port}", target.0, target.1);
+    tokio::spawn(supervise(args, child, local_port));
+    Ok(local_port)
+}
+
+/// Arguments for an `ssh` process that forwards `127.0.0.1:<local_port>` to
+/// `target` and exits when the forward fails or the link goes silent.
+fn ssh_args(config: &Config, local_port: u16, (host, port): &(String, u16)) -> Vec<String> {
+    // IPv6 addresses are bracketed in -L specifications
+    let host = if host.contains(':') { format!("[{host}]") } else { host.clone() };
+    let mut args: Vec<String> = [
+        "-N",
+        "-o",
+        "ExitOnForwardFailure=yes",
+        "-o",
+        "BatchMode=yes",
+        "-o",
+        "ServerAliveInterval=15",
+        "-o",
+        "ServerAliveCountMax=3",
+    ]
+    .map(String::from)
+    .to_vec();
+    args.extend(["-L".to_string(), format!("127.0.0.1:{local_port}:{host}:{port}")]);
+    args.extend(["-p".to_string(), config.ssh_port.to_string()]);
+    if let Some(key) = &config.ssh_key {
+        args.extend(["-i".to_string(), key.clone()]);
+    }
+    if let Some(user) = &config.ssh_user {
+        args.extend(["-l".to_string(), user.clone()]);
+    }
+    // A host starting with `-` must not be read as an option
+    args.push("--".to_string());
+    args.push(config.ssh_host.clone().unwrap_or_default());
+    args
+}
+
+fn spawn(args: &[String]) -> std::io::Result<Child> {
+    // ssh's own messages go to stderr, alongside the server log
+    Command::new("ssh").args(args).stdin(Stdio::null()).stdout(Stdio::null()).kill_on_drop(true).spawn()
+}
+
+async fn wait_until_forwarding(local_port: u16, child: &mut Child) -> std::io::Result<()> {
+    let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Err(std::io::Error::other(format!("ssh exited with {status} before the tunnel was up")));
+        }
+        if tokio::net::TcpStream::connect(("127.0.0.1", local_port)).await.is_ok() {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("ssh did not start forwarding within {}s", STARTUP_TIMEOUT.as_secs()),
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Restart the tunnel on the same local port whenever `ssh` exits.
+async fn supervise(args: Vec<String>, mut child: Child, local_port: u16) {
+    loop {
+        match child.wait().await {
+            Ok(status) => warn!("SSH tunnel on port {local_port} exited with {status}; reconnecting"),
+            Err(e) => warn!("SSH tunnel on port {local_port} failed: {e}; reconnecting"),
+        }
+        loop {
+            tokio::time::sleep(RESTART_DELAY).await;
+            match spawn(&args) {
+                Ok(mut restarted) => match wait_until_forwarding(local_port, &mut restarted).await {
+                    Ok(()) => {
+                        info!("SSH tunnel on port {local_port} reconnected");
+                        child = restarted;
+                        break;
+                    }
+                    Err(e) => warn!("SSH tunnel on port {local_port} did not come back: {e}"),
+                },
+                Err(e) => warn!("Could not start ssh: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssh_args() {
+        let mut config: Config = toml::from_str("username = \"u\"\ndatabase = \"d\"\nssh_host = \"bastion.example.com\"").unwrap();
+        let args = ssh_args(&config, 40123, &("db.internal".to_string(), 3306));
+        assert_eq!(args[..2], ["-N", "-o"]);
+        assert!(args.windows(2).any(|w| w == ["-L", "127.0.0.1:40123:db.internal:3306"]));
+        assert!(args.windows(2).any(|w| w == ["-p", "22"]));
+        assert!(!args.contains(&"-i".to_string()));
+        assert_eq!(args[args.len() - 2..], ["--", "bastion.example.com"]);
+
+        config.ssh_user = Some("ops".to_string());
+        config.ssh_key = Some("~/.ssh/bastion".to_string());
+        config.ssh_port = 2222;
+        let args = ssh_args(&config, 40123, &("fd00::5".to_string(), 3307));
+        assert!(args.windows(2).any(|w| w == ["-L", "127.0.0.1:40123:[fd00::5]:3307"]));
+        assert!(args.windows(2).any(|w| w == ["-i", "~/.ssh/bastion"]));
+        assert!(args.windows(2).any(|w| w == ["-l", "ops"]));
+        assert!(args.windows(2).any(|w| w == ["-p", "2222"]));
+    }
+
+    #[test]
+    fn test_is_configured_target() {
+        let config: Config =
+            toml::from_str("username = \"u\"\ndatabase = \"d\"\nhost = \"DB.internal\"\nssh_host = \"bastion.example.com\"").unwrap();
+        assert!(is_configured_target(&config, &("db.internal".to_string(), 3306)));
+        assert!(!is_configured_target(&config, &("db.internal".to_string(), 3307)));
+        assert!(!is_configured_target(&config, &("10.0.0.9".to_string(), 3306)), "a client-supplied host is not tunneled");
+
+        let config: Config = toml::from_str("database_url = \"mysql://u@db.example:3307/d\"\nssh_host = \"bastion\"").unwrap();
+        assert!(is_configured_target(&config, &("db.example".to_string(), 3307)));
+    }
+}